@@ -1,9 +1,13 @@
 use std::collections::VecDeque;
-use std::sync::mpsc::{channel, Receiver, RecvError, Sender, TryRecvError};
+use std::sync::mpsc::{channel, Receiver, RecvError, RecvTimeoutError, Sender, TryRecvError};
 use std::thread;
+use std::time::Duration;
 use url::Url;
 
-use languageserver_types::{Position, Range};
+use languageserver_types::{
+    CodeAction, Diagnostic, DocumentSymbol, Position, Range, SignatureHelp, SymbolInformation,
+};
+use lark_span::PositionEncoding;
 
 pub type TaskId = usize;
 
@@ -14,9 +18,23 @@ pub enum QueryRequest {
     RenameAtPosition(TaskId, Url, Position, String),
     DefinitionAtPosition(TaskId, Url, Position),
     ReferencesAtPosition(TaskId, Url, Position, bool),
-    OpenFile(Url, String),
-    EditFile(Url, Vec<(Range, String)>),
-    Initialize(TaskId),
+    CompletionAtPosition(TaskId, Url, Position),
+    SignatureHelpAtPosition(TaskId, Url, Position),
+    SemanticTokens(TaskId, Url, Option<String>),
+    CodeActions(TaskId, Url, Range),
+    Formatting(TaskId, Url, Option<Range>),
+    DocumentSymbols(TaskId, Url),
+    WorkspaceSymbols(TaskId, String),
+    InlayHints(TaskId, Url, Range),
+    /// Opens a file with the given version and text.
+    OpenFile(Url, u64, String),
+    /// Changes to the text of a file, to be made in order, and the
+    /// version of the file after them (if the IDE gave one). A change
+    /// without a range replaces the whole text.
+    EditFile(Url, Option<u64>, Vec<(Option<Range>, String)>),
+    /// The IDE closed a file, so it no longer tracks its version.
+    CloseFile(Url),
+    Initialize(TaskId, PositionEncoding),
 }
 impl QueryRequest {
     /// True if this query will cause us to mutate the state of the
@@ -25,11 +43,20 @@ impl QueryRequest {
         match self {
             QueryRequest::OpenFile(..)
             | QueryRequest::EditFile(..)
+            | QueryRequest::CloseFile(..)
             | QueryRequest::RenameAtPosition(..)
             | QueryRequest::Initialize(..) => true,
             QueryRequest::TypeAtPosition(..) => false,
             QueryRequest::DefinitionAtPosition(..) => false,
             QueryRequest::ReferencesAtPosition(..) => false,
+            QueryRequest::CompletionAtPosition(..) => false,
+            QueryRequest::SignatureHelpAtPosition(..) => false,
+            QueryRequest::SemanticTokens(..) => false,
+            QueryRequest::CodeActions(..) => false,
+            QueryRequest::Formatting(..) => false,
+            QueryRequest::DocumentSymbols(..) => false,
+            QueryRequest::WorkspaceSymbols(..) => false,
+            QueryRequest::InlayHints(..) => false,
         }
     }
 }
@@ -41,10 +68,116 @@ pub enum LspResponse {
     Range(TaskId, Url, Range),
     Ranges(TaskId, Vec<(Url, Range)>),
     WorkspaceEdits(TaskId, Vec<(Url, Range, String)>),
+    TextEdits(TaskId, Vec<(Range, String)>),
     Completions(TaskId, Vec<(String, String)>),
-    Initialized(TaskId),
+    SignatureHelp(TaskId, SignatureHelp),
+    SemanticTokens(TaskId, Url, Option<String>, Vec<SemanticToken>),
+    CodeActions(TaskId, Vec<CodeAction>),
+    DocumentSymbols(TaskId, Vec<DocumentSymbol>),
+    WorkspaceSymbols(TaskId, Vec<SymbolInformation>),
+    InlayHints(TaskId, Vec<InlayHint>),
+    Initialized(TaskId, PositionEncoding),
     Nothing(TaskId),
-    Diagnostics(Url, Vec<(Range, String)>),
+    /// All of the diagnostics for a file (with the version of the file
+    /// that they are for), replacing any that were sent before.
+    Diagnostics(Url, Option<u64>, Vec<Diagnostic>),
+    /// The request failed, so there is no result to send.
+    Error(TaskId, RequestError),
+}
+
+/// Why a request failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RequestError {
+    /// The request is not allowed at the moment (as with any request
+    /// after `shutdown`).
+    InvalidRequest(String),
+
+    /// Something went wrong while handling the request (like a query
+    /// panicking).
+    InternalError(String),
+
+    /// The request is valid, but there is no sensible answer to it
+    /// (like the references to a field that does not exist).
+    RequestFailed(String),
+}
+
+/// A piece of a file (within one line), classified by what it means
+/// for semantic highlighting.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SemanticToken {
+    pub range: Range,
+    pub kind: SemanticTokenKind,
+
+    /// True where the token is the name in a declaration, rather than
+    /// a use of the name.
+    pub is_declaration: bool,
+}
+
+/// An inferred type to show in the middle of the text, just after the
+/// variable or argument that it is the type of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlayHint {
+    pub position: Position,
+
+    /// The type, with its permission.
+    pub ty: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SemanticTokenKind {
+    Keyword,
+    Comment,
+    String,
+    Number,
+    Type,
+    TypeParameter,
+    Struct,
+    Enum,
+    EnumMember,
+    Function,
+    Method,
+    Property,
+    Variable,
+    Parameter,
+}
+
+impl SemanticTokenKind {
+    pub const ALL: &'static [SemanticTokenKind] = &[
+        SemanticTokenKind::Keyword,
+        SemanticTokenKind::Comment,
+        SemanticTokenKind::String,
+        SemanticTokenKind::Number,
+        SemanticTokenKind::Type,
+        SemanticTokenKind::TypeParameter,
+        SemanticTokenKind::Struct,
+        SemanticTokenKind::Enum,
+        SemanticTokenKind::EnumMember,
+        SemanticTokenKind::Function,
+        SemanticTokenKind::Method,
+        SemanticTokenKind::Property,
+        SemanticTokenKind::Variable,
+        SemanticTokenKind::Parameter,
+    ];
+
+    /// The name of the kind in the LSP's list of standard token types.
+    pub fn name(self) -> &'static str {
+        match self {
+            SemanticTokenKind::Keyword => "keyword",
+            SemanticTokenKind::Comment => "comment",
+            SemanticTokenKind::String => "string",
+            SemanticTokenKind::Number => "number",
+            SemanticTokenKind::Type => "type",
+            SemanticTokenKind::TypeParameter => "typeParameter",
+            SemanticTokenKind::Struct => "struct",
+            SemanticTokenKind::Enum => "enum",
+            SemanticTokenKind::EnumMember => "enumMember",
+            SemanticTokenKind::Function => "function",
+            SemanticTokenKind::Method => "method",
+            SemanticTokenKind::Property => "property",
+            SemanticTokenKind::Variable => "variable",
+            SemanticTokenKind::Parameter => "parameter",
+        }
+    }
 }
 
 /// An actor in the task system. This gives a uniform way to
@@ -70,6 +203,15 @@ pub trait Actor {
     ///     messages if they have arrived in the meantime.
     ///     - This is only important if you are trying to remove outdated messages.
     fn receive_messages(&mut self, messages: &mut VecDeque<Self::InMessage>);
+
+    /// How long to wait, once there are no more messages, before
+    /// calling `idle`. By default, we wait for messages indefinitely.
+    fn idle_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Invoked when no messages have arrived for `idle_timeout`.
+    fn idle(&mut self) {}
 }
 
 pub struct ActorControl<MessageType: Send + Sync + 'static> {
@@ -82,17 +224,16 @@ pub fn spawn_actor<T: Actor + Send + 'static>(mut actor: T) -> ActorControl<T::I
     let mut message_queue = VecDeque::default();
 
     let handle = thread::spawn(move || loop {
-        match push_all_pending(&actor_rx, &mut message_queue) {
+        match push_all_pending(&actor_rx, &mut message_queue, actor.idle_timeout()) {
+            Ok(()) if message_queue.is_empty() => {
+                actor.idle();
+            }
             Ok(()) => {
                 actor.receive_messages(&mut message_queue);
             }
-            Err(error) => {
-                match error {
-                    PushAllPendingError::Disconnected => {
-                        eprintln!("Failure during top-level message receive");
-                    }
-                }
-
+            Err(PushAllPendingError::Disconnected) => {
+                // Nothing can send us messages any more (as when the
+                // language server has exited), so we are done.
                 break;
             }
         }
@@ -108,13 +249,27 @@ enum PushAllPendingError {
     Disconnected,
 }
 
-fn push_all_pending<T>(rx: &Receiver<T>, vec: &mut VecDeque<T>) -> Result<(), PushAllPendingError> {
+fn push_all_pending<T>(
+    rx: &Receiver<T>,
+    vec: &mut VecDeque<T>,
+    timeout: Option<Duration>,
+) -> Result<(), PushAllPendingError> {
     // If the queue is currently empty, then block until we get at
-    // least one message.
+    // least one message (or until the timeout, if any, leaving the
+    // queue empty).
     if vec.is_empty() {
-        match rx.recv() {
-            Ok(m) => vec.push_back(m),
-            Err(RecvError) => return Err(PushAllPendingError::Disconnected),
+        match timeout {
+            None => match rx.recv() {
+                Ok(m) => vec.push_back(m),
+                Err(RecvError) => return Err(PushAllPendingError::Disconnected),
+            },
+            Some(timeout) => match rx.recv_timeout(timeout) {
+                Ok(m) => vec.push_back(m),
+                Err(RecvTimeoutError::Timeout) => return Ok(()),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(PushAllPendingError::Disconnected)
+                }
+            },
         }
     }
 
@@ -8,6 +8,7 @@ pub fn build(
 ) -> std::io::Result<()> {
     match codegen_type {
         CodegenType::Rust => build_rust(target_filename, src),
+        CodegenType::C => build_c(target_filename, src),
     }
 }
 
@@ -20,14 +21,12 @@ fn create_src_file(codegen_type: CodegenType) -> tempfile::NamedTempFile {
             .rand_bytes(8)
             .tempfile()
             .unwrap(),
-        /*
-        CodegenType::C => tempfile::NamedTempFileOptions::new()
-            .prefix("lark")
-            .suffix(".c")
-            .rand_bytes(6)
-            .create()
+        CodegenType::C => tempfile::Builder::new()
+            .prefix("tmp")
+            .suffix(&".c".to_string())
+            .rand_bytes(8)
+            .tempfile()
             .unwrap(),
-        */
     };
 
     temp_file
@@ -46,6 +45,12 @@ fn build_rust(target_filename: &str, src: &String) -> std::io::Result<()> {
         .arg(src_file_name)
         .arg("-o")
         .arg(target_filename)
+        // Trap on overflow at runtime, just like `lark-eval`, rather
+        // than rejecting overflow that rustc can see statically
+        .arg("-C")
+        .arg("overflow-checks=on")
+        .arg("--cap-lints")
+        .arg("allow")
         .output()
         .expect("Failed to run Rust compiler");
 
@@ -62,3 +67,40 @@ fn build_rust(target_filename: &str, src: &String) -> std::io::Result<()> {
         Err(Error::new(ErrorKind::Other, combined_compile_msg))
     }
 }
+
+/// Invoke the system C compiler (`$CC`, or `cc` if that is not set) to
+/// build the source file
+fn build_c(target_filename: &str, src: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::process::Command;
+
+    let mut src_file = create_src_file(CodegenType::C);
+    src_file.write_all(src.as_bytes()).unwrap();
+    let src_file_name = src_file.path().to_string_lossy().to_string();
+
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    let output = Command::new(compiler)
+        .arg("-std=c99")
+        .arg(src_file_name)
+        .arg("-o")
+        .arg(target_filename)
+        // The generated code is not meant to be read, so warnings
+        // about it aren't useful
+        .arg("-w")
+        .output()
+        .expect("Failed to run C compiler");
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        use std::io::{Error, ErrorKind};
+
+        let compile_stdout = String::from_utf8(output.stdout).unwrap();
+        let compile_stderr = String::from_utf8(output.stderr).unwrap();
+
+        let combined_compile_msg = compile_stdout + &compile_stderr;
+
+        Err(Error::new(ErrorKind::Other, combined_compile_msg))
+    }
+}
@@ -0,0 +1,1178 @@
+use lark_collections::FxIndexMap;
+use lark_entity::{Entity, EntityData, ItemKind, LangItem, MemberKind};
+use lark_error::{Diagnostic, WithError};
+use lark_hir as hir;
+use lark_intern::Untern;
+use lark_parser::ParserDatabase;
+use lark_query_system::LarkDatabase;
+use lark_span::{FileName, Span};
+use lark_ty::base_inferred::BaseInferred;
+use lark_ty::declaration::{Declaration, DeclaredPermKind};
+use lark_ty::{BaseData, BaseKind, BoundVarOr, GenericKind, Generics, Ty};
+use lark_type_check::{TypeCheckDatabase, TypeCheckResults};
+
+mod test;
+
+/// Definitions that the generated code relies on. Arithmetic traps on
+/// overflow (and division by zero), and `lark_panic` reports it just
+/// like `lark run` and the Rust backend do.
+const PRELUDE: &str = r#"#include <inttypes.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef uint8_t lark_unit;
+#define LARK_UNIT ((lark_unit) 0)
+
+static void lark_panic(const char *message) {
+    fflush(stdout);
+    fprintf(stderr, "error: %s\n", message);
+    exit(1);
+}
+
+static uint32_t lark_add_uint(uint32_t l, uint32_t r) {
+    if (r > UINT32_MAX - l) lark_panic("arithmetic overflow");
+    return l + r;
+}
+
+static uint32_t lark_subtract_uint(uint32_t l, uint32_t r) {
+    if (r > l) lark_panic("arithmetic overflow");
+    return l - r;
+}
+
+static uint32_t lark_multiply_uint(uint32_t l, uint32_t r) {
+    uint64_t result = (uint64_t) l * r;
+    if (result > UINT32_MAX) lark_panic("arithmetic overflow");
+    return (uint32_t) result;
+}
+
+static uint32_t lark_divide_uint(uint32_t l, uint32_t r) {
+    if (r == 0) lark_panic("division by zero");
+    return l / r;
+}
+
+static uint32_t lark_modulo_uint(uint32_t l, uint32_t r) {
+    if (r == 0) lark_panic("division by zero");
+    return l % r;
+}
+
+static int32_t lark_checked_int(int64_t result) {
+    if (result < INT32_MIN || result > INT32_MAX) lark_panic("arithmetic overflow");
+    return (int32_t) result;
+}
+
+static int32_t lark_add_int(int32_t l, int32_t r) {
+    return lark_checked_int((int64_t) l + r);
+}
+
+static int32_t lark_subtract_int(int32_t l, int32_t r) {
+    return lark_checked_int((int64_t) l - r);
+}
+
+static int32_t lark_multiply_int(int32_t l, int32_t r) {
+    return lark_checked_int((int64_t) l * r);
+}
+
+static int32_t lark_divide_int(int32_t l, int32_t r) {
+    if (r == 0) lark_panic("division by zero");
+    if (l == INT32_MIN && r == -1) lark_panic("arithmetic overflow");
+    return l / r;
+}
+
+static int32_t lark_modulo_int(int32_t l, int32_t r) {
+    if (r == 0) lark_panic("division by zero");
+    if (l == INT32_MIN && r == -1) lark_panic("arithmetic overflow");
+    return l % r;
+}
+
+static int32_t lark_negate_int(int32_t value) {
+    if (value == INT32_MIN) lark_panic("arithmetic overflow");
+    return -value;
+}
+
+static void lark_write_bool(bool value) {
+    fputs(value ? "true" : "false", stdout);
+}
+
+static void lark_write_uint(uint32_t value) {
+    printf("%" PRIu32, value);
+}
+
+static void lark_write_int(int32_t value) {
+    printf("%" PRId32, value);
+}
+
+static void lark_write_string(const char *value) {
+    fputs(value, stdout);
+}
+
+static void lark_write_unit(lark_unit value) {
+    (void) value;
+    fputs("<void>", stdout);
+}
+"#;
+
+/// A Lark type, with all generic parameters substituted away. Each
+/// distinct `Named` type becomes its own C struct.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Type {
+    Boolean,
+    Uint,
+    Int,
+    String,
+    Unit,
+    Named(Entity, Vec<Type>),
+
+    /// The type of a function used as a value, which C code can't
+    /// represent. Using a function as a value is reported as an error,
+    /// so this only needs a stand-in C type.
+    Function,
+
+    /// The type of something with errors, which are reported instead
+    /// of generating any code for it, so this only needs a stand-in C
+    /// type too.
+    Error,
+}
+
+/// The name used in the runtime helpers (like `lark_add_uint`) for a
+/// primitive type.
+fn primitive_name(ty: &Type) -> &'static str {
+    match ty {
+        Type::Boolean => "bool",
+        Type::Uint => "uint",
+        Type::Int => "int",
+        Type::String => "string",
+        Type::Unit | Type::Function | Type::Error => "unit",
+        Type::Named(..) => unreachable!("{:?} is not a primitive type", ty),
+    }
+}
+
+/// Lark identifiers that are C keywords get a trailing `_`.
+fn c_identifier(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+        "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+        "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+        "union", "unsigned", "void", "volatile", "while", "bool", "true", "false",
+    ];
+
+    if KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Each input file gets its own prefix, like `lark_math_`, so that
+/// items from different modules don't collide.
+fn build_module_prefix(db: &LarkDatabase, file: FileName) -> String {
+    let name: String = db
+        .module_name(file)
+        .untern(db)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+
+    format!("lark_{}_", name)
+}
+
+/// The C name of an item or member, like `lark_math_square` or
+/// `lark_main_Point_sum`.
+fn build_entity_name(db: &LarkDatabase, entity: Entity) -> String {
+    let entity_data = entity.untern(db);
+    match entity_data {
+        EntityData::ItemName { id, .. } => {
+            let file = entity_data.file_name(db).unwrap();
+            format!("{}{}", build_module_prefix(db, file), id.untern(db))
+        }
+        EntityData::MemberName { base, id, .. } => {
+            format!("{}_{}", build_entity_name(db, base), id.untern(db))
+        }
+        x => unreachable!("only items and members are generated, not {:#?}", x),
+    }
+}
+
+/// The name of a struct or variant as `debug` shows it, like `Point` or
+/// `Shape.Circle` (as `lark_vm` does).
+fn display_name(db: &LarkDatabase, entity: Entity) -> String {
+    match entity.untern(db) {
+        EntityData::MemberName { base, id, .. } => {
+            format!("{}.{}", display_name(db, base), id.untern(db))
+        }
+        EntityData::ItemName { id, .. } => id.untern(db).to_string(),
+        x => unreachable!("only items and members have names, not {:#?}", x),
+    }
+}
+
+/// The members of `entity` of the given kind, in declaration order.
+fn members_of_kind(db: &LarkDatabase, entity: Entity, kind: MemberKind) -> Vec<hir::Member> {
+    db.members(entity)
+        .unwrap()
+        .iter()
+        .filter(|member| member.kind == kind)
+        .cloned()
+        .collect()
+}
+
+/// The value of the `tag` of an enum that indicates `variant`, and the
+/// name of the variant.
+fn variant_tag(db: &LarkDatabase, variant: Entity) -> (usize, String) {
+    let enum_entity = variant.untern(db).parent().unwrap();
+    members_of_kind(db, enum_entity, MemberKind::Variant)
+        .iter()
+        .enumerate()
+        .find(|(_, member)| member.entity == variant)
+        .map(|(tag, member)| (tag, c_identifier(&member.name.untern(db))))
+        .unwrap()
+}
+
+fn named_type(db: &LarkDatabase, entity: Entity, generics: Vec<Type>) -> Type {
+    match entity.untern(db) {
+        EntityData::LangItem(LangItem::Boolean) => Type::Boolean,
+        EntityData::LangItem(LangItem::Uint) => Type::Uint,
+        EntityData::LangItem(LangItem::Int) => Type::Int,
+        EntityData::LangItem(LangItem::String) => Type::String,
+        EntityData::LangItem(LangItem::Tuple(0)) => Type::Unit,
+        EntityData::ItemName {
+            kind: ItemKind::Struct,
+            ..
+        }
+        | EntityData::ItemName {
+            kind: ItemKind::Enum,
+            ..
+        } => Type::Named(entity, generics),
+
+        // Otherwise, `entity` is a function (or `debug`)
+        _ => Type::Function,
+    }
+}
+
+/// Converts `ty`, from the declaration of some entity, into a `Type`;
+/// `generics` are the types to substitute for its bound variables.
+fn declared_type(db: &LarkDatabase, ty: &Ty<Declaration>, generics: &[Type]) -> Type {
+    match ty.base.untern(db) {
+        BoundVarOr::BoundVar(bv) => generics[bv.as_usize()].clone(),
+        BoundVarOr::Known(BaseData {
+            kind,
+            generics: ty_generics,
+        }) => match kind {
+            BaseKind::Named(entity) => {
+                let ty_generics = ty_generics
+                    .iter()
+                    .map(|GenericKind::Ty(generic)| declared_type(db, &generic, generics))
+                    .collect();
+                named_type(db, entity, ty_generics)
+            }
+            BaseKind::Placeholder(_) | BaseKind::Error => {
+                unreachable!("declarations only use bound variables, and have no errors")
+            }
+        },
+    }
+}
+
+/// Converts `ty`, from the type-check results of some function, into
+/// a `Type`; `generics` are the types to substitute for the
+/// function's generic parameters.
+fn inferred_type(db: &LarkDatabase, ty: Ty<BaseInferred>, generics: &[Type]) -> Type {
+    let BaseData {
+        kind,
+        generics: ty_generics,
+    } = ty.base.untern(db);
+    match kind {
+        BaseKind::Named(entity) => {
+            let ty_generics = inferred_generics(db, &ty_generics, generics);
+            named_type(db, entity, ty_generics)
+        }
+        BaseKind::Placeholder(placeholder) => generics[placeholder.bound_var.as_usize()].clone(),
+        BaseKind::Error => Type::Error,
+    }
+}
+
+fn inferred_generics(
+    db: &LarkDatabase,
+    ty_generics: &Generics<BaseInferred>,
+    generics: &[Type],
+) -> Vec<Type> {
+    ty_generics
+        .iter()
+        .map(|GenericKind::Ty(generic)| inferred_type(db, generic, generics))
+        .collect()
+}
+
+struct CodegenC<'db> {
+    db: &'db LarkDatabase,
+
+    /// The C name of each type that we have defined.
+    types: FxIndexMap<Type, String>,
+
+    /// The function that writes a value of each struct or enum type as
+    /// `debug` shows it.
+    write_functions: FxIndexMap<Type, String>,
+
+    /// The definitions of the types in `types`, each after the types
+    /// that it contains.
+    type_definitions: String,
+
+    /// The C name of each instantiation of a function (or method) with
+    /// some generic arguments. Those beyond `next_function` have yet to
+    /// be generated.
+    functions: FxIndexMap<(Entity, Vec<Type>), String>,
+    next_function: usize,
+
+    prototypes: String,
+    function_definitions: String,
+    errors: Vec<Diagnostic>,
+}
+
+impl CodegenC<'_> {
+    /// The C name for the instantiation of `entity` with `generics`;
+    /// generic instantiations are numbered to tell them apart.
+    fn instance_name(&self, entity: Entity, generics: &[Type], index: usize) -> String {
+        let name = build_entity_name(self.db, entity);
+        if generics.is_empty() {
+            name
+        } else {
+            format!("{}_{}", name, index)
+        }
+    }
+
+    /// The C type for `ty`, defining it first if need be.
+    fn type_name(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Boolean => "bool".into(),
+            Type::Uint => "uint32_t".into(),
+            Type::Int => "int32_t".into(),
+            Type::String => "const char *".into(),
+            Type::Unit | Type::Function | Type::Error => "lark_unit".into(),
+            Type::Named(entity, generics) => {
+                if let Some(name) = self.types.get(ty) {
+                    return name.clone();
+                }
+
+                let name = self.instance_name(*entity, generics, self.types.len());
+                self.types.insert(ty.clone(), name.clone());
+
+                let body = match entity.untern(self.db) {
+                    EntityData::ItemName {
+                        kind: ItemKind::Enum,
+                        ..
+                    } => self.enum_body(*entity, generics),
+                    _ => self.fields_body(*entity, generics),
+                };
+                self.type_definitions.push_str(&format!(
+                    "typedef struct {} {{\n{}}} {};\n\n",
+                    name, body, name
+                ));
+
+                name
+            }
+        }
+    }
+
+    /// The fields of a struct or variant, one per line.
+    fn fields_body(&mut self, entity: Entity, generics: &[Type]) -> String {
+        let mut output = String::new();
+        for field in members_of_kind(self.db, entity, MemberKind::Field) {
+            let field_ty = self
+                .db
+                .ty(field.entity)
+                .accumulate_errors_into(&mut self.errors);
+            let field_ty = declared_type(self.db, &field_ty, generics);
+            output.push_str(&format!(
+                "    {} {};\n",
+                self.type_name(&field_ty),
+                c_identifier(&field.name.untern(self.db))
+            ));
+        }
+
+        // C doesn't allow empty structs
+        if output.is_empty() {
+            output.push_str("    char unused;\n");
+        }
+
+        output
+    }
+
+    /// An enum is a `tag` (the index of its variant) along with a
+    /// union of the fields of each variant.
+    fn enum_body(&mut self, entity: Entity, generics: &[Type]) -> String {
+        let mut output = String::from("    uint32_t tag;\n    union {\n");
+        for variant in members_of_kind(self.db, entity, MemberKind::Variant) {
+            let fields = self
+                .fields_body(variant.entity, generics)
+                .replace("\n", "\n    ");
+            output.push_str(&format!(
+                "        struct {{\n    {}    }} {};\n",
+                fields,
+                c_identifier(&variant.name.untern(self.db))
+            ));
+        }
+        output.push_str("        char unused;\n    } variants;\n");
+        output
+    }
+
+    /// The function that writes a value of type `ty` to stdout as
+    /// `debug` shows it (like `Point(x: 1, y: 2)`, as `lark_vm` does),
+    /// defining it first if need be.
+    fn write_function(&mut self, ty: &Type) -> String {
+        let (entity, generics) = match ty {
+            Type::Named(entity, generics) => (*entity, generics),
+            _ => return format!("lark_write_{}", primitive_name(ty)),
+        };
+
+        if let Some(name) = self.write_functions.get(ty) {
+            return name.clone();
+        }
+
+        let c_ty = self.type_name(ty);
+        let name = format!("lark_write_{}", c_ty);
+        self.write_functions.insert(ty.clone(), name.clone());
+
+        let body = match entity.untern(self.db) {
+            EntityData::ItemName {
+                kind: ItemKind::Enum,
+                ..
+            } => {
+                let mut body = String::from("    switch (value.tag) {\n");
+                let variants = members_of_kind(self.db, entity, MemberKind::Variant);
+                for (tag, variant) in variants.iter().enumerate() {
+                    let value = format!(
+                        "value.variants.{}",
+                        c_identifier(&variant.name.untern(self.db))
+                    );
+                    body.push_str(&format!("    case {}:\n", tag));
+                    body.push_str(&self.write_fields(variant.entity, generics, &value, "        "));
+                    body.push_str("        break;\n");
+                }
+                body.push_str("    }\n");
+                body
+            }
+            _ => self.write_fields(entity, generics, "value", "    "),
+        };
+
+        let prototype = format!("static void {}({} value)", name, c_ty);
+        self.prototypes.push_str(&format!("{};\n", prototype));
+        self.function_definitions
+            .push_str(&format!("{} {{\n{}}}\n\n", prototype, body));
+
+        name
+    }
+
+    /// Statements that write the name of the struct or variant `entity`
+    /// followed by its fields (which are in `value`), each line starting
+    /// with `indent`.
+    fn write_fields(
+        &mut self,
+        entity: Entity,
+        generics: &[Type],
+        value: &str,
+        indent: &str,
+    ) -> String {
+        let mut output = format!(
+            "{}fputs(\"{}\", stdout);\n",
+            indent,
+            display_name(self.db, entity)
+        );
+
+        let fields = members_of_kind(self.db, entity, MemberKind::Field);
+        for (index, field) in fields.iter().enumerate() {
+            let field_ty = self
+                .db
+                .ty(field.entity)
+                .accumulate_errors_into(&mut self.errors);
+            let field_ty = declared_type(self.db, &field_ty, generics);
+            let field_name = field.name.untern(self.db);
+            let separator = if index == 0 { "(" } else { ", " };
+            output.push_str(&format!(
+                "{}fputs(\"{}{}: \", stdout);\n",
+                indent, separator, field_name
+            ));
+            output.push_str(&format!(
+                "{}{}({}.{});\n",
+                indent,
+                self.write_function(&field_ty),
+                value,
+                c_identifier(&field_name)
+            ));
+        }
+        if !fields.is_empty() {
+            output.push_str(&format!("{}fputs(\")\", stdout);\n", indent));
+        }
+
+        output
+    }
+
+    /// The C name for the instantiation of the function `entity` with
+    /// `generics`, which will be generated if it hasn't been already.
+    fn function_name(&mut self, entity: Entity, generics: Vec<Type>) -> String {
+        let key = (entity, generics);
+        if let Some(name) = self.functions.get(&key) {
+            return name.clone();
+        }
+
+        let name = self.instance_name(entity, &key.1, self.functions.len());
+        self.functions.insert(key, name.clone());
+        name
+    }
+
+    fn codegen_function(&mut self, entity: Entity, generics: &[Type], name: &str) {
+        let db = self.db;
+        let errors = self.errors.len();
+        let fn_body = db.fn_body(entity).accumulate_errors_into(&mut self.errors);
+        let types = db
+            .base_type_check(entity)
+            .accumulate_errors_into(&mut self.errors);
+        let signature = db
+            .signature(entity)
+            .accumulate_errors_into(&mut self.errors);
+
+        // The errors fail the build, so there's no point in generating
+        // a function that has any
+        if self.errors.len() != errors {
+            return;
+        }
+        let (signature, arguments) = match (signature, fn_body.arguments) {
+            (Ok(signature), Ok(arguments)) => (signature, arguments),
+            _ => return,
+        };
+
+        let mut builder = FunctionBuilder {
+            codegen: self,
+            fn_body: &fn_body,
+            types: &types,
+            generics,
+            borrowed_self: None,
+            declarations: String::new(),
+            body: String::new(),
+            indent: 1,
+            temporaries: 0,
+        };
+
+        let mut parameters = vec![];
+        for (index, (argument, argument_ty)) in arguments
+            .iter(&fn_body)
+            .zip(signature.inputs.iter())
+            .enumerate()
+        {
+            let ty = declared_type(db, argument_ty, generics);
+            let c_ty = builder.codegen.type_name(&ty);
+            let variable_name = builder.variable_name(argument);
+
+            // A method that borrows its `self` gets a pointer to it, so
+            // that its changes are visible to the caller.
+            let is_method = match entity.untern(db) {
+                EntityData::MemberName {
+                    kind: MemberKind::Method,
+                    ..
+                } => true,
+                _ => false,
+            };
+            if index == 0 && is_method && argument_ty.perm.untern(db) == DeclaredPermKind::Borrow {
+                builder.borrowed_self = Some(argument);
+                parameters.push(format!("{} *{}", c_ty, variable_name));
+            } else {
+                parameters.push(format!("{} {}", c_ty, variable_name));
+            }
+        }
+
+        // Every other variable is declared at the top of the function
+        for (variable, _) in fn_body.tables.variables.iter_enumerated() {
+            if !arguments
+                .iter(&fn_body)
+                .any(|argument| argument == variable)
+            {
+                let ty = builder.inferred_type(variable);
+                let c_ty = builder.codegen.type_name(&ty);
+                let variable_name = builder.variable_name(variable);
+                builder
+                    .declarations
+                    .push_str(&format!("    {} {};\n", c_ty, variable_name));
+            }
+        }
+
+        let result = builder.expression(fn_body.root_expression);
+        builder.line(format!("return {};", result));
+
+        let FunctionBuilder {
+            declarations, body, ..
+        } = builder;
+
+        let output_ty = declared_type(db, &signature.output, generics);
+        let prototype = format!(
+            "static {} {}({})",
+            self.type_name(&output_ty),
+            name,
+            if parameters.is_empty() {
+                "void".to_string()
+            } else {
+                parameters.join(", ")
+            }
+        );
+
+        self.prototypes.push_str(&format!("{};\n", prototype));
+        self.function_definitions
+            .push_str(&format!("{} {{\n{}{}}}\n\n", prototype, declarations, body));
+    }
+}
+
+struct FunctionBuilder<'me, 'db> {
+    codegen: &'me mut CodegenC<'db>,
+    fn_body: &'me hir::FnBody,
+    types: &'me TypeCheckResults<BaseInferred>,
+
+    /// The types to substitute for the function's generic parameters.
+    generics: &'me [Type],
+
+    /// The `self` of a method that borrows it, and so is accessed
+    /// through a pointer.
+    borrowed_self: Option<hir::Variable>,
+
+    declarations: String,
+    body: String,
+    indent: usize,
+    temporaries: usize,
+}
+
+impl<'db> FunctionBuilder<'_, 'db> {
+    fn db(&self) -> &'db LarkDatabase {
+        self.codegen.db
+    }
+
+    fn line(&mut self, line: impl AsRef<str>) {
+        for _ in 0..self.indent {
+            self.body.push_str("    ");
+        }
+        self.body.push_str(line.as_ref());
+        self.body.push_str("\n");
+    }
+
+    fn inferred_type(&self, index: impl Into<hir::MetaIndex>) -> Type {
+        inferred_type(self.db(), self.types.ty(index), self.generics)
+    }
+
+    /// Every variable gets a distinct name, so that shadowing in Lark
+    /// doesn't need any special handling.
+    fn variable_name(&self, variable: hir::Variable) -> String {
+        let name = self.fn_body[self.fn_body[variable].name].text;
+        format!("{}_{}", name.untern(self.db()), variable.as_usize())
+    }
+
+    /// Reports that the C backend can't generate code for `what` (at
+    /// `span`), returning a stand-in for its value. The build fails, so
+    /// the code generated around it doesn't matter.
+    fn unsupported(&mut self, span: Span<FileName>, what: &str) -> String {
+        let message = format!("{} is not supported by the C backend", what);
+        self.codegen.errors.push(Diagnostic::new(message, span));
+        "LARK_UNIT".into()
+    }
+
+    /// A C lvalue for `variable`.
+    fn variable_place(&self, variable: hir::Variable) -> String {
+        if self.borrowed_self == Some(variable) {
+            format!("(*{})", self.variable_name(variable))
+        } else {
+            self.variable_name(variable)
+        }
+    }
+
+    /// Declares a new temporary of type `ty`.
+    fn temporary(&mut self, ty: &Type) -> String {
+        let c_ty = self.codegen.type_name(ty);
+        let name = format!("tmp_{}", self.temporaries);
+        self.temporaries += 1;
+        self.declarations
+            .push_str(&format!("    {} {};\n", c_ty, name));
+        name
+    }
+
+    /// A C lvalue for `place`, if it is a variable or a field of one;
+    /// using it has no side effects.
+    fn place(&mut self, place: hir::Place) -> Option<String> {
+        match self.fn_body[place] {
+            hir::PlaceData::Variable(variable) => Some(self.variable_place(variable)),
+            hir::PlaceData::Field { owner, name } => {
+                let owner = self.place(owner)?;
+                let name = self.fn_body[name].text.untern(self.db());
+                Some(format!("{}.{}", owner, c_identifier(&name)))
+            }
+            hir::PlaceData::Entity(_) | hir::PlaceData::Temporary(_) => None,
+        }
+    }
+
+    /// Emits the code that evaluates `place`, returning a C expression
+    /// for its value.
+    fn read_place(&mut self, place: hir::Place) -> String {
+        match self.fn_body[place] {
+            hir::PlaceData::Entity(entity) => match entity.untern(self.db()) {
+                EntityData::LangItem(LangItem::True) => "true".into(),
+                EntityData::LangItem(LangItem::False) => "false".into(),
+                _ => self.unsupported(self.fn_body.span(place), "using a function as a value"),
+            },
+            hir::PlaceData::Temporary(expression) => self.expression(expression),
+            hir::PlaceData::Variable(_) => self.place(place).unwrap(),
+            hir::PlaceData::Field { owner, name } => {
+                let owner = self.read_place(owner);
+                let name = self.fn_body[name].text.untern(self.db());
+                format!("{}.{}", owner, c_identifier(&name))
+            }
+        }
+    }
+
+    /// Emits the code that evaluates `expression`, returning a C
+    /// expression for its value. The result is always a temporary (or
+    /// a constant), so evaluating the code emitted later can't change
+    /// it.
+    fn expression(&mut self, expression: hir::Expression) -> String {
+        let db = self.db();
+        let fn_body = self.fn_body;
+
+        match fn_body[expression] {
+            hir::ExpressionData::Let {
+                variable,
+                initializer,
+                body,
+            } => {
+                if let Some(initializer) = initializer {
+                    let value = self.expression(initializer);
+                    let place = self.variable_place(variable);
+                    self.line(format!("{} = {};", place, value));
+                }
+                self.expression(body)
+            }
+
+            hir::ExpressionData::Place { place } => {
+                let value = self.read_place(place);
+                match fn_body[place] {
+                    hir::PlaceData::Entity(_) => value,
+                    _ => {
+                        let ty = self.inferred_type(expression);
+                        let result = self.temporary(&ty);
+                        self.line(format!("{} = {};", result, value));
+                        result
+                    }
+                }
+            }
+
+            hir::ExpressionData::Assignment { place, value } => {
+                let value = self.expression(value);
+                match self.place(place) {
+                    Some(place) => self.line(format!("{} = {};", place, value)),
+                    None => {
+                        let span = fn_body.span(place);
+                        self.unsupported(span, "assigning to something other than a variable");
+                    }
+                }
+                "LARK_UNIT".into()
+            }
+
+            hir::ExpressionData::MethodCall { method, arguments } => {
+                let method_entity = self.types.entities[&method.into()];
+                let owner = arguments.first(fn_body).unwrap();
+                let generics = match self.inferred_type(owner) {
+                    Type::Named(_, generics) => generics,
+                    _ => vec![],
+                };
+                let borrows_self = db
+                    .signature(method_entity)
+                    .into_value()
+                    .map(|signature| signature.inputs[0].perm.untern(db))
+                    == Ok(DeclaredPermKind::Borrow);
+
+                let mut values = vec![];
+                for (index, argument) in arguments.iter(fn_body).enumerate() {
+                    if index == 0 && borrows_self {
+                        // Pass a pointer to the owner, so that the
+                        // method can modify it
+                        let owner_place = match fn_body[argument] {
+                            hir::ExpressionData::Place { place } => self.place(place),
+                            _ => None,
+                        };
+                        let owner_place = match owner_place {
+                            Some(owner_place) => owner_place,
+                            None => self.expression(argument),
+                        };
+                        values.push(format!("&{}", owner_place));
+                    } else {
+                        values.push(self.expression(argument));
+                    }
+                }
+
+                let function = self.codegen.function_name(method_entity, generics);
+                self.call(expression, function, values)
+            }
+
+            hir::ExpressionData::Call {
+                function,
+                arguments,
+            } => {
+                let (place, entity) = match fn_body[function] {
+                    hir::ExpressionData::Place { place } => match fn_body[place] {
+                        hir::PlaceData::Entity(entity) => (place, entity),
+                        _ => return self.unsupported(fn_body.span(function), "calling a value"),
+                    },
+                    _ => return self.unsupported(fn_body.span(function), "calling a value"),
+                };
+
+                match entity.untern(db) {
+                    EntityData::LangItem(LangItem::Debug) => {
+                        for argument in arguments.iter(fn_body) {
+                            let ty = self.inferred_type(argument);
+                            let value = self.expression(argument);
+                            let write_function = self.codegen.write_function(&ty);
+                            self.line(format!("{}({});", write_function, value));
+                            self.line("putchar('\\n');");
+                        }
+                        "LARK_UNIT".into()
+                    }
+                    EntityData::LangItem(_) => {
+                        self.unsupported(fn_body.span(function), "calling a value")
+                    }
+                    _ => {
+                        let generics = match self.types.generics.get(&place.into()) {
+                            Some(generics) => inferred_generics(db, generics, self.generics),
+                            None => vec![],
+                        };
+                        let values = arguments
+                            .iter(fn_body)
+                            .map(|argument| self.expression(argument))
+                            .collect();
+                        let function = self.codegen.function_name(entity, generics);
+                        self.call(expression, function, values)
+                    }
+                }
+            }
+
+            hir::ExpressionData::Sequence { first, second } => {
+                self.expression(first);
+                self.expression(second)
+            }
+
+            hir::ExpressionData::If {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                let condition = self.expression(condition);
+                let ty = self.inferred_type(expression);
+                let result = self.temporary(&ty);
+
+                self.line(format!("if ({}) {{", condition));
+                self.block(if_true, &result);
+                self.line("} else {");
+                self.block(if_false, &result);
+                self.line("}");
+
+                result
+            }
+
+            hir::ExpressionData::While { condition, body } => {
+                self.line("while (1) {");
+                self.indent += 1;
+                let condition = self.expression(condition);
+                self.line(format!("if (!{}) break;", condition));
+                self.expression(body);
+                self.indent -= 1;
+                self.line("}");
+
+                "LARK_UNIT".into()
+            }
+
+            hir::ExpressionData::Match { value, arms } => {
+                self.match_expression(expression, value, arms)
+            }
+
+            hir::ExpressionData::Break {} => {
+                self.line("break;");
+                "LARK_UNIT".into()
+            }
+
+            hir::ExpressionData::Continue {} => {
+                self.line("continue;");
+                "LARK_UNIT".into()
+            }
+
+            hir::ExpressionData::Binary {
+                operator,
+                left,
+                right,
+            } => self.binary(expression, operator, left, right),
+
+            hir::ExpressionData::Unary { operator, value } => {
+                let value = self.expression(value);
+                let ty = self.inferred_type(expression);
+                let result = self.temporary(&ty);
+                match operator {
+                    hir::UnaryOperator::Not => self.line(format!("{} = !{};", result, value)),
+                    hir::UnaryOperator::Negate => {
+                        self.line(format!("{} = lark_negate_int({});", result, value))
+                    }
+                }
+                result
+            }
+
+            hir::ExpressionData::Literal { data } => match data.kind {
+                // The string literal includes its quotes
+                hir::LiteralKind::String => data.value.untern(db).to_string(),
+                hir::LiteralKind::UnsignedInteger | hir::LiteralKind::SignedInteger => {
+                    let digits = data.value.untern(db).replace('_', "");
+                    match self.inferred_type(expression) {
+                        Type::Int => format!("INT32_C({})", digits),
+                        _ => format!("UINT32_C({})", digits),
+                    }
+                }
+            },
+
+            hir::ExpressionData::Aggregate { entity, fields } => {
+                self.aggregate(expression, entity, fields)
+            }
+
+            hir::ExpressionData::Unit {} => "LARK_UNIT".into(),
+
+            hir::ExpressionData::Error { .. } => {
+                self.unsupported(self.fn_body.span(expression), "an expression with errors")
+            }
+        }
+    }
+
+    /// A `match` is a chain of `if`s that test the tag of the value.
+    fn match_expression(
+        &mut self,
+        expression: hir::Expression,
+        value: hir::Expression,
+        arms: hir::List<hir::MatchArm>,
+    ) -> String {
+        let db = self.db();
+        let fn_body = self.fn_body;
+
+        let value = self.expression(value);
+        let ty = self.inferred_type(expression);
+        let result = self.temporary(&ty);
+
+        let mut has_wildcard = false;
+        for (index, arm) in arms.iter(fn_body).enumerate() {
+            let arm_data = fn_body[arm];
+            let else_ = if index == 0 { "" } else { "} else " };
+            match arm_data.variant {
+                Some(variant) => {
+                    let (tag, _) = variant_tag(db, variant);
+                    self.line(format!("{}if ({}.tag == {}) {{", else_, value, tag));
+                }
+                None => {
+                    has_wildcard = true;
+                    self.line(format!("{}{{", else_));
+                }
+            }
+
+            self.indent += 1;
+            if let Some(variant) = arm_data.variant {
+                let (_, variant_name) = variant_tag(db, variant);
+                for binding in arm_data.bindings.iter(fn_body) {
+                    let binding_data = fn_body[binding];
+                    let field = fn_body[binding_data.identifier].text.untern(db);
+                    let place = self.variable_place(binding_data.variable);
+                    self.line(format!(
+                        "{} = {}.variants.{}.{};",
+                        place,
+                        value,
+                        variant_name,
+                        c_identifier(&field)
+                    ));
+                }
+            }
+            let arm_result = self.expression(arm_data.body);
+            self.line(format!("{} = {};", result, arm_result));
+            self.indent -= 1;
+
+            if has_wildcard {
+                break;
+            }
+        }
+
+        if !has_wildcard {
+            self.line("} else {");
+            self.line("    lark_panic(\"no arm of `match` matches the value\");");
+        }
+        self.line("}");
+
+        result
+    }
+
+    fn aggregate(
+        &mut self,
+        expression: hir::Expression,
+        entity: Entity,
+        fields: hir::List<hir::IdentifiedExpression>,
+    ) -> String {
+        let db = self.db();
+        let fn_body = self.fn_body;
+
+        let ty = self.inferred_type(expression);
+        let result = self.temporary(&ty);
+
+        let prefix = match entity.untern(db) {
+            EntityData::MemberName {
+                kind: MemberKind::Variant,
+                ..
+            } => {
+                let (tag, variant_name) = variant_tag(db, entity);
+                self.line(format!("{}.tag = {};", result, tag));
+                format!("{}.variants.{}", result, variant_name)
+            }
+            _ => result.clone(),
+        };
+
+        for field in fields.iter(fn_body) {
+            let field_data = fn_body[field];
+            let name = fn_body[field_data.identifier].text.untern(db);
+            let value = self.expression(field_data.expression);
+            self.line(format!("{}.{} = {};", prefix, c_identifier(&name), value));
+        }
+
+        result
+    }
+
+    /// Emits `expression` as a nested block whose value is stored in
+    /// `result`.
+    fn block(&mut self, expression: hir::Expression, result: &str) {
+        self.indent += 1;
+        let value = self.expression(expression);
+        self.line(format!("{} = {};", result, value));
+        self.indent -= 1;
+    }
+
+    fn call(
+        &mut self,
+        expression: hir::Expression,
+        function: String,
+        values: Vec<String>,
+    ) -> String {
+        let ty = self.inferred_type(expression);
+        let result = self.temporary(&ty);
+        self.line(format!("{} = {}({});", result, function, values.join(", ")));
+        result
+    }
+
+    fn binary(
+        &mut self,
+        expression: hir::Expression,
+        operator: hir::BinaryOperator,
+        left: hir::Expression,
+        right: hir::Expression,
+    ) -> String {
+        let operand_ty = self.inferred_type(left);
+        let left = self.expression(left);
+        let ty = self.inferred_type(expression);
+        let result = self.temporary(&ty);
+
+        // `&&` and `||` only evaluate their right-hand side if needed
+        match operator {
+            hir::BinaryOperator::And | hir::BinaryOperator::Or => {
+                let negate = if operator == hir::BinaryOperator::Or {
+                    "!"
+                } else {
+                    ""
+                };
+                self.line(format!("{} = {};", result, left));
+                self.line(format!("if ({}{}) {{", negate, result));
+                self.block(right, &result);
+                self.line("}");
+                return result;
+            }
+            _ => {}
+        }
+
+        let right = self.expression(right);
+        let value = match operator {
+            hir::BinaryOperator::Add
+            | hir::BinaryOperator::Subtract
+            | hir::BinaryOperator::Multiply
+            | hir::BinaryOperator::Divide
+            | hir::BinaryOperator::Modulo => {
+                let operation = match operator {
+                    hir::BinaryOperator::Add => "add",
+                    hir::BinaryOperator::Subtract => "subtract",
+                    hir::BinaryOperator::Multiply => "multiply",
+                    hir::BinaryOperator::Divide => "divide",
+                    _ => "modulo",
+                };
+                format!(
+                    "lark_{}_{}({}, {})",
+                    operation,
+                    primitive_name(&operand_ty),
+                    left,
+                    right
+                )
+            }
+            hir::BinaryOperator::Equals | hir::BinaryOperator::NotEquals => {
+                let operator = if operator == hir::BinaryOperator::Equals {
+                    "=="
+                } else {
+                    "!="
+                };
+                match operand_ty {
+                    Type::String => format!("strcmp({}, {}) {} 0", left, right, operator),
+                    _ => format!("{} {} {}", left, operator, right),
+                }
+            }
+            hir::BinaryOperator::LessThan => format!("{} < {}", left, right),
+            hir::BinaryOperator::LessThanOrEqual => format!("{} <= {}", left, right),
+            hir::BinaryOperator::GreaterThan => format!("{} > {}", left, right),
+            hir::BinaryOperator::GreaterThanOrEqual => format!("{} >= {}", left, right),
+            hir::BinaryOperator::And | hir::BinaryOperator::Or => unreachable!(),
+        };
+        self.line(format!("{} = {};", result, value));
+        result
+    }
+}
+
+/// Converts the project into a single C source file. Each
+/// instantiation of a generic function or type becomes its own C
+/// function or struct.
+pub fn codegen_c(db: &LarkDatabase) -> WithError<String> {
+    let mut codegen = CodegenC {
+        db,
+        types: FxIndexMap::default(),
+        write_functions: FxIndexMap::default(),
+        type_definitions: String::new(),
+        functions: FxIndexMap::default(),
+        next_function: 0,
+        prototypes: String::new(),
+        function_definitions: String::new(),
+        errors: vec![],
+    };
+
+    let main_functions: Vec<String> = crate::main_functions(db)
+        .into_iter()
+        .map(|(_, entity)| codegen.function_name(entity, vec![]))
+        .collect();
+
+    // Generating a function may require new instantiations of others
+    while codegen.next_function < codegen.functions.len() {
+        let ((entity, generics), name) = codegen
+            .functions
+            .get_index(codegen.next_function)
+            .map(|(key, name)| (key.clone(), name.clone()))
+            .unwrap();
+        codegen.next_function += 1;
+        codegen.codegen_function(entity, &generics, &name);
+    }
+
+    let mut output = String::from(PRELUDE);
+    output.push_str("\n");
+    output.push_str(&codegen.type_definitions);
+    output.push_str(&codegen.prototypes);
+    output.push_str("\n");
+    output.push_str(&codegen.function_definitions);
+    output.push_str("int main(void) {\n");
+    for main_function in main_functions {
+        output.push_str(&format!("    {}();\n", main_function));
+    }
+    output.push_str("    return 0;\n}\n");
+
+    WithError {
+        value: output,
+        errors: codegen.errors,
+    }
+}
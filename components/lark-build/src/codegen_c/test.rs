@@ -0,0 +1,32 @@
+#![cfg(test)]
+
+use crate::codegen_c::codegen_c;
+use lark_parser::ParserDatabaseExt;
+use lark_query_system::LarkDatabase;
+
+fn codegen_errors(text: &str) -> Vec<String> {
+    let mut db = LarkDatabase::default();
+    db.add_file("input.lark", text);
+    codegen_c(&db)
+        .errors
+        .into_iter()
+        .map(|error| error.label)
+        .collect()
+}
+
+#[test]
+fn no_errors() {
+    assert!(codegen_errors("def main() {\n  debug(1 + 2)\n}\n").is_empty());
+}
+
+#[test]
+fn unknown_variable() {
+    let errors = codegen_errors("def main() {\n  debug(unknown + 1)\n}\n");
+    assert!(!errors.is_empty());
+}
+
+#[test]
+fn error_in_called_function() {
+    let text = "def main() {\n  debug(helper(1))\n}\n\ndef helper(x: uint) -> Missing {\n  x\n}\n";
+    assert!(!codegen_errors(text).is_empty());
+}
@@ -5,7 +5,11 @@ use lark_hir as hir;
 use lark_intern::{Intern, Untern};
 use lark_parser::{ParserDatabase, ParserDatabaseExt};
 use lark_query_system::LarkDatabase;
-use lark_ty::Ty;
+use lark_span::FileName;
+use lark_ty::base_inferred::BaseInferred;
+use lark_ty::declaration::DeclaredPermKind;
+use lark_ty::{BaseKind, GenericKind, Ty};
+use lark_type_check::{TypeCheckDatabase, TypeCheckResults};
 
 fn build_variable_name(
     db: &LarkDatabase,
@@ -17,13 +21,108 @@ fn build_variable_name(
     identifier.text.untern(db).to_string()
 }
 
+/// The variable that `place` is (or is a field of), if any.
+fn root_variable(
+    fn_body: &std::sync::Arc<hir::FnBody>,
+    place: hir::Place,
+) -> Option<hir::Variable> {
+    match fn_body.tables[place] {
+        hir::PlaceData::Variable(variable) => Some(variable),
+        hir::PlaceData::Field { owner, .. } => root_variable(fn_body, owner),
+        hir::PlaceData::Entity(_) | hir::PlaceData::Temporary(_) => None,
+    }
+}
+
+/// The permission with which the method `entity` accesses its `self`.
+fn self_permission(db: &LarkDatabase, entity: Entity) -> DeclaredPermKind {
+    let signature = db.signature(entity).into_value().unwrap();
+    signature.inputs[0].perm.untern(db)
+}
+
+/// Returns true if `variable` (or one of its fields) is the target of
+/// an assignment, or the owner of a `borrow` method call, anywhere in
+/// `fn_body`, in which case Rust requires it to be declared `mut`.
+fn is_assigned(
+    db: &LarkDatabase,
+    fn_body: &std::sync::Arc<hir::FnBody>,
+    types: &TypeCheckResults<BaseInferred>,
+    variable: lark_hir::Variable,
+) -> bool {
+    fn_body
+        .tables
+        .expressions
+        .iter()
+        .any(|expression_data| match expression_data {
+            hir::ExpressionData::Assignment { place, .. } => {
+                root_variable(fn_body, *place) == Some(variable)
+            }
+            hir::ExpressionData::MethodCall { method, arguments } => {
+                let method_entity = types.entities[&(*method).into()];
+                let owner = arguments.first(fn_body).unwrap();
+                match fn_body.tables[owner] {
+                    hir::ExpressionData::Place { place } => {
+                        root_variable(fn_body, place) == Some(variable)
+                            && self_permission(db, method_entity) == DeclaredPermKind::Borrow
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        })
+}
+
+fn build_variable_declaration(
+    db: &LarkDatabase,
+    fn_body: &std::sync::Arc<hir::FnBody>,
+    types: &TypeCheckResults<BaseInferred>,
+    variable: lark_hir::Variable,
+) -> String {
+    if is_assigned(db, fn_body, types, variable) {
+        format!("mut {}", build_variable_name(db, fn_body, variable))
+    } else {
+        build_variable_name(db, fn_body, variable)
+    }
+}
+
+/// Each input file becomes a Rust module, named after the Lark
+/// module. The prefix keeps (e.g.) a module `struct` from colliding
+/// with a Rust keyword.
+fn build_module_name(db: &LarkDatabase, file: FileName) -> String {
+    let name: String = db
+        .module_name(file)
+        .untern(db)
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+
+    format!("lark_{}", name)
+}
+
+/// The path to a (top-level) item from within any of the modules,
+/// like `super::math::square`.
+fn build_item_path(db: &LarkDatabase, entity: Entity) -> String {
+    let entity_data = entity.untern(db);
+    match entity_data {
+        EntityData::ItemName { id, .. } => {
+            let file = entity_data.file_name(db).unwrap();
+            format!("super::{}::{}", build_module_name(db, file), id.untern(db))
+        }
+        x => unimplemented!("Unsupported item: {:#?}", x),
+    }
+}
+
 fn build_entity_name(db: &LarkDatabase, entity: Entity) -> String {
     let entity_data = entity.untern(db);
     match entity_data {
         EntityData::LangItem(LangItem::False) => "false".into(),
         EntityData::LangItem(LangItem::True) => "true".into(),
         EntityData::LangItem(LangItem::Debug) => "println!".into(),
-        EntityData::ItemName { id, .. } => id.untern(db).to_string(),
+        EntityData::ItemName { .. } => build_item_path(db, entity),
+        EntityData::MemberName {
+            base,
+            kind: MemberKind::Variant,
+            id,
+        } => format!("{}::{}", build_item_path(db, base), id.untern(db)),
         x => unimplemented!("Unsupported entity name: {:#?}", x),
     }
 }
@@ -31,6 +130,7 @@ fn build_entity_name(db: &LarkDatabase, entity: Entity) -> String {
 pub fn build_place(
     db: &LarkDatabase,
     fn_body: &std::sync::Arc<hir::FnBody>,
+    types: &TypeCheckResults<BaseInferred>,
     place: hir::Place,
 ) -> String {
     match &fn_body.tables[place] {
@@ -41,15 +141,63 @@ pub fn build_place(
 
             format!(
                 "{}.{}",
-                build_place(db, fn_body, *owner),
+                build_place(db, fn_body, types, *owner),
                 identifier.text.untern(db).to_string()
             )
         }
-        hir::PlaceData::Temporary(expression) => build_expression(db, fn_body, *expression),
+        hir::PlaceData::Temporary(expression) => build_expression(db, fn_body, types, *expression),
+    }
+}
+
+/// The names of the generic parameters in scope within `entity`,
+/// indexed by bound variable.
+fn generic_parameter_names(db: &LarkDatabase, entity: Entity) -> Vec<String> {
+    match db.generic_declarations(entity).into_value() {
+        Ok(declarations) => {
+            let mut names = match declarations.parent_item {
+                Some(parent_item) => generic_parameter_names(db, parent_item),
+                None => vec![],
+            };
+            names.extend(
+                declarations
+                    .declarations
+                    .iter()
+                    .map(|GenericKind::Ty(declaration)| declaration.name.untern(db).to_string()),
+            );
+            names
+        }
+        Err(_) => vec![],
     }
 }
 
-pub fn build_type(db: &LarkDatabase, ty: &Ty<lark_ty::declaration::Declaration>) -> String {
+/// The generic parameters declared by `entity` itself (not its
+/// parent), like `<T, U>`, or nothing if there are none.
+fn build_generic_parameters(db: &LarkDatabase, entity: Entity) -> String {
+    match db.generic_declarations(entity).into_value() {
+        Ok(declarations) => {
+            if declarations.declarations.is_empty() {
+                String::new()
+            } else {
+                let names: Vec<String> = declarations
+                    .declarations
+                    .iter()
+                    .map(|GenericKind::Ty(declaration)| declaration.name.untern(db).to_string())
+                    .collect();
+                format!("<{}>", names.join(", "))
+            }
+        }
+        Err(_) => String::new(),
+    }
+}
+
+/// Converts `ty`, which appears in the declaration of `scope`, into a
+/// Rust type. Bound variables are given the names of the generic
+/// parameters in scope.
+pub fn build_type(
+    db: &LarkDatabase,
+    scope: Entity,
+    ty: &Ty<lark_ty::declaration::Declaration>,
+) -> String {
     let boolean_entity = EntityData::LangItem(LangItem::Boolean).intern(db);
     let uint_entity = EntityData::LangItem(LangItem::Uint).intern(db);
     let int_entity = EntityData::LangItem(LangItem::Int).intern(db);
@@ -57,7 +205,9 @@ pub fn build_type(db: &LarkDatabase, ty: &Ty<lark_ty::declaration::Declaration>)
     let void_entity = EntityData::LangItem(LangItem::Tuple(0)).intern(db);
 
     match ty.base.untern(db) {
-        lark_ty::BoundVarOr::BoundVar(_) => unimplemented!("Bound variables not yet supported"),
+        lark_ty::BoundVarOr::BoundVar(bv) => {
+            generic_parameter_names(db, scope)[bv.as_usize()].clone()
+        }
         lark_ty::BoundVarOr::Known(ty) => match ty.kind {
             lark_ty::BaseKind::Named(entity) => {
                 if entity == boolean_entity {
@@ -74,9 +224,24 @@ pub fn build_type(db: &LarkDatabase, ty: &Ty<lark_ty::declaration::Declaration>)
                     match entity.untern(db) {
                         EntityData::ItemName {
                             kind: ItemKind::Struct,
-                            id,
                             ..
-                        } => id.untern(db).to_string(),
+                        }
+                        | EntityData::ItemName {
+                            kind: ItemKind::Enum,
+                            ..
+                        } => {
+                            let path = build_item_path(db, entity);
+                            if ty.generics.is_empty() {
+                                path
+                            } else {
+                                let generics: Vec<String> = ty
+                                    .generics
+                                    .iter()
+                                    .map(|GenericKind::Ty(generic)| build_type(db, scope, &generic))
+                                    .collect();
+                                format!("{}<{}>", path, generics.join(", "))
+                            }
+                        }
                         _ => unimplemented!("Unknown type: {:#?}", entity.debug_with(db)),
                     }
                 }
@@ -96,7 +261,9 @@ pub fn codegen_struct(
     let mut output = String::new();
     let mut errors: Vec<Diagnostic> = vec![];
 
-    output.push_str(&format!("struct {} {{\n", name));
+    let generic_parameters = build_generic_parameters(db, entity);
+
+    output.push_str(&format!("pub struct {}{} {{\n", name, generic_parameters));
 
     // for Rust output, output the fields first between the curlies
     for member in members.iter() {
@@ -110,9 +277,9 @@ pub fn codegen_struct(
             } => {
                 let member_ty = db.ty(member.entity).accumulate_errors_into(&mut errors);
                 output.push_str(&format!(
-                    "{}: {},\n",
+                    "pub {}: {},\n",
                     member_name,
-                    build_type(db, &member_ty)
+                    build_type(db, entity, &member_ty)
                 ));
             }
             _ => {}
@@ -121,7 +288,10 @@ pub fn codegen_struct(
     output.push_str("}\n");
 
     // output the methods in a separate impl
-    output.push_str(&format!("impl {} {{\n", name));
+    output.push_str(&format!(
+        "impl{} {}{} {{\n",
+        generic_parameters, name, generic_parameters
+    ));
     for member in members.iter() {
         match member.entity.untern(db) {
             EntityData::MemberName {
@@ -146,9 +316,48 @@ pub fn codegen_struct(
     }
 }
 
+pub fn codegen_enum(
+    db: &LarkDatabase,
+    entity: Entity,
+    id: lark_string::GlobalIdentifier,
+) -> WithError<String> {
+    let name = id.untern(db);
+    let variants = db.members(entity).unwrap();
+    let mut output = String::new();
+    let mut errors: Vec<Diagnostic> = vec![];
+
+    output.push_str(&format!(
+        "pub enum {}{} {{\n",
+        name,
+        build_generic_parameters(db, entity)
+    ));
+
+    // Each variant is a struct-like variant, so that it can be
+    // constructed and matched by field name
+    for variant in variants.iter() {
+        output.push_str(&format!("{} {{\n", variant.name.untern(db)));
+        for field in db.members(variant.entity).unwrap().iter() {
+            let field_ty = db.ty(field.entity).accumulate_errors_into(&mut errors);
+            output.push_str(&format!(
+                "{}: {},\n",
+                field.name.untern(db),
+                build_type(db, entity, &field_ty)
+            ));
+        }
+        output.push_str("},\n");
+    }
+    output.push_str("}\n");
+
+    WithError {
+        value: output,
+        errors,
+    }
+}
+
 pub fn build_expression(
     db: &LarkDatabase,
     fn_body: &std::sync::Arc<hir::FnBody>,
+    types: &TypeCheckResults<BaseInferred>,
     expression: hir::Expression,
 ) -> String {
     match fn_body.tables[expression] {
@@ -159,26 +368,29 @@ pub fn build_expression(
         } => match initializer {
             Some(init_expression) => format!(
                 "{{ let {} = {};\n{}}}",
-                build_variable_name(db, fn_body, variable),
-                build_expression(db, fn_body, init_expression),
-                build_expression(db, fn_body, body),
+                build_variable_declaration(db, fn_body, types, variable),
+                build_expression(db, fn_body, types, init_expression),
+                build_expression(db, fn_body, types, body),
+            ),
+            None => format!(
+                "let {};\n",
+                build_variable_declaration(db, fn_body, types, variable)
             ),
-            None => format!("let {};\n", build_variable_name(db, fn_body, variable)),
         },
 
-        hir::ExpressionData::Place { place } => build_place(db, fn_body, place),
+        hir::ExpressionData::Place { place } => build_place(db, fn_body, types, place),
 
         hir::ExpressionData::Assignment { place, value } => format!(
             "{} = {};\n",
-            build_place(db, fn_body, place),
-            build_expression(db, fn_body, value)
+            build_place(db, fn_body, types, place),
+            build_expression(db, fn_body, types, value)
         ),
 
         hir::ExpressionData::MethodCall { method, arguments } => {
             let mut arguments = arguments.iter(fn_body);
             let mut output = String::new();
 
-            output.push_str(&build_expression(db, fn_body, arguments.next().unwrap()));
+            output.push_str(&build_expression(db, fn_body, types, arguments.next().unwrap()));
 
             let method_name = fn_body.tables[method].text.untern(db);
             output.push_str(&format!(".{}(", method_name));
@@ -190,7 +402,7 @@ pub fn build_expression(
                 } else {
                     first = false;
                 }
-                output.push_str(&build_expression(db, fn_body, argument));
+                output.push_str(&build_expression(db, fn_body, types, argument));
             }
             output.push_str(")");
 
@@ -203,7 +415,7 @@ pub fn build_expression(
         } => {
             let mut output = String::new();
 
-            output.push_str(&build_expression(db, fn_body, function));
+            output.push_str(&build_expression(db, fn_body, types, function));
 
             output.push_str("(");
 
@@ -231,7 +443,7 @@ pub fn build_expression(
                 } else {
                     first = false;
                 }
-                output.push_str(&build_expression(db, fn_body, argument));
+                output.push_str(&build_expression(db, fn_body, types, argument));
             }
             output.push_str(")");
 
@@ -240,8 +452,8 @@ pub fn build_expression(
 
         hir::ExpressionData::Sequence { first, second } => format!(
             "{};\n {}",
-            build_expression(db, fn_body, first),
-            build_expression(db, fn_body, second)
+            build_expression(db, fn_body, types, first),
+            build_expression(db, fn_body, types, second)
         ),
 
         hir::ExpressionData::If {
@@ -250,36 +462,114 @@ pub fn build_expression(
             if_false,
         } => format!(
             "if {} {{ {} \n}} else {{ {} \n}}",
-            build_expression(db, fn_body, condition),
-            build_expression(db, fn_body, if_true),
-            build_expression(db, fn_body, if_false)
+            build_expression(db, fn_body, types, condition),
+            build_expression(db, fn_body, types, if_true),
+            build_expression(db, fn_body, types, if_false)
+        ),
+
+        hir::ExpressionData::While { condition, body } => format!(
+            "while {} {{ {} \n}}",
+            build_expression(db, fn_body, types, condition),
+            build_expression(db, fn_body, types, body)
         ),
 
+        hir::ExpressionData::Match { value, arms } => {
+            let mut output = String::new();
+
+            output.push_str(&format!(
+                "match {} {{\n",
+                build_expression(db, fn_body, types, value)
+            ));
+
+            for arm in arms.iter(fn_body) {
+                let arm_data = fn_body.tables[arm];
+                match arm_data.variant {
+                    Some(variant) => {
+                        output.push_str(&build_entity_name(db, variant));
+                        output.push_str(" { ");
+                        for binding in arm_data.bindings.iter(fn_body) {
+                            let identified_variable = fn_body.tables[binding];
+                            output.push_str(&format!(
+                                "{}: {}, ",
+                                fn_body.tables[identified_variable.identifier]
+                                    .text
+                                    .untern(db),
+                                build_variable_declaration(
+                                    db,
+                                    fn_body,
+                                    types,
+                                    identified_variable.variable
+                                ),
+                            ));
+                        }
+                        output.push_str(".. }");
+                    }
+                    None => output.push_str("_"),
+                }
+                output.push_str(&format!(
+                    " => {{ {} \n}}\n",
+                    build_expression(db, fn_body, types, arm_data.body)
+                ));
+            }
+            output.push_str("}");
+
+            output
+        }
+
+        hir::ExpressionData::Break {} => "break".to_string(),
+
+        hir::ExpressionData::Continue {} => "continue".to_string(),
+
         hir::ExpressionData::Binary {
             operator,
             left,
             right,
-        } => format!(
-            "({} {} {})",
-            build_expression(db, fn_body, left),
+        } => {
+            let left = build_expression(db, fn_body, types, left);
+            let right = build_expression(db, fn_body, types, right);
+            let checked =
+                |method, right| format!("super::lark_checked(({}).{}({}))", left, method, right);
+            let divisor = || format!("super::lark_divisor({})", right);
             match operator {
-                hir::BinaryOperator::Add => "+",
-                hir::BinaryOperator::Subtract => "-",
-                hir::BinaryOperator::Multiply => "*",
-                hir::BinaryOperator::Divide => "/",
-                hir::BinaryOperator::Equals => "==",
-                hir::BinaryOperator::NotEquals => "!=",
-            },
-            build_expression(db, fn_body, right),
-        ),
+                hir::BinaryOperator::Add => return checked("checked_add", right),
+                hir::BinaryOperator::Subtract => return checked("checked_sub", right),
+                hir::BinaryOperator::Multiply => return checked("checked_mul", right),
+                hir::BinaryOperator::Divide => return checked("checked_div", divisor()),
+                hir::BinaryOperator::Modulo => return checked("checked_rem", divisor()),
+                _ => {}
+            }
 
-        hir::ExpressionData::Unary { operator, value } => format!(
-            "{}({})",
+            format!(
+                "({} {} {})",
+                left,
+                match operator {
+                    hir::BinaryOperator::LessThan => "<",
+                    hir::BinaryOperator::LessThanOrEqual => "<=",
+                    hir::BinaryOperator::GreaterThan => ">",
+                    hir::BinaryOperator::GreaterThanOrEqual => ">=",
+                    hir::BinaryOperator::And => "&&",
+                    hir::BinaryOperator::Or => "||",
+                    hir::BinaryOperator::Equals => "==",
+                    hir::BinaryOperator::NotEquals => "!=",
+                    hir::BinaryOperator::Add
+                    | hir::BinaryOperator::Subtract
+                    | hir::BinaryOperator::Multiply
+                    | hir::BinaryOperator::Divide
+                    | hir::BinaryOperator::Modulo => unreachable!(),
+                },
+                right,
+            )
+        }
+
+        hir::ExpressionData::Unary { operator, value } => {
+            let value = build_expression(db, fn_body, types, value);
             match operator {
-                hir::UnaryOperator::Not => "!",
-            },
-            build_expression(db, fn_body, value)
-        ),
+                hir::UnaryOperator::Not => format!("!({})", value),
+                hir::UnaryOperator::Negate => {
+                    format!("super::lark_checked(({}).checked_neg())", value)
+                }
+            }
+        }
 
         hir::ExpressionData::Literal { data } => match data {
             hir::LiteralData {
@@ -289,7 +579,20 @@ pub fn build_expression(
             hir::LiteralData {
                 kind: hir::LiteralKind::UnsignedInteger,
                 value,
-            } => format!("{}", value.untern(db)),
+            }
+            | hir::LiteralData {
+                kind: hir::LiteralKind::SignedInteger,
+                value,
+            } => {
+                // Suffix integer literals so that rustc agrees with our
+                // choice of `int` or `uint`.
+                let int_entity = EntityData::LangItem(LangItem::Int).intern(db);
+                let suffix = match types.ty(expression).base.untern(db).kind {
+                    BaseKind::Named(entity) if entity == int_entity => "i32",
+                    _ => "u32",
+                };
+                format!("({}{})", value.untern(db), suffix)
+            }
         },
 
         hir::ExpressionData::Unit {} => "()".to_string(),
@@ -313,7 +616,7 @@ pub fn build_expression(
                     fn_body.tables[identified_expression.identifier]
                         .text
                         .untern(db),
-                    build_expression(db, fn_body, identified_expression.expression),
+                    build_expression(db, fn_body, types, identified_expression.expression),
                 ));
             }
             output.push_str("}");
@@ -335,6 +638,7 @@ pub fn codegen_function(
     let mut errors: Vec<Diagnostic> = vec![];
 
     let fn_body = db.fn_body(entity).accumulate_errors_into(&mut errors);
+    let types = db.base_type_check(entity).accumulate_errors_into(&mut errors);
 
     let signature = db
         .signature(entity)
@@ -345,30 +649,45 @@ pub fn codegen_function(
 
     let name = id.untern(db);
 
-    output.push_str(&format!("fn {}(", name));
+    output.push_str(&format!(
+        "pub fn {}{}(",
+        name,
+        build_generic_parameters(db, entity)
+    ));
 
     let mut first = true;
     for (argument, argument_type) in arguments.iter(&fn_body).zip(signature.inputs.iter()) {
-        let variable_data = fn_body.tables[argument];
-        let identifier = fn_body.tables[variable_data.name];
-
-        let argument_name = identifier.text.untern(db);
-
         if !first {
             output.push_str(", ");
         } else {
             first = false;
+
+            // The `self` of a method is declared with its permission
+            // in place of a type.
+            if let EntityData::MemberName {
+                kind: MemberKind::Method,
+                ..
+            } = entity.untern(db)
+            {
+                output.push_str(match argument_type.perm.untern(db) {
+                    DeclaredPermKind::Own => "self",
+                    DeclaredPermKind::Share => "&self",
+                    DeclaredPermKind::Borrow => "&mut self",
+                });
+                continue;
+            }
         }
 
+        let argument_name = build_variable_declaration(db, &fn_body, &types, argument);
         output.push_str(&format!("{}: ", argument_name));
-        output.push_str(&format!("{}", build_type(db, argument_type)));
+        output.push_str(&build_type(db, entity, argument_type));
     }
 
     output.push_str(") -> ");
-    output.push_str(&format!("{}", build_type(db, &signature.output)));
+    output.push_str(&build_type(db, entity, &signature.output));
     output.push_str(&format!(
         " {{\n{} }}\n",
-        build_expression(db, &fn_body, fn_body.root_expression)
+        build_expression(db, &fn_body, &types, fn_body.root_expression)
     ));
 
     WithError {
@@ -377,15 +696,39 @@ pub fn codegen_function(
     }
 }
 
+/// Definitions that the generated code relies on. Arithmetic traps on
+/// overflow (and division by zero), and `lark_panic` reports it just
+/// like `lark run` and the C backend do.
+const PRELUDE: &str = r#"fn lark_panic(message: &str) -> ! {
+    use std::io::Write;
+    let _ = std::io::stdout().flush();
+    eprintln!("error: {}", message);
+    std::process::exit(1)
+}
+
+fn lark_checked<T>(result: Option<T>) -> T {
+    result.unwrap_or_else(|| lark_panic("arithmetic overflow"))
+}
+
+fn lark_divisor<T: Default + PartialEq>(value: T) -> T {
+    if value == T::default() {
+        lark_panic("division by zero");
+    }
+    value
+}
+"#;
+
 /// Converts the MIR context of definitions into Rust source
 pub fn codegen_rust(db: &LarkDatabase) -> WithError<String> {
-    let mut output = String::new();
+    let mut output = String::from(PRELUDE);
     let input_files = db.file_names();
     let mut errors: Vec<Diagnostic> = vec![];
 
     for &input_file in &*input_files {
         let entities = db.top_level_entities_in_file(input_file);
 
+        output.push_str(&format!("mod {} {{\n", build_module_name(db, input_file)));
+
         for &entity in &*entities {
             match entity.untern(&db) {
                 EntityData::ItemName {
@@ -412,10 +755,38 @@ pub fn codegen_rust(db: &LarkDatabase) -> WithError<String> {
                         output.push_str(&result.value);
                     }
                 }
+                EntityData::ItemName {
+                    kind: ItemKind::Enum,
+                    id,
+                    ..
+                } => {
+                    let mut result = codegen_enum(db, entity, id);
+                    if !result.errors.is_empty() {
+                        errors.append(&mut result.errors);
+                    } else {
+                        output.push_str(&result.value);
+                    }
+                }
+                EntityData::ItemName {
+                    kind: ItemKind::Import,
+                    ..
+                } => {
+                    // Nothing to do: references to imported items
+                    // use their full path.
+                }
                 x => unimplemented!("Can not codegen {:#?}", x.debug_with(db)),
             }
         }
+
+        output.push_str("}\n");
+    }
+
+    output.push_str("fn main() {\n");
+    for (input_file, _) in crate::main_functions(db) {
+        let main_module = build_module_name(db, input_file);
+        output.push_str(&format!("{}::main();\n", main_module));
     }
+    output.push_str("}\n");
 
     WithError {
         value: output,
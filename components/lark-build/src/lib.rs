@@ -1,19 +1,25 @@
 mod build;
+mod codegen_c;
 mod codegen_rust;
 
+use lark_entity::{Entity, EntityData, ItemKind};
 use lark_error::WithError;
+use lark_intern::{Intern, Untern};
+use lark_parser::{ParserDatabase, ParserDatabaseExt};
 use lark_query_system::LarkDatabase;
+use lark_span::FileName;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum CodegenType {
     Rust,
+    C,
 }
 
 /// Converts the MIR context of definitions into the chosen source type
 pub fn codegen(db: &LarkDatabase, codegen_type: CodegenType) -> WithError<String> {
     match codegen_type {
         CodegenType::Rust => codegen_rust::codegen_rust(db),
-        //CodegenType::C => codegen_c::codegen_c(context),
+        CodegenType::C => codegen_c::codegen_c(db),
     }
 }
 
@@ -25,3 +31,24 @@ pub fn build(
 ) -> std::io::Result<()> {
     build::build(target_filename, &src, codegen_type)
 }
+
+/// The `main` function of each input file that has one, in order. Like
+/// `lark_eval`, the generated program runs each of them in turn.
+fn main_functions(db: &LarkDatabase) -> Vec<(FileName, Entity)> {
+    let main_name = "main".intern(db);
+    let mut main_functions = vec![];
+    for &input_file in &*db.file_names() {
+        for &entity in &*db.top_level_entities_in_file(input_file) {
+            match entity.untern(db) {
+                EntityData::ItemName {
+                    kind: ItemKind::Function,
+                    id,
+                    ..
+                } if id == main_name => main_functions.push((input_file, entity)),
+                _ => {}
+            }
+        }
+    }
+
+    main_functions
+}
@@ -0,0 +1,268 @@
+//! Parses the command line of `lark`.
+
+use lark_build::CodegenType;
+use termcolor::ColorChoice;
+
+mod test;
+
+crate const USAGE: &str = "\
+Usage:
+  lark check <path>              - reports any errors in the given file or project directory
+  lark build <path> [<output>]   - compiles the given file or project directory
+      -o, --output <output>        where to write the executable
+      --backend rust|c             compile with rustc (the default) or with cc
+  lark run <path>                - runs the given file or project directory
+  lark debug <path>              - runs the given file or project directory in the interpreter,
+                                   pausing at breakpoints and after each step (type `help` when
+                                   paused to list the commands)
+  lark debug-adapter             - run the Lark debug adapter (DAP), which lets an IDE debug
+                                   programs in the interpreter, over stdin and stdout
+  lark repl                      - REPL/interactive mode
+  lark ide                       - run the Lark languge server/IDE support
+      --listen <address>           serve one IDE over TCP (e.g. `127.0.0.1:9257`) rather than
+                                     over stdin and stdout
+  lark fmt [--check] <path>      - formats the given file or project directory
+  lark dump-hir [--full] <path>  - prints the HIR (with its inferred types) of each function
+
+Options:
+  --color auto|always|never      whether to color diagnostics
+  --message-format human|json    print diagnostics as text (on stderr) or as JSON objects,
+                                   one per line (on stdout)
+  --log-level <spec>             what to log, e.g. `error,lark_query_system=info`
+                                   (overriding `RUST_LOG`)
+  --log-dir <directory>          where to write the log files
+  -h, --help                     print this message
+
+Exit status:
+  0  success
+  1  the program has errors or fails at runtime (or, for `fmt --check`, is not formatted; or,
+       for `ide`, the IDE told the server to exit without first shutting it down; or, for
+       `debug-adapter`, the IDE went away without disconnecting)
+  2  the command line is invalid
+  3  lark failed for some other reason (such as an unreadable file)";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    pub color: ColorChoice,
+    pub message_format: MessageFormat,
+
+    /// The `--log-level`, if any; otherwise `RUST_LOG` (or a default)
+    /// applies.
+    pub log_level: Option<String>,
+    pub log_directory: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Check {
+        path: String,
+    },
+    Build {
+        path: String,
+        output: Option<String>,
+        backend: CodegenType,
+    },
+    Run {
+        path: String,
+    },
+    Debug {
+        path: String,
+    },
+    DebugAdapter,
+    Repl,
+    Ide {
+        /// The address to accept a TCP connection on, if any;
+        /// otherwise we use stdin and stdout.
+        listen: Option<String>,
+    },
+    Fmt {
+        path: String,
+        check: bool,
+    },
+    DumpHir {
+        path: String,
+        full: bool,
+    },
+    Help,
+}
+
+/// How diagnostics are reported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageFormat {
+    Human,
+    Json,
+}
+
+/// Flags that are followed by a value (either as the next argument or
+/// after an `=`).
+const VALUE_FLAGS: &[&str] = &[
+    "-o",
+    "--output",
+    "--backend",
+    "--color",
+    "--message-format",
+    "--log-level",
+    "--log-dir",
+    "--listen",
+];
+
+/// Parses the arguments to `lark` (not including the name of the
+/// program itself). On failure, returns a message describing what is
+/// wrong with them.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut args = args.into_iter();
+    let mut positional = vec![];
+    let mut flags: Vec<(String, Option<String>)> = vec![];
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positional.extend(args.by_ref());
+        } else if arg.starts_with('-') && arg != "-" {
+            let (flag, value) = match arg.find('=') {
+                Some(index) => (arg[..index].to_string(), Some(arg[index + 1..].to_string())),
+                None => (arg, None),
+            };
+
+            let value = if VALUE_FLAGS.contains(&&flag[..]) && value.is_none() {
+                match args.next() {
+                    Some(value) => Some(value),
+                    None => return Err(format!("`{}` must be followed by a value", flag)),
+                }
+            } else {
+                value
+            };
+
+            flags.push((flag, value));
+        } else {
+            positional.push(arg);
+        }
+    }
+
+    let mut options = Options {
+        command: Command::Help,
+        color: ColorChoice::Auto,
+        message_format: MessageFormat::Human,
+        log_level: None,
+        log_directory: String::from("log_files"),
+    };
+
+    // Global flags first; the rest depend on the command
+    let mut command_flags = vec![];
+    for (flag, value) in flags {
+        match (&flag[..], value) {
+            ("-h", None) | ("--help", None) => return Ok(options),
+            ("--color", Some(value)) => {
+                options.color = match &value[..] {
+                    "auto" => ColorChoice::Auto,
+                    "always" => ColorChoice::Always,
+                    "never" => ColorChoice::Never,
+                    _ => return Err(format!("unknown color choice `{}`", value)),
+                };
+            }
+            ("--message-format", Some(value)) => {
+                options.message_format = match &value[..] {
+                    "human" => MessageFormat::Human,
+                    "json" => MessageFormat::Json,
+                    _ => return Err(format!("unknown message format `{}`", value)),
+                };
+            }
+            ("--log-level", Some(value)) => options.log_level = Some(value),
+            ("--log-dir", Some(value)) => options.log_directory = value,
+            (_, value) => command_flags.push((flag, value)),
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    let command = match positional.next() {
+        Some(command) => command,
+        None => return Err(String::from("no command given")),
+    };
+
+    options.command = match &command[..] {
+        "check" => Command::Check {
+            path: expect_path(&mut positional, &command)?,
+        },
+        "build" => {
+            let path = expect_path(&mut positional, &command)?;
+            let mut output = positional.next();
+            let mut backend = CodegenType::Rust;
+            for (flag, value) in std::mem::replace(&mut command_flags, vec![]) {
+                match (&flag[..], value) {
+                    ("-o", Some(value)) | ("--output", Some(value)) => output = Some(value),
+                    ("--backend", Some(value)) => {
+                        backend = match &value[..] {
+                            "rust" => CodegenType::Rust,
+                            "c" => CodegenType::C,
+                            _ => return Err(format!("unknown backend `{}`", value)),
+                        };
+                    }
+                    (_, value) => command_flags.push((flag, value)),
+                }
+            }
+            Command::Build {
+                path,
+                output,
+                backend,
+            }
+        }
+        "run" => Command::Run {
+            path: expect_path(&mut positional, &command)?,
+        },
+        "debug" => Command::Debug {
+            path: expect_path(&mut positional, &command)?,
+        },
+        "debug-adapter" => Command::DebugAdapter,
+        "repl" => Command::Repl,
+        "ide" => Command::Ide {
+            listen: take_value(&mut command_flags, "--listen"),
+        },
+        "fmt" => Command::Fmt {
+            path: expect_path(&mut positional, &command)?,
+            check: take_switch(&mut command_flags, "--check"),
+        },
+        "dump-hir" => Command::DumpHir {
+            path: expect_path(&mut positional, &command)?,
+            full: take_switch(&mut command_flags, "--full"),
+        },
+        "help" => Command::Help,
+        _ => return Err(format!("unknown command `{}`", command)),
+    };
+
+    if let Some((flag, _)) = command_flags.first() {
+        return Err(format!("unexpected flag `{}` for `{}`", flag, command));
+    }
+
+    if let Some(arg) = positional.next() {
+        return Err(format!("unexpected argument `{}`", arg));
+    }
+
+    Ok(options)
+}
+
+fn expect_path(args: &mut impl Iterator<Item = String>, command: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("`{}` needs the path of a file or project directory", command))
+}
+
+/// Removes the flag `switch` (which takes no value) from `flags`,
+/// returning whether it was there.
+fn take_switch(flags: &mut Vec<(String, Option<String>)>, switch: &str) -> bool {
+    let len = flags.len();
+    flags.retain(|(flag, value)| !(flag == switch && value.is_none()));
+    flags.len() != len
+}
+
+/// Removes the flag `flag` (which takes a value) from `flags`,
+/// returning its last value (if any).
+fn take_value(flags: &mut Vec<(String, Option<String>)>, flag: &str) -> Option<String> {
+    let mut result = None;
+    flags.retain(|(other, value)| match value {
+        Some(value) if other == flag => {
+            result = Some(value.clone());
+            false
+        }
+        _ => true,
+    });
+    result
+}
@@ -0,0 +1,127 @@
+#![cfg(test)]
+
+use crate::args::{parse, Command, MessageFormat, Options};
+use lark_build::CodegenType;
+use termcolor::ColorChoice;
+
+fn parse_str(args: &str) -> Result<Options, String> {
+    parse(args.split_whitespace().map(String::from))
+}
+
+fn command(args: &str) -> Command {
+    parse_str(args).unwrap().command
+}
+
+#[test]
+fn build() {
+    assert_eq!(
+        command("build foo.lark"),
+        Command::Build {
+            path: "foo.lark".into(),
+            output: None,
+            backend: CodegenType::Rust,
+        }
+    );
+    assert_eq!(
+        command("build foo.lark bar"),
+        Command::Build {
+            path: "foo.lark".into(),
+            output: Some("bar".into()),
+            backend: CodegenType::Rust,
+        }
+    );
+    assert_eq!(
+        command("build --backend c foo.lark -o bar"),
+        Command::Build {
+            path: "foo.lark".into(),
+            output: Some("bar".into()),
+            backend: CodegenType::C,
+        }
+    );
+    assert_eq!(
+        command("build foo.lark --output=bar"),
+        Command::Build {
+            path: "foo.lark".into(),
+            output: Some("bar".into()),
+            backend: CodegenType::Rust,
+        }
+    );
+}
+
+#[test]
+fn switches() {
+    assert_eq!(
+        command("fmt --check src"),
+        Command::Fmt {
+            path: "src".into(),
+            check: true,
+        }
+    );
+    assert_eq!(
+        command("dump-hir foo.lark"),
+        Command::DumpHir {
+            path: "foo.lark".into(),
+            full: false,
+        }
+    );
+}
+
+#[test]
+fn ide() {
+    assert_eq!(command("ide"), Command::Ide { listen: None });
+    assert_eq!(
+        command("ide --listen 127.0.0.1:9257"),
+        Command::Ide {
+            listen: Some("127.0.0.1:9257".into()),
+        }
+    );
+}
+
+#[test]
+fn debug_adapter() {
+    assert_eq!(command("debug-adapter"), Command::DebugAdapter);
+}
+
+#[test]
+fn global_flags() {
+    let options = parse_str("--color never check foo.lark --log-level=debug --log-dir logs");
+    let options = options.unwrap();
+    assert_eq!(
+        options,
+        Options {
+            command: Command::Check {
+                path: "foo.lark".into()
+            },
+            color: ColorChoice::Never,
+            message_format: MessageFormat::Human,
+            log_level: Some("debug".into()),
+            log_directory: "logs".into(),
+        }
+    );
+
+    let options = parse_str("check --message-format=json foo.lark");
+    assert_eq!(options.unwrap().message_format, MessageFormat::Json);
+}
+
+#[test]
+fn help() {
+    assert_eq!(command("--help"), Command::Help);
+    assert_eq!(command("run foo.lark -h"), Command::Help);
+    assert_eq!(command("help"), Command::Help);
+}
+
+#[test]
+fn errors() {
+    assert!(parse_str("").is_err());
+    assert!(parse_str("frobnicate").is_err());
+    assert!(parse_str("check").is_err());
+    assert!(parse_str("check foo.lark bar.lark").is_err());
+    assert!(parse_str("run foo.lark --check").is_err());
+    assert!(parse_str("build foo.lark --backend").is_err());
+    assert!(parse_str("ide --listen").is_err());
+    assert!(parse_str("debug-adapter foo.lark").is_err());
+    assert!(parse_str("check foo.lark --listen 127.0.0.1:9257").is_err());
+    assert!(parse_str("build foo.lark --backend llvm").is_err());
+    assert!(parse_str("check foo.lark --color sometimes").is_err());
+    assert!(parse_str("check foo.lark --message-format xml").is_err());
+}
@@ -1,124 +1,277 @@
+use crate::args::{MessageFormat, Options};
+use crate::json::JsonDiagnostic;
+use crate::project;
+use crate::ExitStatus;
 use flexi_logger::{opt_format, Logger};
 use language_reporting::{emit, Diagnostic, Label, Severity};
 use languageserver_types::Position;
 use lark_actor::Actor;
+use lark_build::CodegenType;
 use lark_entity::{EntityData, ItemKind, MemberKind};
 use lark_intern::{Intern, Untern};
 use lark_language_server::{lsp_serve, LspResponder};
 use lark_parser::{ParserDatabase, ParserDatabaseExt};
 use lark_query_system::ls_ops::Cancelled;
 use lark_query_system::ls_ops::LsDatabase;
+use lark_query_system::ls_ops::RangedDiagnostic;
 use lark_query_system::LarkDatabase;
 use lark_query_system::QuerySystem;
 use lark_span::{ByteIndex, FileName, IntoFileName, Span};
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::sync::Arc;
 use std::{env, io};
 use termcolor::{ColorChoice, StandardStream, WriteColor};
 
-pub fn build(file_name: &str, output_file_name: Option<&str>) {
-    let mut file = match File::open(file_name) {
-        Ok(f) => f,
-        Err(err) => {
-            eprintln!("failed to open `{}`: {}", file_name, err);
-            return;
+/// Loads the project at `path` and reports its diagnostics (as
+/// `options.message_format` says), returning the database only if
+/// there are no errors.
+crate fn load_and_check(path: &str, options: &Options) -> Result<LarkDatabase, ExitStatus> {
+    let mut db = LarkDatabase::default();
+    if let Err(err) = project::load_project(&mut db, path) {
+        eprintln!("failed to load `{}`: {}", path, err);
+        return Err(ExitStatus::Failure);
+    }
+
+    let error_count = match options.message_format {
+        MessageFormat::Human => {
+            let writer = StandardStream::stderr(options.color);
+            let mut out = writer.lock();
+            db.display_errors(&mut out)
+        }
+        MessageFormat::Json => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            db.display_errors_json(&mut out)
         }
     };
 
-    let mut contents = String::new();
-    match file.read_to_string(&mut contents) {
-        Ok(_bytes_read) => {}
-        Err(err) => {
-            eprintln!("failed to read `{}`: {}", file_name, err);
-            return;
+    match error_count {
+        Ok(0) => Ok(db),
+        Ok(error_count) => {
+            if options.message_format == MessageFormat::Json {
+                return Err(ExitStatus::Errors);
+            }
+
+            eprintln!(
+                "\n{} error{} found",
+                error_count,
+                if error_count == 1 { "" } else { "s" }
+            );
+            Err(ExitStatus::Errors)
+        }
+        Err(Cancelled) => {
+            eprintln!("checking `{}` was cancelled", path);
+            Err(ExitStatus::Failure)
         }
     }
+}
 
-    let mut db = LarkDatabase::default();
+/// `lark check`: reports the errors in the project at `path`.
+pub fn check(path: &str, options: &Options) -> ExitStatus {
+    match load_and_check(path, options) {
+        Ok(_) => ExitStatus::Success,
+        Err(status) => status,
+    }
+}
 
-    let file_id: FileName = file_name.into_file_name(&db);
-    db.add_file(file_id, contents);
-
-    let writer = StandardStream::stderr(ColorChoice::Auto);
-    let error_count = db
-        .display_errors(&mut writer.lock())
-        .unwrap_or_else(|Cancelled| panic!("cancelled"));
-
-    if error_count == 0 {
-        let out_file_name = if let Some(path) = output_file_name {
-            path.to_string()
-        } else {
-            let file_path = if cfg!(windows) {
-                std::path::Path::new(file_name).with_extension("exe")
-            } else {
-                std::path::Path::new(file_name).with_extension("")
-            };
-
-            file_path.file_name().unwrap().to_str().unwrap().to_string()
-        };
-
-        db.build(&out_file_name)
-            .unwrap_or_else(|Cancelled| panic!("cancelled"));
+pub fn build(
+    path: &str,
+    output_file_name: Option<&str>,
+    codegen_type: CodegenType,
+    options: &Options,
+) -> ExitStatus {
+    let db = match load_and_check(path, options) {
+        Ok(db) => db,
+        Err(status) => return status,
+    };
+
+    let out_file_name = if let Some(path) = output_file_name {
+        path.to_string()
+    } else if cfg!(windows) {
+        format!("{}.exe", project::project_name(path))
+    } else {
+        project::project_name(path)
+    };
+
+    match db.build(&out_file_name, codegen_type) {
+        Ok(()) => ExitStatus::Success,
+        Err(BuildError::Unsupported(errors)) => {
+            let writer = StandardStream::stderr(options.color);
+            for (index, error) in errors.iter().enumerate() {
+                if index > 0 {
+                    eprintln!("");
+                }
+                display_failure(&db, writer.lock(), &error.label, error.span, vec![]);
+            }
+            ExitStatus::Errors
+        }
+        Err(BuildError::Io(err)) => {
+            eprintln!("failed to build `{}`: {}", path, err);
+            ExitStatus::Failure
+        }
     }
 }
 
 pub trait LarkDatabaseExt {
     fn display_errors(&self, out: impl WriteColor) -> Result<usize, Cancelled>;
 
-    /// Build an executable into `output_file_name`.
-    fn build(&self, output_file_name: &str) -> Result<(), Cancelled>;
+    /// Like `display_errors`, but writes each diagnostic to `out` as a
+    /// line of JSON (see the `json` module for the format).
+    fn display_errors_json(&self, out: impl Write) -> Result<usize, Cancelled>;
+
+    /// Build an executable into `output_file_name`, generating code
+    /// with the given backend.
+    fn build(&self, output_file_name: &str, codegen_type: CodegenType) -> Result<(), BuildError>;
+}
+
+/// Why building an executable failed.
+#[derive(Debug)]
+pub enum BuildError {
+    /// The program uses things that the backend can't generate code for.
+    Unsupported(Vec<lark_error::Diagnostic>),
+
+    /// Writing or compiling the generated code failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::Unsupported(errors) => {
+                let labels: Vec<_> = errors.iter().map(|error| &error.label[..]).collect();
+                write!(f, "{}", labels.join("; "))
+            }
+            BuildError::Io(err) => write!(f, "{}", err),
+        }
+    }
 }
 
 impl LarkDatabaseExt for LarkDatabase {
-    fn build(&self, output_file_name: &str) -> Result<(), Cancelled> {
-        let source_file = lark_build::codegen(self, lark_build::CodegenType::Rust);
+    fn build(&self, output_file_name: &str, codegen_type: CodegenType) -> Result<(), BuildError> {
+        let source_file = lark_build::codegen(self, codegen_type);
 
-        lark_build::build(
-            &output_file_name,
-            &source_file.value,
-            lark_build::CodegenType::Rust,
-        )
-        .unwrap();
+        // A backend may not support everything that type checks
+        if !source_file.errors.is_empty() {
+            return Err(BuildError::Unsupported(source_file.errors));
+        }
 
-        Ok(())
+        lark_build::build(&output_file_name, &source_file.value, codegen_type)
+            .map_err(BuildError::Io)
     }
 
-    /// Displays all errors for the project on stderr. Returns `Ok(n)` where
-    /// n is the number of errors (or `Cancelled` if execution is cancelled).
+    /// Displays all diagnostics for the project on stderr. Returns `Ok(n)`
+    /// where n is the number of errors (or `Cancelled` if execution is
+    /// cancelled).
     fn display_errors(&self, mut out: impl WriteColor) -> Result<usize, Cancelled> {
         let db = self;
 
-        let errors = db.errors_for_project()?;
+        let errors = sorted_errors_for_project(db)?;
         let mut first = true;
         let mut error_count = 0;
 
-        for (file_name, ranged_diagnostics) in errors {
-            let file_id: FileName = file_name.into_file_name(&db);
-
+        for (_, ranged_diagnostics) in errors {
             for ranged_diagnostic in ranged_diagnostics {
-                error_count += 1;
+                if ranged_diagnostic.severity == lark_error::Severity::Error {
+                    error_count += 1;
+                }
                 if !std::mem::replace(&mut first, false) {
                     eprintln!("");
                 }
 
-                let range = ranged_diagnostic.range;
-                let error = Diagnostic::new(Severity::Error, ranged_diagnostic.label);
+                display_diagnostic(db, &mut out, ranged_diagnostic);
+            }
+        }
+
+        Ok(error_count)
+    }
 
-                let span = Span::new(
-                    file_id,
-                    db.byte_index(file_id, range.start.line, range.start.character),
-                    db.byte_index(file_id, range.end.line, range.end.character),
-                );
+    fn display_errors_json(&self, mut out: impl Write) -> Result<usize, Cancelled> {
+        let mut error_count = 0;
 
-                let error = error.with_label(Label::new_primary(span));
+        for (file_name, ranged_diagnostics) in sorted_errors_for_project(self)? {
+            for ranged_diagnostic in &ranged_diagnostics {
+                if ranged_diagnostic.severity == lark_error::Severity::Error {
+                    error_count += 1;
+                }
 
-                emit(&mut out, &db, &error, &language_reporting::DefaultConfig).unwrap();
+                let diagnostic = JsonDiagnostic::new(self, &file_name, ranged_diagnostic);
+                serde_json::to_writer(&mut out, &diagnostic).unwrap();
+                writeln!(out).unwrap();
             }
         }
 
         Ok(error_count)
     }
 }
+
+/// Writes `ranged_diagnostic` to `out`, along with the code that it
+/// points at.
+crate fn display_diagnostic(
+    db: &LarkDatabase,
+    mut out: impl WriteColor,
+    ranged_diagnostic: RangedDiagnostic,
+) {
+    let severity = match ranged_diagnostic.severity {
+        lark_error::Severity::Error => Severity::Error,
+        lark_error::Severity::Warning => Severity::Warning,
+        lark_error::Severity::Note => Severity::Note,
+    };
+    let mut error = Diagnostic::new(severity, ranged_diagnostic.label)
+        .with_label(Label::new_primary(ranged_diagnostic.span));
+
+    for label in ranged_diagnostic.secondary_labels {
+        let label = Label::new_secondary(label.span).with_message(label.label);
+        error = error.with_label(label);
+    }
+
+    emit(&mut out, &db, &error, &language_reporting::DefaultConfig).unwrap();
+}
+
+/// Writes `error` to `out` like a compile error, with a label on each
+/// call that led to it.
+crate fn display_runtime_error(
+    db: &LarkDatabase,
+    out: impl WriteColor,
+    error: &lark_eval::RuntimeError,
+) {
+    let calls = error.call_stack.iter().map(|frame| {
+        let name = lark_eval::debugger::function_name(db, frame.function);
+        (name.to_string(), frame.span)
+    });
+    display_failure(db, out, &error.message, error.span, calls);
+}
+
+/// Writes the failure of the part of the program at `span` to `out`
+/// like a compile error, with a label on each of the `calls` (the name
+/// of the function called, and the call) that led to it.
+crate fn display_failure(
+    db: &LarkDatabase,
+    mut out: impl WriteColor,
+    message: &str,
+    span: Span<FileName>,
+    calls: impl IntoIterator<Item = (String, Span<FileName>)>,
+) {
+    let mut diagnostic =
+        Diagnostic::new(Severity::Error, message).with_label(Label::new_primary(span));
+
+    for (name, span) in calls {
+        let label = Label::new_secondary(span).with_message(format!("in this call to `{}`", name));
+        diagnostic = diagnostic.with_label(label);
+    }
+
+    emit(&mut out, &db, &diagnostic, &language_reporting::DefaultConfig).unwrap();
+}
+
+/// The diagnostics for each file of the project, ordered by file name
+/// so that they are always reported in the same order.
+fn sorted_errors_for_project(
+    db: &LarkDatabase,
+) -> Result<Vec<(String, Vec<RangedDiagnostic>)>, Cancelled> {
+    let mut errors: Vec<_> = db.errors_for_project()?.into_iter().collect();
+    errors.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(errors)
+}
@@ -0,0 +1,251 @@
+//! `lark debug`: runs a program in the interpreter, pausing at
+//! breakpoints and after each step to take commands from stdin. Also
+//! `lark debug-adapter`, which does the same for an IDE.
+
+use crate::args::Options;
+use crate::build::{self, display_runtime_error, LarkDatabaseExt};
+use crate::project;
+use crate::ExitStatus;
+use lark_debug_adapter::dap_serve;
+use lark_eval::debugger::{self, function_name, Debugger, Frame, StepMode, Stepper};
+use lark_eval::{EvalState, IOHandler};
+use lark_hir as hir;
+use lark_language_server::Connection;
+use lark_parser::ParserDatabase;
+use lark_query_system::ls_ops::Cancelled;
+use lark_query_system::LarkDatabase;
+use lark_span::{FileName, IntoFileName};
+use std::io::{stdin, stdout, Write};
+use termcolor::{NoColor, StandardStream};
+
+const HELP: &str = "\
+Commands available:
+  break [<file>:]<line>   (b)  pause whenever the program reaches the given line
+  delete [<file>:]<line>  (d)  remove a breakpoint
+  continue                (c)  run until the next breakpoint
+  step                    (s)  run until the next line, stepping into calls
+  next                    (n)  run until the next line, stepping over calls
+  finish                  (f)  run until the current call returns
+  print [<variable>]      (p)  print the variables of the current call (or just one)
+  backtrace               (bt) print the calls that are in progress
+  quit                    (q)  stop the program";
+
+pub fn debug(path: &str, options: &Options) -> ExitStatus {
+    let db = match build::load_and_check(path, options) {
+        Ok(db) => db,
+        Err(status) => return status,
+    };
+
+    println!("Lark debugger (`help` lists the commands)");
+
+    let mut state = EvalState::new();
+    state.debugger = Some(Box::new(CommandLineDebugger {
+        stepper: Stepper::new(),
+    }));
+
+    match lark_eval::eval_with_state(&db, &mut state, &mut IOHandler::new(false)) {
+        Ok(()) => {
+            println!("The program finished");
+            ExitStatus::Success
+        }
+        Err(error) => {
+            let writer = StandardStream::stderr(options.color);
+            display_runtime_error(&db, &mut writer.lock(), &error);
+            ExitStatus::Errors
+        }
+    }
+}
+
+/// `lark debug-adapter`: lets an IDE debug programs over the Debug
+/// Adapter Protocol, on stdin and stdout. Succeeds if the IDE
+/// disconnected, rather than going away.
+pub fn debug_adapter() -> ExitStatus {
+    let connection = Connection::stdio();
+    if dap_serve(connection.reader, connection.writer, load_program) {
+        ExitStatus::Success
+    } else {
+        ExitStatus::Errors
+    }
+}
+
+/// Loads the program that the IDE launches, giving back its
+/// diagnostics (as text) if it has errors.
+fn load_program(path: &str) -> Result<LarkDatabase, String> {
+    let mut db = LarkDatabase::default();
+    if let Err(err) = project::load_project(&mut db, path) {
+        return Err(format!("failed to load `{}`: {}", path, err));
+    }
+
+    let mut out = NoColor::new(vec![]);
+    match db.display_errors(&mut out) {
+        Ok(0) => Ok(db),
+        Ok(_) => Err(String::from_utf8_lossy(&out.into_inner()).into_owned()),
+        Err(Cancelled) => Err(format!("checking `{}` was cancelled", path)),
+    }
+}
+
+struct CommandLineDebugger {
+    stepper: Stepper,
+}
+
+impl Debugger for CommandLineDebugger {
+    fn before_expression(
+        &mut self,
+        db: &LarkDatabase,
+        fn_body: &hir::FnBody,
+        expression: hir::Expression,
+        state: &EvalState,
+    ) {
+        if self
+            .stepper
+            .should_pause(db, fn_body, expression, state)
+            .is_none()
+        {
+            return;
+        }
+
+        let frames = debugger::backtrace(state, fn_body, expression);
+        print_frame(db, &frames[0], None);
+
+        loop {
+            print!("(debug) ");
+            let _ = stdout().flush();
+
+            let mut input = String::new();
+            match stdin().read_line(&mut input) {
+                Ok(0) | Err(_) => quit(),
+                Ok(_) => {}
+            }
+
+            let mut words = input.split_whitespace();
+            let command = match words.next() {
+                Some(command) => command,
+                None => continue,
+            };
+            let argument = words.next();
+
+            let mode = match (command, argument) {
+                ("continue", None) | ("c", None) => StepMode::Continue,
+                ("step", None) | ("s", None) => StepMode::StepIn,
+                ("next", None) | ("n", None) => StepMode::StepOver,
+                ("finish", None) | ("f", None) => StepMode::StepOut,
+                ("break", Some(location)) | ("b", Some(location)) => {
+                    if let Some((file, line)) = parse_location(db, location, &frames[0]) {
+                        if self.stepper.add_breakpoint(file, line) {
+                            println!("Breakpoint at {}:{}", file.untern(db), line + 1);
+                        } else {
+                            println!("There is already a breakpoint there");
+                        }
+                    }
+                    continue;
+                }
+                ("delete", Some(location)) | ("d", Some(location)) => {
+                    if let Some((file, line)) = parse_location(db, location, &frames[0]) {
+                        if !self.stepper.remove_breakpoint(file, line) {
+                            println!("There is no breakpoint there");
+                        }
+                    }
+                    continue;
+                }
+                ("print", name) | ("p", name) => {
+                    print_variables(db, state, &frames[0], name);
+                    continue;
+                }
+                ("backtrace", None) | ("bt", None) => {
+                    for (index, frame) in frames.iter().enumerate() {
+                        print_frame(db, frame, Some(index));
+                    }
+                    continue;
+                }
+                ("quit", None) | ("q", None) => quit(),
+                ("help", None) | ("?", None) => {
+                    println!("{}", HELP);
+                    continue;
+                }
+                _ => {
+                    println!(
+                        "Unknown command `{}` (`help` lists the commands)",
+                        input.trim()
+                    );
+                    continue;
+                }
+            };
+
+            self.stepper.resume(mode, state);
+            return;
+        }
+    }
+}
+
+/// Stops the program (and `lark`) at the user's request.
+fn quit() -> ! {
+    std::process::exit(ExitStatus::Success as i32)
+}
+
+/// Prints the function and line that `frame` is at, along with the
+/// text of the line. Frames in a backtrace are numbered by `index`.
+fn print_frame(db: &LarkDatabase, frame: &Frame, index: Option<usize>) {
+    let file = frame.span.file();
+    let line = db.location(file, frame.span.start()).line;
+
+    if let Some(index) = index {
+        print!("#{} ", index);
+    }
+    println!(
+        "{} at {}:{}",
+        function_name(db, frame.function),
+        file.untern(db),
+        line + 1
+    );
+
+    if index.is_none() {
+        let text = db.file_text(file);
+        println!("{:>4} | {}", line + 1, text.lines().nth(line).unwrap_or(""));
+    }
+}
+
+/// Prints the variables of `frame` (or, if a `name` is given, just
+/// that one).
+fn print_variables(db: &LarkDatabase, state: &EvalState, frame: &Frame, name: Option<&str>) {
+    let variables = debugger::variables(db, state, frame);
+
+    match name {
+        Some(name) => match variables
+            .iter()
+            .find(|(variable, _)| variable.as_ref() == name)
+        {
+            Some((_, value)) => println!("{}", value),
+            None => println!("There is no variable `{}` here", name),
+        },
+        None if variables.is_empty() => println!("There are no variables here"),
+        None => {
+            for (variable, value) in variables {
+                println!("{} = {}", variable, value);
+            }
+        }
+    }
+}
+
+/// Parses the `[<file>:]<line>` of a breakpoint, returning the file
+/// (by default, the one that `frame` is in) and the 0-based line.
+fn parse_location(db: &LarkDatabase, location: &str, frame: &Frame) -> Option<(FileName, usize)> {
+    let (file, line) = match location.rfind(':') {
+        Some(index) => {
+            let file = (&location[..index]).into_file_name(db);
+            if !db.file_names().contains(&file) {
+                println!("There is no file `{}`", &location[..index]);
+                return None;
+            }
+            (file, &location[index + 1..])
+        }
+        None => (frame.span.file(), location),
+    };
+
+    match line.parse::<usize>() {
+        Ok(line) if line > 0 => Some((file, line - 1)),
+        _ => {
+            println!("`{}` is not a line number", line);
+            None
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Prints the HIR of each function in a project, annotated with the
+//! results of type checking it. This is used by `lark dump-hir` and by
+//! the `//~ hir` test directive.
+
+use crate::project;
+use crate::ExitStatus;
+use lark_debug_with::DebugWith;
+use lark_entity::Entity;
+use lark_entity::EntityData;
+use lark_entity::EntityTables;
+use lark_entity::ItemKind;
+use lark_entity::MemberKind;
+use lark_hir as hir;
+use lark_intern::Intern;
+use lark_intern::Untern;
+use lark_parser::ParserDatabase;
+use lark_query_system::LarkDatabase;
+use lark_string::GlobalIdentifierTables;
+use lark_ty::base_inferred::BaseInferredTables;
+use lark_ty::full_inferred::FullInferredTables;
+use lark_ty::TypeFamily;
+use lark_type_check::TypeCheckDatabase;
+use lark_type_check::TypeCheckResults;
+use std::fmt::Write;
+
+mod test;
+
+/// Which type-check results to include.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HirMode {
+    Base,
+    Full,
+}
+
+/// `lark dump-hir`: prints the HIR of the project at `path` to stdout.
+/// Unlike the other commands, this doesn't stop at errors, since the
+/// HIR is often most interesting when there are some.
+crate fn dump_hir_command(path: &str, full: bool) -> ExitStatus {
+    let mut db = LarkDatabase::default();
+    if let Err(err) = project::load_project(&mut db, path) {
+        eprintln!("failed to load `{}`: {}", path, err);
+        return ExitStatus::Failure;
+    }
+
+    let mode = if full { HirMode::Full } else { HirMode::Base };
+    print!("{}", dump_hir(&db, mode));
+
+    ExitStatus::Success
+}
+
+pub fn dump_hir(db: &LarkDatabase, mode: HirMode) -> String {
+    let input_files = db.file_names();
+
+    let mut text = String::new();
+
+    for &input_file in &*input_files {
+        let file_entity = EntityData::InputFile { file: input_file }.intern(db);
+        for &entity in db.descendant_entities(file_entity).iter() {
+            let has_hir = match entity.untern(db) {
+                EntityData::ItemName {
+                    kind: ItemKind::Function,
+                    ..
+                }
+                | EntityData::MemberName {
+                    kind: MemberKind::Method,
+                    ..
+                } => true,
+                _ => false,
+            };
+
+            if !has_hir {
+                continue;
+            }
+
+            writeln!(text, "{:?}", entity.debug_with(db)).unwrap();
+            writeln!(text, "{}", dump_fn_hir(db, entity, mode)).unwrap();
+        }
+    }
+
+    text
+}
+
+/// The HIR of the function (or method) `entity`.
+pub fn dump_fn_hir(db: &LarkDatabase, entity: Entity, mode: HirMode) -> String {
+    let fn_body = &db.fn_body(entity).into_value();
+
+    match mode {
+        HirMode::Base => {
+            let results = &db.base_type_check(entity).into_value();
+            let info = BaseInfo {
+                db,
+                fn_body,
+                results,
+            };
+            format!("{:#?}", &fn_body.debug_with(&info))
+        }
+        HirMode::Full => {
+            let results = &db.full_type_check(entity).into_value();
+            let info = BaseInfo {
+                db,
+                fn_body,
+                results,
+            };
+            format!("{:#?}", &fn_body.debug_with(&info))
+        }
+    }
+}
+
+struct BaseInfo<'me, F>
+where
+    F: TypeFamily,
+{
+    db: &'me LarkDatabase,
+    fn_body: &'me hir::FnBody,
+    results: &'me TypeCheckResults<F>,
+}
+
+impl<F> AsRef<hir::FnBodyTables> for BaseInfo<'_, F>
+where
+    F: TypeFamily,
+{
+    fn as_ref(&self) -> &hir::FnBodyTables {
+        self.fn_body.as_ref()
+    }
+}
+
+impl<F> AsRef<EntityTables> for BaseInfo<'_, F>
+where
+    F: TypeFamily,
+{
+    fn as_ref(&self) -> &EntityTables {
+        self.db.as_ref()
+    }
+}
+
+impl<F> AsRef<GlobalIdentifierTables> for BaseInfo<'_, F>
+where
+    F: TypeFamily,
+{
+    fn as_ref(&self) -> &GlobalIdentifierTables {
+        self.db.as_ref()
+    }
+}
+
+impl<F> AsRef<BaseInferredTables> for BaseInfo<'_, F>
+where
+    F: TypeFamily,
+{
+    fn as_ref(&self) -> &BaseInferredTables {
+        self.db.as_ref()
+    }
+}
+
+impl<F> AsRef<FullInferredTables> for BaseInfo<'_, F>
+where
+    F: TypeFamily,
+{
+    fn as_ref(&self) -> &FullInferredTables {
+        self.db.as_ref()
+    }
+}
+
+impl<F> hir::FnBodyExtraDebug for BaseInfo<'_, F>
+where
+    F: TypeFamily,
+{
+    fn extended_debug_with(
+        &self,
+        index: hir::MetaIndex,
+        debug: &mut std::fmt::DebugStruct<'_, '_>,
+    ) -> std::fmt::Result {
+        if let Some(v) = self.results.max_types.get(&index) {
+            debug.field("max_types", &v.debug_with(self.db));
+        }
+
+        if let Some(v) = self.results.generics.get(&index) {
+            debug.field("generics", &v.debug_with(self.db));
+        }
+
+        if let Some(v) = self.results.entities.get(&index) {
+            debug.field("entity", &v.debug_with(self.db));
+        }
+
+        if let hir::MetaIndex::Expression(e) = index {
+            if let Some(v) = self.results.access_types.get(&e) {
+                debug.field("access_types", &v.debug_with(self.db));
+            }
+
+            if let Some(v) = self.results.access_permissions.get(&e) {
+                debug.field("access_permissions", &v.debug_with(self.db));
+            }
+        }
+
+        Ok(())
+    }
+}
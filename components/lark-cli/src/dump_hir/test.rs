@@ -0,0 +1,75 @@
+#![cfg(test)]
+
+use crate::dump_hir::{dump_fn_hir, dump_hir, HirMode};
+use lark_entity::{Entity, EntityData, MemberKind};
+use lark_intern::{Intern, Untern};
+use lark_parser::{ParserDatabase, ParserDatabaseExt};
+use lark_query_system::LarkDatabase;
+
+const TEXT: &str = "\
+struct Counter {
+  value: uint,
+
+  def share get() -> uint {
+    self.value
+  }
+}
+
+def main() {
+  let counter = Counter(value: 22)
+  debug(counter.get())
+}
+";
+
+fn db() -> LarkDatabase {
+    let mut db = LarkDatabase::default();
+    db.add_file("counter.lark", TEXT);
+    db
+}
+
+fn method(db: &LarkDatabase) -> Entity {
+    let file_entity = EntityData::InputFile {
+        file: db.file_names()[0],
+    }
+    .intern(db);
+    db.descendant_entities(file_entity)
+        .iter()
+        .cloned()
+        .find(|entity| match entity.untern(db) {
+            EntityData::MemberName {
+                kind: MemberKind::Method,
+                ..
+            } => true,
+            _ => false,
+        })
+        .unwrap()
+}
+
+#[test]
+fn method_hir() {
+    let db = db();
+    let hir = dump_fn_hir(&db, method(&db), HirMode::Base);
+    assert!(hir.starts_with("FnBody {"), "{}", hir);
+    assert!(hir.contains("text: \"self\""), "{}", hir);
+    assert!(hir.contains("text: \"value\""), "{}", hir);
+}
+
+#[test]
+fn dump_hir_includes_each_function() {
+    let db = db();
+    for &mode in &[HirMode::Base, HirMode::Full] {
+        let text = dump_hir(&db, mode);
+        assert!(text.contains(&dump_fn_hir(&db, method(&db), mode)));
+        assert_eq!(text.matches("FnBody {").count(), 2, "{}", text);
+    }
+}
+
+#[test]
+fn full_types() {
+    // Only full type checking infers permissions
+    let db = db();
+    let base = dump_fn_hir(&db, method(&db), HirMode::Base);
+    let full = dump_fn_hir(&db, method(&db), HirMode::Full);
+    assert!(base.contains("perm: Erased"), "{}", base);
+    assert!(!full.contains("perm: Erased"), "{}", full);
+}
@@ -0,0 +1,94 @@
+//! `lark fmt`, which gives Lark files their canonical layout (see the
+//! `lark_fmt` crate for what that is).
+
+use crate::args::{MessageFormat, Options};
+use crate::build::display_diagnostic;
+use crate::json::JsonDiagnostic;
+use crate::project;
+use crate::ExitStatus;
+use lark_error::Diagnostic;
+use lark_parser::ParserDatabase;
+use lark_query_system::ls_ops::LsDatabase;
+use lark_query_system::LarkDatabase;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use termcolor::StandardStream;
+
+/// `lark fmt`: formats the file at `path`, or each `.lark` file beneath
+/// the directory at `path`. With `check`, the files are left as they
+/// are, and the ones that are not formatted are listed on stdout.
+pub fn fmt(path: &str, check: bool, options: &Options) -> ExitStatus {
+    let files = match project::lark_files(path) {
+        Ok(files) => files,
+        Err(err) => {
+            eprintln!("failed to load `{}`: {}", path, err);
+            return ExitStatus::Failure;
+        }
+    };
+
+    let mut db = LarkDatabase::default();
+    let mut status = ExitStatus::Success;
+
+    for file in files {
+        let file_name = match project::add_file(&mut db, &file) {
+            Ok(file_name) => file_name,
+            Err(err) => {
+                eprintln!("failed to load `{}`: {}", file.display(), err);
+                return ExitStatus::Failure;
+            }
+        };
+
+        match lark_fmt::format_edits(&db, file_name) {
+            Ok(ref edits) if edits.is_empty() => {}
+            Ok(edits) => {
+                if check {
+                    println!("{}", file.display());
+                    status = ExitStatus::Errors;
+                } else {
+                    let formatted = lark_fmt::apply_edits(&db.file_text(file_name), &edits);
+                    if let Err(err) = fs::write(&file, formatted) {
+                        eprintln!("failed to write `{}`: {}", file.display(), err);
+                        return ExitStatus::Failure;
+                    }
+                }
+            }
+            Err(errors) => {
+                display_syntax_errors(&db, &file, &errors, options);
+                status = ExitStatus::Errors;
+            }
+        }
+    }
+
+    status
+}
+
+/// Reports the syntax errors that keep `file` from being formatted.
+fn display_syntax_errors(
+    db: &LarkDatabase,
+    file: &Path,
+    errors: &[Diagnostic],
+    options: &Options,
+) {
+    match options.message_format {
+        MessageFormat::Human => {
+            let writer = StandardStream::stderr(options.color);
+            for error in errors {
+                display_diagnostic(db, writer.lock(), db.ranged_diagnostic(error));
+                eprintln!("");
+            }
+            eprintln!("cannot format `{}` because of syntax errors", file.display());
+        }
+        MessageFormat::Json => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            let file = file.to_string_lossy();
+            for error in errors {
+                let ranged_diagnostic = db.ranged_diagnostic(error);
+                let diagnostic = JsonDiagnostic::new(db, &file, &ranged_diagnostic);
+                serde_json::to_writer(&mut out, &diagnostic).unwrap();
+                writeln!(out).unwrap();
+            }
+        }
+    }
+}
@@ -1,11 +1,37 @@
+use crate::ExitStatus;
 use lark_actor::{spawn_actor, Actor, LspResponse, QueryRequest};
-use lark_language_server::{lsp_serve, LspResponder};
+use lark_language_server::{lsp_serve, Connection, LspResponder};
 use lark_query_system::QuerySystem;
+use std::net::TcpListener;
 use std::sync::mpsc::{channel, Receiver, RecvError, Sender, TryRecvError};
 
-pub fn ide() {
-    let lsp_responder = spawn_actor(LspResponder);
-    let query_system = spawn_actor(QuerySystem::new(lsp_responder.channel));
+/// Serves an IDE over stdin and stdout or -- given an address to
+/// `listen` on -- over the first TCP connection to that address.
+/// Succeeds if the IDE shut us down before telling us to exit.
+pub fn ide(listen: Option<&str>) -> ExitStatus {
+    let connection = match listen {
+        None => Connection::stdio(),
+        Some(address) => {
+            let connection = TcpListener::bind(address).and_then(|listener| {
+                eprintln!("Listening on {}", listener.local_addr()?);
+                Connection::accept(&listener)
+            });
+            match connection {
+                Ok(connection) => connection,
+                Err(error) => {
+                    eprintln!("error: cannot serve on `{}`: {}", address, error);
+                    return ExitStatus::Failure;
+                }
+            }
+        }
+    };
 
-    lsp_serve(query_system.channel);
+    let lsp_responder = spawn_actor(LspResponder::new(connection.writer));
+    let query_system = spawn_actor(QuerySystem::new(lsp_responder.channel.clone()));
+
+    if lsp_serve(connection.reader, query_system.channel, lsp_responder.channel) {
+        ExitStatus::Success
+    } else {
+        ExitStatus::Errors
+    }
 }
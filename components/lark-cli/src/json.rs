@@ -0,0 +1,117 @@
+//! The JSON form of a diagnostic, as printed by
+//! `--message-format json`. Each diagnostic is one object on a line of
+//! its own, like:
+//!
+//! ```json
+//! {"file":"foo.lark","severity":"error","message":"mismatched types (uint vs bool)",
+//!  "span":{"start":38,"end":39},
+//!  "range":{"start":{"line":2,"column":3},"end":{"line":2,"column":4}},
+//!  "labels":[]}
+//! ```
+//!
+//! (but without the line breaks). The `span` is in bytes from the start
+//! of the file. The `range` is the same part of the file, with lines
+//! and columns that start from 1; columns count characters (Unicode
+//! scalar values), just as in the human-readable output. Each of the
+//! `labels` has a `message`, `span` and `range` of its own.
+
+use lark_error::Severity;
+use lark_parser::ParserDatabase;
+use lark_query_system::ls_ops::{RangedDiagnostic, RangedLabel};
+use lark_query_system::LarkDatabase;
+use lark_span::{ByteIndex, FileName, Span};
+use serde::Serialize;
+
+#[derive(Serialize)]
+crate struct JsonDiagnostic<'me> {
+    file: &'me str,
+    severity: &'static str,
+    message: &'me str,
+    span: JsonSpan,
+    range: JsonRange,
+    labels: Vec<JsonLabel<'me>>,
+}
+
+#[derive(Serialize)]
+struct JsonLabel<'me> {
+    message: &'me str,
+    span: JsonSpan,
+    range: JsonRange,
+}
+
+#[derive(Serialize)]
+struct JsonSpan {
+    start: usize,
+    end: usize,
+}
+
+#[derive(Serialize)]
+struct JsonRange {
+    start: JsonPosition,
+    end: JsonPosition,
+}
+
+#[derive(Serialize)]
+struct JsonPosition {
+    line: u64,
+    column: u64,
+}
+
+impl JsonDiagnostic<'me> {
+    crate fn new(db: &LarkDatabase, file: &'me str, diagnostic: &'me RangedDiagnostic) -> Self {
+        JsonDiagnostic {
+            file,
+            severity: match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note => "note",
+            },
+            message: &diagnostic.label,
+            span: JsonSpan::new(diagnostic.span),
+            range: JsonRange::new(db, diagnostic.span),
+            labels: diagnostic
+                .secondary_labels
+                .iter()
+                .map(|label| JsonLabel::new(db, label))
+                .collect(),
+        }
+    }
+}
+
+impl JsonLabel<'me> {
+    fn new(db: &LarkDatabase, label: &'me RangedLabel) -> Self {
+        JsonLabel {
+            message: &label.label,
+            span: JsonSpan::new(label.span),
+            range: JsonRange::new(db, label.span),
+        }
+    }
+}
+
+impl JsonSpan {
+    fn new(span: Span<FileName>) -> Self {
+        JsonSpan {
+            start: span.start().to_usize(),
+            end: span.end().to_usize(),
+        }
+    }
+}
+
+impl JsonRange {
+    fn new(db: &LarkDatabase, span: Span<FileName>) -> Self {
+        JsonRange {
+            start: JsonPosition::new(db, span.file(), span.start()),
+            end: JsonPosition::new(db, span.file(), span.end()),
+        }
+    }
+}
+
+impl JsonPosition {
+    fn new(db: &LarkDatabase, file: FileName, index: ByteIndex) -> Self {
+        let location = db.location(file, index);
+        JsonPosition {
+            line: location.line as u64 + 1,
+            column: location.column as u64 + 1,
+        }
+    }
+}
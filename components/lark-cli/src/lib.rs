@@ -9,41 +9,87 @@
 #![feature(const_fn)]
 #![feature(try_from)]
 #![feature(trace_macros)]
+#![feature(specialization)]
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+use crate::args::Command;
 use flexi_logger::{opt_format, Logger};
 use std::{env, io};
 
+pub mod args;
 pub mod build;
+mod debug;
+pub mod dump_hir;
+mod fmt;
 mod ide;
+mod json;
+mod line_editor;
+mod project;
 mod repl;
 mod run;
 
+/// The exit status of `lark`, so that scripts (and CI) can tell what
+/// happened.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success = 0,
+
+    /// The program has errors or fails at runtime (or, for `lark fmt
+    /// --check`, is not formatted; or, for `lark ide`, the IDE went away
+    /// without shutting down the server).
+    Errors = 1,
+
+    /// The command line is invalid.
+    Usage = 2,
+
+    /// Something else went wrong, like failing to read the program or
+    /// to compile the generated code.
+    Failure = 3,
+}
+
 pub fn main() {
-    Logger::with_env_or_str("error,lark_query_system=info")
+    let options = match args::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, args::USAGE);
+            std::process::exit(ExitStatus::Usage as i32);
+        }
+    };
+
+    let logger = match &options.log_level {
+        Some(log_level) => Logger::with_str(log_level),
+        None => Logger::with_env_or_str("error,lark_query_system=info"),
+    };
+    logger
         .log_to_file()
-        .directory("log_files")
+        .directory(options.log_directory.clone())
         .format(opt_format)
         .start()
         .unwrap_or_else(|e| panic!("Logger initialization failed with {}", e));
 
-    let mut args = std::env::args();
-
-    match (args.next(), args.next(), args.next(), args.next()) {
-        (_, Some(ref cmd), Some(ref x), Some(ref out)) if cmd == "build" => {
-            build::build(x, Some(out))
+    let status = match &options.command {
+        Command::Check { path } => build::check(path, &options),
+        Command::Build {
+            path,
+            output,
+            backend,
+        } => build::build(path, output.as_ref().map(|s| &s[..]), *backend, &options),
+        Command::Run { path } => run::run(path, &options),
+        Command::Debug { path } => debug::debug(path, &options),
+        Command::DebugAdapter => debug::debug_adapter(),
+        Command::Repl => {
+            repl::repl();
+            ExitStatus::Success
         }
-        (_, Some(ref cmd), Some(ref x), None) if cmd == "build" => build::build(x, None),
-        (_, Some(ref cmd), Some(ref x), None) if cmd == "run" => run::run(x),
-        (_, Some(ref cmd), None, None) if cmd == "repl" => repl::repl(),
-        (_, Some(ref cmd), None, None) if cmd == "ide" => ide::ide(),
-        _ => {
-            println!("Usage:");
-            println!("  lark build <file> [<output>] - compiles the given file");
-            println!("  lark run <file>              - runs the given file");
-            println!("  lark repl                    - REPL/interactive mode");
-            println!("  lark ide                     - run the Lark languge server/IDE support");
+        Command::Ide { listen } => ide::ide(listen.as_ref().map(|s| &s[..])),
+        Command::Fmt { path, check } => fmt::fmt(path, *check, &options),
+        Command::DumpHir { path, full } => dump_hir::dump_hir_command(path, *full),
+        Command::Help => {
+            println!("{}", args::USAGE);
+            ExitStatus::Success
         }
-    }
+    };
+
+    std::process::exit(status as i32);
 }
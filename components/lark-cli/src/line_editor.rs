@@ -0,0 +1,497 @@
+//! The line editor of the REPL. On a terminal, it lets the user move
+//! around and edit an input (which may span several lines), recall
+//! earlier inputs (which are kept in a history file between sessions)
+//! and complete names with tab. Otherwise, it just reads lines.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, stdin, stdout, Write};
+use std::path::PathBuf;
+
+mod test;
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+
+/// The most inputs that the history file keeps.
+const HISTORY_LIMIT: usize = 1000;
+
+/// What the line editor needs to know about the language.
+pub trait Helper {
+    /// Whether `input` continues onto the next line.
+    fn is_incomplete(&self, input: &str) -> bool;
+
+    /// The names that could complete the one that ends at byte `cursor`
+    /// of `input`, along with the byte at which that name starts.
+    fn complete(&mut self, input: &str, cursor: usize) -> (usize, Vec<String>);
+}
+
+pub struct LineEditor {
+    /// Earlier inputs, oldest first.
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+
+    /// Whether stdin is a terminal (rather than, say, a pipe).
+    interactive: bool,
+}
+
+impl LineEditor {
+    /// Creates a line editor that keeps its history in `history_file`
+    /// (if any, and if stdin is a terminal).
+    pub fn new(history_file: Option<PathBuf>) -> LineEditor {
+        let interactive = is_terminal();
+        let history_file = history_file.filter(|_| interactive);
+        let history = match &history_file {
+            Some(path) => load_history(path),
+            None => vec![],
+        };
+
+        LineEditor {
+            history,
+            history_file,
+            interactive,
+        }
+    }
+
+    /// The default history file, `.lark_history` in the home directory.
+    pub fn default_history_file() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".lark_history"))
+    }
+
+    /// Reads the next input, which continues onto further lines while
+    /// `helper` says it is incomplete, or until a blank line. Returns
+    /// `None` at the end of stdin.
+    pub fn read(&mut self, helper: &mut impl Helper) -> Option<String> {
+        let result = if self.interactive {
+            self.read_from_terminal(helper)
+        } else {
+            read_lines(helper)
+        };
+
+        match result {
+            Ok(input) => input,
+            Err(error) => {
+                eprintln!("error: could not read input: {}", error);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn read_from_terminal(&mut self, helper: &mut impl Helper) -> io::Result<Option<String>> {
+        read_lines(helper)
+    }
+
+    #[cfg(unix)]
+    fn read_from_terminal(&mut self, helper: &mut impl Helper) -> io::Result<Option<String>> {
+        use termion::event::Key;
+        use termion::input::TermRead;
+        use termion::raw::IntoRawMode;
+
+        let mut out = stdout().into_raw_mode()?;
+        let mut screen = Screen::default();
+        let mut buffer = Buffer::default();
+
+        // Where we are in the history; the input being written (rather
+        // than recalled) is at `history.len()`, and kept in `draft`.
+        let mut history_index = self.history.len();
+        let mut draft = String::new();
+
+        screen.draw(&mut out, &buffer)?;
+
+        let stdin = stdin();
+        for key in stdin.lock().keys() {
+            match key? {
+                Key::Char('\n') => {
+                    let blank_line = buffer.cursor == buffer.text.len()
+                        && (buffer.text.is_empty() || buffer.text.ends_with('\n'));
+                    if blank_line || !helper.is_incomplete(&buffer.text) {
+                        buffer.cursor = buffer.text.len();
+                        screen.draw(&mut out, &buffer)?;
+                        writeln!(out, "\r")?;
+                        out.flush()?;
+
+                        self.add_to_history(&buffer.text);
+                        return Ok(Some(buffer.text));
+                    }
+                    buffer.insert("\n");
+                }
+                Key::Char('\t') => {
+                    let (start, mut candidates) = helper.complete(&buffer.text, buffer.cursor);
+                    candidates.dedup();
+
+                    let typed = buffer.cursor - start;
+                    let common = common_prefix(&candidates);
+                    if common.len() > typed {
+                        buffer.text.replace_range(start..buffer.cursor, common);
+                        buffer.cursor = start + common.len();
+                    } else if candidates.len() > 1 {
+                        screen.list(&mut out, &buffer, &candidates)?;
+                    } else if candidates.is_empty() {
+                        write!(out, "\x07")?;
+                    }
+                }
+                Key::Char(c) => buffer.insert(c.encode_utf8(&mut [0; 4])),
+                Key::Backspace => {
+                    if let Some(c) = buffer.text[..buffer.cursor].chars().next_back() {
+                        buffer.cursor -= c.len_utf8();
+                        buffer.text.remove(buffer.cursor);
+                    }
+                }
+                Key::Ctrl('d') if buffer.text.is_empty() => {
+                    writeln!(out, "\r")?;
+                    return Ok(None);
+                }
+                Key::Delete | Key::Ctrl('d') => {
+                    if buffer.cursor < buffer.text.len() {
+                        buffer.text.remove(buffer.cursor);
+                    }
+                }
+                Key::Ctrl('c') => {
+                    // Abandon the input, leaving it on the screen.
+                    buffer.cursor = buffer.text.len();
+                    screen.draw(&mut out, &buffer)?;
+                    writeln!(out, "^C\r")?;
+
+                    screen = Screen::default();
+                    buffer = Buffer::default();
+                    history_index = self.history.len();
+                }
+                Key::Left | Key::Ctrl('b') => {
+                    if let Some(c) = buffer.text[..buffer.cursor].chars().next_back() {
+                        buffer.cursor -= c.len_utf8();
+                    }
+                }
+                Key::Right | Key::Ctrl('f') => {
+                    if let Some(c) = buffer.text[buffer.cursor..].chars().next() {
+                        buffer.cursor += c.len_utf8();
+                    }
+                }
+                Key::Home | Key::Ctrl('a') => buffer.cursor = buffer.line_start(),
+                Key::End | Key::Ctrl('e') => buffer.cursor = buffer.line_end(),
+                Key::Ctrl('k') => {
+                    let end = buffer.line_end();
+                    buffer.text.replace_range(buffer.cursor..end, "");
+                }
+                Key::Ctrl('u') => {
+                    let start = buffer.line_start();
+                    buffer.text.replace_range(start..buffer.cursor, "");
+                    buffer.cursor = start;
+                }
+                Key::Ctrl('w') => {
+                    let before = &buffer.text[..buffer.cursor];
+                    let start = before
+                        .trim_end_matches(|c: char| c == ' ')
+                        .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_')
+                        .len();
+                    buffer.text.replace_range(start..buffer.cursor, "");
+                    buffer.cursor = start;
+                }
+
+                // Up and down move between the lines of the input, and
+                // beyond them, through the history.
+                Key::Up | Key::Ctrl('p') => {
+                    if !buffer.move_up() && history_index > 0 {
+                        if history_index == self.history.len() {
+                            draft = buffer.text.clone();
+                        }
+                        history_index -= 1;
+                        buffer = Buffer::new(self.history[history_index].clone());
+                    }
+                }
+                Key::Down | Key::Ctrl('n') => {
+                    if !buffer.move_down() && history_index < self.history.len() {
+                        history_index += 1;
+                        buffer = match self.history.get(history_index) {
+                            Some(input) => Buffer::new(input.clone()),
+                            None => Buffer::new(draft.clone()),
+                        };
+                    }
+                }
+                _ => {}
+            }
+
+            screen.draw(&mut out, &buffer)?;
+        }
+
+        writeln!(out, "\r")?;
+        Ok(None)
+    }
+
+    /// Adds `input` to the history (unless it is blank, or the same as
+    /// the last input), and to the end of the history file.
+    fn add_to_history(&mut self, input: &str) {
+        let input = input.trim_end();
+        if input.trim().is_empty() || self.history.last().map(|last| &last[..]) == Some(input) {
+            return;
+        }
+        self.history.push(input.to_string());
+
+        if let Some(path) = &self.history_file {
+            let written = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{}", escape(input)));
+
+            // The history is a convenience, so losing it isn't worth
+            // interrupting the user for more than once.
+            if let Err(error) = written {
+                eprintln!("warning: could not save history: {}", error);
+                self.history_file = None;
+            }
+        }
+    }
+}
+
+/// Reads an input a line at a time, without any editing.
+fn read_lines(helper: &impl Helper) -> io::Result<Option<String>> {
+    let mut input = String::new();
+
+    print!("{}", PROMPT);
+    loop {
+        stdout().flush()?;
+        match stdin().read_line(&mut input)? {
+            0 if input.is_empty() => return Ok(None),
+            0 => return Ok(Some(input)),
+            _ => {}
+        }
+
+        // A blank line ends an input even if it is incomplete, so that
+        // a stray brace cannot swallow everything that follows.
+        if !helper.is_incomplete(&input) || input.ends_with("\n\n") {
+            return Ok(Some(input));
+        }
+        print!("{}", CONTINUATION_PROMPT);
+    }
+}
+
+#[cfg(unix)]
+fn is_terminal() -> bool {
+    termion::is_tty(&stdin())
+}
+
+#[cfg(not(unix))]
+fn is_terminal() -> bool {
+    false
+}
+
+/// Reads the history file, which has an input per line. Inputs that
+/// span lines have their newlines escaped.
+fn load_history(path: &PathBuf) -> Vec<String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return vec![],
+    };
+
+    let mut history: Vec<String> = text.lines().map(unescape).collect();
+    if history.len() > HISTORY_LIMIT {
+        history.drain(..history.len() - HISTORY_LIMIT);
+
+        // Rewrite the file so that it doesn't grow forever.
+        let lines: Vec<String> = history.iter().map(|input| escape(input)).collect();
+        let _ = fs::write(path, lines.join("\n") + "\n");
+    }
+    history
+}
+
+fn escape(input: &str) -> String {
+    input.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(line: &str) -> String {
+    let mut input = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                input.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                input.push('\\');
+                chars.next();
+            }
+            _ => input.push(c),
+        }
+    }
+    input
+}
+
+/// The longest prefix that all of `candidates` share.
+fn common_prefix(candidates: &[String]) -> &str {
+    let first = match candidates.first() {
+        Some(first) => first,
+        None => return "",
+    };
+
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first[..len]
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(len.min(candidate.len()), |((index, _), _)| index);
+    }
+    &first[..len]
+}
+
+/// The input being edited, and the byte in it that the cursor is at.
+#[derive(Default)]
+struct Buffer {
+    text: String,
+    cursor: usize,
+}
+
+impl Buffer {
+    fn new(text: String) -> Buffer {
+        let cursor = text.len();
+        Buffer { text, cursor }
+    }
+
+    fn insert(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    fn line_start(&self) -> usize {
+        self.text[..self.cursor]
+            .rfind('\n')
+            .map_or(0, |index| index + 1)
+    }
+
+    fn line_end(&self) -> usize {
+        self.text[self.cursor..]
+            .find('\n')
+            .map_or(self.text.len(), |index| self.cursor + index)
+    }
+
+    /// The cursor's column, in characters.
+    fn column(&self) -> usize {
+        self.text[self.line_start()..self.cursor].chars().count()
+    }
+
+    /// Moves the cursor to the line above, if there is one, keeping its
+    /// column where possible.
+    fn move_up(&mut self) -> bool {
+        let start = self.line_start();
+        if start == 0 {
+            return false;
+        }
+
+        let column = self.column();
+        self.cursor = start - 1;
+        self.cursor = self.line_start();
+        self.move_to_column(column);
+        true
+    }
+
+    /// Moves the cursor to the line below, if there is one, keeping its
+    /// column where possible.
+    fn move_down(&mut self) -> bool {
+        let end = self.line_end();
+        if end == self.text.len() {
+            return false;
+        }
+
+        let column = self.column();
+        self.cursor = end + 1;
+        self.move_to_column(column);
+        true
+    }
+
+    /// Moves the cursor from the start of a line to `column` (or the
+    /// end of the line, if it is shorter).
+    fn move_to_column(&mut self, column: usize) {
+        let end = self.line_end();
+        self.cursor = self.text[self.cursor..end]
+            .char_indices()
+            .nth(column)
+            .map_or(end, |(index, _)| self.cursor + index);
+    }
+}
+
+/// Tracks what we have drawn on the terminal, so that we can redraw it.
+#[derive(Default)]
+struct Screen {
+    /// The row that the terminal's cursor is on, counting from the
+    /// first row of the input.
+    cursor_row: usize,
+}
+
+#[cfg(unix)]
+impl Screen {
+    /// Redraws `buffer`, each line after its prompt, and puts the
+    /// terminal's cursor where the buffer's is.
+    fn draw(&mut self, out: &mut impl Write, buffer: &Buffer) -> io::Result<()> {
+        use termion::{clear, cursor};
+
+        let width = match termion::terminal_size() {
+            Ok((width, _)) if width > 0 => usize::from(width),
+            _ => 80,
+        };
+
+        if self.cursor_row > 0 {
+            write!(out, "{}", cursor::Up(self.cursor_row as u16))?;
+        }
+        write!(out, "\r{}", clear::AfterCursor)?;
+
+        // Lines that are too long for the terminal wrap onto further rows.
+        let mut row = 0;
+        let mut cursor = (0, 0);
+        let mut end = (0, 0);
+        let mut line_start = 0;
+        for (index, line) in buffer.text.split('\n').enumerate() {
+            let prompt = if index == 0 {
+                PROMPT
+            } else {
+                writeln!(out, "\r")?;
+                CONTINUATION_PROMPT
+            };
+            write!(out, "{}{}", prompt, line)?;
+
+            let line_end = line_start + line.len();
+            if line_start <= buffer.cursor && buffer.cursor <= line_end {
+                let column = prompt.len() + buffer.text[line_start..buffer.cursor].chars().count();
+                cursor = (row + column / width, column % width);
+            }
+
+            let columns = prompt.len() + line.chars().count();
+            end = (row + columns / width, columns % width);
+            row = end.0 + 1;
+            line_start = line_end + 1;
+        }
+
+        // A line that exactly fills the width leaves the terminal's
+        // cursor at its end, rather than at the start of the next row.
+        if end.1 == 0 {
+            writeln!(out, "\r")?;
+        }
+
+        if end.0 > cursor.0 {
+            write!(out, "{}", cursor::Up((end.0 - cursor.0) as u16))?;
+        }
+        write!(out, "\r")?;
+        if cursor.1 > 0 {
+            write!(out, "{}", cursor::Right(cursor.1 as u16))?;
+        }
+        self.cursor_row = cursor.0;
+
+        out.flush()
+    }
+
+    /// Lists `candidates` below the input, and then draws the input
+    /// again below them.
+    fn list(
+        &mut self,
+        out: &mut impl Write,
+        buffer: &Buffer,
+        candidates: &[String],
+    ) -> io::Result<()> {
+        let end = Buffer::new(buffer.text.clone());
+        self.draw(out, &end)?;
+        write!(out, "\r\n{}\r\n", candidates.join("  "))?;
+
+        self.cursor_row = 0;
+        self.draw(out, buffer)
+    }
+}
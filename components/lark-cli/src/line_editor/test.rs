@@ -0,0 +1,149 @@
+#![cfg(test)]
+
+use crate::line_editor::{common_prefix, escape, load_history, unescape, Buffer, HISTORY_LIMIT};
+use std::fs;
+use std::path::PathBuf;
+
+fn buffer_at(text: &str, cursor: usize) -> Buffer {
+    Buffer {
+        text: text.to_string(),
+        cursor,
+    }
+}
+
+fn strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn move_up_keeps_column() {
+    let mut buffer = Buffer::new("let x = 1\nx + 22".to_string());
+    assert!(buffer.move_up());
+    assert_eq!(buffer.cursor, 6);
+
+    // There is nothing above the first line
+    assert!(!buffer.move_up());
+    assert_eq!(buffer.cursor, 6);
+}
+
+#[test]
+fn move_up_to_shorter_line() {
+    let mut buffer = Buffer::new("ab\nabcdef".to_string());
+    assert!(buffer.move_up());
+    assert_eq!(buffer.cursor, 2);
+}
+
+#[test]
+fn move_up_counts_characters() {
+    // The column is in characters, so the cursor lands after the `é`
+    // rather than in the middle of it
+    let mut buffer = Buffer::new("héllo\nhi".to_string());
+    assert!(buffer.move_up());
+    assert_eq!(buffer.cursor, 3);
+}
+
+#[test]
+fn move_down() {
+    let mut buffer = buffer_at("abcdef\nab\nabcd", 4);
+    assert!(buffer.move_down());
+    assert_eq!(buffer.cursor, 9);
+
+    // The column is that of the line we moved from, not the first one
+    assert!(buffer.move_down());
+    assert_eq!(buffer.cursor, 12);
+
+    assert!(!buffer.move_down());
+    assert_eq!(buffer.cursor, 12);
+}
+
+#[test]
+fn move_through_blank_line() {
+    let mut buffer = buffer_at("abc\n\nabc", 2);
+    assert!(buffer.move_down());
+    assert_eq!(buffer.cursor, 4);
+    assert!(buffer.move_down());
+    assert_eq!(buffer.cursor, 5);
+    assert!(buffer.move_up());
+    assert!(buffer.move_up());
+    assert_eq!(buffer.cursor, 0);
+}
+
+#[test]
+fn move_to_column() {
+    let mut buffer = buffer_at("abc\ndef\nghi", 4);
+    buffer.move_to_column(1);
+    assert_eq!(buffer.cursor, 5);
+
+    // A column past the end of the line moves to its end
+    let mut buffer = buffer_at("abc\ndef\nghi", 4);
+    buffer.move_to_column(10);
+    assert_eq!(buffer.cursor, 7);
+}
+
+#[test]
+fn common_prefix_of_candidates() {
+    assert_eq!(common_prefix(&[]), "");
+    assert_eq!(common_prefix(&strings(&["abc"])), "abc");
+    assert_eq!(
+        common_prefix(&strings(&["counter", "count", "country"])),
+        "count"
+    );
+    assert_eq!(common_prefix(&strings(&["abc", "ab"])), "ab");
+    assert_eq!(common_prefix(&strings(&["x", "y"])), "");
+    assert_eq!(common_prefix(&strings(&["éa", "éb"])), "é");
+}
+
+#[test]
+fn escape_newlines_and_backslashes() {
+    assert_eq!(escape("a\nb"), "a\\nb");
+    assert_eq!(escape("a\\nb"), "a\\\\nb");
+    assert_eq!(unescape("a\\nb"), "a\nb");
+    assert_eq!(unescape("a\\\\nb"), "a\\nb");
+
+    // Backslashes that don't start an escape are kept as they are
+    assert_eq!(unescape("a\\b\\"), "a\\b\\");
+
+    for input in &["", "a\nb", "a\\nb\n", "\\", "\\\\n\n\n", "\"\\\\\""] {
+        assert!(!escape(input).contains('\n'));
+        assert_eq!(unescape(&escape(input)), *input);
+    }
+}
+
+fn history_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("lark_history_{}_{}", name, std::process::id()))
+}
+
+#[test]
+fn load_missing_history() {
+    assert!(load_history(&history_file("missing")).is_empty());
+}
+
+#[test]
+fn load_history_with_newlines() {
+    let path = history_file("newlines");
+    fs::write(&path, "let x = 1\nstruct Point {\\n  x: uint\\n}\n").unwrap();
+
+    let history = load_history(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(history, strings(&["let x = 1", "struct Point {\n  x: uint\n}"]));
+}
+
+#[test]
+fn truncate_history() {
+    let path = history_file("truncate");
+    let lines: Vec<String> = (0..HISTORY_LIMIT + 5)
+        .map(|index| escape(&format!("debug({})\n", index)))
+        .collect();
+    fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+    // Only the latest inputs are kept, in order, and so is the file
+    let history = load_history(&path);
+    assert_eq!(history.len(), HISTORY_LIMIT);
+    assert_eq!(history[0], "debug(5)\n");
+    assert_eq!(history[HISTORY_LIMIT - 1], format!("debug({})\n", HISTORY_LIMIT + 4));
+
+    let text = fs::read_to_string(&path).unwrap();
+    assert_eq!(text.lines().count(), HISTORY_LIMIT);
+    assert_eq!(load_history(&path), history);
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,95 @@
+use lark_intern::Untern;
+use lark_parser::{ParserDatabase, ParserDatabaseExt};
+use lark_query_system::LarkDatabase;
+use lark_span::{FileName, IntoFileName};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Adds the files of the Lark project at `path` to the database. If
+/// `path` is a directory, the project is every `.lark` file beneath
+/// it; otherwise, it is the given file along with the modules (the
+/// `.lark` files next to it) that it transitively imports.
+pub fn load_project(db: &mut LarkDatabase, path: &str) -> io::Result<()> {
+    let path = Path::new(path);
+
+    if path.is_dir() {
+        let mut files = vec![];
+        find_lark_files(path, &mut files)?;
+        for file in files {
+            add_file(db, &file)?;
+        }
+        return Ok(());
+    }
+
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut loaded = HashSet::new();
+    let mut pending = vec![path.to_path_buf()];
+
+    while let Some(file) = pending.pop() {
+        if !loaded.insert(file.clone()) {
+            continue;
+        }
+
+        let file_name = add_file(db, &file)?;
+        for module in db.imported_modules(file_name).iter() {
+            let module_path = directory.join(format!("{}.lark", module.untern(&*db)));
+
+            // Modules that don't exist are reported when the import is
+            // resolved, so we just skip them here.
+            if module_path.is_file() {
+                pending.push(module_path);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The name of the project at `path`: the name of the directory, or
+/// of the file without its extension.
+pub fn project_name(path: &str) -> String {
+    let path = Path::new(path);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("main"))
+}
+
+/// The `.lark` files of the project at `path`: every one beneath it, if
+/// it is a directory, and otherwise just the file itself.
+pub fn lark_files(path: &str) -> io::Result<Vec<PathBuf>> {
+    let path = Path::new(path);
+    let mut files = vec![];
+    if path.is_dir() {
+        find_lark_files(path, &mut files)?;
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(files)
+}
+
+fn find_lark_files(directory: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            find_lark_files(&entry, files)?;
+        } else if entry.extension().map(|ext| ext == "lark").unwrap_or(false) {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+crate fn add_file(db: &mut LarkDatabase, path: &Path) -> io::Result<FileName> {
+    let contents = fs::read_to_string(path)?;
+    let file_name = path.to_string_lossy().into_owned().into_file_name(&*db);
+    db.add_file(file_name, contents);
+    Ok(file_name)
+}
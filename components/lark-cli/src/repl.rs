@@ -1,123 +1,657 @@
-use crate::build::LarkDatabaseExt;
-use lark_debug_with::DebugWith;
-use lark_entity::{EntityData, ItemKind};
-use lark_eval::Value;
+//! `lark repl`: reads definitions and statements from stdin and
+//! evaluates them as they come.
+//!
+//! The definitions (structs, enums and functions) entered so far make up the
+//! file `__REPL__.lark`, each one its own item, so that entering a
+//! definition with the name of an earlier one replaces it. Each input
+//! that is not a definition becomes the body of a function that takes
+//! the variables created by earlier inputs as its parameters; we call
+//! it with their values, and keep the final values of its parameters
+//! and of the variables that it creates for the next input. So nothing
+//! is ever evaluated twice.
+
+use crate::build::{display_runtime_error, LarkDatabaseExt};
+use crate::dump_hir::{dump_fn_hir, HirMode};
+use crate::line_editor::{Helper, LineEditor};
+use lark_entity::{Entity, EntityData, ItemKind, LangItem};
+use lark_eval::{EvalState, IOHandler, Value};
 use lark_hir as hir;
 use lark_intern::{Intern, Untern};
 use lark_parser::{ParserDatabase, ParserDatabaseExt};
+use lark_pretty_print::PrettyPrint;
 use lark_query_system::ls_ops::LsDatabase;
 use lark_query_system::LarkDatabase;
-use lark_span::IntoFileName;
+use lark_span::{ByteIndex, FileName, IntoFileName};
+use lark_ty::full_inferred::FullInferred;
+use lark_ty::{BaseKind, Ty};
+use lark_type_check::TypeCheckDatabase;
 use salsa::Database;
-use std::collections::HashMap;
-use std::io::{stdin, stdout, Write};
-use termcolor::{ColorChoice, StandardStream, WriteColor};
+use std::io;
+use termcolor::{ColorChoice, NoColor, StandardStream};
+
+mod test;
 
 const REPL_FILENAME: &str = "__REPL__.lark";
 
-pub fn get_body(db: &LarkDatabase) -> lark_error::WithError<std::sync::Arc<lark_hir::FnBody>> {
-    let main_name = "main".intern(&db);
-    let repl_filename = REPL_FILENAME.intern(&db);
-    let entities = db.top_level_entities_in_file(repl_filename);
+/// The name of the function that each input (other than a definition)
+/// becomes.
+const INPUT_FUNCTION: &str = "__repl_input";
 
-    for &entity in &*entities {
-        match entity.untern(&db) {
-            EntityData::ItemName {
-                kind: ItemKind::Function,
-                id,
-                ..
-            } => {
-                if id == main_name {
-                    let body = db.fn_body(entity);
-                    return body;
+/// The variable that holds the value an input ends with, while we find
+/// out its type.
+const RESULT_VARIABLE: &str = "__repl_result";
+
+const HELP: &str = "\
+Enter a statement to run it, or a `struct`, `enum` or `def` to define (or redefine) it. An input
+continues onto the next line while it has unclosed braces or parentheses (or until a
+blank line).
+
+Tab completes the names of variables, functions and struct fields. Up and down move between
+the lines of an input, and through the inputs of earlier sessions.
+
+Commands available:
+  :q             - quit
+  :p             - view the definitions entered so far
+  :v             - view variables
+  :type <expr>   - view the type of an expression, without running it
+  :hir <expr>    - view the HIR of an expression, without running it
+  :load <file>   - add the definitions of a file
+  :reset         - forget all definitions and variables";
+
+/// A variable created by an earlier input.
+struct Binding {
+    name: String,
+
+    /// The type of the variable, as it is written in a declaration.
+    ty: String,
+
+    value: Value,
+}
+
+struct Repl {
+    db: LarkDatabase,
+    file: FileName,
+
+    /// The name and text of each definition, in the order in which
+    /// they were entered.
+    items: Vec<(String, String)>,
+
+    bindings: Vec<Binding>,
+    io_handler: IOHandler,
+}
+
+pub fn repl() {
+    let mut repl = Repl::new();
+    let mut editor = LineEditor::new(LineEditor::default_history_file());
+
+    println!("Lark repl (:? - command help)");
+    while let Some(input) = editor.read(&mut repl) {
+        let (command, argument) = match split_command(&input) {
+            Some((command, argument)) => (command, argument.trim()),
+            None => {
+                match input.trim() {
+                    "" => {}
+                    input if is_definition(input) => repl.define(input),
+                    input => repl.evaluate(input),
                 }
+                continue;
             }
+        };
+
+        match (command, argument) {
+            (":q", "") => break,
+            (":p", "") => println!("{}", repl.source(None)),
+            (":v", "") => {
+                for binding in &repl.bindings {
+                    println!("{}: {} = {}", binding.name, binding.ty, binding.value);
+                }
+            }
+            (":?", "") => println!("{}", HELP),
+            (":type", expression) if !expression.is_empty() => repl.show_type(expression),
+            (":hir", expression) if !expression.is_empty() => repl.show_hir(expression),
+            (":load", path) if !path.is_empty() => repl.load(path),
+            (":reset", "") => repl = Repl::new(),
+            _ => eprintln!(
+                "error: unknown command `{}` (:? - command help)",
+                input.trim()
+            ),
+        }
+    }
+}
+
+/// Splits an input that is a command (like `:type x + 1`) into the
+/// command and its argument, which is the rest of the input.
+fn split_command(input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start();
+    if !input.starts_with(':') {
+        return None;
+    }
+
+    let end = input
+        .find(char::is_whitespace)
+        .unwrap_or_else(|| input.len());
+    Some((&input[..end], &input[end..]))
+}
+
+/// Whether `input` defines a struct, enum or function (rather than
+/// being a statement).
+fn is_definition(input: &str) -> bool {
+    match input.split_whitespace().next() {
+        Some("struct") | Some("enum") | Some("def") => true,
+        _ => false,
+    }
+}
+
+/// Whether `input` has braces, brackets or parentheses (or a string or
+/// comment) that are still open.
+fn is_incomplete(input: &str) -> bool {
+    let code = match strip_comments(input) {
+        Some(code) => code,
+        None => return true,
+    };
+
+    let mut depth = 0;
+    let mut rest = &code[..];
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            '"' => match rest.find('"') {
+                Some(end) => rest = &rest[end + 1..],
+                None => return true,
+            },
             _ => {}
         }
     }
 
-    panic!("Internal error: Lost track of function bytecode")
+    depth > 0
 }
 
-pub fn repl() {
-    let mut virtual_fn: Vec<String> = vec![];
-    let mut io_handler = lark_eval::IOHandler::new(false);
-    let mut db = LarkDatabase::default();
+/// `input` without its comments, or `None` if it ends inside a block
+/// comment.
+fn strip_comments(input: &str) -> Option<String> {
+    let mut code = String::new();
+    let mut rest = input;
 
-    let _ = db.add_file(
-        REPL_FILENAME,
-        format!("def main() {{\n{}\n}}", virtual_fn.join("\n")),
-    );
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("//") {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+            continue;
+        }
 
-    let repl_filename = REPL_FILENAME.into_file_name(&db);
+        if rest.starts_with("/*") {
+            // Block comments nest.
+            let mut comment_depth = 0;
+            loop {
+                if rest.starts_with("/*") {
+                    comment_depth += 1;
+                    rest = &rest[2..];
+                } else if rest.starts_with("*/") {
+                    comment_depth -= 1;
+                    rest = &rest[2..];
+                    if comment_depth == 0 {
+                        break;
+                    }
+                } else if let Some(c) = rest.chars().next() {
+                    rest = &rest[c.len_utf8()..];
+                } else {
+                    return None;
+                }
+            }
+            continue;
+        }
 
-    let mut eval_state = lark_eval::EvalState::new();
-    eval_state.is_repl = true;
+        // Strings can contain what look like comments.
+        let len = match c {
+            '"' => rest[1..].find('"').map_or(rest.len(), |end| end + 2),
+            _ => c.len_utf8(),
+        };
+        code.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
 
-    println!("Lark repl (:? - command help)");
-    loop {
-        let mut input = String::new();
+    Some(code)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Helper for Repl {
+    fn is_incomplete(&self, input: &str) -> bool {
+        is_incomplete(input)
+    }
+
+    fn complete(&mut self, input: &str, cursor: usize) -> (usize, Vec<String>) {
+        // Only the argument of `:type` and `:hir` is code.
+        let code_start = match split_command(input) {
+            Some((":type", argument)) | Some((":hir", argument)) => input.len() - argument.len(),
+            Some(_) => return (cursor, vec![]),
+            None => 0,
+        };
+        if cursor < code_start {
+            return (cursor, vec![]);
+        }
+
+        let name_start = input[..cursor].trim_end_matches(is_identifier_char).len();
+        let name = &input[name_start..cursor];
+        let names = self.completions(&input[code_start..], cursor - code_start);
+
+        let names = names
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(name))
+            .collect();
+        (name_start, names)
+    }
+}
+
+impl Repl {
+    fn new() -> Repl {
+        let mut db = LarkDatabase::default();
+        db.add_file(REPL_FILENAME, String::new());
+        let file = REPL_FILENAME.into_file_name(&db);
+
+        Repl {
+            db,
+            file,
+            items: vec![],
+            bindings: vec![],
+            io_handler: IOHandler::new(false),
+        }
+    }
 
-        print!("> ");
-        let _ = stdout().flush();
-        stdin().read_line(&mut input).expect("Could not read input");
-        input = input.trim().to_string();
+    /// The text of the REPL file: the definitions, followed by the
+    /// function for an `input`, if any.
+    fn source(&self, input: Option<&str>) -> String {
+        let mut parts: Vec<&str> = self.items.iter().map(|(_, text)| &text[..]).collect();
+        parts.extend(input);
+        parts.join("\n\n")
+    }
+
+    fn set_source(&mut self, text: String) {
+        self.db
+            .query_mut(lark_parser::FileTextQuery)
+            .set(self.file, text.into());
+    }
 
-        if input == ":q" {
-            break;
+    /// Where an input starts in the text of the REPL file.
+    fn input_offset(&self) -> usize {
+        match self.source(None).len() {
+            0 => 0,
+            len => len + 2,
         }
-        if input == ":p" {
+    }
+
+    /// Reports the errors in the REPL file (unless `quiet`), returning
+    /// how many there are.
+    fn error_count(&self, quiet: bool) -> usize {
+        let count = if quiet {
+            self.db.display_errors(NoColor::new(io::sink()))
+        } else {
+            let writer = StandardStream::stderr(ColorChoice::Auto);
+            let mut out = writer.lock();
+            self.db.display_errors(&mut out)
+        };
+        count.unwrap_or_else(|_| panic!("cancelled"))
+    }
+
+    /// Adds the structs, enums and functions of `input`, replacing any earlier
+    /// definitions of the same names, unless that leads to errors.
+    fn define(&mut self, input: &str) {
+        let prefix = self.source(None);
+        let start = self.input_offset();
+        let text = self.source(Some(input));
+        self.set_source(text.clone());
+
+        let mut definitions = vec![];
+        let mut rest = input.to_string();
+        for parsed_entity in self.db.parsed_file(self.file).value.entities.iter() {
+            let span = parsed_entity.full_span;
+            if span.start().to_usize() < start {
+                continue;
+            }
+
+            if let EntityData::ItemName { id, .. } = parsed_entity.entity.untern(&self.db) {
+                let definition = &text[span.start().to_usize()..span.end().to_usize()];
+                definitions.push((id.untern(&self.db).to_string(), definition.to_string()));
+                rest = rest.replacen(definition, "", 1);
+            }
+        }
+
+        // Anything that is not part of a definition is a syntax error,
+        // which is easiest to report in place.
+        let rest_is_blank = strip_comments(&rest).map_or(false, |rest| rest.trim().is_empty());
+        if definitions.is_empty() || !rest_is_blank {
+            if self.error_count(false) == 0 {
+                eprintln!("error: expected a struct, enum or function definition");
+            }
+            self.set_source(prefix);
+            return;
+        }
+
+        let previous_items = self.items.clone();
+        self.items
+            .retain(|(name, _)| definitions.iter().all(|(new_name, _)| new_name != name));
+        self.items.extend(definitions);
+
+        let text = self.source(None);
+        self.set_source(text);
+        if self.error_count(false) > 0 {
+            self.items = previous_items;
+            let text = self.source(None);
+            self.set_source(text);
+        }
+    }
+
+    /// The function that runs `input`, with the bindings as its
+    /// parameters and with the given return type, if any.
+    fn input_function(&self, input: &str, return_type: Option<&str>) -> String {
+        format!(
+            "{}{}\n}}",
+            self.input_function_header(return_type),
+            input.trim_end()
+        )
+    }
+
+    /// The start of the input function, up to the line where the input
+    /// begins.
+    fn input_function_header(&self, return_type: Option<&str>) -> String {
+        let parameters: Vec<String> = self
+            .bindings
+            .iter()
+            .map(|binding| format!("{}: {}", binding.name, binding.ty))
+            .collect();
+        let return_type = match return_type {
+            Some(ty) => format!(" -> {}", ty),
+            None => String::new(),
+        };
+
+        format!(
+            "def {}({}){} {{\n",
+            INPUT_FUNCTION,
+            parameters.join(", "),
+            return_type,
+        )
+    }
+
+    /// The entity of the function that runs the current input.
+    fn input_entity(&self) -> Entity {
+        let name = INPUT_FUNCTION.intern(&self.db);
+        self.db
+            .top_level_entities_in_file(self.file)
+            .iter()
+            .cloned()
+            .find(|&entity| match entity.untern(&self.db) {
+                EntityData::ItemName {
+                    kind: ItemKind::Function,
+                    id,
+                    ..
+                } => id == name,
+                _ => false,
+            })
+            .expect("Internal error: Lost track of the input function")
+    }
+
+    /// Makes `input` the body of the input function, which returns the
+    /// value that it ends with (if any). Reports the errors, if there
+    /// are any, returning whether there were none.
+    fn prepare_input(&mut self, input: &str) -> bool {
+        let text = self.source(Some(&self.input_function(input, None)));
+        self.set_source(text.clone());
+
+        if let Some(ty) = self.result_type(&text) {
+            let text = self.source(Some(&self.input_function(input, Some(&ty))));
+            self.set_source(text);
+        }
+
+        self.error_count(false) == 0
+    }
+
+    /// If the input in `text` (the current source) ends with an
+    /// expression that has a value, rather than with a statement like a
+    /// `let`, the type of that value (as it is written in a
+    /// declaration).
+    fn result_type(&mut self, text: &str) -> Option<String> {
+        let fn_body = self.db.fn_body(self.input_entity()).into_value();
+        let tail = tail_expression(&fn_body);
+        match fn_body[tail] {
+            hir::ExpressionData::Let { .. }
+            | hir::ExpressionData::Assignment { .. }
+            | hir::ExpressionData::While { .. }
+            | hir::ExpressionData::Break {}
+            | hir::ExpressionData::Continue {}
+            | hir::ExpressionData::Unit {}
+            | hir::ExpressionData::Error { .. } => return None,
+            _ => {}
+        }
+
+        // The function doesn't return anything yet, which would be the
+        // type expected of the expression (and of each arm, if it is a
+        // `match`). As the initializer of a `let`, it gets its own type.
+        let start = fn_body.span(tail).start().to_usize();
+        self.set_source(format!(
+            "{}let {} = {}",
+            &text[..start],
+            RESULT_VARIABLE,
+            &text[start..]
+        ));
+
+        let entity = self.input_entity();
+        let fn_body = self.db.fn_body(entity).into_value();
+        let types = self.db.full_type_check(entity).into_value();
+        let variable = *top_level_variables(&fn_body).last()?;
+        types
+            .opt_ty(variable)
+            .and_then(|ty| declared_type(&self.db, ty))
+    }
+
+    /// Runs the statements of `input`, printing the value that they end
+    /// with (if any), unless they have errors.
+    fn evaluate(&mut self, input: &str) {
+        if self.prepare_input(input) {
+            self.run();
+        }
+
+        let text = self.source(None);
+        self.set_source(text);
+    }
+
+    /// `:type`: prints the type of `expression`.
+    fn show_type(&mut self, expression: &str) {
+        if self.prepare_input(expression) {
+            let entity = self.input_entity();
+            let fn_body = self.db.fn_body(entity).into_value();
+            let types = self.db.full_type_check(entity).into_value();
+            if let Some(ty) = types.opt_ty(tail_expression(&fn_body)) {
+                println!("{}", ty.pretty_print(&self.db));
+            }
+        }
+
+        let text = self.source(None);
+        self.set_source(text);
+    }
+
+    /// `:hir`: prints the HIR of the input function for `expression`,
+    /// with its types.
+    fn show_hir(&mut self, expression: &str) {
+        if self.prepare_input(expression) {
             println!(
-                "Will execute: {}",
-                format!("def main() {{\n{}\n}}", virtual_fn.join("\n"))
+                "{}",
+                dump_fn_hir(&self.db, self.input_entity(), HirMode::Full)
             );
-            continue;
         }
-        if input == ":v" {
-            println!("{:#?}", eval_state.variables);
-            continue;
+
+        let text = self.source(None);
+        self.set_source(text);
+    }
+
+    /// `:load`: adds the definitions in the file at `path`.
+    fn load(&mut self, path: &str) {
+        match std::fs::read_to_string(path) {
+            Ok(text) => self.define(&text),
+            Err(error) => eprintln!("error: could not read `{}`: {}", path, error),
         }
-        if input == ":?" {
-            println!("Commands available:");
-            println!("  :q - quit");
-            println!("  :p - view currently accepted source lines");
-            println!("  :v - view variables");
-            continue;
+    }
+
+    /// The names that the language server would offer to complete at
+    /// byte `cursor` of `input` (which may be a definition or a
+    /// statement).
+    fn completions(&mut self, input: &str, cursor: usize) -> Vec<String> {
+        let (text, index) = if is_definition(input) {
+            (self.source(Some(input)), self.input_offset() + cursor)
+        } else {
+            let header = self.input_function_header(None);
+            let function = format!("{}{}\n}}", header, input);
+            (
+                self.source(Some(&function)),
+                self.input_offset() + header.len() + cursor,
+            )
+        };
+        self.set_source(text);
+
+        let position = self.db.position(self.file, ByteIndex::from(index));
+        let completions = self
+            .db
+            .completions_at_position(REPL_FILENAME, position)
+            .unwrap_or_default();
+
+        let text = self.source(None);
+        self.set_source(text);
+
+        let mut names: Vec<String> = vec![];
+        for (name, _) in completions {
+            if name != INPUT_FUNCTION && !names.contains(&name) {
+                names.push(name);
+            }
         }
+        names
+    }
 
-        virtual_fn.push(input);
+    /// Calls the input function with the values of the bindings,
+    /// updating them (and adding the variables that it creates) if it
+    /// succeeds.
+    fn run(&mut self) {
+        let db = &self.db;
+        let entity = self.input_entity();
+        let fn_body = db.fn_body(entity).into_value();
+        let parameters = fn_body.arguments.unwrap();
 
-        db.query_mut(lark_parser::FileTextQuery).set(
-            repl_filename,
-            format!("def main() {{\n{}\n}}", virtual_fn.join("\n")).into(),
-        );
+        let mut state = EvalState::new();
+        state.is_repl = true;
+        state.current_function = Some(entity);
+        for (parameter, binding) in parameters.iter(&fn_body).zip(&self.bindings) {
+            state.create_variable(parameter);
+            state.assign_to_variable(parameter, binding.value.clone());
+        }
 
-        let writer = StandardStream::stderr(ColorChoice::Auto);
-        let error_count = db
-            .display_errors(&mut writer.lock())
-            .unwrap_or_else(|_| panic!("cancelled"));
+        match lark_eval::eval_function(db, &fn_body, &mut state, &mut self.io_handler) {
+            Ok(value) => {
+                let final_value = |variable| state.variables[&variable].last().unwrap().clone();
 
-        if error_count > 0 {
-            // The last command was bad. Let's remove it from our function body
-            virtual_fn.pop();
-        } else {
-            // No errors, so let's run the last line of our function body
-            let fn_body = get_body(&mut db).value;
-
-            let output = lark_eval::eval_expression(
-                &db,
-                &fn_body,
-                fn_body.root_expression,
-                &mut eval_state,
-                &mut io_handler,
-            );
+                for (parameter, binding) in parameters.iter(&fn_body).zip(&mut self.bindings) {
+                    binding.value = final_value(parameter);
+                }
+
+                let types = db.full_type_check(entity).into_value();
+                for variable in top_level_variables(&fn_body) {
+                    let name = fn_body.tables[fn_body.tables[variable].name].text;
+                    let name = name.untern(db).to_string();
+                    self.bindings.retain(|binding| binding.name != name);
+
+                    // Variables whose types we can't write down (like
+                    // those with errors) can't be carried over.
+                    let ty = types.opt_ty(variable).and_then(|ty| declared_type(db, ty));
+                    if let Some(ty) = ty {
+                        self.bindings.push(Binding {
+                            name,
+                            ty,
+                            value: final_value(variable),
+                        });
+                    }
+                }
+
+                match value {
+                    Value::Void => {}
+                    value => println!("{}", value),
+                }
+            }
+            Err(error) => {
+                let writer = StandardStream::stderr(ColorChoice::Auto);
+                display_runtime_error(db, &mut writer.lock(), &error);
+            }
+        }
+    }
+}
 
-            eval_state.skip_until = eval_state.current_expression;
+/// The expression that the body of a function ends with, after its
+/// `let`s and sequences.
+fn tail_expression(fn_body: &hir::FnBody) -> hir::Expression {
+    let mut expression = fn_body.root_expression;
+    loop {
+        match fn_body[expression] {
+            hir::ExpressionData::Let { body, .. } => expression = body,
+            hir::ExpressionData::Sequence { second, .. } => expression = second,
+            _ => return expression,
+        }
+    }
+}
 
-            match output {
-                Value::Void => {}
-                x => println!("{}", x),
+/// The variables created by the `let`s at the top level of a function
+/// body (rather than in one of its blocks), in order.
+fn top_level_variables(fn_body: &hir::FnBody) -> Vec<hir::Variable> {
+    let mut variables = vec![];
+    let mut pending = vec![fn_body.root_expression];
+    while let Some(expression) = pending.pop() {
+        match fn_body[expression] {
+            hir::ExpressionData::Let { variable, body, .. } => {
+                variables.push(variable);
+                pending.push(body);
+            }
+            hir::ExpressionData::Sequence { first, second } => {
+                pending.push(second);
+                pending.push(first);
             }
+            _ => {}
         }
     }
+    variables
+}
+
+/// `ty` as it would be written in a declaration, if it is a type that
+/// can be (the built-in types, structs and enums, with their generic
+/// arguments, but not `void`).
+fn declared_type(db: &LarkDatabase, ty: Ty<FullInferred>) -> Option<String> {
+    let base = ty.base.untern(db);
+    let name = match base.kind {
+        BaseKind::Named(entity) => match entity.untern(db) {
+            EntityData::LangItem(LangItem::Boolean)
+            | EntityData::LangItem(LangItem::Uint)
+            | EntityData::LangItem(LangItem::Int)
+            | EntityData::LangItem(LangItem::String) => entity.pretty_print(db),
+            EntityData::ItemName {
+                kind: ItemKind::Struct,
+                id,
+                ..
+            }
+            | EntityData::ItemName {
+                kind: ItemKind::Enum,
+                id,
+                ..
+            } => id.untern(db).to_string(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    if base.generics.is_empty() {
+        return Some(name);
+    }
+
+    let arguments = base
+        .generics
+        .iter()
+        .map(|generic| declared_type(db, generic.assert_ty()))
+        .collect::<Option<Vec<_>>>()?;
+    Some(format!("{}<{}>", name, arguments.join(", ")))
 }
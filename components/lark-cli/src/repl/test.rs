@@ -0,0 +1,63 @@
+#![cfg(test)]
+
+use crate::line_editor::Helper;
+use crate::repl::Repl;
+
+/// Completes the name that ends at the end of `input`.
+fn complete(repl: &mut Repl, input: &str) -> (usize, Vec<String>) {
+    repl.complete(input, input.len())
+}
+
+#[test]
+fn complete_variable() {
+    let mut repl = Repl::new();
+    repl.evaluate("let counter = 22");
+    repl.evaluate("let count = 1");
+
+    assert_eq!(
+        complete(&mut repl, "debug(coun"),
+        (6, vec!["counter".to_string(), "count".to_string()])
+    );
+    assert_eq!(
+        complete(&mut repl, "debug(counte"),
+        (6, vec!["counter".to_string()])
+    );
+}
+
+#[test]
+fn complete_function() {
+    let mut repl = Repl::new();
+    repl.define("def double(x: uint) -> uint { x * 2 }");
+
+    assert_eq!(complete(&mut repl, "dou"), (0, vec!["double".to_string()]));
+
+    // Completing doesn't change the definitions or the variables
+    assert_eq!(repl.source(None), "def double(x: uint) -> uint { x * 2 }");
+    assert!(repl.bindings.is_empty());
+}
+
+#[test]
+fn complete_field() {
+    let mut repl = Repl::new();
+    repl.define("struct Point {\n  x: uint,\n  y: uint,\n}");
+    repl.evaluate("let point = Point(x: 3, y: 4)");
+
+    assert_eq!(
+        complete(&mut repl, "point."),
+        (6, vec!["x".to_string(), "y".to_string()])
+    );
+    assert_eq!(complete(&mut repl, "point.y"), (6, vec!["y".to_string()]));
+}
+
+#[test]
+fn complete_command_argument() {
+    let mut repl = Repl::new();
+    repl.evaluate("let counter = 22");
+
+    // Only the argument of `:type` and `:hir` is code
+    assert_eq!(
+        complete(&mut repl, ":type coun"),
+        (6, vec!["counter".to_string()])
+    );
+    assert_eq!(complete(&mut repl, ":load coun"), (10, vec![]));
+}
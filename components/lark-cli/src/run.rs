@@ -1,28 +1,168 @@
-use lark_parser::ParserDatabaseExt;
+use crate::args::Options;
+use crate::build;
+use crate::ExitStatus;
+use lark_parser::ParserDatabase;
 use lark_query_system::LarkDatabase;
-use std::fs::File;
-use std::io::Read;
+use lark_span::{IntoFileName, Span};
+use lark_vm::bytecode::SourceSpan;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use termcolor::StandardStream;
 
-pub fn run(filename: &str) {
-    let mut file = match File::open(filename) {
-        Ok(f) => f,
+pub fn run(path: &str, options: &Options) -> ExitStatus {
+    let db = match build::load_and_check(path, options) {
+        Ok(db) => db,
+        Err(status) => return status,
+    };
+
+    let program = compiled_program(&db);
+
+    let stdout = io::stdout();
+    match lark_vm::run(&program, BufWriter::new(stdout.lock())) {
+        Ok(()) => ExitStatus::Success,
+        Err(lark_vm::Error::Fault(fault)) => {
+            report_fault(&db, &program, &fault, options);
+            ExitStatus::Errors
+        }
         Err(err) => {
-            eprintln!("failed to open `{}`: {}", filename, err);
-            return;
+            eprintln!("failed to run `{}`: {}", path, err);
+            ExitStatus::Failure
         }
+    }
+}
+
+/// Reports a fault of `program` (the compiled project in `db`), with
+/// where it happened and the calls that led to it.
+fn report_fault(
+    db: &LarkDatabase,
+    program: &lark_vm::Program,
+    fault: &lark_vm::Fault,
+    options: &Options,
+) {
+    let span = |span: SourceSpan| {
+        let file = program.strings[span.file as usize].into_file_name(db);
+        Span::new(file, span.start as usize, span.end as usize)
     };
 
-    let mut contents = String::new();
-    match file.read_to_string(&mut contents) {
-        Ok(_bytes_read) => {}
-        Err(err) => {
-            eprintln!("failed to read `{}`: {}", filename, err);
-            return;
+    let writer = StandardStream::stderr(options.color);
+    let mut out = writer.lock();
+    match fault.span {
+        Some(fault_span) => {
+            let calls = fault.call_stack.iter().filter_map(|call| {
+                let name = program.functions[call.function as usize].name.clone();
+                Some((name, span(call.span?)))
+            });
+            build::display_failure(db, &mut out, &fault.message, span(fault_span), calls);
+        }
+        None => {
+            let _ = writeln!(out, "error: {}", fault.message);
+        }
+    }
+}
+
+/// Compiles the project in `db` to bytecode, reusing the bytecode
+/// from an earlier run if the sources haven't changed since.
+fn compiled_program(db: &LarkDatabase) -> lark_vm::Program {
+    let cache_path = match cache_path(db) {
+        Some(cache_path) => cache_path,
+        None => return lark_vm::compile(db),
+    };
+
+    if let Ok(file) = File::open(&cache_path) {
+        // A cached program that fails to load (say, because it was
+        // written by an older version of Lark) is just recompiled
+        if let Ok(program) = lark_vm::decode(&mut BufReader::new(file)) {
+            return program;
         }
     }
 
-    let mut db = LarkDatabase::default();
-    let _ = db.add_file(filename, contents.to_string());
+    let program = lark_vm::compile(db);
+
+    // Likewise, failing to save the program only means that we'll have
+    // to compile it again next time.
+    let _ = save_program(&program, &cache_path);
+
+    program
+}
+
+/// The file in which we cache the bytecode for the project in `db`,
+/// which is named after a hash of the project's sources and of the
+/// versions of Lark and of the bytecode that compiled them (so that a
+/// new build of Lark never runs bytecode compiled by an older one).
+fn cache_path(db: &LarkDatabase) -> Option<PathBuf> {
+    let mut hasher = StableHasher::new();
+    hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.write(&lark_vm::VERSION.to_le_bytes());
+    for &file in &*db.file_names() {
+        hasher.write(file.untern(db).as_bytes());
+        hasher.write(db.file_text(file).as_bytes());
+    }
+
+    Some(cache_directory()?.join(format!("{:016x}.lkbc", hasher.finish())))
+}
+
+/// The directory in which we cache bytecode: `$XDG_CACHE_HOME/lark`,
+/// or `~/.cache/lark`. It belongs to the user (unlike, say, a shared
+/// temporary directory), as anyone who can write to it can make them
+/// run any program.
+fn cache_directory() -> Option<PathBuf> {
+    let non_empty = |name| env::var_os(name).filter(|value| !value.is_empty());
+    let base = non_empty("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(non_empty("HOME")?).join(".cache")))?;
+    Some(base.join("lark"))
+}
+
+/// Creates `directory` (and its parents) if need be, readable and
+/// writable by the user alone.
+fn create_private_directory(directory: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(directory)
+}
+
+/// A 64-bit FNV-1a hash. Unlike the hashers in `std`, it gives the same
+/// result in every build, which the names of cached files rely on.
+struct StableHasher {
+    hash: u64,
+}
+
+impl StableHasher {
+    fn new() -> Self {
+        StableHasher {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+
+    /// Adds `bytes` to the hash, preceded by their length, so that
+    /// (say) `"ab", "c"` and `"a", "bc"` hash differently.
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.hash ^= u64::from(byte);
+            self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+fn save_program(program: &lark_vm::Program, path: &PathBuf) -> io::Result<()> {
+    create_private_directory(path.parent().unwrap())?;
 
-    lark_eval::eval(&mut db, &mut lark_eval::IOHandler::new(false));
+    // Write to a temporary file first, so that a concurrent `lark run`
+    // never sees a partially written program.
+    let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    let mut writer = BufWriter::new(File::create(&temporary_path)?);
+    lark_vm::encode(program, &mut writer)?;
+    writer.flush()?;
+    fs::rename(temporary_path, path)
 }
@@ -0,0 +1,532 @@
+//! The Debug Adapter Protocol (or DAP) server, which lets an IDE debug
+//! Lark programs. The program runs in the interpreter, inside the
+//! server: while it runs, a `Debugger` takes the IDE's requests between
+//! expressions, and while it is paused, waits for them.
+
+use lark_eval::debugger::{self, function_name, Debugger, Frame, Pause, StepMode, Stepper};
+use lark_eval::{EvalState, IOHandler, RuntimeError};
+use lark_hir as hir;
+use lark_language_server::read_message;
+use lark_parser::ParserDatabase;
+use lark_query_system::LarkDatabase;
+use lark_span::{FileName, IntoFileName, Span};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+mod protocol;
+
+use self::protocol::{
+    Client, InitializeArguments, LaunchArguments, Output, Request, ScopesArguments,
+    SetBreakpointsArguments, StackTraceArguments, VariablesArguments,
+};
+
+/// The interpreter only runs one thread, so this is the ID of every
+/// thread we tell the IDE about.
+const THREAD_ID: u64 = 1;
+
+/// How the IDE counts lines and columns (which, by default, start at 1).
+#[derive(Copy, Clone, Debug)]
+struct Numbering {
+    first_line: usize,
+    first_column: usize,
+}
+
+/// The workhorse function for debugging: takes the requests sent by the
+/// IDE through `reader`, and sends the responses and events through
+/// `writer`. The program to debug is loaded with `load`, which returns
+/// its errors (as text) if it can't be run. Returns once the IDE
+/// disconnects (or goes away), with whether it disconnected.
+///
+/// As the program runs inside the server, disconnecting while it is
+/// running stops the whole process.
+pub fn dap_serve(
+    reader: impl BufRead + Send + 'static,
+    writer: impl Write + 'static,
+    load: impl Fn(&str) -> Result<LarkDatabase, String>,
+) -> bool {
+    let requests = Rc::new(spawn_reader(reader));
+    let client = Rc::new(Client::new(writer));
+
+    let mut numbering = Numbering {
+        first_line: 1,
+        first_column: 1,
+    };
+    let mut breakpoints: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut launched = None;
+    let mut configured = false;
+    let mut finished = false;
+
+    while let Ok(request) = requests.recv() {
+        match &request.command[..] {
+            "initialize" => match request.arguments::<InitializeArguments>() {
+                Ok(arguments) => {
+                    numbering = Numbering {
+                        first_line: arguments.lines_start_at1 as usize,
+                        first_column: arguments.columns_start_at1 as usize,
+                    };
+                    client.respond(
+                        &request,
+                        json!({ "supportsConfigurationDoneRequest": true }),
+                    );
+                    client.send_event("initialized", json!({}));
+                }
+                Err(message) => client.respond_error(&request, message),
+            },
+            "launch" if launched.is_some() || finished => {
+                client.respond_error(&request, "a program has already been launched");
+            }
+            "launch" => match request.arguments::<LaunchArguments>() {
+                Ok(arguments) => match load(&arguments.program) {
+                    Ok(db) => {
+                        client.respond(&request, Value::Null);
+                        launched = Some((db, arguments.stop_on_entry));
+                    }
+                    Err(message) => client.respond_error(&request, message),
+                },
+                Err(message) => client.respond_error(&request, message),
+            },
+            "setBreakpoints" => {
+                if let Some((path, lines)) = set_breakpoints(&client, &request, numbering) {
+                    breakpoints.insert(path, lines);
+                }
+            }
+            "configurationDone" => {
+                client.respond(&request, Value::Null);
+                configured = true;
+            }
+            "threads" => respond_threads(&client, &request),
+            "disconnect" | "terminate" => {
+                client.respond(&request, Value::Null);
+                return true;
+            }
+            "continue" | "next" | "stepIn" | "stepOut" | "pause" | "stackTrace" | "scopes"
+            | "variables" => {
+                client.respond_error(&request, "the program is not running");
+            }
+            _ => client.respond_error(&request, "unsupported request"),
+        }
+
+        // The IDE may finish configuring (by setting breakpoints, say)
+        // before or after it launches the program.
+        if configured {
+            if let Some((db, stop_on_entry)) = launched.take() {
+                let mut stepper = Stepper::new();
+                for (path, lines) in breakpoints.drain() {
+                    stepper.set_breakpoints((&path[..]).into_file_name(&db), lines);
+                }
+
+                let debugger = AdapterDebugger {
+                    client: client.clone(),
+                    requests: requests.clone(),
+                    numbering,
+                    stepper,
+                    entry: stop_on_entry,
+                    pause_requested: false,
+                    detached: false,
+                };
+                run(&db, &client, debugger);
+                finished = true;
+            }
+        }
+    }
+
+    false
+}
+
+/// Reads the requests from the IDE on another thread, so that we can
+/// check for them while the program runs.
+fn spawn_reader(mut reader: impl BufRead + Send + 'static) -> Receiver<Request> {
+    let (sender, receiver) = channel();
+
+    thread::spawn(move || loop {
+        let message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => return,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                return;
+            }
+        };
+
+        match serde_json::from_str::<Request>(&message) {
+            Ok(request) => {
+                if sender.send(request).is_err() {
+                    return;
+                }
+            }
+            Err(e) => eprintln!("Error handling request: {:?}", e),
+        }
+    });
+
+    receiver
+}
+
+/// Runs the program to the end, reporting what it prints (and any
+/// runtime error) to the IDE.
+fn run(db: &LarkDatabase, client: &Rc<Client>, mut debugger: AdapterDebugger) {
+    let mut state = EvalState::new();
+
+    // The stepper starts out ready to pause at the first line.
+    if !debugger.entry {
+        debugger.stepper.resume(StepMode::Continue, &state);
+    }
+    state.debugger = Some(Box::new(debugger));
+
+    let mut io_handler = IOHandler::with_writer(Output::new(client.clone()));
+    let exit_code = match lark_eval::eval_with_state(db, &mut state, &mut io_handler) {
+        Ok(()) => 0,
+        Err(error) => {
+            client.send_event(
+                "output",
+                json!({
+                    "category": "stderr",
+                    "output": describe_runtime_error(db, &error),
+                }),
+            );
+            1
+        }
+    };
+
+    client.send_event("exited", json!({ "exitCode": exit_code }));
+    client.send_event("terminated", json!({}));
+}
+
+/// The text of a runtime error, followed by the calls that were in
+/// progress (as in a backtrace).
+fn describe_runtime_error(db: &LarkDatabase, error: &RuntimeError) -> String {
+    let mut text = format!("error: {}\n", error.message);
+    text.push_str(&format!("  at {}\n", describe_span(db, error.span)));
+    for frame in &error.call_stack {
+        text.push_str(&format!(
+            "  in this call to `{}`, at {}\n",
+            function_name(db, frame.function),
+            describe_span(db, frame.span)
+        ));
+    }
+    text
+}
+
+/// `span` as `file:line:column` (with 1-based lines and columns).
+fn describe_span(db: &LarkDatabase, span: Span<FileName>) -> String {
+    let location = db.location(span.file(), span.start());
+    format!(
+        "{}:{}:{}",
+        span.file().untern(db),
+        location.line + 1,
+        location.column + 1
+    )
+}
+
+/// Responds to `setBreakpoints`, returning the path of the file and the
+/// (0-based) lines of its breakpoints.
+fn set_breakpoints(
+    client: &Client,
+    request: &Request,
+    numbering: Numbering,
+) -> Option<(String, Vec<usize>)> {
+    let arguments = match request.arguments::<SetBreakpointsArguments>() {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            client.respond_error(request, message);
+            return None;
+        }
+    };
+
+    let path = match arguments.source.path {
+        Some(path) => path,
+        None => {
+            client.respond_error(request, "breakpoints can only be set in files");
+            return None;
+        }
+    };
+
+    let lines: Vec<Option<usize>> = arguments
+        .breakpoints
+        .iter()
+        .map(|breakpoint| breakpoint.line.checked_sub(numbering.first_line))
+        .collect();
+
+    let breakpoints: Vec<Value> = arguments
+        .breakpoints
+        .iter()
+        .zip(&lines)
+        .map(|(breakpoint, line)| {
+            json!({
+                "verified": line.is_some(),
+                "line": breakpoint.line,
+            })
+        })
+        .collect();
+    client.respond(request, json!({ "breakpoints": breakpoints }));
+
+    Some((path, lines.into_iter().flatten().collect()))
+}
+
+fn respond_threads(client: &Client, request: &Request) {
+    client.respond(
+        request,
+        json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+    );
+}
+
+/// Takes the IDE's requests while the program runs.
+struct AdapterDebugger {
+    client: Rc<Client>,
+    requests: Rc<Receiver<Request>>,
+    numbering: Numbering,
+    stepper: Stepper,
+
+    /// Whether the next pause is the one at the start of the program.
+    entry: bool,
+
+    /// Whether the next pause is because the IDE asked for it.
+    pause_requested: bool,
+
+    /// Set once the IDE has gone away, after which we let the program
+    /// run to the end.
+    detached: bool,
+}
+
+impl Debugger for AdapterDebugger {
+    fn before_expression(
+        &mut self,
+        db: &LarkDatabase,
+        fn_body: &hir::FnBody,
+        expression: hir::Expression,
+        state: &EvalState,
+    ) {
+        if self.detached {
+            return;
+        }
+
+        loop {
+            match self.requests.try_recv() {
+                Ok(request) => {
+                    self.handle(db, state, &request, None);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.detached = true;
+                    return;
+                }
+            }
+        }
+
+        let pause = match self.stepper.should_pause(db, fn_body, expression, state) {
+            Some(pause) => pause,
+            None => return,
+        };
+
+        let reason = if self.entry {
+            "entry"
+        } else if self.pause_requested {
+            "pause"
+        } else {
+            match pause {
+                Pause::Breakpoint => "breakpoint",
+                Pause::Step => "step",
+            }
+        };
+        self.entry = false;
+        self.pause_requested = false;
+
+        self.client.send_event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "threadId": THREAD_ID,
+                "allThreadsStopped": true,
+            }),
+        );
+
+        let frames = debugger::backtrace(state, fn_body, expression);
+        loop {
+            let request = match self.requests.recv() {
+                Ok(request) => request,
+                Err(_) => {
+                    self.detached = true;
+                    return;
+                }
+            };
+
+            if let Some(mode) = self.handle(db, state, &request, Some(&frames)) {
+                self.stepper.resume(mode, state);
+                return;
+            }
+        }
+    }
+}
+
+impl AdapterDebugger {
+    /// Handles a request that came in while the program runs or -- if
+    /// we have the `frames` of the calls in progress -- is paused.
+    /// Returns how to resume the program, if the request resumes it.
+    fn handle(
+        &mut self,
+        db: &LarkDatabase,
+        state: &EvalState,
+        request: &Request,
+        frames: Option<&[Frame]>,
+    ) -> Option<StepMode> {
+        let client = self.client.clone();
+
+        let mode = match (&request.command[..], frames) {
+            ("continue", Some(_)) => StepMode::Continue,
+            ("next", Some(_)) => StepMode::StepOver,
+            ("stepIn", Some(_)) => StepMode::StepIn,
+            ("stepOut", Some(_)) => StepMode::StepOut,
+            ("stackTrace", Some(frames)) => {
+                self.respond_stack_trace(db, request, frames);
+                return None;
+            }
+            ("scopes", Some(frames)) => {
+                match request.arguments::<ScopesArguments>() {
+                    Ok(ScopesArguments { frame_id }) if frame_id < frames.len() => {
+                        // The variables of each frame are referred to by
+                        // the frame's index plus one (as 0 means "none").
+                        client.respond(
+                            request,
+                            json!({
+                                "scopes": [{
+                                    "name": "Locals",
+                                    "variablesReference": frame_id + 1,
+                                    "expensive": false,
+                                }]
+                            }),
+                        );
+                    }
+                    Ok(_) => client.respond_error(request, "there is no such frame"),
+                    Err(message) => client.respond_error(request, message),
+                }
+                return None;
+            }
+            ("variables", Some(frames)) => {
+                match request.arguments::<VariablesArguments>() {
+                    Ok(VariablesArguments {
+                        variables_reference,
+                    }) if variables_reference >= 1 && variables_reference <= frames.len() => {
+                        let frame = &frames[variables_reference - 1];
+                        let variables: Vec<Value> = debugger::variables(db, state, frame)
+                            .into_iter()
+                            .map(|(name, value)| {
+                                json!({
+                                    "name": name.to_string(),
+                                    "value": value.to_string(),
+                                    "variablesReference": 0,
+                                })
+                            })
+                            .collect();
+                        client.respond(request, json!({ "variables": variables }));
+                    }
+                    Ok(_) => client.respond_error(request, "there are no such variables"),
+                    Err(message) => client.respond_error(request, message),
+                }
+                return None;
+            }
+            ("pause", Some(_)) => {
+                client.respond(request, Value::Null);
+                return None;
+            }
+            ("pause", None) => {
+                self.pause_requested = true;
+                self.stepper.resume(StepMode::StepIn, state);
+                client.respond(request, Value::Null);
+                return None;
+            }
+            ("setBreakpoints", _) => {
+                if let Some((path, lines)) = set_breakpoints(&client, request, self.numbering) {
+                    self.stepper
+                        .set_breakpoints((&path[..]).into_file_name(db), lines);
+                }
+                return None;
+            }
+            ("threads", _) => {
+                respond_threads(&client, request);
+                return None;
+            }
+            ("disconnect", _) | ("terminate", _) => {
+                client.respond(request, Value::Null);
+                client.send_event("terminated", json!({}));
+                std::process::exit(0);
+            }
+            ("continue", None)
+            | ("next", None)
+            | ("stepIn", None)
+            | ("stepOut", None)
+            | ("stackTrace", None)
+            | ("scopes", None)
+            | ("variables", None) => {
+                client.respond_error(request, "the program is not paused");
+                return None;
+            }
+            ("initialize", _) | ("launch", _) | ("configurationDone", _) => {
+                client.respond_error(request, "the program is already running");
+                return None;
+            }
+            _ => {
+                client.respond_error(request, "unsupported request");
+                return None;
+            }
+        };
+
+        let body = if mode == StepMode::Continue {
+            json!({ "allThreadsContinued": true })
+        } else {
+            Value::Null
+        };
+        client.respond(request, body);
+        Some(mode)
+    }
+
+    fn respond_stack_trace(&self, db: &LarkDatabase, request: &Request, frames: &[Frame]) {
+        let arguments = match request.arguments::<StackTraceArguments>() {
+            Ok(arguments) => arguments,
+            Err(message) => {
+                self.client.respond_error(request, message);
+                return;
+            }
+        };
+
+        let levels = match arguments.levels {
+            0 => frames.len(),
+            levels => levels,
+        };
+
+        // The ID of each frame is its index (innermost first).
+        let stack_frames: Vec<Value> = frames
+            .iter()
+            .enumerate()
+            .skip(arguments.start_frame)
+            .take(levels)
+            .map(|(id, frame)| {
+                let file = frame.span.file();
+                let path = file.untern(db).to_string();
+                let name = Path::new(&path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned());
+                let location = db.location(file, frame.span.start());
+
+                json!({
+                    "id": id,
+                    "name": function_name(db, frame.function).to_string(),
+                    "source": { "name": name, "path": path },
+                    "line": location.line + self.numbering.first_line,
+                    "column": location.column + self.numbering.first_column,
+                })
+            })
+            .collect();
+
+        self.client.respond(
+            request,
+            json!({
+                "stackFrames": stack_frames,
+                "totalFrames": frames.len(),
+            }),
+        );
+    }
+}
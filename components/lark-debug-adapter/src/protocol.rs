@@ -0,0 +1,191 @@
+//! The messages of the Debug Adapter Protocol that we use, and the
+//! `Client` that sends ours to the IDE.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::cell::{Cell, RefCell};
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// A request from the IDE. The `arguments` depend on the `command`, so
+/// we parse them (with `Request::arguments`) once we know what it is.
+#[derive(Debug, Deserialize)]
+pub struct Request {
+    pub seq: u64,
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+impl Request {
+    pub fn arguments<T: DeserializeOwned>(&self) -> Result<T, String> {
+        serde_json::from_value(self.arguments.clone())
+            .map_err(|error| format!("invalid arguments to `{}`: {}", self.command, error))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializeArguments {
+    #[serde(default = "starts_at_1")]
+    pub lines_start_at1: bool,
+    #[serde(default = "starts_at_1")]
+    pub columns_start_at1: bool,
+}
+
+fn starts_at_1() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchArguments {
+    /// The path of the program to debug.
+    pub program: String,
+    #[serde(default)]
+    pub stop_on_entry: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetBreakpointsArguments {
+    pub source: Source,
+    #[serde(default)]
+    pub breakpoints: Vec<SourceBreakpoint>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Source {
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SourceBreakpoint {
+    pub line: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackTraceArguments {
+    #[serde(default)]
+    pub start_frame: usize,
+
+    /// The number of frames to return, where 0 means all of them.
+    #[serde(default)]
+    pub levels: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopesArguments {
+    pub frame_id: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariablesArguments {
+    pub variables_reference: usize,
+}
+
+/// Sends our responses and events to the IDE, numbering them as the
+/// protocol requires.
+pub struct Client {
+    writer: RefCell<Box<dyn Write>>,
+    seq: Cell<u64>,
+}
+
+impl Client {
+    pub fn new(writer: impl Write + 'static) -> Client {
+        Client {
+            writer: RefCell::new(Box::new(writer)),
+            seq: Cell::new(1),
+        }
+    }
+
+    pub fn respond(&self, request: &Request, body: Value) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": true,
+            "command": request.command,
+            "body": body,
+        }));
+    }
+
+    pub fn respond_error(&self, request: &Request, message: impl Into<String>) {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": false,
+            "command": request.command,
+            "message": message.into(),
+        }));
+    }
+
+    pub fn send_event(&self, event: &str, body: Value) {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }));
+    }
+
+    fn send(&self, mut message: Value) {
+        message["seq"] = json!(self.seq.get());
+        self.seq.set(self.seq.get() + 1);
+
+        let content = message.to_string();
+        let mut writer = self.writer.borrow_mut();
+
+        // If the IDE has gone away, we will find out when we next read
+        // from it.
+        let _ = write!(
+            writer,
+            "Content-Length: {}\r\n\r\n{}",
+            content.len(),
+            content
+        );
+        let _ = writer.flush();
+    }
+}
+
+/// Sends what the program prints to the IDE as `output` events, a line
+/// at a time.
+pub struct Output {
+    client: Rc<Client>,
+    line: Vec<u8>,
+}
+
+impl Output {
+    pub fn new(client: Rc<Client>) -> Output {
+        Output {
+            client,
+            line: vec![],
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        if self.line.ends_with(b"\n") {
+            self.flush()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line.is_empty() {
+            let output = String::from_utf8_lossy(&self.line).into_owned();
+            self.client.send_event(
+                "output",
+                json!({
+                    "category": "stdout",
+                    "output": output,
+                }),
+            );
+            self.line.clear();
+        }
+        Ok(())
+    }
+}
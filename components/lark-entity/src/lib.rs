@@ -125,6 +125,18 @@ impl EntityData {
                 kind: ItemKind::Struct,
                 ..
             }
+            | EntityData::ItemName {
+                kind: ItemKind::Enum,
+                ..
+            }
+            | EntityData::ItemName {
+                kind: ItemKind::Import,
+                ..
+            }
+            | EntityData::MemberName {
+                kind: MemberKind::Variant,
+                ..
+            }
             | EntityData::LangItem(LangItem::Int)
             | EntityData::LangItem(LangItem::Tuple(_))
             | EntityData::LangItem(LangItem::String)
@@ -158,10 +170,22 @@ impl EntityData {
                 kind: ItemKind::Struct,
                 ..
             }
+            | EntityData::ItemName {
+                kind: ItemKind::Enum,
+                ..
+            }
+            | EntityData::ItemName {
+                kind: ItemKind::Import,
+                ..
+            }
             | EntityData::MemberName {
                 kind: MemberKind::Field,
                 ..
             }
+            | EntityData::MemberName {
+                kind: MemberKind::Variant,
+                ..
+            }
             | EntityData::LangItem(_)
             | EntityData::Error(_) => false,
 
@@ -197,12 +221,23 @@ pub enum LangItem {
 pub enum ItemKind {
     Struct,
     Function,
+
+    /// An `enum`, whose members are its variants.
+    Enum,
+
+    /// A `use` of an item from another module; name resolution
+    /// replaces it with the item that it imports.
+    Import,
 }
 
 #[derive(Copy, Clone, Debug, DebugWith, PartialEq, Eq, Hash)]
 pub enum MemberKind {
     Field,
     Method,
+
+    /// A variant of an `enum`; its payload fields are its own
+    /// `Field` members.
+    Variant,
 }
 
 lark_intern::intern_tables! {
@@ -65,11 +65,95 @@ impl ErrorReported {
 pub struct Diagnostic {
     pub span: Span<FileName>,
     pub label: String,
+    pub severity: Severity,
+
+    /// A short name for the kind of problem (such as
+    /// `unknown-identifier`), which IDEs show with the message.
+    pub code: Option<&'static str>,
+
+    /// Other spans that help to explain the diagnostic, each with a
+    /// label of its own.
+    pub secondary_labels: Vec<SecondaryLabel>,
+
+    /// Changes that the user might make to fix the problem.
+    pub suggestions: Vec<Suggestion>,
 }
 
 impl Diagnostic {
+    /// Creates an error with no secondary labels.
     pub fn new(label: String, span: Span<FileName>) -> Self {
-        Diagnostic { label, span }
+        Diagnostic {
+            span,
+            label,
+            severity: Severity::Error,
+            code: None,
+            secondary_labels: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_secondary_label(mut self, label: impl Into<String>, span: Span<FileName>) -> Self {
+        self.secondary_labels.push(SecondaryLabel {
+            span,
+            label: label.into(),
+        });
+        self
+    }
+
+    pub fn with_suggestion(mut self, label: impl Into<String>, edits: Vec<SuggestedEdit>) -> Self {
+        self.suggestions.push(Suggestion {
+            label: label.into(),
+            edits,
+        });
+        self
+    }
+}
+
+#[derive(Copy, Clone, Debug, DebugWith, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+#[derive(Clone, Debug, DebugWith, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SecondaryLabel {
+    pub span: Span<FileName>,
+    pub label: String,
+}
+
+/// A fix for a diagnostic, such as replacing a misspelled name, which
+/// the IDE can offer to apply.
+#[derive(Clone, Debug, DebugWith, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Suggestion {
+    pub label: String,
+    pub edits: Vec<SuggestedEdit>,
+}
+
+/// Replaces the text at `span` (which is empty for an insertion) with
+/// `replacement`.
+#[derive(Clone, Debug, DebugWith, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SuggestedEdit {
+    pub span: Span<FileName>,
+    pub replacement: String,
+}
+
+impl SuggestedEdit {
+    pub fn new(span: Span<FileName>, replacement: impl Into<String>) -> Self {
+        SuggestedEdit {
+            span,
+            replacement: replacement.into(),
+        }
     }
 }
 
@@ -0,0 +1,237 @@
+//! Support for debuggers, which watch the evaluation of a program and
+//! pause it at breakpoints or after a step. The debugger itself (say,
+//! `lark debug`) decides what to do while the program is paused.
+
+use crate::{EvalState, Value};
+use lark_entity::{Entity, EntityData};
+use lark_hir as hir;
+use lark_intern::Untern;
+use lark_parser::ParserDatabase;
+use lark_query_system::LarkDatabase;
+use lark_span::{FileName, Span};
+use lark_string::Text;
+use std::collections::{BTreeSet, HashMap};
+
+pub trait Debugger {
+    /// Called before `expression` (in `fn_body`) is evaluated; the
+    /// program stays paused until this returns.
+    fn before_expression(
+        &mut self,
+        db: &LarkDatabase,
+        fn_body: &hir::FnBody,
+        expression: hir::Expression,
+        state: &EvalState,
+    );
+}
+
+/// How far to let the program run before pausing it again (unless it
+/// reaches a breakpoint first).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StepMode {
+    /// Run until a breakpoint.
+    Continue,
+
+    /// Pause at the next line, even if that is in a function that the
+    /// current line calls.
+    StepIn,
+
+    /// Pause at the next line of the current call (or, once it
+    /// returns, of its caller).
+    StepOver,
+
+    /// Pause once the current call has returned to its caller.
+    StepOut,
+}
+
+/// Why the program paused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pause {
+    Breakpoint,
+    Step,
+}
+
+/// Decides where to pause the program, given the breakpoints and how
+/// it was last resumed. Like most debuggers, we pause at lines rather
+/// than at expressions: each time evaluation moves to another line (or
+/// into or out of a call), we are at a new line.
+pub struct Stepper {
+    /// The (0-based) lines to pause at in each file.
+    breakpoints: HashMap<FileName, BTreeSet<usize>>,
+
+    mode: StepMode,
+
+    /// The number of calls in progress when the program was resumed.
+    depth: usize,
+
+    /// The file, line and call depth of the last expression evaluated.
+    previous: Option<(FileName, usize, usize)>,
+}
+
+impl Default for Stepper {
+    fn default() -> Self {
+        Stepper::new()
+    }
+}
+
+impl Stepper {
+    /// A stepper that pauses at the first line of the program.
+    pub fn new() -> Stepper {
+        Stepper {
+            breakpoints: HashMap::new(),
+            mode: StepMode::StepIn,
+            depth: 0,
+            previous: None,
+        }
+    }
+
+    /// Replaces the breakpoints in `file` with `lines`.
+    pub fn set_breakpoints(&mut self, file: FileName, lines: impl IntoIterator<Item = usize>) {
+        self.breakpoints.insert(file, lines.into_iter().collect());
+    }
+
+    /// Adds a breakpoint, returning false if it was already there.
+    pub fn add_breakpoint(&mut self, file: FileName, line: usize) -> bool {
+        self.breakpoints.entry(file).or_default().insert(line)
+    }
+
+    /// Removes a breakpoint, returning false if there wasn't one.
+    pub fn remove_breakpoint(&mut self, file: FileName, line: usize) -> bool {
+        match self.breakpoints.get_mut(&file) {
+            Some(lines) => lines.remove(&line),
+            None => false,
+        }
+    }
+
+    /// Lets the program run again, in the given `mode`.
+    pub fn resume(&mut self, mode: StepMode, state: &EvalState) {
+        self.mode = mode;
+        self.depth = state.call_stack.len();
+    }
+
+    /// Whether (and why) to pause before evaluating `expression` (in
+    /// `fn_body`).
+    pub fn should_pause(
+        &mut self,
+        db: &LarkDatabase,
+        fn_body: &hir::FnBody,
+        expression: hir::Expression,
+        state: &EvalState,
+    ) -> Option<Pause> {
+        let span = fn_body.span(expression);
+        let line = db.location(span.file(), span.start()).line;
+        let depth = state.call_stack.len();
+
+        let position = Some((span.file(), line, depth));
+        if position == self.previous {
+            return None;
+        }
+        self.previous = position;
+
+        let at_breakpoint = self
+            .breakpoints
+            .get(&span.file())
+            .map_or(false, |lines| lines.contains(&line));
+        let step_done = match self.mode {
+            StepMode::Continue => false,
+            StepMode::StepIn => true,
+            StepMode::StepOver => depth <= self.depth,
+            StepMode::StepOut => depth < self.depth,
+        };
+
+        if at_breakpoint {
+            Some(Pause::Breakpoint)
+        } else if step_done {
+            Some(Pause::Step)
+        } else {
+            None
+        }
+    }
+}
+
+/// A call that is in progress, as a debugger shows it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub function: Entity,
+
+    /// What the call is evaluating: for the innermost call, the
+    /// expression that we are about to evaluate; for the others, the
+    /// call that they are waiting on.
+    pub span: Span<FileName>,
+
+    /// The index of the call's variables in `EvalState::scopes`.
+    pub depth: usize,
+}
+
+/// The calls that are in progress (innermost first) when we are about
+/// to evaluate `expression` in `fn_body`.
+pub fn backtrace(
+    state: &EvalState,
+    fn_body: &hir::FnBody,
+    expression: hir::Expression,
+) -> Vec<Frame> {
+    let innermost = Frame {
+        function: state
+            .current_function
+            .expect("evaluating an expression outside of a function"),
+        span: fn_body.span(expression),
+        depth: state.call_stack.len(),
+    };
+
+    let callers = state
+        .call_stack
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(depth, call)| Frame {
+            function: call.caller,
+            span: call.span,
+            depth,
+        });
+
+    std::iter::once(innermost).chain(callers).collect()
+}
+
+/// The name of the function (or method) `entity`.
+pub fn function_name(db: &LarkDatabase, entity: Entity) -> Text {
+    match entity.untern(db) {
+        EntityData::ItemName { id, .. } | EntityData::MemberName { id, .. } => id.untern(db),
+        _ => unreachable!("{:?} is not a function", entity),
+    }
+}
+
+/// The variables of the call `frame` (in the order in which they were
+/// created), with their names in the source and their current values.
+pub fn variables<'state>(
+    db: &LarkDatabase,
+    state: &'state EvalState,
+    frame: &Frame,
+) -> Vec<(Text, &'state Value)> {
+    let fn_body = db.fn_body(frame.function).value;
+    let scope = &state.scopes[frame.depth];
+    let outer_variables: Vec<hir::Variable> = state.scopes[..frame.depth]
+        .iter()
+        .flatten()
+        .cloned()
+        .collect();
+
+    // All the calls share `state.variables`, which has a stack of
+    // values for each variable; the value of our variable is the one
+    // after those of the outer calls (and of any earlier variable of
+    // ours that it shadows).
+    let count = |variables: &[hir::Variable], variable| {
+        variables.iter().filter(|&&v| v == variable).count()
+    };
+
+    scope
+        .iter()
+        .enumerate()
+        .filter(|&(position, variable)| !scope[position + 1..].contains(variable))
+        .map(|(position, &variable)| {
+            let index = count(&outer_variables, variable) + count(&scope[..position], variable);
+            let name = fn_body.tables[fn_body.tables[variable].name]
+                .text
+                .untern(db);
+            (name, &state.variables[&variable][index])
+        })
+        .collect()
+}
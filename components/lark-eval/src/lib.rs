@@ -1,28 +1,111 @@
-use lark_debug_with::DebugWith;
-use lark_entity::{Entity, EntityData, ItemKind, LangItem, MemberKind};
+use lark_entity::{Entity, EntityData, ItemKind, LangItem};
 use lark_hir as hir;
 use lark_intern::{Intern, Untern};
 use lark_parser::{ParserDatabase, ParserDatabaseExt};
 use lark_query_system::LarkDatabase;
+use lark_span::{FileName, Span};
+use lark_ty::declaration::DeclaredPermKind;
+use lark_ty::{BaseData, BaseKind};
+use lark_type_check::TypeCheckDatabase;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
+use std::io::Write;
+
+pub mod debugger;
+
+use crate::debugger::Debugger;
 
 pub struct EvalState {
     pub variables: HashMap<hir::Variable, Vec<Value>>,
-    pub skip_until: Option<hir::Expression>,
-    pub current_expression: Option<hir::Expression>,
+
+    /// Keeps the variables of each `let` (and match arm) after their
+    /// scope ends, so that the REPL can carry them over to the next
+    /// input.
     pub is_repl: bool,
+
+    /// Set by `break` or `continue` and consumed by the innermost
+    /// enclosing `while`; while set, the rest of the loop body is skipped.
+    pub loop_control: Option<LoopControl>,
+
+    /// The function whose body is being evaluated; its type-check
+    /// results tell us whether an integer literal is an `int` or a `uint`.
+    pub current_function: Option<Entity>,
+
+    /// The calls that are in progress, outermost first.
+    pub call_stack: Vec<CallFrame>,
+
+    /// The variables that were created (and not yet popped) by the
+    /// function that evaluation started in, followed by those of each
+    /// call in `call_stack`. This tells a debugger which of the values
+    /// in `variables` belong to which call.
+    pub scopes: Vec<Vec<hir::Variable>>,
+
+    /// Consulted before each expression is evaluated, if set.
+    pub debugger: Option<Box<dyn Debugger>>,
+}
+
+/// A call of a Lark function that is in progress.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CallFrame {
+    /// The function that was called.
+    pub function: Entity,
+
+    /// The function that made the call.
+    pub caller: Entity,
+
+    /// The call expression.
+    pub span: Span<FileName>,
+}
+
+/// An error that stops evaluation, like an arithmetic overflow or a
+/// division by zero.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub message: String,
+
+    /// The part of the program that failed.
+    pub span: Span<FileName>,
+
+    /// The calls that were in progress when the error occurred,
+    /// innermost first.
+    pub call_stack: Vec<CallFrame>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoopControl {
+    Break,
+    Continue,
 }
 
 impl EvalState {
     pub fn create_variable(&mut self, variable: hir::Variable) {
         let variable_stack = self.variables.entry(variable).or_insert(Vec::new());
         variable_stack.push(Value::Void);
+        self.scopes.last_mut().unwrap().push(variable);
     }
 
-    pub fn pop_variable(&mut self, variable: hir::Variable) {
+    pub fn pop_variable(&mut self, variable: hir::Variable) -> Value {
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some(index) = scope.iter().rposition(|&v| v == variable) {
+            scope.remove(index);
+        }
+
         let variable_stack = self.variables.get_mut(&variable).unwrap();
-        variable_stack.pop();
+        variable_stack.pop().unwrap()
+    }
+
+    /// Pops all of the variables of the innermost scope, returning
+    /// their values in the order in which they were created.
+    fn pop_scope(&mut self) -> Vec<Value> {
+        let scope = self.scopes.pop().unwrap();
+        let mut values: Vec<Value> = scope
+            .iter()
+            .rev()
+            .map(|variable| self.variables.get_mut(variable).unwrap().pop().unwrap())
+            .collect();
+        values.reverse();
+        values
     }
 
     pub fn assign_to_variable(&mut self, variable: hir::Variable, value: Value) {
@@ -33,31 +116,31 @@ impl EvalState {
     pub fn new() -> EvalState {
         EvalState {
             variables: HashMap::new(),
-            skip_until: None,
-            current_expression: None,
             is_repl: false,
+            loop_control: None,
+            current_function: None,
+            call_stack: vec![],
+            scopes: vec![vec![]],
+            debugger: None,
         }
     }
 
-    pub fn set_current_expression(&mut self, expression: hir::Expression) {
-        self.current_expression = Some(expression);
-
-        if self.skip_until.is_some() {
-            if let Some(skip_until_expr) = self.skip_until {
-                if skip_until_expr == expression {
-                    self.skip_until = None;
-                }
-            }
+    /// An error at `span`, raised by the innermost call in progress.
+    pub fn error(&self, span: Span<FileName>, message: impl Into<String>) -> RuntimeError {
+        RuntimeError {
+            message: message.into(),
+            span,
+            call_stack: self.call_stack.iter().rev().cloned().collect(),
         }
     }
-
-    pub fn ready_to_execute(&self) -> bool {
-        self.skip_until.is_none()
-    }
 }
 
 pub struct IOHandler {
     pub redirect: Option<String>,
+
+    /// Where the output goes if it isn't redirected (stdout, unless
+    /// this is set).
+    writer: Option<Box<dyn Write>>,
 }
 
 impl IOHandler {
@@ -65,9 +148,21 @@ impl IOHandler {
         if redirect_output {
             IOHandler {
                 redirect: Some(String::new()),
+                writer: None,
             }
         } else {
-            IOHandler { redirect: None }
+            IOHandler {
+                redirect: None,
+                writer: None,
+            }
+        }
+    }
+
+    /// Sends the output to `writer` as it is printed.
+    pub fn with_writer(writer: impl Write + 'static) -> IOHandler {
+        IOHandler {
+            redirect: None,
+            writer: Some(Box::new(writer)),
         }
     }
 
@@ -75,6 +170,8 @@ impl IOHandler {
         if let Some(redirect_output) = &mut self.redirect {
             redirect_output.push_str(&output);
             redirect_output.push_str("\n");
+        } else if let Some(writer) = &mut self.writer {
+            let _ = writeln!(writer, "{}", output);
         } else {
             println!("{}", output);
         }
@@ -86,12 +183,10 @@ pub enum Value {
     Void,
     Bool(bool),
     U32(u32),
+    I32(i32),
     Str(String),
     Struct(Entity, HashMap<lark_string::GlobalIdentifier, Value>),
     Reference(usize), // a reference into the value stack
-
-    // REPL: placeholder value to denote we're currently skipping eval
-    Skipped,
 }
 
 impl fmt::Display for Value {
@@ -101,12 +196,12 @@ impl fmt::Display for Value {
             "{}",
             match self {
                 Value::U32(u) => u.to_string(),
+                Value::I32(i) => i.to_string(),
                 Value::Str(s) => s.clone(),
                 Value::Bool(b) => b.to_string(),
                 Value::Reference(r) => format!("reference to {}", r),
                 Value::Void => "<void>".into(),
                 Value::Struct(_, s) => format!("{:?}", s),
-                Value::Skipped => "<repl placeholder>".into(),
             }
         )
     }
@@ -117,63 +212,282 @@ pub fn eval_place(
     fn_body: &hir::FnBody,
     place: hir::Place,
     state: &mut EvalState,
-) -> Value {
+) -> Result<Value, RuntimeError> {
     let place_data = &fn_body.tables[place];
 
     match place_data {
         hir::PlaceData::Entity(entity) => match entity.untern(db) {
-            EntityData::LangItem(LangItem::True) => Value::Bool(true),
-            EntityData::LangItem(LangItem::False) => Value::Bool(false),
-            _ => unimplemented!("EntityData not yet support in eval"),
+            EntityData::LangItem(LangItem::True) => Ok(Value::Bool(true)),
+            EntityData::LangItem(LangItem::False) => Ok(Value::Bool(false)),
+            _ => Err(state.error(fn_body.span(place), "eval does not yet support this entity")),
         },
         hir::PlaceData::Variable(variable) => {
             let stack = state.variables.get(variable).unwrap();
-            stack.last().unwrap().clone()
+            Ok(stack.last().unwrap().clone())
         }
         hir::PlaceData::Field { owner, name } => {
-            let target = eval_place(db, fn_body, *owner, state);
+            let target = eval_place(db, fn_body, *owner, state)?;
             match target {
-                Value::Struct(_, s) => match fn_body.tables[*name] {
-                    hir::IdentifierData { text } => s.get(&text).unwrap().clone(),
-                },
-                _ => panic!("Member access (.) into value that is not a struct"),
+                Value::Struct(_, mut s) => {
+                    let text = fn_body.tables[*name].text;
+                    s.remove(&text).ok_or_else(|| {
+                        state.error(
+                            fn_body.span(*name),
+                            format!("value has no field `{}`", text.untern(db)),
+                        )
+                    })
+                }
+                _ => Err(state.error(
+                    fn_body.span(place),
+                    "member access (.) into value that is not a struct",
+                )),
             }
         }
-        hir::PlaceData::Temporary { .. } => unimplemented!("Can't yet eval temporary places"),
+        hir::PlaceData::Temporary { .. } => Err(state.error(
+            fn_body.span(place),
+            "eval does not yet support temporary places",
+        )),
     }
 }
 
 fn eval_fn_call(
     db: &LarkDatabase,
     fn_body: &hir::FnBody,
+    expression: hir::Expression,
     entity: Entity,
     arguments: hir::List<hir::Expression>,
     state: &mut EvalState,
-    ready_to_execute: bool,
     io_handler: &mut IOHandler,
-) -> Value {
+) -> Result<Value, RuntimeError> {
+    let argument_values = arguments
+        .iter(fn_body)
+        .map(|argument| eval_expression(db, fn_body, argument, state, io_handler))
+        .collect::<Result<_, _>>()?;
+
+    let (return_value, _) = eval_call(
+        db,
+        entity,
+        fn_body.span(expression),
+        argument_values,
+        state,
+        io_handler,
+    )?;
+
+    Ok(return_value)
+}
+
+/// Invokes the function (or method) `entity` on the given arguments,
+/// returning its result along with the final values of its parameters.
+/// The call itself is at `call_span`.
+fn eval_call(
+    db: &LarkDatabase,
+    entity: Entity,
+    call_span: Span<FileName>,
+    argument_values: Vec<Value>,
+    state: &mut EvalState,
+    io_handler: &mut IOHandler,
+) -> Result<(Value, Vec<Value>), RuntimeError> {
     let target = db.fn_body(entity).value;
+    let parameters = target.arguments.unwrap();
 
-    for (arg, param) in arguments
-        .iter(fn_body)
-        .zip(target.arguments.unwrap().iter(&target))
-    {
-        let arg_value = eval_expression(db, fn_body, arg, state, io_handler);
-        state.create_variable(param);
-        state.assign_to_variable(param, arg_value);
+    state.scopes.push(vec![]);
+    for (parameter, argument_value) in parameters.iter(&target).zip(argument_values) {
+        state.create_variable(parameter);
+        state.assign_to_variable(parameter, argument_value);
+    }
+
+    let caller = state.current_function.replace(entity);
+    state.call_stack.push(CallFrame {
+        function: entity,
+        caller: caller.expect("call outside of a function"),
+        span: call_span,
+    });
+    let return_value = eval_function(db, &target, state, io_handler);
+    state.call_stack.pop();
+    state.current_function = caller;
+
+    // Even if the call failed, the variables that it created go out of
+    // scope (the REPL carries on after an error). The parameters were
+    // created first.
+    let mut parameter_values = state.pop_scope();
+    parameter_values.truncate(parameters.len());
+
+    Ok((return_value?, parameter_values))
+}
+
+/// The method that the type checker resolved `method` (the name in a
+/// method call) to.
+fn method_entity(db: &LarkDatabase, state: &EvalState, method: hir::Identifier) -> Entity {
+    let function = state
+        .current_function
+        .expect("evaluating an expression outside of a function");
+    let results = db.base_type_check(function).into_value();
+    results.entities[&method.into()]
+}
+
+/// The permission with which the method `entity` accesses its `self`.
+fn self_permission(db: &LarkDatabase, entity: Entity) -> DeclaredPermKind {
+    let signature = db.signature(entity).into_value().unwrap();
+    signature.inputs[0].perm.untern(db)
+}
+
+fn values_equal(lhs: Value, rhs: Value) -> Result<bool, &'static str> {
+    match (lhs, rhs) {
+        (Value::U32(l), Value::U32(r)) => Ok(l == r),
+        (Value::I32(l), Value::I32(r)) => Ok(l == r),
+        (Value::Bool(l), Value::Bool(r)) => Ok(l == r),
+        (Value::Str(l), Value::Str(r)) => Ok(l == r),
+        _ => Err("comparison of unsupported values"),
     }
+}
+
+/// Applies an arithmetic operator, failing on overflow and division
+/// by zero (where the built program panics).
+fn eval_arithmetic(
+    operator: hir::BinaryOperator,
+    lhs: Value,
+    rhs: Value,
+) -> Result<Value, &'static str> {
+    let divides = match operator {
+        hir::BinaryOperator::Divide | hir::BinaryOperator::Modulo => true,
+        _ => false,
+    };
+
+    let result = match (lhs, rhs) {
+        (Value::U32(_), Value::U32(0)) | (Value::I32(_), Value::I32(0)) if divides => {
+            return Err("division by zero");
+        }
+        (Value::U32(l), Value::U32(r)) => match operator {
+            hir::BinaryOperator::Add => l.checked_add(r),
+            hir::BinaryOperator::Subtract => l.checked_sub(r),
+            hir::BinaryOperator::Multiply => l.checked_mul(r),
+            hir::BinaryOperator::Divide => l.checked_div(r),
+            hir::BinaryOperator::Modulo => l.checked_rem(r),
+            _ => unreachable!(),
+        }
+        .map(Value::U32),
+        (Value::I32(l), Value::I32(r)) => match operator {
+            hir::BinaryOperator::Add => l.checked_add(r),
+            hir::BinaryOperator::Subtract => l.checked_sub(r),
+            hir::BinaryOperator::Multiply => l.checked_mul(r),
+            hir::BinaryOperator::Divide => l.checked_div(r),
+            hir::BinaryOperator::Modulo => l.checked_rem(r),
+            _ => unreachable!(),
+        }
+        .map(Value::I32),
+        _ => return Err("arithmetic on non-numeric values"),
+    };
+
+    result.ok_or("arithmetic overflow")
+}
 
-    let return_value = if ready_to_execute {
-        eval_function(db, &target, state, io_handler)
-    } else {
-        Value::Skipped
+fn eval_comparison(
+    operator: hir::BinaryOperator,
+    lhs: Value,
+    rhs: Value,
+) -> Result<Value, &'static str> {
+    let ordering = match (lhs, rhs) {
+        (Value::U32(l), Value::U32(r)) => l.cmp(&r),
+        (Value::I32(l), Value::I32(r)) => l.cmp(&r),
+        _ => return Err("comparison of non-numeric values"),
     };
 
-    for argument in target.arguments.unwrap().iter(&target) {
-        state.pop_variable(argument);
+    Ok(Value::Bool(match operator {
+        hir::BinaryOperator::LessThan => ordering == Ordering::Less,
+        hir::BinaryOperator::LessThanOrEqual => ordering != Ordering::Greater,
+        hir::BinaryOperator::GreaterThan => ordering == Ordering::Greater,
+        hir::BinaryOperator::GreaterThanOrEqual => ordering != Ordering::Less,
+        _ => unreachable!(),
+    }))
+}
+
+/// True if the type checker decided that the integer literal
+/// `expression` is an `int` (rather than a `uint`).
+fn is_int_literal(db: &LarkDatabase, state: &EvalState, expression: hir::Expression) -> bool {
+    let function = state
+        .current_function
+        .expect("evaluating an expression outside of a function");
+    let results = db.base_type_check(function).into_value();
+    match results.ty(expression).base.untern(db) {
+        BaseData {
+            kind: BaseKind::Named(entity),
+            ..
+        } => entity.untern(db) == EntityData::LangItem(LangItem::Int),
+        _ => false,
+    }
+}
+
+/// Stores `value` into `place`; for a field, this updates the struct
+/// that the field belongs to.
+fn assign_to_place(
+    db: &LarkDatabase,
+    fn_body: &hir::FnBody,
+    place: hir::Place,
+    value: Value,
+    state: &mut EvalState,
+) -> Result<(), RuntimeError> {
+    match fn_body.tables[place] {
+        hir::PlaceData::Variable(variable) => state.assign_to_variable(variable, value),
+        hir::PlaceData::Field { owner, name } => {
+            let mut owner_value = eval_place(db, fn_body, owner, state)?;
+            match &mut owner_value {
+                Value::Struct(_, fields) => {
+                    fields.insert(fn_body.tables[name].text, value);
+                }
+                _ => {
+                    return Err(state.error(
+                        fn_body.span(place),
+                        "member access (.) into value that is not a struct",
+                    ));
+                }
+            }
+            assign_to_place(db, fn_body, owner, owner_value, state)?;
+        }
+        _ => {
+            return Err(state.error(
+                fn_body.span(place),
+                "eval does not yet support assigning to this place",
+            ));
+        }
     }
 
-    return_value
+    Ok(())
+}
+
+/// Evaluates the body of the match arm `arm`, with its bindings
+/// assigned from the `fields` of the value being matched.
+fn eval_match_arm(
+    db: &LarkDatabase,
+    fn_body: &hir::FnBody,
+    arm: hir::MatchArm,
+    mut fields: HashMap<lark_string::GlobalIdentifier, Value>,
+    state: &mut EvalState,
+    io_handler: &mut IOHandler,
+) -> Result<Value, RuntimeError> {
+    let hir::MatchArmData { bindings, body, .. } = fn_body.tables[arm];
+
+    for binding in bindings.iter(fn_body) {
+        let hir::IdentifiedVariableData {
+            identifier,
+            variable,
+        } = fn_body.tables[binding];
+        let field_value = fields
+            .remove(&fn_body.tables[identifier].text)
+            .expect("Match arm binds a field that the value does not have");
+
+        state.create_variable(variable);
+        state.assign_to_variable(variable, field_value);
+    }
+
+    let body_result = eval_expression(db, fn_body, body, state, io_handler)?;
+
+    if !state.is_repl {
+        for binding in bindings.iter(fn_body) {
+            state.pop_variable(fn_body.tables[binding].variable);
+        }
+    }
+
+    Ok(body_result)
 }
 
 pub fn eval_expression(
@@ -182,33 +496,32 @@ pub fn eval_expression(
     expression: hir::Expression,
     state: &mut EvalState,
     io_handler: &mut IOHandler,
-) -> Value {
-    match fn_body.tables[expression] {
-        hir::ExpressionData::Unit { .. } => {}
-        _ => state.set_current_expression(expression),
+) -> Result<Value, RuntimeError> {
+    if let Some(mut debugger) = state.debugger.take() {
+        debugger.before_expression(db, fn_body, expression, state);
+        state.debugger = Some(debugger);
     }
 
-    let ready_to_execute = state.ready_to_execute();
+    // Reports a failure of this expression itself (rather than of one
+    // of its subexpressions).
+    let error_here =
+        |state: &EvalState, message: &str| state.error(fn_body.span(expression), message);
 
-    match fn_body.tables[expression] {
+    let value = match fn_body.tables[expression] {
         hir::ExpressionData::Let {
             variable,
             initializer,
             body,
         } => {
-            if ready_to_execute {
-                state.create_variable(variable);
-            }
+            state.create_variable(variable);
 
             if let Some(expression) = initializer {
-                let result = eval_expression(db, fn_body, expression, state, io_handler);
+                let result = eval_expression(db, fn_body, expression, state, io_handler)?;
 
-                if ready_to_execute {
-                    state.assign_to_variable(variable, result);
-                }
+                state.assign_to_variable(variable, result);
             }
 
-            let body_result = eval_expression(db, fn_body, body, state, io_handler);
+            let body_result = eval_expression(db, fn_body, body, state, io_handler)?;
 
             if !state.is_repl {
                 state.pop_variable(variable);
@@ -217,114 +530,46 @@ pub fn eval_expression(
             body_result
         }
 
-        hir::ExpressionData::Place { place } => {
-            if ready_to_execute {
-                eval_place(db, fn_body, place, state)
-            } else {
-                Value::Skipped
-            }
-        }
+        hir::ExpressionData::Place { place } => eval_place(db, fn_body, place, state)?,
 
         hir::ExpressionData::Assignment { place, value } => {
-            let rhs = eval_expression(db, fn_body, value, state, io_handler);
-            match &fn_body.tables[place] {
-                hir::PlaceData::Variable(variable) => {
-                    if ready_to_execute {
-                        state.assign_to_variable(*variable, rhs);
-                    }
-                }
-                _ => unimplemented!("PlaceData not yet supported in eval"),
-            }
+            let rhs = eval_expression(db, fn_body, value, state, io_handler)?;
+            assign_to_place(db, fn_body, place, rhs, state)?;
             Value::Void
         }
 
         hir::ExpressionData::MethodCall { method, arguments } => {
-            match fn_body[arguments.first(fn_body).unwrap()] {
-                hir::ExpressionData::Place {
-                    place: function_place,
-                } => match fn_body[function_place] {
-                    hir::PlaceData::Entity(entity) => {
-                        match db.member_entity(entity, MemberKind::Method, fn_body[method].text) {
-                            Some(entity) => match entity.untern(db) {
-                                EntityData::ItemName { .. } => eval_fn_call(
-                                    db,
-                                    fn_body,
-                                    entity,
-                                    arguments,
-                                    state,
-                                    ready_to_execute,
-                                    io_handler,
-                                ),
-
-                                x => unimplemented!(
-                                    "Method not yet supported in eval: {:#?}",
-                                    x.debug_with(db)
-                                ),
-                            },
-                            x => unimplemented!(
-                                "Method not yet supported in eval: {:#?}",
-                                x.debug_with(db)
-                            ),
+            let argument_values = arguments
+                .iter(fn_body)
+                .map(|argument| eval_expression(db, fn_body, argument, state, io_handler))
+                .collect::<Result<_, _>>()?;
+
+            let method_entity = method_entity(db, state, method);
+            let (return_value, mut parameter_values) = eval_call(
+                db,
+                method_entity,
+                fn_body.span(expression),
+                argument_values,
+                state,
+                io_handler,
+            )?;
+
+            // A `borrow` method may modify its `self`, so copy the
+            // result back into the place that we invoked it on.
+            if self_permission(db, method_entity) == DeclaredPermKind::Borrow {
+                let owner = arguments.first(fn_body).unwrap();
+                if let hir::ExpressionData::Place { place } = fn_body[owner] {
+                    match fn_body[place] {
+                        hir::PlaceData::Variable(_) | hir::PlaceData::Field { .. } => {
+                            let self_value = parameter_values.swap_remove(0);
+                            assign_to_place(db, fn_body, place, self_value, state)?;
                         }
+                        hir::PlaceData::Entity(_) | hir::PlaceData::Temporary(_) => {}
                     }
-                    hir::PlaceData::Variable(variable) => {
-                        let stack = state.variables.get(&variable).unwrap();
-                        let object = stack.last().unwrap().clone();
-
-                        match object {
-                            Value::Struct(entity, _) => {
-                                match db.member_entity(
-                                    entity,
-                                    MemberKind::Method,
-                                    fn_body[method].text,
-                                ) {
-                                    Some(entity) => match entity.untern(db) {
-                                        EntityData::ItemName { .. } => eval_fn_call(
-                                            db,
-                                            fn_body,
-                                            entity,
-                                            arguments,
-                                            state,
-                                            ready_to_execute,
-                                            io_handler,
-                                        ),
-
-                                        EntityData::MemberName {
-                                            kind: MemberKind::Method,
-                                            ..
-                                        } => eval_fn_call(
-                                            db,
-                                            fn_body,
-                                            entity,
-                                            arguments,
-                                            state,
-                                            ready_to_execute,
-                                            io_handler,
-                                        ),
-
-                                        x => unimplemented!(
-                                            "Method not yet supported in eval: {:#?}",
-                                            x.debug_with(db)
-                                        ),
-                                    },
-                                    x => unimplemented!(
-                                        "Method not yet supported in eval: {:#?}",
-                                        x.debug_with(db)
-                                    ),
-                                }
-                            }
-                            x => unimplemented!(
-                                "Invoking method not yet support on non-struct: {:#?}",
-                                x
-                            ),
-                        }
-                    }
-                    x => {
-                        unimplemented!("Method not yet supported in eval: {:#?}", x.debug_with(db))
-                    }
-                },
-                x => unimplemented!("Method not yet supported in eval: {:#?}", x.debug_with(db)),
+                }
             }
+
+            return_value
         }
 
         hir::ExpressionData::Call {
@@ -337,37 +582,31 @@ pub fn eval_expression(
                 hir::PlaceData::Entity(entity) => match entity.untern(db) {
                     EntityData::LangItem(LangItem::Debug) => {
                         for argument in arguments.iter(fn_body) {
-                            let result = eval_expression(db, fn_body, argument, state, io_handler);
+                            let result = eval_expression(db, fn_body, argument, state, io_handler)?;
 
-                            if ready_to_execute {
-                                io_handler.println(format!("{}", result));
-                            }
+                            io_handler.println(format!("{}", result));
                         }
 
                         Value::Void
                     }
                     EntityData::ItemName { .. } => eval_fn_call(
-                        db,
-                        fn_body,
-                        entity,
-                        arguments,
-                        state,
-                        ready_to_execute,
-                        io_handler,
-                    ),
-                    x => unimplemented!(
-                        "Call entity not yet supported in eval: {:#?}",
-                        x.debug_with(db)
-                    ),
+                        db, fn_body, expression, entity, arguments, state, io_handler,
+                    )?,
+                    _ => return Err(error_here(state, "eval does not yet support calling this")),
                 },
-                x => unimplemented!("Call not yet supported in eval: {:#?}", x.debug_with(db)),
+                _ => return Err(error_here(state, "eval does not yet support calling this")),
             },
-            x => unimplemented!("Call not yet supported in eval: {:#?}", x.debug_with(db)),
+            _ => return Err(error_here(state, "eval does not yet support calling this")),
         },
 
         hir::ExpressionData::Sequence { first, second } => {
-            eval_expression(db, fn_body, first, state, io_handler);
-            eval_expression(db, fn_body, second, state, io_handler)
+            eval_expression(db, fn_body, first, state, io_handler)?;
+
+            if state.loop_control.is_some() {
+                return Ok(Value::Void);
+            }
+
+            eval_expression(db, fn_body, second, state, io_handler)?
         }
 
         hir::ExpressionData::Binary {
@@ -375,23 +614,54 @@ pub fn eval_expression(
             left,
             right,
         } => {
-            let lhs_eval = eval_expression(db, fn_body, left, state, io_handler);
-            let rhs_eval = eval_expression(db, fn_body, right, state, io_handler);
-
-            if ready_to_execute {
-                match operator {
-                    hir::BinaryOperator::Add => match (lhs_eval, rhs_eval) {
-                        (Value::U32(l), Value::U32(r)) => Value::U32(l + r),
-                        _ => panic!("Addition of non-numeric values"),
-                    },
-                    hir::BinaryOperator::Subtract => match (lhs_eval, rhs_eval) {
-                        (Value::U32(l), Value::U32(r)) => Value::U32(l - r),
-                        _ => panic!("Subtraction of non-numeric values"),
-                    },
-                    _ => unimplemented!("Operator not yet supported"),
+            let lhs_eval = eval_expression(db, fn_body, left, state, io_handler)?;
+
+            // `&&` and `||` only evaluate their right-hand side if needed
+            match (operator, &lhs_eval) {
+                (hir::BinaryOperator::And, Value::Bool(false)) => return Ok(Value::Bool(false)),
+                (hir::BinaryOperator::Or, Value::Bool(true)) => return Ok(Value::Bool(true)),
+                _ => {}
+            }
+
+            let rhs_eval = eval_expression(db, fn_body, right, state, io_handler)?;
+
+            let result = match operator {
+                hir::BinaryOperator::Add
+                | hir::BinaryOperator::Subtract
+                | hir::BinaryOperator::Multiply
+                | hir::BinaryOperator::Divide
+                | hir::BinaryOperator::Modulo => eval_arithmetic(operator, lhs_eval, rhs_eval),
+                hir::BinaryOperator::LessThan
+                | hir::BinaryOperator::LessThanOrEqual
+                | hir::BinaryOperator::GreaterThan
+                | hir::BinaryOperator::GreaterThanOrEqual => {
+                    eval_comparison(operator, lhs_eval, rhs_eval)
+                }
+                hir::BinaryOperator::Equals => values_equal(lhs_eval, rhs_eval).map(Value::Bool),
+                hir::BinaryOperator::NotEquals => {
+                    values_equal(lhs_eval, rhs_eval).map(|equal| Value::Bool(!equal))
+                }
+                hir::BinaryOperator::And | hir::BinaryOperator::Or => Ok(rhs_eval),
+            };
+
+            result.map_err(|message| error_here(state, message))?
+        }
+
+        hir::ExpressionData::Unary { operator, value } => {
+            let value_eval = eval_expression(db, fn_body, value, state, io_handler)?;
+
+            match (operator, value_eval) {
+                (hir::UnaryOperator::Not, Value::Bool(b)) => Value::Bool(!b),
+                (hir::UnaryOperator::Negate, Value::I32(i)) => match i.checked_neg() {
+                    Some(negated) => Value::I32(negated),
+                    None => return Err(error_here(state, "arithmetic overflow")),
+                },
+                _ => {
+                    return Err(error_here(
+                        state,
+                        "unary operator applied to unsupported value",
+                    ));
                 }
-            } else {
-                Value::Skipped
             }
         }
 
@@ -399,27 +669,30 @@ pub fn eval_expression(
             hir::LiteralData {
                 kind: hir::LiteralKind::UnsignedInteger,
                 value,
+            }
+            | hir::LiteralData {
+                kind: hir::LiteralKind::SignedInteger,
+                value,
             } => {
-                if ready_to_execute {
-                    let string = value.untern(db);
-                    let value: u32 = string.parse().unwrap();
-                    Value::U32(value)
+                let string = value.untern(db).replace('_', "");
+                let parsed = if is_int_literal(db, state, expression) {
+                    string.parse().map(Value::I32).ok()
                 } else {
-                    Value::Skipped
+                    string.parse().map(Value::U32).ok()
+                };
+                match parsed {
+                    Some(value) => value,
+                    None => return Err(error_here(state, "integer literal is out of range")),
                 }
             }
             hir::LiteralData {
                 kind: hir::LiteralKind::String,
                 value,
             } => {
-                if ready_to_execute {
-                    let text = value.untern(db);
-                    let string = text.to_string();
-                    let string = string[1..string.len()-1].to_string();
-                    Value::Str(string)
-                } else {
-                    Value::Skipped
-                }
+                let text = value.untern(db);
+                let string = text.to_string();
+                let string = string[1..string.len()-1].to_string();
+                Value::Str(string)
             }
         },
 
@@ -431,16 +704,12 @@ pub fn eval_expression(
                     identifier,
                     expression,
                 } = fn_body.tables[identified_expression];
-                let arg_result = eval_expression(db, fn_body, expression, state, io_handler);
+                let arg_result = eval_expression(db, fn_body, expression, state, io_handler)?;
 
                 result_struct.insert(fn_body.tables[identifier].text, arg_result);
             }
 
-            if ready_to_execute {
-                Value::Struct(entity, result_struct)
-            } else {
-                Value::Skipped
-            }
+            Value::Struct(entity, result_struct)
         }
 
         hir::ExpressionData::Unit {} => Value::Void,
@@ -450,31 +719,90 @@ pub fn eval_expression(
             if_true,
             if_false,
         } => {
-            let cond_value = eval_expression(db, fn_body, condition, state, io_handler);
+            let cond_value = eval_expression(db, fn_body, condition, state, io_handler)?;
 
             match cond_value {
-                Value::Bool(true) => eval_expression(db, fn_body, if_true, state, io_handler),
-                Value::Bool(false) => eval_expression(db, fn_body, if_false, state, io_handler),
-                Value::Skipped => {
-                    // Because the condition is skipped (during REPL)
-                    // we need to look in both branches for where to continue
-                    let mut result = eval_expression(db, fn_body, if_true, state, io_handler);
-
-                    if !state.ready_to_execute() {
-                        result = eval_expression(db, fn_body, if_false, state, io_handler);
+                Value::Bool(true) => eval_expression(db, fn_body, if_true, state, io_handler)?,
+                Value::Bool(false) => eval_expression(db, fn_body, if_false, state, io_handler)?,
+                _ => {
+                    return Err(
+                        state.error(fn_body.span(condition), "unsupported conditional in 'if'")
+                    );
+                }
+            }
+        }
+
+        hir::ExpressionData::While { condition, body } => {
+            loop {
+                let cond_value = eval_expression(db, fn_body, condition, state, io_handler)?;
+
+                match cond_value {
+                    Value::Bool(true) => {
+                        eval_expression(db, fn_body, body, state, io_handler)?;
+                    }
+                    Value::Bool(false) => break,
+                    _ => {
+                        return Err(state.error(
+                            fn_body.span(condition),
+                            "unsupported conditional in 'while'",
+                        ));
                     }
+                }
 
-                    result
+                if let Some(LoopControl::Break) = state.loop_control.take() {
+                    break;
                 }
-                _ => panic!("Unsupported conditional in 'if'"),
             }
+
+            Value::Void
         }
 
-        ref x => unimplemented!(
-            "Eval does not yet support this expression type: {:#?}",
-            x.debug_with(db)
-        ),
-    }
+        hir::ExpressionData::Match { value, arms } => {
+            let match_value = eval_expression(db, fn_body, value, state, io_handler)?;
+
+            match match_value {
+                Value::Struct(variant, fields) => {
+                    let arm = arms
+                        .iter(fn_body)
+                        .find(|&arm| match fn_body.tables[arm].variant {
+                            Some(arm_variant) => arm_variant == variant,
+                            None => true,
+                        });
+                    match arm {
+                        Some(arm) => eval_match_arm(db, fn_body, arm, fields, state, io_handler)?,
+                        None => {
+                            return Err(state.error(
+                                fn_body.span(value),
+                                "no arm of 'match' matches the value",
+                            ));
+                        }
+                    }
+                }
+                _ => {
+                    return Err(state.error(fn_body.span(value), "unsupported value in 'match'"));
+                }
+            }
+        }
+
+        hir::ExpressionData::Break {} => {
+            state.loop_control = Some(LoopControl::Break);
+            Value::Void
+        }
+
+        hir::ExpressionData::Continue {} => {
+            state.loop_control = Some(LoopControl::Continue);
+            Value::Void
+        }
+
+        _ => {
+            return Err(error_here(
+                state,
+                "eval does not yet support this kind of expression",
+            ));
+        }
+    };
+
+    Ok(value)
 }
 
 pub fn eval_function(
@@ -482,14 +810,24 @@ pub fn eval_function(
     fn_body: &hir::FnBody,
     state: &mut EvalState,
     io_handler: &mut IOHandler,
-) -> Value {
+) -> Result<Value, RuntimeError> {
     eval_expression(db, fn_body, fn_body.root_expression, state, io_handler)
 }
 
-pub fn eval(db: &LarkDatabase, io_handler: &mut IOHandler) {
-    let input_files = db.file_names();
+/// Runs the `main` function of each input file, stopping at the first
+/// runtime error.
+pub fn eval(db: &LarkDatabase, io_handler: &mut IOHandler) -> Result<(), RuntimeError> {
+    eval_with_state(db, &mut EvalState::new(), io_handler)
+}
 
-    let mut eval_state = EvalState::new();
+/// Like `eval`, but starting from `eval_state` (which may, say, have a
+/// `debugger`).
+pub fn eval_with_state(
+    db: &LarkDatabase,
+    eval_state: &mut EvalState,
+    io_handler: &mut IOHandler,
+) -> Result<(), RuntimeError> {
+    let input_files = db.file_names();
 
     let main_name = "main".intern(&db);
 
@@ -505,12 +843,15 @@ pub fn eval(db: &LarkDatabase, io_handler: &mut IOHandler) {
                 } => {
                     if id == main_name {
                         let fn_body = db.fn_body(entity);
+                        eval_state.current_function = Some(entity);
 
-                        eval_function(db, &fn_body.value, &mut eval_state, io_handler);
+                        eval_function(db, &fn_body.value, eval_state, io_handler)?;
                     }
                 }
                 _ => {}
             }
         }
     }
+
+    Ok(())
 }
@@ -0,0 +1,579 @@
+//! Gives Lark files a canonical layout:
+//!
+//! - four spaces of indentation for each `{` or `(` that is left open
+//!   at the end of a line;
+//! - one space around operators and after `,` and `:`, and none inside
+//!   parentheses;
+//! - in a list (of fields, arguments, match arms, and so forth) that
+//!   spans lines, each element on a line of its own, ending in a comma;
+//!   in one that does not, no trailing comma;
+//! - at most one blank line in a row, and one between items.
+//!
+//! The formatter only ever changes what lies *between* two tokens:
+//! whitespace, plus the comma at the end of a list. It never moves a
+//! token from one line to another except within a list, where a
+//! newline and a comma are interchangeable, so formatting cannot change
+//! what a file means. This also means that its result can be given as
+//! a set of small edits, which is what an IDE wants.
+
+#![deny(rust_2018_idioms)]
+#![feature(in_band_lifetimes)]
+
+use lark_entity::{EntityData, ItemKind};
+use lark_error::{Diagnostic, SuggestedEdit};
+use lark_intern::Untern;
+use lark_parser::{LexToken, ParserDatabase};
+use lark_span::{FileName, Span};
+use std::collections::HashSet;
+
+/// The indentation for each level of nesting.
+const INDENT: &str = "    ";
+
+/// Keywords that are followed by a space even when a `(` comes next,
+/// and after which a `-` is a negation.
+const KEYWORDS: &[&str] = &["if", "else", "while", "match", "let", "return"];
+
+/// The edits that give the file `file_name` its canonical layout, in
+/// order. Files that have syntax errors are not formatted; their errors
+/// are returned instead.
+pub fn format_edits(
+    db: &impl ParserDatabase,
+    file_name: FileName,
+) -> Result<Vec<SuggestedEdit>, Vec<Diagnostic>> {
+    let lex_tokens = db.file_tokens(file_name);
+    let parsed_file = db.parsed_file(file_name);
+
+    let mut errors = lex_tokens.errors;
+    errors.extend(parsed_file.errors);
+    errors.extend(
+        lex_tokens
+            .value
+            .iter()
+            .filter(|token| token.value == LexToken::Error)
+            .map(|token| Diagnostic::new("unrecognized token".to_string(), token.span)),
+    );
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let text = db.file_text(file_name);
+    let tokens: Vec<Token<'_>> = lex_tokens
+        .value
+        .iter()
+        .filter_map(|token| Token::new(&text, token.value, token.span))
+        .collect();
+    let items: Vec<Item> = parsed_file
+        .value
+        .entities
+        .iter()
+        .filter_map(|entity| match entity.entity.untern(db) {
+            EntityData::ItemName { kind, .. } => Some(Item {
+                start: entity.full_span.start().to_usize(),
+                kind,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    Ok(Formatter::new(&text, tokens, &items)
+        .edits()
+        .into_iter()
+        .map(|(start, end, replacement)| {
+            SuggestedEdit::new(Span::new(file_name, start, end), replacement)
+        })
+        .collect())
+}
+
+/// The text of the file `file_name` in its canonical layout.
+pub fn format_file(
+    db: &impl ParserDatabase,
+    file_name: FileName,
+) -> Result<String, Vec<Diagnostic>> {
+    let edits = format_edits(db, file_name)?;
+    Ok(apply_edits(&db.file_text(file_name), &edits))
+}
+
+/// Applies `edits` (which must be in order, and must not overlap) to
+/// `text`.
+pub fn apply_edits(text: &str, edits: &[SuggestedEdit]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut position = 0;
+    for edit in edits {
+        result.push_str(&text[position..edit.span.start().to_usize()]);
+        result.push_str(&edit.replacement);
+        position = edit.span.end().to_usize();
+    }
+    result.push_str(&text[position..]);
+    result
+}
+
+/// Where an item of the file starts, and what kind it is.
+struct Item {
+    start: usize,
+    kind: ItemKind,
+}
+
+/// A token other than whitespace or a newline. The newline at the end
+/// of a `//` comment (along with any whitespace before it) is left out
+/// of the comment, so that it counts as whitespace too.
+#[derive(Copy, Clone, Debug)]
+struct Token<'t> {
+    kind: LexToken,
+    text: &'t str,
+    start: usize,
+    end: usize,
+}
+
+impl Token<'t> {
+    fn new(text: &'t str, kind: LexToken, span: Span<FileName>) -> Option<Self> {
+        let start = span.start().to_usize();
+        let token_text = match kind {
+            LexToken::Whitespace | LexToken::Newline | LexToken::EOF => return None,
+            LexToken::Comment => text[start..span.end().to_usize()].trim_end(),
+            _ => &text[start..span.end().to_usize()],
+        };
+
+        Some(Token {
+            kind,
+            text: token_text,
+            start,
+            end: start + token_text.len(),
+        })
+    }
+
+    fn is_sigil(&self, text: &str) -> bool {
+        self.kind == LexToken::Sigil && self.text == text
+    }
+
+    fn is_opener(&self) -> bool {
+        self.is_sigil("(") || self.is_sigil("{")
+    }
+
+    fn is_closer(&self) -> bool {
+        self.is_sigil(")") || self.is_sigil("}")
+    }
+
+    fn is_keyword(&self) -> bool {
+        self.kind == LexToken::Identifier && KEYWORDS.contains(&self.text)
+    }
+
+    /// Whether the token would run together with `next` if there were
+    /// no space between them (like the two `-` of `- -x`). A `>` only
+    /// runs together with an `=`, so that `Box<T>,` lexes as it should.
+    fn joins(&self, next: &Token<'_>) -> bool {
+        let is_word = |token: &Token<'_>| match token.kind {
+            LexToken::Identifier | LexToken::Integer => true,
+            _ => false,
+        };
+        let is_operator = |token: &Token<'_>| {
+            token.kind == LexToken::Sigil && !token.is_opener() && !token.is_closer()
+        };
+
+        (is_word(self) && is_word(next))
+            || (is_operator(self)
+                && is_operator(next)
+                && (!self.is_sigil(">") || next.text.starts_with('=')))
+    }
+}
+
+/// What we know about a `(` or `{`.
+#[derive(Copy, Clone, Debug, Default)]
+struct Group {
+    /// Whether there is a comma directly within it, which makes it a
+    /// list (a block never has one).
+    is_list: bool,
+
+    /// Whether its matching `)` or `}` is on a later line.
+    is_multiline: bool,
+}
+
+/// Where the `(` and `{` of a file are, and how they match up.
+struct Groups {
+    /// For each token that is a `(` or `{`, what we know about it.
+    groups: Vec<Option<Group>>,
+
+    /// For each token that is a `)` or `}`, the index of its `(` or `{`.
+    openers: Vec<Option<usize>>,
+
+    /// For each token, the index of the innermost `(` or `{` that it is
+    /// within (not counting its own, for a `(` or `)`).
+    enclosing: Vec<Option<usize>>,
+}
+
+impl Groups {
+    fn new(text: &str, tokens: &[Token<'_>]) -> Self {
+        let mut groups = vec![None; tokens.len()];
+        let mut openers = vec![None; tokens.len()];
+        let mut enclosing = Vec::with_capacity(tokens.len());
+        let mut stack: Vec<usize> = vec![];
+
+        for (index, token) in tokens.iter().enumerate() {
+            if token.is_closer() {
+                if let Some(opener) = stack.pop() {
+                    openers[index] = Some(opener);
+                    groups[opener] = Some(Group {
+                        is_multiline: text[tokens[opener].end..token.start].contains('\n'),
+                        ..groups[opener].unwrap()
+                    });
+                }
+            }
+
+            enclosing.push(stack.last().cloned());
+
+            if token.is_sigil(",") {
+                if let Some(&opener) = stack.last() {
+                    groups[opener] = Some(Group {
+                        is_list: true,
+                        ..groups[opener].unwrap()
+                    });
+                }
+            }
+
+            if token.is_opener() {
+                groups[index] = Some(Group::default());
+                stack.push(index);
+            }
+        }
+
+        Groups {
+            groups,
+            openers,
+            enclosing,
+        }
+    }
+
+    /// Marks the bodies of structs, enums and `match`es as lists, even
+    /// if they have only one element (or their elements are separated
+    /// by newlines alone).
+    fn mark_bodies(&mut self, text: &str, tokens: &[Token<'_>], items: &[Item]) {
+        let mut bodies = vec![];
+        for item in items {
+            if let ItemKind::Struct | ItemKind::Enum = item.kind {
+                bodies.extend(
+                    tokens
+                        .iter()
+                        .position(|token| token.start >= item.start && token.is_sigil("{")),
+                );
+            }
+        }
+
+        for (index, token) in tokens.iter().enumerate() {
+            if !token.is_sigil("{") {
+                continue;
+            }
+
+            // Look for a `match` earlier on the same line.
+            let mut start = index;
+            while start > 0
+                && self.enclosing[start - 1] == self.enclosing[index]
+                && !text[tokens[start - 1].end..tokens[start].start].contains('\n')
+            {
+                start -= 1;
+            }
+            if tokens[start..index]
+                .iter()
+                .any(|token| token.text == "match")
+            {
+                bodies.push(index);
+            }
+        }
+
+        for opener in bodies {
+            self.groups[opener] = Some(Group {
+                is_list: true,
+                ..self.groups[opener].unwrap()
+            });
+        }
+    }
+
+    /// Whether the contents of the group that `opener` opens go on
+    /// lines of their own: true of a block, or a list, that spans
+    /// lines.
+    fn is_broken(&self, tokens: &[Token<'_>], opener: usize) -> bool {
+        match self.groups[opener] {
+            Some(group) => group.is_multiline && (group.is_list || tokens[opener].is_sigil("{")),
+            None => false,
+        }
+    }
+
+    /// Whether `index` is directly within a list whose elements go on
+    /// lines of their own.
+    fn in_broken_list(&self, tokens: &[Token<'_>], index: usize) -> bool {
+        match self.enclosing[index] {
+            Some(opener) => self.groups[opener].unwrap().is_list && self.is_broken(tokens, opener),
+            None => false,
+        }
+    }
+}
+
+struct Formatter<'t> {
+    text: &'t str,
+    tokens: Vec<Token<'t>>,
+    groups: Groups,
+
+    /// The `<` and `>` that surround generic arguments (as opposed to
+    /// comparing two values).
+    angles: HashSet<usize>,
+
+    /// For each token, whether there is a line break before it.
+    breaks: Vec<bool>,
+
+    /// For each token, whether there is a blank line before it.
+    blanks: Vec<bool>,
+}
+
+impl Formatter<'t> {
+    fn new(text: &'t str, tokens: Vec<Token<'t>>, items: &[Item]) -> Self {
+        // Drop the trailing comma of each list that fits on one line.
+        let groups = Groups::new(text, &tokens);
+        let tokens: Vec<Token<'t>> = tokens
+            .iter()
+            .enumerate()
+            .filter(|&(index, token)| {
+                let is_trailing_comma = token.is_sigil(",")
+                    && tokens.get(index + 1).map_or(false, |next| next.is_closer())
+                    && groups.openers[index + 1]
+                        .map_or(false, |opener| !groups.groups[opener].unwrap().is_multiline);
+                !is_trailing_comma
+            })
+            .map(|(_, &token)| token)
+            .collect();
+
+        let mut groups = Groups::new(text, &tokens);
+        groups.mark_bodies(text, &tokens, items);
+
+        let mut formatter = Formatter {
+            text,
+            groups,
+            angles: HashSet::new(),
+            breaks: vec![false; tokens.len()],
+            blanks: vec![false; tokens.len()],
+            tokens,
+        };
+        formatter.find_angles();
+        formatter.find_breaks();
+        formatter.separate_items(items);
+        formatter
+    }
+
+    fn find_angles(&mut self) {
+        let tokens = &self.tokens;
+        for (index, token) in tokens.iter().enumerate() {
+            // Generic arguments directly follow a name, as in `Box<T>`
+            let follows_name = index > 0
+                && tokens[index - 1].kind == LexToken::Identifier
+                && tokens[index - 1].end == token.start;
+            if !token.is_sigil("<") || !follows_name {
+                continue;
+            }
+
+            let mut depth = 0;
+            for (end, token) in tokens.iter().enumerate().skip(index) {
+                if token.is_sigil("<") {
+                    depth += 1;
+                } else if token.is_sigil(">") {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.angles.insert(index);
+                        self.angles.insert(end);
+                        break;
+                    }
+                } else if token.kind != LexToken::Identifier && !token.is_sigil(",") {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn find_breaks(&mut self) {
+        for index in 1..self.tokens.len() {
+            let (previous, token) = (&self.tokens[index - 1], &self.tokens[index]);
+            let newlines = self.text[previous.end..token.start].matches('\n').count();
+
+            let opens_broken_group = self.groups.is_broken(&self.tokens, index - 1);
+            let closes_broken_group = self.groups.openers[index]
+                .map_or(false, |opener| self.groups.is_broken(&self.tokens, opener));
+            let separates_elements =
+                previous.is_sigil(",") && self.groups.in_broken_list(&self.tokens, index - 1);
+
+            self.breaks[index] =
+                newlines > 0 || opens_broken_group || closes_broken_group || separates_elements;
+            self.blanks[index] = newlines > 1 && !previous.is_opener() && !token.is_closer();
+        }
+    }
+
+    /// Puts a blank line before each item (along with the comments
+    /// just above it), except between one `use` and the next.
+    fn separate_items(&mut self, items: &[Item]) {
+        let item_indices: Vec<(usize, ItemKind)> = items
+            .iter()
+            .filter_map(|item| {
+                let index = self
+                    .tokens
+                    .iter()
+                    .position(|token| token.start == item.start)?;
+                Some((index, item.kind))
+            })
+            .collect();
+
+        for (&(_, previous_kind), &(index, kind)) in
+            item_indices.iter().zip(item_indices.iter().skip(1))
+        {
+            if previous_kind == ItemKind::Import && kind == ItemKind::Import {
+                continue;
+            }
+
+            let mut start = index;
+            while start > 1
+                && self.tokens[start - 1].kind == LexToken::Comment
+                && self.breaks[start - 1]
+                && !self.blanks[start]
+            {
+                start -= 1;
+            }
+            self.blanks[start] = true;
+        }
+    }
+
+    /// The edits that lay out the file: for each stretch between two
+    /// tokens that is not as it should be, its start, end, and what
+    /// should be there instead.
+    fn edits(&self) -> Vec<(usize, usize, String)> {
+        let tokens = &self.tokens;
+        let mut edits = vec![];
+        let mut replace = |start: usize, end: usize, replacement: String| {
+            if self.text[start..end] != replacement[..] {
+                edits.push((start, end, replacement));
+            }
+        };
+
+        if tokens.is_empty() {
+            replace(0, self.text.len(), String::new());
+            return edits;
+        }
+
+        replace(0, tokens[0].start, String::new());
+
+        // The indentation of the line that each open `(` or `{` is on
+        let mut open: Vec<usize> = vec![];
+        let mut indent = 0;
+
+        for index in 1..tokens.len() {
+            let (previous, token) = (&tokens[index - 1], &tokens[index]);
+            if previous.is_opener() {
+                open.push(indent);
+            } else if previous.is_closer() {
+                open.pop();
+            }
+
+            let comma = if self.needs_comma(index) { "," } else { "" };
+            let replacement = if self.breaks[index] {
+                indent = if token.is_closer() {
+                    open.last().cloned().unwrap_or(0)
+                } else {
+                    open.last().map_or(0, |indent| indent + 1)
+                };
+
+                format!(
+                    "{}{}{}",
+                    comma,
+                    if self.blanks[index] { "\n\n" } else { "\n" },
+                    INDENT.repeat(indent),
+                )
+            } else if self.needs_space(index) {
+                format!("{} ", comma)
+            } else {
+                comma.to_string()
+            };
+
+            replace(previous.end, token.start, replacement);
+        }
+
+        replace(
+            tokens[tokens.len() - 1].end,
+            self.text.len(),
+            "\n".to_string(),
+        );
+
+        edits
+    }
+
+    /// Whether a comma must be added before the token at `index`, to
+    /// end an element of a list whose elements go on lines of their
+    /// own. (If the element is followed by a comment, the comma goes
+    /// before the comment.)
+    fn needs_comma(&self, index: usize) -> bool {
+        let (previous, token) = (&self.tokens[index - 1], &self.tokens[index]);
+        let ends_line = self.breaks[index]
+            || (token.kind == LexToken::Comment
+                && self.breaks.get(index + 1).cloned().unwrap_or(true));
+
+        let ends_element = match previous.kind {
+            LexToken::Identifier | LexToken::Integer | LexToken::String => true,
+            LexToken::Sigil => previous.is_sigil(")") || self.angles.contains(&(index - 1)),
+            _ => false,
+        };
+
+        ends_line
+            && ends_element
+            && !token.is_sigil(",")
+            && self.groups.in_broken_list(&self.tokens, index - 1)
+    }
+
+    /// Whether there is a space before the token at `index` (if it is on
+    /// the same line as the one before it).
+    fn needs_space(&self, index: usize) -> bool {
+        let (previous, token) = (&self.tokens[index - 1], &self.tokens[index]);
+
+        let space = if previous.kind == LexToken::Comment || token.kind == LexToken::Comment {
+            true
+        } else if self.is_unary(index - 1) || previous.is_sigil("(") || token.is_sigil(")") {
+            false
+        } else if previous.is_sigil("{") {
+            !token.is_sigil("}")
+        } else if token.is_sigil("}") {
+            true
+        } else if previous.is_sigil(".")
+            || [",", ":", ";", "."]
+                .iter()
+                .any(|sigil| token.is_sigil(sigil))
+        {
+            false
+        } else if token.is_sigil("(") {
+            // Calls, and the parameters of a function, hug the name
+            let is_name = previous.kind == LexToken::Identifier && !previous.is_keyword();
+            !is_name && !previous.is_sigil(")") && !self.angles.contains(&(index - 1))
+        } else if self.angles.contains(&index) {
+            false
+        } else {
+            !(self.angles.contains(&(index - 1)) && previous.is_sigil("<"))
+        };
+
+        space || previous.joins(token)
+    }
+
+    /// Whether the token at `index` is a `!` or a `-` that negates what
+    /// follows (rather than subtracting it from what comes before).
+    fn is_unary(&self, index: usize) -> bool {
+        let token = &self.tokens[index];
+        if token.is_sigil("!") {
+            return true;
+        }
+        if !token.is_sigil("-") {
+            return false;
+        }
+        if index == 0 || self.breaks[index] {
+            return true;
+        }
+
+        let previous = &self.tokens[index - 1];
+        match previous.kind {
+            LexToken::Sigil => {
+                !previous.is_closer()
+                    && !(previous.is_sigil(">") && self.angles.contains(&(index - 1)))
+            }
+            LexToken::Identifier => previous.is_keyword(),
+            _ => false,
+        }
+    }
+}
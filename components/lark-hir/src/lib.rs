@@ -75,6 +75,12 @@ pub struct FnBodyTables {
     /// A `a: b` pair.
     pub identified_expressions: IndexVec<IdentifiedExpression, IdentifiedExpressionData>,
 
+    /// Map each match arm index to its associated data.
+    pub match_arms: IndexVec<MatchArm, MatchArmData>,
+
+    /// A `a: b` pair, where `b` is a variable bound by a pattern.
+    pub identified_variables: IndexVec<IdentifiedVariable, IdentifiedVariableData>,
+
     /// Map each place index to its associated data.
     pub places: IndexVec<Place, PlaceData>,
 
@@ -297,6 +303,8 @@ macro_rules! define_meta_index {
 define_meta_index! {
     (Expression, ExpressionData, expressions),
     (IdentifiedExpression, IdentifiedExpressionData, identified_expressions),
+    (MatchArm, MatchArmData, match_arms),
+    (IdentifiedVariable, IdentifiedVariableData, identified_variables),
     (Place, PlaceData, places),
     (Variable, VariableData, variables),
     (Identifier, IdentifierData, identifiers),
@@ -463,6 +471,25 @@ pub enum ExpressionData {
         if_false: Expression,
     },
 
+    /// while E1 { E2 }
+    While {
+        condition: Expression,
+        body: Expression,
+    },
+
+    /// match E { P1 => E1, ... }
+    Match {
+        value: Expression,
+        arms: List<MatchArm>,
+    },
+
+    /// `break` -- exits the innermost enclosing loop
+    Break {},
+
+    /// `continue` -- skips to the next iteration of the innermost
+    /// enclosing loop
+    Continue {},
+
     /// E1 (op) E2
     Binary {
         operator: BinaryOperator,
@@ -479,10 +506,11 @@ pub enum ExpressionData {
     /// A literal value
     Literal { data: LiteralData },
 
-    /// Construct a value of some aggregate type, such as a struct or
-    /// tuple:
+    /// Construct a value of some aggregate type, such as a struct,
+    /// enum variant, or tuple:
     ///
     /// - `Struct { field1: expression1, ... fieldN: expressionN }`
+    /// - `Enum.Variant { field1: expression1, ... fieldN: expressionN }`
     Aggregate {
         entity: Entity,
         fields: List<IdentifiedExpression>,
@@ -501,13 +529,25 @@ pub enum BinaryOperator {
     Subtract,
     Multiply,
     Divide,
+    Modulo,
     Equals,
     NotEquals,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+
+    /// `&&` -- only evaluates the right-hand side if the left is true
+    And,
+
+    /// `||` -- only evaluates the right-hand side if the left is false
+    Or,
 }
 
 #[derive(Copy, Clone, Debug, DebugWith, PartialEq, Eq, Hash)]
 pub enum UnaryOperator {
     Not,
+    Negate,
 }
 
 lark_collections::index_type! {
@@ -520,6 +560,34 @@ pub struct IdentifiedExpressionData {
     pub expression: Expression,
 }
 
+lark_collections::index_type! {
+    pub struct MatchArm { .. }
+}
+
+/// An arm `Enum.Variant(field1: variable1, ...) => body` of a match,
+/// or `_ => body`.
+#[derive(Copy, Clone, Debug, DebugWith, PartialEq, Eq, Hash)]
+pub struct MatchArmData {
+    /// The variant that this arm matches, or `None` for `_` (which
+    /// matches anything).
+    pub variant: Option<Entity>,
+
+    /// The fields of the variant bound to variables in `body`.
+    pub bindings: List<IdentifiedVariable>,
+
+    pub body: Expression,
+}
+
+lark_collections::index_type! {
+    pub struct IdentifiedVariable { .. }
+}
+
+#[derive(Copy, Clone, Debug, DebugWith, PartialEq, Eq, Hash)]
+pub struct IdentifiedVariableData {
+    pub identifier: Identifier,
+    pub variable: Variable,
+}
+
 lark_collections::index_type! {
     pub struct Place { .. }
 }
@@ -545,6 +613,10 @@ pub struct LiteralData {
 #[derive(Copy, Clone, Debug, DebugWith, PartialEq, Eq, Hash)]
 pub enum LiteralKind {
     UnsignedInteger,
+
+    /// An integer literal written with a leading `-`, like `-22`.
+    SignedInteger,
+
     String,
 }
 
@@ -576,4 +648,7 @@ pub enum ErrorData {
     CanOnlyConstructStructs,
     Unimplemented,
     UnknownIdentifier { text: GlobalIdentifier },
+    OutsideOfLoop { text: GlobalIdentifier },
+    UnknownVariant { text: GlobalIdentifier },
+    EmptyMatch,
 }
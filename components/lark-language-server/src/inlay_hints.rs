@@ -0,0 +1,36 @@
+//! Inlay hints, which let the IDE show the types that we inferred in
+//! the middle of the text. Like semantic tokens, these are newer than
+//! the `languageserver-types` we use, so the protocol types are
+//! defined here.
+
+use languageserver_types::{Position, Range, TextDocumentIdentifier};
+use serde::{Deserialize, Serialize};
+
+/// The `kind` of a hint that shows a type (rather than the name of a
+/// parameter).
+pub const TYPE_KIND: u64 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHintParams {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlayHint {
+    pub position: Position,
+    pub label: String,
+    pub kind: u64,
+}
+
+impl From<lark_actor::InlayHint> for InlayHint {
+    fn from(hint: lark_actor::InlayHint) -> InlayHint {
+        InlayHint {
+            position: hint.position,
+            label: format!(": {}", hint.ty),
+            kind: TYPE_KIND,
+        }
+    }
+}
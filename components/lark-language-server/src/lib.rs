@@ -1,12 +1,24 @@
-use lark_actor::{self, Actor, LspResponse, QueryRequest};
+use lark_actor::{self, Actor, LspResponse, QueryRequest, RequestError};
+use lark_span::PositionEncoding;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::io;
-use std::io::prelude::{Read, Write};
+use std::io::prelude::{BufRead, Write};
+use std::io::BufReader;
+use std::net::TcpListener;
 use std::sync::mpsc::Sender;
 use url::Url;
 
+pub mod inlay_hints;
+pub mod semantic_tokens;
+
+use self::inlay_hints::{InlayHint, InlayHintParams};
+use self::semantic_tokens::{
+    SemanticTokens, SemanticTokensDelta, SemanticTokensDeltaParams, SemanticTokensDeltaResult,
+    SemanticTokensOptions, SemanticTokensParams,
+};
+
 /// The command given by the IDE to the LSP server. These represent the actions of the user in the IDE,
 /// as well as actions the IDE might perform as a result of user actions (like cancelling a task)
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,9 +27,13 @@ use url::Url;
 pub enum LSPCommand {
     initialize {
         id: usize,
-        params: languageserver_types::InitializeParams,
+        params: InitializeParams,
     },
     initialized,
+    shutdown {
+        id: usize,
+    },
+    exit,
     #[serde(rename = "textDocument/didOpen")]
     didOpen {
         params: languageserver_types::DidOpenTextDocumentParams,
@@ -26,6 +42,10 @@ pub enum LSPCommand {
     didChange {
         params: languageserver_types::DidChangeTextDocumentParams,
     },
+    #[serde(rename = "textDocument/didClose")]
+    didClose {
+        params: languageserver_types::DidCloseTextDocumentParams,
+    },
     #[serde(rename = "textDocument/hover")]
     hover {
         id: usize,
@@ -36,6 +56,51 @@ pub enum LSPCommand {
         id: usize,
         params: languageserver_types::CompletionParams,
     },
+    #[serde(rename = "textDocument/signatureHelp")]
+    signatureHelp {
+        id: usize,
+        params: languageserver_types::TextDocumentPositionParams,
+    },
+    #[serde(rename = "textDocument/semanticTokens/full")]
+    semanticTokensFull {
+        id: usize,
+        params: SemanticTokensParams,
+    },
+    #[serde(rename = "textDocument/semanticTokens/full/delta")]
+    semanticTokensFullDelta {
+        id: usize,
+        params: SemanticTokensDeltaParams,
+    },
+    #[serde(rename = "textDocument/codeAction")]
+    codeAction {
+        id: usize,
+        params: languageserver_types::CodeActionParams,
+    },
+    #[serde(rename = "textDocument/formatting")]
+    formatting {
+        id: usize,
+        params: languageserver_types::DocumentFormattingParams,
+    },
+    #[serde(rename = "textDocument/rangeFormatting")]
+    rangeFormatting {
+        id: usize,
+        params: languageserver_types::DocumentRangeFormattingParams,
+    },
+    #[serde(rename = "textDocument/documentSymbol")]
+    documentSymbol {
+        id: usize,
+        params: languageserver_types::DocumentSymbolParams,
+    },
+    #[serde(rename = "textDocument/inlayHint")]
+    inlayHint {
+        id: usize,
+        params: InlayHintParams,
+    },
+    #[serde(rename = "workspace/symbol")]
+    workspaceSymbol {
+        id: usize,
+        params: languageserver_types::WorkspaceSymbolParams,
+    },
     #[serde(rename = "textDocument/definition")]
     definition {
         id: usize,
@@ -80,6 +145,40 @@ impl<T> JsonRPCResponse<T> {
     }
 }
 
+/// A wrapper for error responses back to the IDE, for requests that failed. These
+/// must follow the JSON 2.0 RPC spec
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonRPCErrorResponse {
+    jsonrpc: String,
+    pub id: usize,
+    pub error: JsonRPCError,
+}
+impl JsonRPCErrorResponse {
+    pub fn new(id: usize, error: JsonRPCError) -> Self {
+        JsonRPCErrorResponse {
+            jsonrpc: "2.0".into(),
+            id,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonRPCError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// The error code for a request that is not allowed at the moment.
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// The error code for a request that failed because of a bug.
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// The error code for a valid request that there is no sensible answer
+/// to.
+pub const REQUEST_FAILED: i64 = -32803;
+
 /// A wrapper for proactive notifications to the IDE (eg. diagnostics). These must
 /// follow the JSON 2.0 RPC spec
 #[derive(Debug, Serialize, Deserialize)]
@@ -98,24 +197,88 @@ impl<T> JsonRPCNotification<T> {
     }
 }
 
-/// Helper function to do the work of sending a result back to the IDE
-fn send_response<T: Serialize>(id: usize, result: T) {
-    let response = JsonRPCResponse::new(id, result);
-    let response_raw = serde_json::to_string(&response).unwrap();
+/// The parameters of `initialize`: the ones in `languageserver-types`,
+/// and the position encodings that the IDE supports, which are a newer
+/// capability (`general.positionEncodings`) that it doesn't know about.
+#[derive(Debug)]
+pub struct InitializeParams {
+    pub standard: languageserver_types::InitializeParams,
+    pub position_encodings: Vec<String>,
+}
+
+impl InitializeParams {
+    /// The position encoding to use with the IDE: UTF-8 if it supports
+    /// that (as it saves counting), otherwise UTF-16, which every IDE
+    /// supports.
+    fn position_encoding(&self) -> PositionEncoding {
+        if self
+            .position_encodings
+            .iter()
+            .any(|name| PositionEncoding::from_name(name) == Some(PositionEncoding::Utf8))
+        {
+            PositionEncoding::Utf8
+        } else {
+            PositionEncoding::Utf16
+        }
+    }
+}
+
+impl Serialize for InitializeParams {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = serde_json::to_value(&self.standard).map_err(serde::ser::Error::custom)?;
+        if !self.position_encodings.is_empty() {
+            value["capabilities"]["general"] = serde_json::json!({
+                "positionEncodings": self.position_encodings,
+            });
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for InitializeParams {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let position_encodings = match value.pointer("/capabilities/general/positionEncodings") {
+            Some(encodings) => {
+                serde_json::from_value(encodings.clone()).map_err(serde::de::Error::custom)?
+            }
+            None => vec![],
+        };
+        let standard = serde_json::from_value(value).map_err(serde::de::Error::custom)?;
 
-    print!("Content-Length: {}\r\n\r\n", response_raw.len());
-    print!("{}", response_raw);
-    let _ = io::stdout().flush();
+        Ok(InitializeParams {
+            standard,
+            position_encodings,
+        })
+    }
 }
 
-/// Helper function to send a proactive notification back to the IDE
-fn send_notification<T: Serialize>(method: String, notice: T) {
-    let response = JsonRPCNotification::new(method, notice);
-    let response_raw = serde_json::to_string(&response).unwrap();
+/// The result of `initialize`, which is like the one in
+/// `languageserver-types` but can include newer capabilities.
+#[derive(Debug, Serialize)]
+struct InitializeResult {
+    capabilities: ServerCapabilities,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerCapabilities {
+    #[serde(flatten)]
+    standard: languageserver_types::ServerCapabilities,
+    semantic_tokens_provider: SemanticTokensOptions,
+    position_encoding: &'static str,
+    inlay_hint_provider: bool,
+}
 
-    print!("Content-Length: {}\r\n\r\n", response_raw.len());
-    print!("{}", response_raw);
-    let _ = io::stdout().flush();
+/// The parameters of `textDocument/publishDiagnostics`, which are like
+/// the ones in `languageserver-types` but can include the version of
+/// the document that the diagnostics are for.
+#[derive(Debug, Serialize)]
+struct PublishDiagnosticsParams {
+    #[serde(flatten)]
+    standard: languageserver_types::PublishDiagnosticsParams,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<u64>,
 }
 
 /// The LSP service is split into two parts:
@@ -124,7 +287,64 @@ fn send_notification<T: Serialize>(method: String, notice: T) {
 /// The server sends messages *to* the task manager for work that
 /// needs to be done. The responder receives messages *from* the
 /// task manager for work that has been accomplished.
-pub struct LspResponder;
+pub struct LspResponder {
+    /// Where the messages to the IDE go.
+    writer: Box<dyn Write + Send>,
+
+    /// The semantic tokens last sent for each file, with the id of
+    /// that result, so that we can send just the changes next time.
+    semantic_tokens: HashMap<Url, (String, Vec<u64>)>,
+    next_result_id: usize,
+}
+
+impl LspResponder {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        LspResponder {
+            writer,
+            semantic_tokens: HashMap::new(),
+            next_result_id: 0,
+        }
+    }
+
+    /// Helper function to do the work of sending a result back to the IDE
+    fn send_response<T: Serialize>(&mut self, id: usize, result: T) {
+        let response = JsonRPCResponse::new(id, result);
+        self.send_message(&response);
+    }
+
+    /// Helper function to send an error back to the IDE, for a request
+    /// that failed
+    fn send_error(&mut self, id: usize, error: RequestError) {
+        let (code, message) = match error {
+            RequestError::InvalidRequest(message) => (INVALID_REQUEST, message),
+            RequestError::InternalError(message) => (INTERNAL_ERROR, message),
+            RequestError::RequestFailed(message) => (REQUEST_FAILED, message),
+        };
+        let response = JsonRPCErrorResponse::new(id, JsonRPCError { code, message });
+        self.send_message(&response);
+    }
+
+    /// Helper function to send a proactive notification back to the IDE
+    fn send_notification<T: Serialize>(&mut self, method: String, notice: T) {
+        let notification = JsonRPCNotification::new(method, notice);
+        self.send_message(&notification);
+    }
+
+    fn send_message<T: Serialize>(&mut self, message: &T) {
+        let message_raw = serde_json::to_string(message).unwrap();
+
+        let result = write!(
+            self.writer,
+            "Content-Length: {}\r\n\r\n{}",
+            message_raw.len(),
+            message_raw
+        )
+        .and_then(|()| self.writer.flush());
+        if let Err(error) = result {
+            log::error!("cannot send message to the IDE: {}", error);
+        }
+    }
+}
 
 impl Actor for LspResponder {
     type InMessage = LspResponse;
@@ -142,12 +362,12 @@ impl Actor for LspResponder {
                     range: None,
                 };
 
-                send_response(id, result);
+                self.send_response(id, result);
             }
             LspResponse::Range(id, uri, range) => {
                 let result = languageserver_types::Location { uri, range };
 
-                send_response(id, result);
+                self.send_response(id, result);
             }
             LspResponse::Ranges(id, vec_of_uri_range) => {
                 let result: Vec<languageserver_types::Location> = vec_of_uri_range
@@ -158,7 +378,7 @@ impl Actor for LspResponder {
                     })
                     .collect();
 
-                send_response(id, result);
+                self.send_response(id, result);
             }
             LspResponse::WorkspaceEdits(id, vec_of_edits) => {
                 let mut map_of_edits: HashMap<Url, Vec<languageserver_types::TextEdit>> =
@@ -177,10 +397,18 @@ impl Actor for LspResponder {
                     document_changes: None,
                 };
 
-                send_response(id, result);
+                self.send_response(id, result);
+            }
+            LspResponse::TextEdits(id, edits) => {
+                let result: Vec<languageserver_types::TextEdit> = edits
+                    .into_iter()
+                    .map(|(range, new_text)| languageserver_types::TextEdit { range, new_text })
+                    .collect();
+
+                self.send_response(id, result);
             }
             LspResponse::Nothing(id) => {
-                send_response(id, ());
+                self.send_response(id, ());
             }
             LspResponse::Completions(id, completions) => {
                 let mut completion_items = vec![];
@@ -197,174 +425,396 @@ impl Actor for LspResponder {
                     items: completion_items,
                 };
 
-                send_response(id, result);
+                self.send_response(id, result);
+            }
+            LspResponse::SignatureHelp(id, signature_help) => {
+                self.send_response(id, signature_help);
+            }
+            LspResponse::SemanticTokens(id, url, previous_result_id, tokens) => {
+                let data = semantic_tokens::encode(&tokens);
+                self.next_result_id += 1;
+                let result_id = self.next_result_id.to_string();
+
+                let previous = self.semantic_tokens.remove(&url);
+                match previous_result_id {
+                    None => {
+                        let result = SemanticTokens {
+                            result_id: Some(result_id.clone()),
+                            data: data.clone(),
+                        };
+
+                        self.send_response(id, result);
+                    }
+                    Some(previous_result_id) => {
+                        // We can only send the changes from what the IDE
+                        // has if it is what we last sent
+                        let previous_data = previous
+                            .filter(|(previous_id, _)| *previous_id == previous_result_id)
+                            .map(|(_, previous_data)| previous_data);
+                        let result = match previous_data {
+                            Some(previous_data) => {
+                                SemanticTokensDeltaResult::Delta(SemanticTokensDelta {
+                                    result_id: Some(result_id.clone()),
+                                    edits: semantic_tokens::diff(&previous_data, &data),
+                                })
+                            }
+                            None => SemanticTokensDeltaResult::Full(SemanticTokens {
+                                result_id: Some(result_id.clone()),
+                                data: data.clone(),
+                            }),
+                        };
+
+                        self.send_response(id, result);
+                    }
+                }
+
+                self.semantic_tokens.insert(url, (result_id, data));
+            }
+            LspResponse::CodeActions(id, actions) => {
+                self.send_response(id, actions);
+            }
+            LspResponse::DocumentSymbols(id, symbols) => {
+                let result = languageserver_types::DocumentSymbolResponse::Nested(symbols);
+
+                self.send_response(id, result);
+            }
+            LspResponse::WorkspaceSymbols(id, symbols) => {
+                self.send_response(id, symbols);
+            }
+            LspResponse::InlayHints(id, hints) => {
+                let result: Vec<InlayHint> = hints.into_iter().map(InlayHint::from).collect();
+                self.send_response(id, result);
             }
-            LspResponse::Initialized(id) => {
-                let result = languageserver_types::InitializeResult {
-                    capabilities: languageserver_types::ServerCapabilities {
-                        text_document_sync: Some(
-                            languageserver_types::TextDocumentSyncCapability::Kind(
-                                languageserver_types::TextDocumentSyncKind::Incremental,
+            LspResponse::Initialized(id, encoding) => {
+                let result = InitializeResult {
+                    capabilities: ServerCapabilities {
+                        standard: languageserver_types::ServerCapabilities {
+                            text_document_sync: Some(
+                                languageserver_types::TextDocumentSyncCapability::Kind(
+                                    languageserver_types::TextDocumentSyncKind::Incremental,
+                                ),
                             ),
-                        ),
-                        hover_provider: Some(true),
-                        /*
-                        completion_provider: Some(languageserver_types::CompletionOptions {
-                            resolve_provider: Some(true),
-                            trigger_characters: Some(vec![".".into()]),
-                        }),
-                        */
-                        completion_provider: None,
-                        signature_help_provider: None,
-                        definition_provider: Some(true),
-                        type_definition_provider: None,
-                        implementation_provider: None,
-                        references_provider: Some(true),
-                        document_highlight_provider: None,
-                        document_symbol_provider: None,
-                        workspace_symbol_provider: None,
-                        code_action_provider: None,
-                        code_lens_provider: None,
-                        document_formatting_provider: None,
-                        document_range_formatting_provider: None,
-                        document_on_type_formatting_provider: None,
-                        rename_provider: Some(
-                            languageserver_types::RenameProviderCapability::Simple(true),
-                        ),
-                        color_provider: None,
-                        folding_range_provider: None,
-                        execute_command_provider: None,
-                        workspace: None,
+                            hover_provider: Some(true),
+                            completion_provider: Some(languageserver_types::CompletionOptions {
+                                resolve_provider: None,
+                                trigger_characters: Some(vec![".".into()]),
+                            }),
+                            signature_help_provider: Some(
+                                languageserver_types::SignatureHelpOptions {
+                                    trigger_characters: Some(vec!["(".into(), ",".into()]),
+                                },
+                            ),
+                            definition_provider: Some(true),
+                            type_definition_provider: None,
+                            implementation_provider: None,
+                            references_provider: Some(true),
+                            document_highlight_provider: None,
+                            document_symbol_provider: Some(true),
+                            workspace_symbol_provider: Some(true),
+                            code_action_provider: Some(
+                                languageserver_types::CodeActionProviderCapability::Simple(true),
+                            ),
+                            code_lens_provider: None,
+                            document_formatting_provider: Some(true),
+                            document_range_formatting_provider: Some(true),
+                            document_on_type_formatting_provider: None,
+                            rename_provider: Some(
+                                languageserver_types::RenameProviderCapability::Simple(true),
+                            ),
+                            color_provider: None,
+                            folding_range_provider: None,
+                            execute_command_provider: None,
+                            workspace: None,
+                        },
+                        semantic_tokens_provider: semantic_tokens::options(),
+                        inlay_hint_provider: true,
+                        position_encoding: encoding.name(),
                     },
                 };
 
-                send_response(id, result);
+                self.send_response(id, result);
             }
-            LspResponse::Diagnostics(url, diagnostics) => {
-                let lsp_diagnostics: Vec<languageserver_types::Diagnostic> = diagnostics
-                    .iter()
-                    .map(|(range, diag)| {
-                        languageserver_types::Diagnostic::new_simple(*range, diag.clone())
-                    })
-                    .collect();
-
-                let notice = languageserver_types::PublishDiagnosticsParams {
-                    uri: url,
-                    diagnostics: lsp_diagnostics,
+            LspResponse::Diagnostics(url, version, diagnostics) => {
+                let notice = PublishDiagnosticsParams {
+                    standard: languageserver_types::PublishDiagnosticsParams {
+                        uri: url,
+                        diagnostics,
+                    },
+                    version,
                 };
 
-                send_notification("textDocument/publishDiagnostics".into(), notice);
+                self.send_notification("textDocument/publishDiagnostics".into(), notice);
+            }
+            LspResponse::Error(id, error) => {
+                self.send_error(id, error);
+            }
+        }
+    }
+}
+
+/// How we talk to the IDE: the messages that it sends come in through
+/// `reader`, and the messages that we send go out through `writer`.
+pub struct Connection {
+    pub reader: Box<dyn BufRead + Send>,
+    pub writer: Box<dyn Write + Send>,
+}
+
+impl Connection {
+    /// Talks to the IDE over stdin and stdout, as most IDEs expect.
+    pub fn stdio() -> Connection {
+        Connection {
+            reader: Box::new(BufReader::new(io::stdin())),
+            writer: Box::new(io::stdout()),
+        }
+    }
+
+    /// Waits for an IDE (perhaps on another machine, or outside of our
+    /// container) to connect to `listener`, and talks to it over that
+    /// connection.
+    pub fn accept(listener: &TcpListener) -> io::Result<Connection> {
+        let (stream, _) = listener.accept()?;
+        Ok(Connection {
+            reader: Box::new(BufReader::new(stream.try_clone()?)),
+            writer: Box::new(stream),
+        })
+    }
+}
+
+/// Reads the next message from the IDE: a header, of which we only
+/// need the `Content-Length`, followed by a blank line and the content
+/// itself. Returns `None` once the IDE has gone away. (The Debug
+/// Adapter Protocol frames its messages the same way.)
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
             }
+        } else if line.starts_with("Content-Length:") {
+            let length = line["Content-Length:".len()..]
+                .trim()
+                .parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "bad `Content-Length`"))?;
+            content_length = Some(length);
         }
     }
+
+    let mut buffer = vec![0u8; content_length.unwrap()];
+    reader.read_exact(&mut buffer)?;
+    String::from_utf8(buffer)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
 }
 
 /// The workhorse function for handling incoming requests from the IDE. This will
-/// take instructions from stdin sent by the IDE and then send them to the appropriate
-/// system.
-pub fn lsp_serve(send_to_query_channel: Sender<QueryRequest>) {
+/// take instructions sent by the IDE through `reader` and then send them to the
+/// appropriate system. Returns once the IDE tells us to exit (or goes away), with
+/// whether it asked us to shut down first, as it should have.
+pub fn lsp_serve(
+    mut reader: impl BufRead,
+    send_to_query_channel: Sender<QueryRequest>,
+    send_to_responder_channel: Sender<LspResponse>,
+) -> bool {
+    let mut shut_down = false;
+
     loop {
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let content_length_items: Vec<&str> = input.split(' ').collect();
-                if content_length_items[0] == "Content-Length:" {
-                    let num_bytes: usize = content_length_items[1].trim().parse().unwrap();
-                    let mut buffer = vec![0u8; num_bytes + 2];
-                    let _ = io::stdin().read_exact(&mut buffer);
-
-                    let buffer_string = String::from_utf8(buffer).unwrap();
-
-                    let command = serde_json::from_str::<LSPCommand>(&buffer_string);
-
-                    match command {
-                        Ok(LSPCommand::initialize { id, .. }) => {
-                            let _ = send_to_query_channel.send(QueryRequest::Initialize(id));
-                        }
-                        Ok(LSPCommand::initialized) => {
-                            //eprintln!("Initialized received");
-                        }
-                        Ok(LSPCommand::didOpen { params }) => {
-                            //eprintln!("didOpen: {:#?}", params);
-
-                            let _ = send_to_query_channel.send(QueryRequest::OpenFile(
-                                params.text_document.uri.clone(),
-                                params.text_document.text.clone(),
-                            ));
-                        }
-                        Ok(LSPCommand::didChange { params }) => {
-                            //eprintln!("didChange: {:#?}", params);
-
-                            let changes = params
-                                .content_changes
-                                .iter()
-                                .map(|x| (x.range.unwrap(), x.text.clone()))
-                                .collect();
-
-                            let _ = send_to_query_channel.send(QueryRequest::EditFile(
-                                params.text_document.uri.clone(),
-                                changes,
-                            ));
-                        }
-                        Ok(LSPCommand::hover { id, params }) => {
-                            //eprintln!("hover: id={} {:#?}", id, params);
-
-                            let _ = send_to_query_channel.send(QueryRequest::TypeAtPosition(
-                                id,
-                                params.text_document.uri.clone(),
-                                params.position.clone(),
-                            ));
-                        }
-                        Ok(LSPCommand::definition { id, params }) => {
-                            let _ = send_to_query_channel.send(QueryRequest::DefinitionAtPosition(
-                                id,
-                                params.text_document.uri.clone(),
-                                params.position.clone(),
-                            ));
-                        }
-                        Ok(LSPCommand::references { id, params }) => {
-                            let _ = send_to_query_channel.send(QueryRequest::ReferencesAtPosition(
-                                id,
-                                params.text_document.uri.clone(),
-                                params.position.clone(),
-                                true,
-                            ));
-                        }
-                        Ok(LSPCommand::rename { id, params }) => {
-                            let _ = send_to_query_channel.send(QueryRequest::RenameAtPosition(
-                                id,
-                                params.text_document.uri.clone(),
-                                params.position.clone(),
-                                params.new_name.clone(),
-                            ));
-                        }
-                        Ok(LSPCommand::completion { .. }) => {
-                            //eprintln!("completion: id={} {:#?}", id, params);
-                        }
-                        Ok(LSPCommand::completionItemResolve { .. }) => {
-                            //Note: this is here in case we need it, though it looks like it's only used
-                            //for more expensive computations on a completion (like fetching the docs)
-                            //eprintln!("resolve completion item: id={} {:#?}", id, params);
-                        }
-                        Ok(LSPCommand::cancelRequest {
-                            params: languageserver_types::CancelParams { id },
-                        }) => match id {
-                            languageserver_types::NumberOrString::Number(_num) => {
-                                //eprintln!("cancelling item: id={}", num);
-                                /* FIXME FIXME: removing cancelling for the time being
-                                let _ = send_to_manager_channel
-                                    .send(MsgToManager::Cancel(num as usize));
-                                */
-                            }
-                            _ => unimplemented!(
-                                "Non-number cancellation IDs not currently supported"
-                            ),
-                        },
-                        Err(e) => eprintln!("Error handling command: {:?}", e),
-                    }
-                }
+        let buffer_string = match read_message(&mut reader) {
+            Ok(Some(buffer_string)) => buffer_string,
+            Ok(None) => return false,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                return false;
+            }
+        };
+
+        let message = match serde_json::from_str::<serde_json::Value>(&buffer_string) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Error handling command: {:?}", e);
+                continue;
+            }
+        };
+
+        // Once we have been shut down, the only thing left for the IDE
+        // to do is to tell us to exit
+        if shut_down && message["method"] != "exit" {
+            if let Some(id) = message["id"].as_u64() {
+                let error = RequestError::InvalidRequest(String::from("the server is shut down"));
+                let _ = send_to_responder_channel.send(LspResponse::Error(id as usize, error));
+            }
+            continue;
+        }
+
+        let command = serde_json::from_value::<LSPCommand>(message);
+
+        match command {
+            Ok(LSPCommand::initialize { id, params }) => {
+                let _ = send_to_query_channel
+                    .send(QueryRequest::Initialize(id, params.position_encoding()));
+            }
+            Ok(LSPCommand::initialized) => {
+                //eprintln!("Initialized received");
+            }
+            Ok(LSPCommand::shutdown { id }) => {
+                shut_down = true;
+                let _ = send_to_responder_channel.send(LspResponse::Nothing(id));
+            }
+            Ok(LSPCommand::exit) => {
+                return shut_down;
+            }
+            Ok(LSPCommand::didOpen { params }) => {
+                //eprintln!("didOpen: {:#?}", params);
+
+                let _ = send_to_query_channel.send(QueryRequest::OpenFile(
+                    params.text_document.uri.clone(),
+                    params.text_document.version,
+                    params.text_document.text.clone(),
+                ));
+            }
+            Ok(LSPCommand::didChange { params }) => {
+                //eprintln!("didChange: {:#?}", params);
+
+                let changes = params
+                    .content_changes
+                    .iter()
+                    .map(|x| (x.range, x.text.clone()))
+                    .collect();
+
+                let _ = send_to_query_channel.send(QueryRequest::EditFile(
+                    params.text_document.uri.clone(),
+                    params.text_document.version,
+                    changes,
+                ));
+            }
+            Ok(LSPCommand::didClose { params }) => {
+                let _ = send_to_query_channel
+                    .send(QueryRequest::CloseFile(params.text_document.uri.clone()));
+            }
+            Ok(LSPCommand::hover { id, params }) => {
+                //eprintln!("hover: id={} {:#?}", id, params);
+
+                let _ = send_to_query_channel.send(QueryRequest::TypeAtPosition(
+                    id,
+                    params.text_document.uri.clone(),
+                    params.position,
+                ));
+            }
+            Ok(LSPCommand::definition { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::DefinitionAtPosition(
+                    id,
+                    params.text_document.uri.clone(),
+                    params.position,
+                ));
+            }
+            Ok(LSPCommand::references { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::ReferencesAtPosition(
+                    id,
+                    params.text_document.uri.clone(),
+                    params.position,
+                    true,
+                ));
+            }
+            Ok(LSPCommand::rename { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::RenameAtPosition(
+                    id,
+                    params.text_document.uri.clone(),
+                    params.position,
+                    params.new_name.clone(),
+                ));
+            }
+            Ok(LSPCommand::completion { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::CompletionAtPosition(
+                    id,
+                    params.text_document.uri,
+                    params.position,
+                ));
+            }
+            Ok(LSPCommand::signatureHelp { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::SignatureHelpAtPosition(
+                    id,
+                    params.text_document.uri,
+                    params.position,
+                ));
+            }
+            Ok(LSPCommand::semanticTokensFull { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::SemanticTokens(
+                    id,
+                    params.text_document.uri,
+                    None,
+                ));
             }
-            Err(error) => eprintln!("error: {}", error),
+            Ok(LSPCommand::semanticTokensFullDelta { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::SemanticTokens(
+                    id,
+                    params.text_document.uri,
+                    Some(params.previous_result_id),
+                ));
+            }
+            Ok(LSPCommand::codeAction { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::CodeActions(
+                    id,
+                    params.text_document.uri,
+                    params.range,
+                ));
+            }
+            Ok(LSPCommand::formatting { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::Formatting(
+                    id,
+                    params.text_document.uri,
+                    None,
+                ));
+            }
+            Ok(LSPCommand::rangeFormatting { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::Formatting(
+                    id,
+                    params.text_document.uri,
+                    Some(params.range),
+                ));
+            }
+            Ok(LSPCommand::documentSymbol { id, params }) => {
+                let _ = send_to_query_channel
+                    .send(QueryRequest::DocumentSymbols(id, params.text_document.uri));
+            }
+            Ok(LSPCommand::inlayHint { id, params }) => {
+                let _ = send_to_query_channel.send(QueryRequest::InlayHints(
+                    id,
+                    params.text_document.uri.clone(),
+                    params.range,
+                ));
+            }
+            Ok(LSPCommand::workspaceSymbol { id, params }) => {
+                let _ =
+                    send_to_query_channel.send(QueryRequest::WorkspaceSymbols(id, params.query));
+            }
+            Ok(LSPCommand::completionItemResolve { .. }) => {
+                //Note: this is here in case we need it, though it looks like it's only used
+                //for more expensive computations on a completion (like fetching the docs)
+                //eprintln!("resolve completion item: id={} {:#?}", id, params);
+            }
+            Ok(LSPCommand::cancelRequest {
+                params: languageserver_types::CancelParams { id },
+            }) => match id {
+                languageserver_types::NumberOrString::Number(_num) => {
+                    //eprintln!("cancelling item: id={}", num);
+                    /* FIXME FIXME: removing cancelling for the time being
+                    let _ = send_to_manager_channel
+                        .send(MsgToManager::Cancel(num as usize));
+                    */
+                }
+                languageserver_types::NumberOrString::String(id) => {
+                    // The IDs of the requests we handle are numbers,
+                    // so there is nothing to cancel
+                    log::info!("ignoring cancellation of unknown request {:?}", id);
+                }
+            },
+            Err(e) => eprintln!("Error handling command: {:?}", e),
         }
     }
 }
@@ -0,0 +1,151 @@
+//! Semantic tokens, which let the IDE color names by what they refer
+//! to. These are newer than the `languageserver-types` we use, so the
+//! protocol types are defined here.
+
+use languageserver_types::TextDocumentIdentifier;
+use lark_actor::{SemanticToken, SemanticTokenKind};
+use serde::{Deserialize, Serialize};
+
+/// The only token modifier we report.
+const DECLARATION_MODIFIER: &str = "declaration";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensDeltaParams {
+    pub text_document: TextDocumentIdentifier,
+    pub previous_result_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokens {
+    pub result_id: Option<String>,
+
+    /// Five numbers per token: its line and start character (each
+    /// relative to the previous token), its length, the index of its
+    /// kind in the legend, and a bit set of its modifiers.
+    pub data: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensDelta {
+    pub result_id: Option<String>,
+    pub edits: Vec<SemanticTokensEdit>,
+}
+
+/// Replaces `delete_count` numbers of the previous `data`, starting
+/// at `start`, with `data`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensEdit {
+    pub start: u64,
+    pub delete_count: u64,
+    pub data: Vec<u64>,
+}
+
+/// The answer to a delta request, which is just the full tokens if we
+/// no longer have the result that the IDE has.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SemanticTokensDeltaResult {
+    Delta(SemanticTokensDelta),
+    Full(SemanticTokens),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticTokensLegend {
+    pub token_types: Vec<String>,
+    pub token_modifiers: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SemanticTokensFullOptions {
+    pub delta: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SemanticTokensOptions {
+    pub legend: SemanticTokensLegend,
+    pub full: SemanticTokensFullOptions,
+}
+
+/// What we tell the IDE in our capabilities: which kinds of token we
+/// report, and that we can send just the changes to them.
+pub fn options() -> SemanticTokensOptions {
+    SemanticTokensOptions {
+        legend: SemanticTokensLegend {
+            token_types: SemanticTokenKind::ALL
+                .iter()
+                .map(|kind| kind.name().to_string())
+                .collect(),
+            token_modifiers: vec![DECLARATION_MODIFIER.to_string()],
+        },
+        full: SemanticTokensFullOptions { delta: true },
+    }
+}
+
+/// Encodes `tokens` (which must be in order) as the `data` of a
+/// `SemanticTokens`.
+pub fn encode(tokens: &[SemanticToken]) -> Vec<u64> {
+    let mut data = Vec::with_capacity(tokens.len() * 5);
+    let mut line = 0;
+    let mut character = 0;
+
+    for token in tokens {
+        let start = token.range.start;
+        if start.line != line {
+            character = 0;
+        }
+
+        data.push(start.line - line);
+        data.push(start.character - character);
+        data.push(token.range.end.character - start.character);
+        data.push(
+            SemanticTokenKind::ALL
+                .iter()
+                .position(|&kind| kind == token.kind)
+                .unwrap() as u64,
+        );
+        data.push(if token.is_declaration { 1 } else { 0 });
+
+        line = start.line;
+        character = start.character;
+    }
+
+    data
+}
+
+/// The edits that turn `previous` into `data`: none if they are the
+/// same, and otherwise one that replaces whatever lies between their
+/// common prefix and common suffix.
+pub fn diff(previous: &[u64], data: &[u64]) -> Vec<SemanticTokensEdit> {
+    let prefix = previous
+        .iter()
+        .zip(data)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == previous.len() && prefix == data.len() {
+        return vec![];
+    }
+
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(data[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    vec![SemanticTokensEdit {
+        start: prefix as u64,
+        delete_count: (previous.len() - prefix - suffix) as u64,
+        data: data[prefix..data.len() - suffix].to_vec(),
+    }]
+}
@@ -10,7 +10,8 @@ use lark_entity::Entity;
 use lark_error::ErrorReported;
 use lark_error::WithError;
 use lark_hir as hir;
-use lark_span::{FileName, Span};
+use lark_span::{FileName, Span, Spanned};
+use lark_string::GlobalIdentifier;
 use lark_ty as ty;
 use lark_ty::declaration::Declaration;
 use std::sync::Arc;
@@ -64,4 +65,12 @@ impl LazyParsedEntity for ParsedFile {
     fn parse_fn_body(&self, entity: Entity, db: &dyn ParserDatabase) -> WithError<hir::FnBody> {
         InvalidParsedEntity.parse_fn_body(entity, db)
     }
+
+    fn parse_imported_module(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        InvalidParsedEntity.parse_imported_module(entity, db)
+    }
 }
@@ -3,5 +3,5 @@ crate mod matchers;
 
 crate mod definition;
 crate mod test;
-crate mod token;
+pub mod token;
 crate mod tools;
@@ -15,7 +15,7 @@ crate enum LexerState {
     ContinueIdent,
     StringLiteral,
     Sigil,
-    Slash,
+    SigilAfterGreaterThan,
     Number,
     Comment(u32),
     EolComment,
@@ -35,8 +35,9 @@ impl LexerDelegateTrait for LexerState {
             LexerState::Top => match c {
                 None => LexerNext::EOF,
                 Some(c) => match c {
-                    '/' => LexerNext::begin(Slash),
-                    c if UnicodeXID::is_xid_start(c) => LexerNext::begin(StartIdent),
+                    '/' if rest.starts_with("//") => LexerNext::begin(EolComment),
+                    '/' if rest.starts_with("/*") => consume_str("/*").and_push(Comment(1)),
+                    c if UnicodeXID::is_xid_start(c) || c == '_' => LexerNext::begin(StartIdent),
                     c if is_delimiter_sigil_char(c) => {
                         consume(c).and_emit(LexToken::Sigil).and_remain()
                     }
@@ -48,19 +49,10 @@ impl LexerDelegateTrait for LexerState {
                     '"' => consume(c).and_transition(StringLiteral),
                     '\n' => LexerNext::sigil(LexToken::Newline),
                     c if c.is_whitespace() => LexerNext::begin(Whitespace),
-                    _ if rest.starts_with("/*") => consume_str("/*").and_push(Comment(1)),
                     _ => consume(c).and_emit(LexToken::Error).and_remain(),
                 },
             },
 
-            LexerState::Slash => match c {
-                None => reconsume()
-                    .and_emit(LexToken::Sigil)
-                    .and_transition(LexerState::Top),
-                Some('/') => reconsume().and_transition(LexerState::EolComment),
-                Some(c) => consume(c).and_transition(LexerState::Sigil),
-            },
-
             LexerState::Sigil => match c {
                 None => reconsume()
                     .and_emit(LexToken::Sigil)
@@ -68,12 +60,22 @@ impl LexerDelegateTrait for LexerState {
                 Some(c) if is_delimiter_sigil_char(c) => reconsume()
                     .and_emit(LexToken::Sigil)
                     .and_transition(LexerState::Top),
+                Some('>') => consume('>').and_transition(LexerState::SigilAfterGreaterThan),
                 Some(c) if is_sigil_char(c) => consume(c).and_remain(),
                 _ => reconsume()
                     .and_emit(LexToken::Sigil)
                     .and_transition(LexerState::Top),
             },
 
+            // A `>` only continues into `>=`, so that (e.g.) the `>>` in
+            // `Box<Box<T>>` or the `>,` in `Box<T>, x` are two tokens.
+            LexerState::SigilAfterGreaterThan => match c {
+                Some('=') => consume('=').and_transition(LexerState::Sigil),
+                _ => reconsume()
+                    .and_emit(LexToken::Sigil)
+                    .and_transition(LexerState::Top),
+            },
+
             LexerState::Number => match c {
                 None => reconsume()
                     .and_emit(LexToken::Integer)
@@ -188,7 +190,8 @@ impl LexerDelegateTrait for LexerState {
 
 fn is_sigil_char(c: char) -> bool {
     match c {
-        '{' | '}' | '(' | ')' | '+' | '-' | '*' | '/' | ':' | ',' | '>' | '<' | '=' | '.' => true,
+        '{' | '}' | '(' | ')' | '+' | '-' | '*' | '/' | '%' | ':' | ',' | '>' | '<' | '='
+        | '!' | '&' | '|' | '.' => true,
         _ => false,
     }
 }
@@ -5,9 +5,9 @@
 #![feature(try_blocks)]
 #![allow(dead_code)]
 
-use crate::lexer::token::LexToken;
 use crate::macros::EntityMacroDefinition;
 use crate::syntax::entity::ParsedEntity;
+use languageserver_types::Position;
 use lark_collections::{FxIndexMap, Seq};
 use lark_debug_derive::DebugWith;
 use lark_entity::Entity;
@@ -23,6 +23,8 @@ use lark_span::ByteIndex;
 use lark_span::FileName;
 use lark_span::IntoFileName;
 use lark_span::Location;
+use lark_span::OutOfBounds;
+use lark_span::PositionEncoding;
 use lark_span::Span;
 use lark_span::Spanned;
 use lark_string::GlobalIdentifier;
@@ -44,6 +46,7 @@ pub mod syntax;
 mod type_conversion;
 
 pub use self::ir::ParsedFile;
+pub use self::lexer::token::LexToken;
 
 #[salsa::query_group(ParserStorage)]
 pub trait ParserDatabase:
@@ -55,6 +58,11 @@ pub trait ParserDatabase:
     #[salsa::input]
     fn file_text(&self, id: FileName) -> Text;
 
+    /// How the columns of the positions exchanged with the IDE are
+    /// counted (UTF-16 code units, unless the IDE agreed otherwise).
+    #[salsa::input]
+    fn position_encoding(&self) -> PositionEncoding;
+
     #[salsa::invoke(query_definitions::entity_span)]
     fn entity_span(&self, entity: Entity) -> Span<FileName>;
 
@@ -71,10 +79,16 @@ pub trait ParserDatabase:
     #[salsa::invoke(query_definitions::location)]
     fn location(&self, id: FileName, index: ByteIndex) -> Location;
 
-    /// Given a (zero-based) line number `line` and column within
-    /// the line, gives a byte-index into the file's text.
+    /// The LSP position of `index` in the given file, with its column
+    /// counted according to the `position_encoding`.
+    #[salsa::invoke(query_definitions::position)]
+    fn position(&self, id: FileName, index: ByteIndex) -> Position;
+
+    /// Given a (zero-based) line number `line` and column within the
+    /// line (counted according to the `position_encoding`), gives a
+    /// byte-index into the file's text.
     #[salsa::invoke(query_definitions::byte_index)]
-    fn byte_index(&self, id: FileName, line: u64, column: u64) -> ByteIndex;
+    fn byte_index(&self, id: FileName, line: u64, column: u64) -> Result<ByteIndex, OutOfBounds>;
 
     // FIXME: In general, this is wasteful of space, and not
     // esp. incremental friendly. It would be better store
@@ -143,6 +157,26 @@ pub trait ParserDatabase:
     /// Resolve a type name that appears in the given entity.
     #[salsa::invoke(scope::resolve_name)]
     fn resolve_name(&self, scope: Entity, name: GlobalIdentifier) -> Option<Entity>;
+
+    /// The name by which other files can `use` items from the given
+    /// file: its file name, without directory or extension.
+    #[salsa::invoke(scope::module_name)]
+    fn module_name(&self, file: FileName) -> GlobalIdentifier;
+
+    /// The names of the modules that the given file imports items
+    /// from (with `use`).
+    #[salsa::invoke(scope::imported_modules)]
+    fn imported_modules(&self, file: FileName) -> Seq<GlobalIdentifier>;
+
+    /// For a `use` item, the item that it imports.
+    #[salsa::invoke(scope::import_target)]
+    fn import_target(&self, entity: Entity) -> WithError<Result<Entity, ErrorReported>>;
+
+    /// Errors in how the given file fits in with the other modules:
+    /// another file with the same module name, or two `use` items
+    /// that import different items under the same name.
+    #[salsa::invoke(scope::module_errors)]
+    fn module_errors(&self, file: FileName) -> WithError<()>;
 }
 
 #[derive(Clone, Debug, DebugWith, PartialEq, Eq)]
@@ -160,6 +194,7 @@ pub enum HoverTargetKind {
 pub trait ParserDatabaseExt: ParserDatabase {
     fn init_parser_db(&mut self) {
         self.set_file_names(Default::default());
+        self.set_position_encoding(Default::default());
     }
 
     fn add_file(&mut self, path: impl IntoFileName, contents: impl Into<Text>) {
@@ -213,6 +248,8 @@ fn macro_definitions(
         macros(
             "struct" => macros::struct_declaration::StructDeclaration,
             "def" => macros::function_declaration::FunctionDeclaration,
+            "use" => macros::use_declaration::UseDeclaration,
+            "enum" => macros::enum_declaration::EnumDeclaration,
         ),
     )
 }
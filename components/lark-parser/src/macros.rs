@@ -6,8 +6,10 @@ use lark_span::FileName;
 use lark_span::Spanned;
 use lark_string::GlobalIdentifier;
 
+crate mod enum_declaration;
 crate mod function_declaration;
 crate mod struct_declaration;
+crate mod use_declaration;
 
 crate trait EntityMacroDefinition: Send {
     /// Invoked when the macro name has been recognized and
@@ -1,10 +1,12 @@
 use crate::macros::EntityMacroDefinition;
 use crate::parser::Parser;
+use crate::syntax::entity::InvalidParsedEntity;
 use crate::syntax::entity::LazyParsedEntity;
 use crate::syntax::entity::ParsedEntity;
 use crate::syntax::entity::ParsedEntityThunk;
 use crate::syntax::fn_signature::FunctionSignature;
 use crate::syntax::fn_signature::ParsedFunctionSignature;
+use crate::syntax::generic_declaration::{self, GenericParameters};
 use crate::syntax::identifier::SpannedGlobalIdentifier;
 use crate::syntax::skip_newline::SkipNewline;
 use crate::ParserDatabase;
@@ -28,7 +30,7 @@ use lark_ty::GenericDeclarations;
 use std::sync::Arc;
 
 /// ```ignore
-/// `def` <id> `(` <id> `:` <ty> `)` [ `->` <ty> ] <block>
+/// `def` <id> [ `<` <id> `>` ] `(` <id> `:` <ty> `)` [ `->` <ty> ] <block>
 /// ```
 #[derive(Default)]
 pub struct FunctionDeclaration;
@@ -48,6 +50,11 @@ impl EntityMacroDefinition for FunctionDeclaration {
 
         let function_name = parser.expect(SkipNewline(SpannedGlobalIdentifier))?;
 
+        let generic_parameters = match parser.parse_if_present(GenericParameters) {
+            Some(generic_parameters) => generic_parameters?,
+            None => Seq::default(),
+        };
+
         let signature = parser.expect(FunctionSignature)?;
 
         let entity = EntityData::ItemName {
@@ -64,13 +71,17 @@ impl EntityMacroDefinition for FunctionDeclaration {
             entity,
             full_span,
             characteristic_span,
-            ParsedEntityThunk::new(ParsedFunctionDeclaration { signature }),
+            ParsedEntityThunk::new(ParsedFunctionDeclaration {
+                generic_parameters,
+                signature,
+            }),
         ))
     }
 }
 
 #[derive(Clone, DebugWith)]
 pub struct ParsedFunctionDeclaration {
+    pub generic_parameters: Seq<Spanned<GlobalIdentifier, FileName>>,
     pub signature: ParsedFunctionSignature,
 }
 
@@ -85,11 +96,10 @@ impl LazyParsedEntity for ParsedFunctionDeclaration {
 
     fn parse_generic_declarations(
         &self,
-        _entity: Entity,
+        entity: Entity,
         _db: &dyn ParserDatabase,
     ) -> WithError<Result<Arc<GenericDeclarations>, ErrorReported>> {
-        // FIXME -- no support for generics yet
-        WithError::ok(Ok(GenericDeclarations::empty(None)))
+        generic_declaration::generic_declarations(entity, None, &self.generic_parameters)
     }
 
     fn parse_type(
@@ -101,13 +111,14 @@ impl LazyParsedEntity for ParsedFunctionDeclaration {
         // Rust.
         match db.generic_declarations(entity).into_value() {
             Ok(generic_declarations) => {
-                assert!(generic_declarations.is_empty());
+                let generics =
+                    crate::type_conversion::bound_var_generics(db, &generic_declarations);
                 let ty = crate::type_conversion::declaration_ty_named(
                     &db,
                     entity,
                     ty::declaration::DeclaredPermKind::Own,
                     ty::ReprKind::Direct,
-                    ty::Generics::empty(),
+                    generics,
                 );
                 WithError::ok(ty)
             }
@@ -126,4 +137,12 @@ impl LazyParsedEntity for ParsedFunctionDeclaration {
     fn parse_fn_body(&self, entity: Entity, db: &dyn ParserDatabase) -> WithError<hir::FnBody> {
         self.signature.parse_fn_body(entity, db, None)
     }
+
+    fn parse_imported_module(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        InvalidParsedEntity.parse_imported_module(entity, db)
+    }
 }
@@ -4,6 +4,7 @@ use crate::syntax::delimited::Delimited;
 use crate::syntax::entity::{
     InvalidParsedEntity, LazyParsedEntity, ParsedEntity, ParsedEntityThunk,
 };
+use crate::syntax::generic_declaration::{self, GenericParameters};
 use crate::syntax::identifier::SpannedGlobalIdentifier;
 use crate::syntax::list::CommaList;
 use crate::syntax::member::{Member, ParsedMember};
@@ -29,8 +30,8 @@ use lark_ty::declaration::Declaration;
 use std::sync::Arc;
 
 /// ```ignore
-/// struct <id> {
-///   <id>: <ty> // separated by `,` or newline
+/// struct <id> [ `<` <id> `>` ] {
+///   <member> // separated by `,` or newline
 /// }
 /// ```
 #[derive(Default)]
@@ -52,6 +53,12 @@ impl EntityMacroDefinition for StructDeclaration {
         log::trace!("StructDeclaration::parse: parsing name");
         let struct_name = parser.expect(SkipNewline(SpannedGlobalIdentifier))?;
 
+        log::trace!("StructDeclaration::parse: parsing generic parameters");
+        let generic_parameters = match parser.parse_if_present(GenericParameters) {
+            Some(generic_parameters) => generic_parameters?,
+            None => Seq::default(),
+        };
+
         log::trace!("StructDeclaration::parse: parsing fields");
         let fields = parser
             .expect(SkipNewline(Delimited(Curlies, CommaList(Member))))
@@ -72,12 +79,16 @@ impl EntityMacroDefinition for StructDeclaration {
             entity,
             full_span,
             characteristic_span,
-            ParsedEntityThunk::new(ParsedStructDeclaration { fields }),
+            ParsedEntityThunk::new(ParsedStructDeclaration {
+                generic_parameters,
+                fields,
+            }),
         ))
     }
 }
 
 struct ParsedStructDeclaration {
+    generic_parameters: Seq<Spanned<GlobalIdentifier, FileName>>,
     fields: Seq<Spanned<ParsedMember, FileName>>,
 }
 
@@ -135,11 +146,10 @@ impl LazyParsedEntity for ParsedStructDeclaration {
 
     fn parse_generic_declarations(
         &self,
-        _entity: Entity,
+        entity: Entity,
         _db: &dyn ParserDatabase,
     ) -> WithError<Result<Arc<ty::GenericDeclarations>, ErrorReported>> {
-        // FIXME -- no support for generics yet
-        WithError::ok(Ok(ty::GenericDeclarations::empty(None)))
+        generic_declaration::generic_declarations(entity, None, &self.generic_parameters)
     }
 
     fn parse_signature(
@@ -158,13 +168,14 @@ impl LazyParsedEntity for ParsedStructDeclaration {
         // For each struct `Foo`, the "type" is just `own Foo`
         match db.generic_declarations(entity).into_value() {
             Ok(generic_declarations) => {
-                assert!(generic_declarations.is_empty());
+                let generics =
+                    crate::type_conversion::bound_var_generics(db, &generic_declarations);
                 let ty = crate::type_conversion::declaration_ty_named(
                     &db,
                     entity,
                     ty::declaration::DeclaredPermKind::Own,
                     ty::ReprKind::Direct,
-                    ty::Generics::empty(),
+                    generics,
                 );
                 WithError::ok(ty)
             }
@@ -178,4 +189,12 @@ impl LazyParsedEntity for ParsedStructDeclaration {
             entity.debug_with(db)
         )
     }
+
+    fn parse_imported_module(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        InvalidParsedEntity.parse_imported_module(entity, db)
+    }
 }
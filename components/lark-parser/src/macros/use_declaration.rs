@@ -0,0 +1,121 @@
+use crate::macros::EntityMacroDefinition;
+use crate::parser::Parser;
+use crate::syntax::entity::{
+    InvalidParsedEntity, LazyParsedEntity, ParsedEntity, ParsedEntityThunk,
+};
+use crate::syntax::guard::Guard;
+use crate::syntax::identifier::SpannedGlobalIdentifier;
+use crate::syntax::sigil::Dot;
+use crate::syntax::skip_newline::SkipNewline;
+use crate::ParserDatabase;
+use lark_collections::Seq;
+use lark_debug_derive::DebugWith;
+use lark_debug_with::DebugWith;
+use lark_entity::Entity;
+use lark_entity::EntityData;
+use lark_entity::ItemKind;
+use lark_error::ErrorReported;
+use lark_error::WithError;
+use lark_hir as hir;
+use lark_intern::Intern;
+use lark_span::FileName;
+use lark_span::Spanned;
+use lark_string::GlobalIdentifier;
+use lark_ty as ty;
+use lark_ty::declaration::Declaration;
+use std::sync::Arc;
+
+/// ```ignore
+/// `use` <module> `.` <id>
+/// ```
+///
+/// Brings the item `<id>` from the module `<module>` (i.e., the file
+/// named `<module>.lark`) into scope.
+#[derive(Default)]
+pub struct UseDeclaration;
+
+impl EntityMacroDefinition for UseDeclaration {
+    fn expect(
+        &self,
+        parser: &mut Parser<'_>,
+        base: Entity,
+        macro_name: Spanned<GlobalIdentifier, FileName>,
+    ) -> Result<ParsedEntity, ErrorReported> {
+        log::trace!(
+            "UseDeclaration::parse(base={}, macro_name={})",
+            base.debug_with(parser),
+            macro_name.debug_with(parser)
+        );
+
+        let module = parser.expect(SkipNewline(SpannedGlobalIdentifier))?;
+        let item = parser.expect(Guard(Dot, SpannedGlobalIdentifier))?;
+
+        let entity = EntityData::ItemName {
+            base,
+            kind: ItemKind::Import,
+            id: item.value,
+        }
+        .intern(parser);
+
+        let full_span = macro_name.span.extended_until_end_of(parser.last_span());
+        let characteristic_span = item.span;
+
+        Ok(ParsedEntity::new(
+            entity,
+            full_span,
+            characteristic_span,
+            ParsedEntityThunk::new(ParsedUseDeclaration { module }),
+        ))
+    }
+}
+
+#[derive(Clone, DebugWith)]
+pub struct ParsedUseDeclaration {
+    pub module: Spanned<GlobalIdentifier, FileName>,
+}
+
+impl LazyParsedEntity for ParsedUseDeclaration {
+    fn parse_children(
+        &self,
+        _entity: Entity,
+        _db: &dyn ParserDatabase,
+    ) -> WithError<Seq<ParsedEntity>> {
+        WithError::ok(Seq::default())
+    }
+
+    fn parse_generic_declarations(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> WithError<Result<Arc<ty::GenericDeclarations>, ErrorReported>> {
+        InvalidParsedEntity.parse_generic_declarations(entity, db)
+    }
+
+    fn parse_type(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> WithError<ty::Ty<Declaration>> {
+        InvalidParsedEntity.parse_type(entity, db)
+    }
+
+    fn parse_signature(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> WithError<Result<ty::Signature<Declaration>, ErrorReported>> {
+        InvalidParsedEntity.parse_signature(entity, db)
+    }
+
+    fn parse_fn_body(&self, entity: Entity, db: &dyn ParserDatabase) -> WithError<hir::FnBody> {
+        InvalidParsedEntity.parse_fn_body(entity, db)
+    }
+
+    fn parse_imported_module(
+        &self,
+        _entity: Entity,
+        _db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        Ok(self.module)
+    }
+}
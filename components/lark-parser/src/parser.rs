@@ -297,6 +297,13 @@ impl Parser<'parse> {
     ) -> ErrorReported {
         report_error(&mut self.errors, message, span)
     }
+
+    /// Report an error that was built up elsewhere (for example, with
+    /// suggestions for how to fix it).
+    crate fn report_diagnostic(&mut self, diagnostic: Diagnostic) -> ErrorReported {
+        self.errors.push(diagnostic);
+        ErrorReported::at_diagnostic(self.errors.last().unwrap())
+    }
 }
 
 impl AsRef<GlobalIdentifierTables> for Parser<'_> {
@@ -346,7 +353,7 @@ fn report_error(
     span: Span<FileName>,
 ) -> ErrorReported {
     let message: String = message.into();
-    let diagnostic = crate::diagnostic(message, span);
+    let diagnostic = crate::diagnostic(message, span).with_code("syntax-error");
     errors.push(diagnostic);
     ErrorReported::at_diagnostic(errors.last().unwrap())
 }
@@ -9,6 +9,7 @@ use crate::HoverTarget;
 use crate::HoverTargetKind;
 use crate::ParserDatabase;
 
+use languageserver_types::Position;
 use lark_collections::Seq;
 use lark_debug_with::DebugWith;
 use lark_entity::MemberKind;
@@ -18,7 +19,7 @@ use lark_error::ErrorSentinel;
 use lark_error::WithError;
 use lark_hir as hir;
 use lark_intern::{Intern, Untern};
-use lark_span::{ByteIndex, FileName, Location, Span, Spanned};
+use lark_span::{ByteIndex, FileName, Location, OutOfBounds, Span, Spanned};
 use lark_string::GlobalIdentifier;
 use std::sync::Arc;
 
@@ -33,10 +34,10 @@ crate fn file_tokens(
     while let Some(token) = tokenizer.next() {
         match token {
             Ok(t) => tokens.push(t.in_file_named(file_name)),
-            Err(span) => errors.push(crate::diagnostic(
-                "unrecognized token",
-                span.in_file_named(file_name),
-            )),
+            Err(span) => errors.push(
+                crate::diagnostic("unrecognized token", span.in_file_named(file_name))
+                    .with_code("syntax-error"),
+            ),
         }
     }
 
@@ -71,15 +72,13 @@ crate fn child_parsed_entities(
     match entity.untern(db) {
         EntityData::InputFile { file } => WithError::ok(db.parsed_file(file).into_value().entities),
 
-        EntityData::ItemName { .. } => db
+        EntityData::ItemName { .. } | EntityData::MemberName { .. } => db
             .parsed_entity(entity)
             .thunk
             .parse_children(entity, db)
             .map(Seq::from),
 
-        EntityData::Error { .. } | EntityData::MemberName { .. } | EntityData::LangItem(_) => {
-            WithError::ok(Seq::default())
-        }
+        EntityData::Error { .. } | EntityData::LangItem(_) => WithError::ok(Seq::default()),
     }
 }
 
@@ -152,51 +151,38 @@ crate fn characteristic_entity_span(db: &impl ParserDatabase, entity: Entity) ->
 }
 
 crate fn line_offsets(db: &impl ParserDatabase, id: FileName) -> Seq<usize> {
-    let text: &str = &db.file_text(id);
-    let mut accumulator = 0;
-    text.lines()
-        .map(|line_text| {
-            let line_start = accumulator;
-            accumulator += line_text.len();
-            if text[accumulator..].starts_with("\r\n") {
-                accumulator += 2;
-            } else if text[accumulator..].starts_with("\n") {
-                accumulator += 1;
-            }
-            line_start
-        })
-        .chain(std::iter::once(text.len()))
-        .collect()
+    lark_span::line_offsets(&db.file_text(id)).into()
 }
 
 crate fn location(db: &impl ParserDatabase, id: FileName, index: ByteIndex) -> Location {
+    let text: &str = &db.file_text(id);
     let line_offsets = db.line_offsets(id);
-    match line_offsets.binary_search(&index.to_usize()) {
-        Ok(line) => {
-            // Found the start of a line directly:
-            return Location::new(line, 0, index);
-        }
-        Err(next_line) => {
-            let line = next_line - 1;
+    let line = lark_span::line_of(text, &line_offsets, index.to_usize());
 
-            // Found something in the middle.
-            let line_start = line_offsets[line];
+    // count utf-8 characters to find column
+    let column = text[line_offsets[line]..index.to_usize()].chars().count();
 
-            // count utf-8 characters to find column
-            let text: &str = &db.file_text(id);
-            let column = text[line_start..index.to_usize()].chars().count();
+    Location::new(line, column, index)
+}
 
-            Location::new(line, column, index)
-        }
-    }
+crate fn position(db: &impl ParserDatabase, id: FileName, index: ByteIndex) -> Position {
+    let line_offsets = db.line_offsets(id);
+    db.position_encoding()
+        .position(&db.file_text(id), &line_offsets, index)
 }
 
-crate fn byte_index(db: &impl ParserDatabase, id: FileName, line: u64, column: u64) -> ByteIndex {
-    let line = line as usize;
-    let column = column as usize;
+crate fn byte_index(
+    db: &impl ParserDatabase,
+    id: FileName,
+    line: u64,
+    column: u64,
+) -> Result<ByteIndex, OutOfBounds> {
     let line_offsets = db.line_offsets(id);
-    let line_start = line_offsets[line];
-    ByteIndex::from(line_start + column)
+    db.position_encoding().byte_index(
+        &db.file_text(id),
+        &line_offsets,
+        Position::new(line, column),
+    )
 }
 
 crate fn descendant_entities(db: &impl ParserDatabase, root: Entity) -> Seq<Entity> {
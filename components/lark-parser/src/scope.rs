@@ -1,10 +1,17 @@
 use crate::ParserDatabase;
+use lark_collections::{FxIndexMap, Seq};
+use lark_debug_with::DebugWith;
 use lark_entity::Entity;
 use lark_entity::EntityData;
+use lark_entity::ItemKind;
 use lark_entity::LangItem;
+use lark_error::ErrorReported;
+use lark_error::WithError;
 use lark_intern::Intern;
 use lark_intern::Untern;
+use lark_span::{FileName, Span};
 use lark_string::GlobalIdentifier;
+use std::path::Path;
 
 crate fn resolve_name(
     db: &impl ParserDatabase,
@@ -26,6 +33,18 @@ crate fn resolve_name(
                     | EntityData::InputFile { .. } => false,
                 })
                 .next()
+                .map(|entity| match entity.untern(db) {
+                    // A `use` resolves to the item that it imports
+                    EntityData::ItemName {
+                        kind: ItemKind::Import,
+                        ..
+                    } => match db.import_target(entity).into_value() {
+                        Ok(target) => target,
+                        Err(report) => EntityData::Error(report).intern(db),
+                    },
+
+                    _ => entity,
+                })
                 .or_else(|| {
                     // Implicit root scope:
                     let bool_id = "bool".intern(db);
@@ -68,3 +87,164 @@ crate fn resolve_name(
         EntityData::Error(_) => Some(scope),
     }
 }
+
+crate fn module_name(db: &impl ParserDatabase, file: FileName) -> GlobalIdentifier {
+    let path = file.id.untern(db);
+    let stem = Path::new(path.as_ref())
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_else(|| path.as_ref());
+    stem.intern(db)
+}
+
+crate fn imported_modules(db: &impl ParserDatabase, file: FileName) -> Seq<GlobalIdentifier> {
+    let file_entity = EntityData::InputFile { file }.intern(db);
+    db.child_entities(file_entity)
+        .iter()
+        .filter_map(|&entity| match entity.untern(db) {
+            EntityData::ItemName {
+                kind: ItemKind::Import,
+                ..
+            } => db
+                .parsed_entity(entity)
+                .thunk
+                .parse_imported_module(entity, db)
+                .ok()
+                .map(|module| module.value),
+            _ => None,
+        })
+        .collect()
+}
+
+crate fn import_target(
+    db: &impl ParserDatabase,
+    entity: Entity,
+) -> WithError<Result<Entity, ErrorReported>> {
+    let item = match entity.untern(db) {
+        EntityData::ItemName { id, .. } => id,
+        _ => panic!("import_target invoked on {:?}", entity.debug_with(db)),
+    };
+    let item_span = db.characteristic_entity_span(entity);
+
+    let module = match db.parsed_entity(entity).thunk.parse_imported_module(entity, db) {
+        Ok(module) => module,
+        Err(report) => return WithError::ok(Err(report)),
+    };
+
+    let module_files: Vec<FileName> = db
+        .file_names()
+        .iter()
+        .cloned()
+        .filter(|&file| db.module_name(file) == module.value)
+        .collect();
+
+    let module_file = match &module_files[..] {
+        [module_file] => *module_file,
+        [] => {
+            return WithError::report_error(
+                db,
+                format!("no module named `{}`", module.value.untern(db)),
+                module.span,
+            );
+        }
+        _ => {
+            return WithError::report_error(
+                db,
+                format!("more than one module named `{}`", module.value.untern(db)),
+                module.span,
+            );
+        }
+    };
+
+    // Imports are not themselves importable, so that every `use`
+    // leads directly to the item that it names.
+    let module_entity = EntityData::InputFile { file: module_file }.intern(db);
+    let target = db
+        .child_entities(module_entity)
+        .iter()
+        .cloned()
+        .find(|entity| match entity.untern(db) {
+            EntityData::ItemName { kind, id, .. } => kind != ItemKind::Import && id == item,
+            _ => false,
+        });
+
+    match target {
+        Some(target) => WithError::ok(Ok(target)),
+        None => WithError::report_error(
+            db,
+            format!(
+                "no item named `{}` in module `{}`",
+                item.untern(db),
+                module.value.untern(db)
+            ),
+            item_span,
+        ),
+    }
+}
+
+crate fn module_errors(db: &impl ParserDatabase, file: FileName) -> WithError<()> {
+    let mut errors = vec![];
+
+    // Files with the same name in different directories would be the
+    // same module, so all but the first are errors
+    let name = db.module_name(file);
+    let first_file = db
+        .file_names()
+        .iter()
+        .cloned()
+        .find(|&other| db.module_name(other) == name);
+    if let Some(first_file) = first_file.filter(|&first_file| first_file != file) {
+        errors.push(
+            crate::diagnostic(
+                format!("duplicate module `{}`", name.untern(db)),
+                Span::new(file, 0, 0),
+            )
+            .with_code("duplicate-module")
+            .with_secondary_label(
+                format!("also the module of `{}`", first_file.id.untern(db)),
+                Span::new(first_file, 0, 0),
+            ),
+        );
+    }
+
+    // The same item can be imported twice, but a name can't refer to
+    // items from two modules
+    let file_entity = EntityData::InputFile { file }.intern(db);
+    let mut imports = FxIndexMap::default();
+    for parsed_entity in db.child_parsed_entities(file_entity).into_value().iter() {
+        let item = match parsed_entity.entity.untern(db) {
+            EntityData::ItemName {
+                kind: ItemKind::Import,
+                id,
+                ..
+            } => id,
+            _ => continue,
+        };
+        let module = match parsed_entity
+            .thunk
+            .parse_imported_module(parsed_entity.entity, db)
+        {
+            Ok(module) => module.value,
+            Err(_) => continue,
+        };
+
+        let (first_module, first_span) = *imports
+            .entry(item)
+            .or_insert((module, parsed_entity.full_span));
+        if first_module != module {
+            errors.push(
+                crate::diagnostic(
+                    format!("conflicting imports of `{}`", item.untern(db)),
+                    parsed_entity.full_span,
+                )
+                .with_code("conflicting-import")
+                .with_secondary_label("first imported here", first_span),
+            );
+        }
+    }
+
+    WithError {
+        value: (),
+        errors,
+    }
+}
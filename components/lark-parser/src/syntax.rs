@@ -9,6 +9,7 @@ pub mod entity;
 pub mod expression;
 pub mod fn_body;
 pub mod fn_signature;
+pub mod generic_declaration;
 pub mod guard;
 pub mod identifier;
 pub mod list;
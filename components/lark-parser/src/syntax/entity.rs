@@ -13,6 +13,8 @@ use lark_error::WithError;
 use lark_hir as hir;
 use lark_span::FileName;
 use lark_span::Span;
+use lark_span::Spanned;
+use lark_string::GlobalIdentifier;
 use lark_ty as ty;
 use lark_ty::declaration::Declaration;
 use std::sync::Arc;
@@ -159,6 +161,15 @@ impl ParsedEntityThunk {
     ) -> WithError<hir::FnBody> {
         self.object.parse_fn_body(entity, db)
     }
+
+    /// See [`LazyParsedEntity::parse_imported_module`]
+    crate fn parse_imported_module(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        self.object.parse_imported_module(entity, db)
+    }
 }
 
 impl std::fmt::Debug for ParsedEntityThunk {
@@ -221,6 +232,15 @@ pub trait LazyParsedEntity {
     /// - `entity`: the entity id of self
     /// - `db`: the necessary bits/pieces of the parser database
     fn parse_fn_body(&self, entity: Entity, db: &dyn ParserDatabase) -> WithError<hir::FnBody>;
+
+    /// The name of the module that this entity imports from,
+    /// panicking if it is not an import. e.g., for `use math.square`,
+    /// this would be `math`.
+    fn parse_imported_module(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported>;
 }
 
 crate struct ErrorParsedEntity {
@@ -263,6 +283,14 @@ impl LazyParsedEntity for ErrorParsedEntity {
     fn parse_fn_body(&self, _entity: Entity, db: &dyn ParserDatabase) -> WithError<hir::FnBody> {
         WithError::ok(ErrorSentinel::error_sentinel(&db, self.err))
     }
+
+    fn parse_imported_module(
+        &self,
+        _entity: Entity,
+        _db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        Err(self.err)
+    }
 }
 
 /// Convenience type: implemnts `ParsedDatabase` but just
@@ -318,4 +346,15 @@ impl LazyParsedEntity for InvalidParsedEntity {
             entity.debug_with(db)
         )
     }
+
+    fn parse_imported_module(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        panic!(
+            "cannot invoke `parse_imported_module` on {:?}",
+            entity.debug_with(db)
+        )
+    }
 }
@@ -6,13 +6,15 @@ crate mod expr1_group;
 crate mod expr2_unary;
 crate mod expr34_math;
 crate mod expr5_eq;
+crate mod expr67_logical;
 crate mod ident;
 crate mod literal;
+crate mod match_arm;
 crate mod member_access;
 crate mod scope;
 
 use crate::parser::Parser;
-use crate::syntax::expression::expr5_eq::Expression5;
+use crate::syntax::expression::expr67_logical::Expression7;
 use crate::syntax::expression::ident::HirIdentifier;
 use crate::syntax::expression::scope::ExpressionScope;
 use crate::syntax::sigil::{Colon, Equals};
@@ -96,19 +98,19 @@ impl Syntax<'parse> for Expression<'me, 'parse> {
     type Data = ParsedExpression;
 
     fn test(&mut self, parser: &Parser<'parse>) -> bool {
-        parser.test(Expression5::new(self.scope))
+        parser.test(Expression7::new(self.scope))
     }
 
     fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
-        // Parse `Expression5`
-        let expression = parser.expect(Expression5::new(self.scope))?;
+        // Parse `Expression7`
+        let expression = parser.expect(Expression7::new(self.scope))?;
 
-        // Check for `Expression5 = Expression5`
+        // Check for `Expression7 = Expression7`
         if let Some(_operator) = parser.parse_if_present(Equals) {
             let place = expression.to_hir_place(self.scope);
 
             let value = parser
-                .expect(SkipNewline(Expression5::new(self.scope)))?
+                .expect(SkipNewline(Expression7::new(self.scope)))?
                 .to_hir_expression(self.scope);
 
             let span = self
@@ -147,10 +149,13 @@ impl Syntax<'parse> for IdentifiedExpression<'me, 'parse> {
         let identifier = parser.expect(HirIdentifier::new(self.scope))?;
         parser.expect(Colon)?;
         let expression = parser.expect(SkipNewline(HirExpression::new(self.scope)))?;
+
+        // Not the span of `expression`, which leaves out any parentheses
+        // around it
         let span = self
             .scope
             .span(identifier)
-            .extended_until_end_of(self.scope.span(expression));
+            .extended_until_end_of(parser.last_span());
         Ok(self.scope.add(
             span,
             hir::IdentifiedExpressionData {
@@ -5,6 +5,7 @@ use crate::syntax::expression::ParsedExpression;
 use crate::syntax::expression::{HirExpression, IdentifiedExpression};
 use crate::syntax::list::CommaList;
 use crate::syntax::sigil::{OpenParenthesis, Parentheses};
+use crate::syntax::skip_newline::SkipNewline;
 use crate::syntax::Syntax;
 use derive_new::new;
 use lark_collections::Seq;
@@ -51,7 +52,8 @@ impl Syntax<'parse> for IdentifiedCallArguments<'me, 'parse> {
     fn test(&mut self, parser: &Parser<'parse>) -> bool {
         let mut parser = parser.checkpoint();
         if let Some(_) = parser.parse_if_present(OpenParenthesis) {
-            parser.test(IdentifiedExpression::new(self.scope))
+            // Like any list, the fields may start on the next line
+            parser.test(SkipNewline(IdentifiedExpression::new(self.scope)))
         } else {
             false
         }
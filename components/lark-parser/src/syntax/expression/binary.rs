@@ -64,7 +64,12 @@ where
                 );
 
                 match operator {
-                    hir::BinaryOperator::Equals | hir::BinaryOperator::NotEquals => {
+                    hir::BinaryOperator::Equals
+                    | hir::BinaryOperator::NotEquals
+                    | hir::BinaryOperator::LessThan
+                    | hir::BinaryOperator::LessThanOrEqual
+                    | hir::BinaryOperator::GreaterThan
+                    | hir::BinaryOperator::GreaterThanOrEqual => {
                         // Do not parse `a == b == c` etc
                         break;
                     }
@@ -72,7 +77,10 @@ where
                     hir::BinaryOperator::Add
                     | hir::BinaryOperator::Subtract
                     | hir::BinaryOperator::Multiply
-                    | hir::BinaryOperator::Divide => {
+                    | hir::BinaryOperator::Divide
+                    | hir::BinaryOperator::Modulo
+                    | hir::BinaryOperator::And
+                    | hir::BinaryOperator::Or => {
                         // `a + b + c` is ok
                     }
                 }
@@ -88,18 +96,29 @@ where
 crate const BINARY_OPERATORS_EXPR3: &[(&str, hir::BinaryOperator)] = &[
     ("*", hir::BinaryOperator::Multiply),
     ("/", hir::BinaryOperator::Divide),
+    ("%", hir::BinaryOperator::Modulo),
 ];
 
 crate const BINARY_OPERATORS_EXPR4: &[(&str, hir::BinaryOperator)] = &[
     ("+", hir::BinaryOperator::Add),
-    ("_", hir::BinaryOperator::Subtract),
+    ("-", hir::BinaryOperator::Subtract),
 ];
 
 crate const BINARY_OPERATORS_EXPR5: &[(&str, hir::BinaryOperator)] = &[
     ("==", hir::BinaryOperator::Equals),
     ("!=", hir::BinaryOperator::NotEquals),
+    ("<", hir::BinaryOperator::LessThan),
+    ("<=", hir::BinaryOperator::LessThanOrEqual),
+    (">", hir::BinaryOperator::GreaterThan),
+    (">=", hir::BinaryOperator::GreaterThanOrEqual),
 ];
 
+crate const BINARY_OPERATORS_EXPR6: &[(&str, hir::BinaryOperator)] =
+    &[("&&", hir::BinaryOperator::And)];
+
+crate const BINARY_OPERATORS_EXPR7: &[(&str, hir::BinaryOperator)] =
+    &[("||", hir::BinaryOperator::Or)];
+
 #[derive(new, DebugWith)]
 crate struct BinaryOperator {
     operators: &'static [(&'static str, hir::BinaryOperator)],
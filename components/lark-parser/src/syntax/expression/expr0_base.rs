@@ -2,11 +2,13 @@ use crate::parser::Parser;
 use crate::syntax::delimited::Delimited;
 use crate::syntax::expression::block::Block;
 use crate::syntax::expression::literal::Literal;
+use crate::syntax::expression::match_arm::MatchArm;
 use crate::syntax::expression::scope::ExpressionScope;
 use crate::syntax::expression::ParsedExpression;
 use crate::syntax::expression::{Expression, HirExpression};
 use crate::syntax::identifier::SpannedLocalIdentifier;
-use crate::syntax::sigil::Parentheses;
+use crate::syntax::list::CommaList;
+use crate::syntax::sigil::{Curlies, OpenParenthesis, Parentheses};
 use crate::syntax::skip_newline::SkipNewline;
 use crate::syntax::Syntax;
 use derive_new::new;
@@ -14,6 +16,7 @@ use lark_debug_derive::DebugWith;
 use lark_error::ErrorReported;
 use lark_hir as hir;
 use lark_intern::Intern;
+use lark_span::Spanned;
 
 #[derive(new, DebugWith)]
 crate struct Expression0<'me, 'parse> {
@@ -30,6 +33,10 @@ impl Syntax<'parse> for Expression0<'me, 'parse> {
     fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
         // Expression0 = Identifier
         // Expression0 = "if" Expression Block [ "else" Block ]
+        // Expression0 = "while" Expression Block
+        // Expression0 = "match" Expression "{" MatchArm "}" // arms separated by `,`
+        // Expression0 = "break"
+        // Expression0 = "continue"
         if parser.test(SpannedLocalIdentifier) {
             let text = parser.expect(SpannedLocalIdentifier)?;
 
@@ -55,6 +62,72 @@ impl Syntax<'parse> for Expression0<'me, 'parse> {
                 return Ok(ParsedExpression::Expression(expression));
             }
 
+            if text.value == "while" {
+                let condition = parser.expect(HirExpression::new(self.scope))?;
+
+                self.scope.loop_depth += 1;
+                let body = parser.expect(Block::new(self.scope));
+                self.scope.loop_depth -= 1;
+                let body = body?;
+
+                let expression = self
+                    .scope
+                    .add(text.span, hir::ExpressionData::While { condition, body });
+
+                return Ok(ParsedExpression::Expression(expression));
+            }
+
+            if text.value == "match" {
+                let value = parser.expect(HirExpression::new(self.scope))?;
+                let arms = parser.expect(SkipNewline(Delimited(
+                    Curlies,
+                    CommaList(MatchArm::new(self.scope)),
+                )))?;
+
+                if arms.is_empty() {
+                    let span = text.span.extended_until_end_of(parser.last_span());
+                    let error_expression = self.scope.report_error_expression(
+                        parser,
+                        span,
+                        hir::ErrorData::EmptyMatch,
+                    );
+                    return Ok(ParsedExpression::Expression(error_expression));
+                }
+
+                let arms = hir::List::from_iterator(
+                    &mut self.scope.fn_body_tables,
+                    arms.iter().cloned(),
+                );
+                let expression = self
+                    .scope
+                    .add(text.span, hir::ExpressionData::Match { value, arms });
+
+                return Ok(ParsedExpression::Expression(expression));
+            }
+
+            if text.value == "break" || text.value == "continue" {
+                if self.scope.loop_depth == 0 {
+                    let error_expression = self.scope.report_error_expression(
+                        parser,
+                        text.span,
+                        hir::ErrorData::OutsideOfLoop {
+                            text: text.value.intern(&self.scope.db),
+                        },
+                    );
+
+                    return Ok(ParsedExpression::Expression(error_expression));
+                }
+
+                let data = if text.value == "break" {
+                    hir::ExpressionData::Break {}
+                } else {
+                    hir::ExpressionData::Continue {}
+                };
+                let expression = self.scope.add(text.span, data);
+
+                return Ok(ParsedExpression::Expression(expression));
+            }
+
             if let Some(variable) = self.scope.lookup_variable(text.value) {
                 let place = self
                     .scope
@@ -68,13 +141,24 @@ impl Syntax<'parse> for Expression0<'me, 'parse> {
                 return Ok(ParsedExpression::Place(place));
             }
 
-            let error_expression = self.scope.report_error_expression(
-                parser,
-                text.span,
-                hir::ErrorData::UnknownIdentifier {
-                    text: text.value.intern(&self.scope.db),
-                },
-            );
+            let data = hir::ErrorData::UnknownIdentifier { text: id };
+
+            // If it is being called, `Expression1` reports it instead,
+            // once it has parsed the arguments.
+            if parser.test(OpenParenthesis) {
+                self.scope.unknown_callee = Some(Spanned {
+                    value: id,
+                    span: text.span,
+                });
+                let error_expression = self
+                    .scope
+                    .already_reported_error_expression(text.span, data);
+                return Ok(ParsedExpression::Expression(error_expression));
+            }
+
+            let error_expression = self
+                .scope
+                .report_error_expression(parser, text.span, data);
 
             return Ok(ParsedExpression::Expression(error_expression));
         }
@@ -4,11 +4,17 @@ use crate::syntax::expression::expr0_base::Expression0;
 use crate::syntax::expression::member_access::MemberAccess;
 use crate::syntax::expression::scope::ExpressionScope;
 use crate::syntax::expression::ParsedExpression;
+use crate::syntax::identifier::SpannedGlobalIdentifier;
+use crate::syntax::sigil::Dot;
+use crate::syntax::skip_newline::SkipNewline;
 use crate::syntax::Syntax;
 use derive_new::new;
 use lark_debug_derive::DebugWith;
+use lark_entity::{Entity, EntityData, ItemKind, MemberKind};
 use lark_error::ErrorReported;
 use lark_hir as hir;
+use lark_intern::Untern;
+use lark_span::Spanned;
 
 #[derive(new, DebugWith)]
 crate struct Expression1<'me, 'parse> {
@@ -25,11 +31,71 @@ impl Syntax<'parse> for Expression1<'me, 'parse> {
     fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
         let mut expr = parser.expect(Expression0::new(self.scope))?;
 
+        // Take this before parsing any arguments, which may have
+        // unknown callees of their own.
+        let unknown_callee = self.scope.unknown_callee.take();
+
+        // Enum.Variant(f: a, g: b) -- enum variant construction
+        // Enum.Variant -- the same, for a variant without fields
+        if let Some(enum_entity) = self.enum_entity(expr) {
+            if parser.test(SkipNewline(Dot)) {
+                parser.expect(SkipNewline(Dot))?;
+
+                // As for `foo.` (see `MemberAccess`), recover from a
+                // missing variant name.
+                if !parser.test(SpannedGlobalIdentifier) {
+                    parser.report_error("expected a variant name", parser.last_span());
+                    return Ok(expr);
+                }
+
+                let variant_name = parser.expect(SpannedGlobalIdentifier)?;
+
+                let fields = match parser.parse_if_present(IdentifiedCallArguments::new(self.scope))
+                {
+                    Some(fields) => fields?,
+                    None => hir::List::default(),
+                };
+
+                let span = self
+                    .scope
+                    .span(expr)
+                    .extended_until_end_of(parser.last_span());
+
+                let variant_id = variant_name.value;
+                match self
+                    .scope
+                    .db
+                    .member_entity(enum_entity, MemberKind::Variant, variant_id)
+                {
+                    Some(entity) => {
+                        let expression = self
+                            .scope
+                            .add(span, hir::ExpressionData::Aggregate { entity, fields });
+                        return Ok(ParsedExpression::Expression(expression));
+                    }
+
+                    None => {
+                        let error_expression = self.scope.report_error_expression(
+                            parser,
+                            variant_name.span,
+                            hir::ErrorData::UnknownVariant { text: variant_id },
+                        );
+                        return Ok(ParsedExpression::Expression(error_expression));
+                    }
+                }
+            }
+        }
+
         // foo(f: a, g: b) -- struct construction
         //
         // FIXME -- we probably want to support `foo.bar.baz(f: a, g:
         // b)`, too? Have to figure out the module system.
         if let Some(fields) = parser.parse_if_present(IdentifiedCallArguments::new(self.scope)) {
+            if let Some(callee) = unknown_callee {
+                let Spanned { value, span } = callee;
+                self.scope.report_unknown_function(parser, span, value, None);
+            }
+
             let fields = fields?;
 
             let place = expr.to_hir_place(self.scope);
@@ -66,6 +132,12 @@ impl Syntax<'parse> for Expression1<'me, 'parse> {
         //
         // NB. This must be tested *after* the "identified" form.
         if let Some(arguments) = parser.parse_if_present(CallArguments::new(None, self.scope)) {
+            if let Some(callee) = unknown_callee {
+                let Spanned { value, span } = callee;
+                let arguments = arguments.as_ref().ok().cloned();
+                self.scope.report_unknown_function(parser, span, value, arguments);
+            }
+
             let arguments = arguments?;
             let function = expr.to_hir_expression(self.scope);
             let span = self
@@ -92,3 +164,22 @@ impl Syntax<'parse> for Expression1<'me, 'parse> {
         Ok(expr)
     }
 }
+
+impl Expression1<'me, 'parse> {
+    /// If `expr` names an enum, returns that enum.
+    fn enum_entity(&self, expr: ParsedExpression) -> Option<Entity> {
+        if let ParsedExpression::Place(place) = expr {
+            if let hir::PlaceData::Entity(entity) = self.scope[place] {
+                if let EntityData::ItemName {
+                    kind: ItemKind::Enum,
+                    ..
+                } = entity.untern(&self.scope.db)
+                {
+                    return Some(entity);
+                }
+            }
+        }
+
+        None
+    }
+}
@@ -1,14 +1,16 @@
+use crate::lexer::token::LexToken;
 use crate::parser::Parser;
 use crate::syntax::expression::expr1_group::Expression1;
 use crate::syntax::expression::scope::ExpressionScope;
 use crate::syntax::expression::ParsedExpression;
-use crate::syntax::sigil::ExclamationPoint;
+use crate::syntax::sigil::{ExclamationPoint, Minus};
 use crate::syntax::skip_newline::SkipNewline;
 use crate::syntax::Syntax;
 use derive_new::new;
 use lark_debug_derive::DebugWith;
 use lark_error::ErrorReported;
 use lark_hir as hir;
+use lark_intern::Intern;
 use lark_span::{Spanned, FileName};
 
 #[derive(new, DebugWith)]
@@ -32,6 +34,21 @@ impl Syntax<'parse> for Expression2<'me, 'parse> {
     fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
         if let Some(operator) = parser.parse_if_present(UnaryOperator) {
             let operator = operator?;
+
+            // `-` directly applied to an integer is a negative literal
+            if operator.value == hir::UnaryOperator::Negate && parser.is(LexToken::Integer) {
+                let text = format!("-{}", parser.peek_str());
+                let token = parser.shift();
+                let span = operator.span.extended_until_end_of(token.span);
+                let data = hir::LiteralData {
+                    kind: hir::LiteralKind::SignedInteger,
+                    value: text.intern(parser),
+                };
+                return Ok(ParsedExpression::Expression(
+                    self.scope.add(span, hir::ExpressionData::Literal { data }),
+                ));
+            }
+
             let value = parser
                 .expect(SkipNewline(Expression2::new(self.scope)))?
                 .to_hir_expression(self.scope);
@@ -56,10 +73,14 @@ impl Syntax<'parse> for UnaryOperator {
     type Data = Spanned<hir::UnaryOperator, FileName>;
 
     fn test(&mut self, parser: &Parser<'parse>) -> bool {
-        parser.test(ExclamationPoint)
+        parser.test(ExclamationPoint) || parser.test(Minus)
     }
 
     fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
+        if let Some(spanned) = parser.parse_if_present(Minus) {
+            return Ok(spanned?.map(|_| hir::UnaryOperator::Negate));
+        }
+
         let spanned = parser.expect(ExclamationPoint)?;
         Ok(spanned.map(|_| hir::UnaryOperator::Not))
     }
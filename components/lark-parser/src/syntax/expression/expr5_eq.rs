@@ -15,6 +15,12 @@ crate struct Expression5<'me, 'parse> {
     scope: &'me mut ExpressionScope<'parse>,
 }
 
+impl AsMut<ExpressionScope<'parse>> for Expression5<'_, 'parse> {
+    fn as_mut(&mut self) -> &mut ExpressionScope<'parse> {
+        self.scope
+    }
+}
+
 impl Syntax<'parse> for Expression5<'me, 'parse> {
     type Data = ParsedExpression;
 
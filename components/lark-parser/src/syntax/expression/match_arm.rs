@@ -0,0 +1,159 @@
+use crate::parser::Parser;
+use crate::syntax::delimited::Delimited;
+use crate::syntax::expression::ident::HirIdentifier;
+use crate::syntax::expression::scope::ExpressionScope;
+use crate::syntax::expression::HirExpression;
+use crate::syntax::identifier::SpannedGlobalIdentifier;
+use crate::syntax::list::CommaList;
+use crate::syntax::sigil::{Colon, Dot, FatArrow, Parentheses};
+use crate::syntax::skip_newline::SkipNewline;
+use crate::syntax::Syntax;
+use derive_new::new;
+use lark_collections::Seq;
+use lark_debug_derive::DebugWith;
+use lark_entity::{Entity, EntityData, ItemKind, MemberKind};
+use lark_error::{ErrorReported, ErrorSentinel};
+use lark_hir as hir;
+use lark_intern::Untern;
+use lark_span::{FileName, Spanned};
+use lark_string::GlobalIdentifier;
+
+/// ```ignore
+/// `_` `=>` <expression>
+/// <enum> `.` <variant> [ `(` <binding> `)` ] `=>` <expression> // bindings separated by `,`
+/// ```
+#[derive(new, DebugWith)]
+crate struct MatchArm<'me, 'parse> {
+    scope: &'me mut ExpressionScope<'parse>,
+}
+
+impl Syntax<'parse> for MatchArm<'me, 'parse> {
+    type Data = hir::MatchArm;
+
+    fn test(&mut self, parser: &Parser<'parse>) -> bool {
+        parser.test(SpannedGlobalIdentifier)
+    }
+
+    fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
+        let name = parser.expect(SpannedGlobalIdentifier)?;
+
+        // The bindings are only in scope within the body of the arm
+        let variables_on_entry = self.scope.save_scope();
+
+        let (variant, bindings) = if name.value.untern(&self.scope.db) == "_" {
+            (None, hir::List::default())
+        } else {
+            parser.expect(SkipNewline(Dot))?;
+            let variant_name = parser.expect(SpannedGlobalIdentifier)?;
+            let variant = self.resolve_variant(parser, name.value, variant_name);
+
+            let bindings = match parser.parse_if_present(Delimited(
+                Parentheses,
+                CommaList(IdentifiedVariable::new(self.scope)),
+            )) {
+                Some(bindings) => bindings?,
+                None => Seq::default(),
+            };
+            let bindings = hir::List::from_iterator(
+                &mut self.scope.fn_body_tables,
+                bindings.iter().cloned(),
+            );
+
+            (Some(variant), bindings)
+        };
+
+        parser.expect(SkipNewline(FatArrow))?;
+        let body = parser.expect(SkipNewline(HirExpression::new(self.scope)));
+
+        self.scope.restore_scope(variables_on_entry);
+        let body = body?;
+
+        let span = name.span.extended_until_end_of(parser.last_span());
+        Ok(self.scope.add(
+            span,
+            hir::MatchArmData {
+                variant,
+                bindings,
+                body,
+            },
+        ))
+    }
+}
+
+impl MatchArm<'me, 'parse> {
+    /// Resolves `Enum.Variant`, reporting an error (and returning an
+    /// error entity) if there is no such variant.
+    fn resolve_variant(
+        &self,
+        parser: &mut Parser<'parse>,
+        enum_name: GlobalIdentifier,
+        variant_name: Spanned<GlobalIdentifier, FileName>,
+    ) -> Entity {
+        let db = self.scope.db;
+        let enum_entity = db.resolve_name(self.scope.item_entity, enum_name);
+
+        let variant = match enum_entity.map(|entity| (entity, entity.untern(&db))) {
+            Some((entity, EntityData::ItemName { kind: ItemKind::Enum, .. })) => {
+                db.member_entity(entity, MemberKind::Variant, variant_name.value)
+            }
+            Some((entity, EntityData::Error(_))) => Some(entity),
+            _ => None,
+        };
+
+        variant.unwrap_or_else(|| {
+            let report = parser.report_error(
+                format!("unknown variant `{}`", variant_name.value.untern(&db)),
+                variant_name.span,
+            );
+            Entity::error_sentinel(&db, report)
+        })
+    }
+}
+
+/// ```ignore
+/// <field> [ `:` <variable> ]
+/// ```
+#[derive(new, DebugWith)]
+struct IdentifiedVariable<'me, 'parse> {
+    scope: &'me mut ExpressionScope<'parse>,
+}
+
+impl Syntax<'parse> for IdentifiedVariable<'me, 'parse> {
+    type Data = hir::IdentifiedVariable;
+
+    fn test(&mut self, parser: &Parser<'parse>) -> bool {
+        parser.test(SpannedGlobalIdentifier)
+    }
+
+    fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
+        let identifier = parser.expect(HirIdentifier::new(self.scope))?;
+
+        // `field` is short for `field: field`
+        let name = match parser.parse_if_present(Colon) {
+            Some(colon) => {
+                colon?;
+                parser.expect(SkipNewline(HirIdentifier::new(self.scope)))?
+            }
+            None => identifier,
+        };
+
+        let name_span = self.scope.span(name);
+        let variable = self.scope.add(name_span, hir::VariableData { name });
+
+        // Subtle: as with `let`, this is visible to the parsers that
+        // come after us; `MatchArm` restores the scope after the body.
+        self.scope.introduce_variable(variable);
+
+        let span = self
+            .scope
+            .span(identifier)
+            .extended_until_end_of(name_span);
+        Ok(self.scope.add(
+            span,
+            hir::IdentifiedVariableData {
+                identifier,
+                variable,
+            },
+        ))
+    }
+}
@@ -36,6 +36,16 @@ impl Syntax<'parse> for MemberAccess<'me, 'parse> {
 
     fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
         parser.expect(SkipNewline(Dot))?;
+
+        // `foo.` with no member name is an error, but it is also what
+        // the text looks like while the name is being typed -- so keep
+        // `foo` and carry on, leaving the rest of the body (and the type
+        // of `foo`, for completions) intact.
+        if !parser.test(HirIdentifier::new(self.scope)) {
+            parser.report_error("expected a field or method name", parser.last_span());
+            return Ok(self.owner);
+        }
+
         let member_name = parser.expect(HirIdentifier::new(self.scope))?;
 
         if let Some(arguments) =
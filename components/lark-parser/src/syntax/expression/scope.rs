@@ -2,11 +2,13 @@ use crate::parser::Parser;
 use crate::ParserDatabase;
 use lark_collections::FxIndexMap;
 use lark_debug_with::DebugWith;
-use lark_entity::Entity;
+use lark_entity::{Entity, EntityData, LangItem};
+use lark_error::{Diagnostic, SuggestedEdit};
 use lark_hir as hir;
 use lark_intern::{Intern, Untern};
 use lark_span::FileName;
 use lark_span::Span;
+use lark_span::Spanned;
 use lark_string::{GlobalIdentifier, GlobalIdentifierTables};
 use std::rc::Rc;
 
@@ -20,6 +22,14 @@ crate struct ExpressionScope<'parse> {
     // should do so).
     crate variables: Rc<FxIndexMap<GlobalIdentifier, hir::Variable>>,
 
+    /// Number of loops enclosing the expression currently being
+    /// parsed; `break` and `continue` are only legal when non-zero.
+    crate loop_depth: usize,
+
+    /// An unknown identifier that is being called, which `Expression1`
+    /// reports once it has parsed the arguments (see `Expression0`).
+    crate unknown_callee: Option<Spanned<GlobalIdentifier, FileName>>,
+
     crate fn_body_tables: hir::FnBodyTables,
 }
 
@@ -66,6 +76,58 @@ impl ExpressionScope<'parse> {
         span: Span<FileName>,
         data: hir::ErrorData,
     ) -> hir::Expression {
+        let diagnostic = self.error_diagnostic(span, data.clone());
+        parser.report_diagnostic(diagnostic);
+
+        self.already_reported_error_expression(span, data)
+    }
+
+    /// Reports the unknown identifier `text`, which is being called
+    /// (see `Expression0`). If the `arguments` of the call could be
+    /// parsed, also suggests creating a function that takes them.
+    crate fn report_unknown_function(
+        &mut self,
+        parser: &mut Parser<'parser>,
+        span: Span<FileName>,
+        text: GlobalIdentifier,
+        arguments: Option<hir::List<hir::Expression>>,
+    ) {
+        let data = hir::ErrorData::UnknownIdentifier { text };
+        let mut diagnostic = self.error_diagnostic(span, data);
+        if let Some(arguments) = arguments {
+            let name = text.untern(&self.db);
+
+            // Parameters can't share a name, so number the repeats (like
+            // `foo(x, x)`)
+            let mut names: Vec<String> = vec![];
+            let mut parameters: Vec<String> = vec![];
+            for (index, argument) in arguments.iter(self).enumerate() {
+                let (name, ty) = self.parameter_for_argument(index, argument);
+                let mut unique_name = name.clone();
+                for suffix in 1.. {
+                    if !names.contains(&unique_name) {
+                        break;
+                    }
+                    unique_name = format!("{}{}", name, suffix);
+                }
+                parameters.push(format!("{}: {}", unique_name, ty));
+                names.push(unique_name);
+            }
+
+            // Put the new function after the item we are in
+            let item_end = self.db.entity_span(self.top_level_item()).end();
+            diagnostic = diagnostic.with_suggestion(
+                format!("Create function `{}`", name),
+                vec![SuggestedEdit::new(
+                    Span::new(span.file(), item_end, item_end),
+                    format!("\n\ndef {}({}) {{\n}}", name, parameters.join(", ")),
+                )],
+            );
+        }
+        parser.report_diagnostic(diagnostic);
+    }
+
+    fn error_diagnostic(&self, span: Span<FileName>, data: hir::ErrorData) -> Diagnostic {
         let message = match data {
             hir::ErrorData::Misc => "error".to_string(),
             hir::ErrorData::Unimplemented => "unimplemented".to_string(),
@@ -75,11 +137,130 @@ impl ExpressionScope<'parse> {
             hir::ErrorData::UnknownIdentifier { text } => {
                 format!("unknown identifier `{}`", text.untern(&self.db))
             }
+            hir::ErrorData::OutsideOfLoop { text } => {
+                format!("`{}` outside of a loop", text.untern(&self.db))
+            }
+            hir::ErrorData::UnknownVariant { text } => {
+                format!("unknown variant `{}`", text.untern(&self.db))
+            }
+            hir::ErrorData::EmptyMatch => "match must have at least one arm".to_string(),
         };
 
-        parser.report_error(message, span);
+        let code = match data {
+            hir::ErrorData::Misc | hir::ErrorData::Unimplemented => None,
+            hir::ErrorData::CanOnlyConstructStructs => Some("not-a-struct"),
+            hir::ErrorData::UnknownIdentifier { .. } => Some("unknown-identifier"),
+            hir::ErrorData::OutsideOfLoop { .. } => Some("outside-of-loop"),
+            hir::ErrorData::UnknownVariant { .. } => Some("unknown-variant"),
+            hir::ErrorData::EmptyMatch => Some("empty-match"),
+        };
 
-        self.already_reported_error_expression(span, data)
+        let mut diagnostic = crate::diagnostic(message, span);
+        diagnostic.code = code;
+        if let hir::ErrorData::UnknownIdentifier { text } = data {
+            diagnostic = self.suggest_replacing_unknown_identifier(diagnostic, text);
+        }
+        diagnostic
+    }
+
+    /// Suggests replacing an unknown identifier with the most similar
+    /// name in scope (if any is similar enough).
+    fn suggest_replacing_unknown_identifier(
+        &self,
+        diagnostic: Diagnostic,
+        text: GlobalIdentifier,
+    ) -> Diagnostic {
+        let span = diagnostic.span;
+        let name = text.untern(&self.db).to_string();
+
+        let closest = self
+            .names_in_scope()
+            .into_iter()
+            .map(|candidate| (edit_distance(&name, &candidate), candidate))
+            .filter(|(distance, _)| *distance <= std::cmp::max(1, name.len() / 3))
+            .min();
+        match closest {
+            Some((_, candidate)) => diagnostic.with_suggestion(
+                format!("Replace with `{}`", candidate),
+                vec![SuggestedEdit::new(span, candidate)],
+            ),
+            None => diagnostic,
+        }
+    }
+
+    /// The name and type of a parameter for a new function to take
+    /// `argument`: named after it, if it is a variable or a field, and
+    /// with its type, if it is a literal (and otherwise `uint`, for the
+    /// user to correct).
+    fn parameter_for_argument(
+        &self,
+        index: usize,
+        argument: hir::Expression,
+    ) -> (String, &'static str) {
+        let ty = match self[argument] {
+            hir::ExpressionData::Literal { data } => match data.kind {
+                hir::LiteralKind::UnsignedInteger => "uint",
+                hir::LiteralKind::SignedInteger => "int",
+                hir::LiteralKind::String => "String",
+            },
+
+            hir::ExpressionData::Place { place } => match self[place] {
+                hir::PlaceData::Variable(variable) => {
+                    let name = self[variable].name;
+                    return (self[name].text.untern(&self.db).to_string(), "uint");
+                }
+
+                hir::PlaceData::Field { name, .. } => {
+                    return (self[name].text.untern(&self.db).to_string(), "uint");
+                }
+
+                hir::PlaceData::Entity(entity) => match entity.untern(&self.db) {
+                    EntityData::LangItem(LangItem::True)
+                    | EntityData::LangItem(LangItem::False) => "bool",
+                    _ => "uint",
+                },
+
+                hir::PlaceData::Temporary(_) => "uint",
+            },
+
+            _ => "uint",
+        };
+
+        (format!("arg{}", index), ty)
+    }
+
+    /// The names that an identifier could refer to here: the variables
+    /// in scope, the items of the file, and the built-in names.
+    fn names_in_scope(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .variables
+            .keys()
+            .map(|name| name.untern(&self.db).to_string())
+            .collect();
+
+        if let Some(file) = self.item_entity.input_file(&self.db) {
+            let file_entity = EntityData::InputFile { file }.intern(&self.db);
+            for entity in self.db.child_entities(file_entity).iter() {
+                if let EntityData::ItemName { id, .. } = entity.untern(&self.db) {
+                    names.push(id.untern(&self.db).to_string());
+                }
+            }
+        }
+
+        names.extend(BUILT_IN_NAMES.iter().map(|name| name.to_string()));
+        names
+    }
+
+    /// The item, directly within the file, that we are in.
+    fn top_level_item(&self) -> Entity {
+        let mut entity = self.item_entity;
+        while let Some(base) = entity.untern(&self.db).parent() {
+            match base.untern(&self.db) {
+                EntityData::InputFile { .. } => break,
+                _ => entity = base,
+            }
+        }
+        entity
     }
 
     crate fn already_reported_error_expression(
@@ -128,3 +309,36 @@ impl DebugWith for ExpressionScope<'parse> {
             .finish()
     }
 }
+
+/// The names that are always in scope (see `scope::resolve_name`).
+const BUILT_IN_NAMES: &[&str] = &["bool", "int", "uint", "String", "true", "false", "debug"];
+
+/// The number of characters that must be inserted, deleted or replaced
+/// (or pairs of adjacent characters that must be swapped) to turn `a`
+/// into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // `distances[i][j]` is the distance from the first `i` characters
+    // of `a` to the first `j` characters of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            distances[i][j] = if i == 0 || j == 0 {
+                i + j
+            } else {
+                let replace = distances[i - 1][j - 1] + if a[i - 1] == b[j - 1] { 0 } else { 1 };
+                let mut distance = replace
+                    .min(distances[i - 1][j] + 1)
+                    .min(distances[i][j - 1] + 1);
+                if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                    distance = distance.min(distances[i - 2][j - 2] + 1);
+                }
+                distance
+            };
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
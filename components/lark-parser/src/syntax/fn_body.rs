@@ -40,14 +40,28 @@ use std::sync::Arc;
 // # Factored into "almost LL" form:
 //
 // Expression = {
+//   Expression7,
+//   Expression7 `=` Expression7,
+// }
+//
+// Expression7 = {
+//   Expression6,
+//   Expression7 \n* `||` Expression6,
+// }
+//
+// Expression6 = {
 //   Expression5,
-//   Expression5 `=` Expression5,
+//   Expression6 \n* `&&` Expression5,
 // }
 //
 // Expression5 = {
 //   Expression4,
 //   Expression4 \n* `==` Expression4,
 //   Expression4 \n* `!=` Expression4,
+//   Expression4 \n* `<` Expression4,
+//   Expression4 \n* `<=` Expression4,
+//   Expression4 \n* `>` Expression4,
+//   Expression4 \n* `>=` Expression4,
 // }
 //
 // Expression4 = {
@@ -60,11 +74,13 @@ use std::sync::Arc;
 //   Expression2,
 //   Expression3 \n* `*` Expression2,
 //   Expression3 \n* `/` Expression2,
+//   Expression3 \n* `%` Expression2,
 // }
 //
 // Expression2 = {
 //   Expression1,
 //   UnaryOp Expression0,
+//   `-` Integer,
 // }
 //
 // Expression1 = {
@@ -117,6 +133,8 @@ crate fn parse_fn_body(
         db,
         item_entity,
         variables: Default::default(),
+        loop_depth: 0,
+        unknown_callee: None,
         fn_body_tables: Default::default(),
     };
 
@@ -0,0 +1,74 @@
+use crate::parser::Parser;
+use crate::syntax::delimited::Delimited;
+use crate::syntax::identifier::SpannedGlobalIdentifier;
+use crate::syntax::list::CommaList;
+use crate::syntax::sigil::Angles;
+use crate::syntax::Syntax;
+use lark_collections::Seq;
+use lark_debug_derive::DebugWith;
+use lark_entity::Entity;
+use lark_error::Diagnostic;
+use lark_error::ErrorReported;
+use lark_error::WithError;
+use lark_span::FileName;
+use lark_span::Spanned;
+use lark_string::GlobalIdentifier;
+use lark_ty as ty;
+use std::sync::Arc;
+
+/// ```ignore
+/// `<` <id> `>` // with the ids separated by `,` or newline
+/// ```
+#[derive(DebugWith)]
+pub struct GenericParameters;
+
+impl Syntax<'parse> for GenericParameters {
+    type Data = Seq<Spanned<GlobalIdentifier, FileName>>;
+
+    fn test(&mut self, parser: &Parser<'parse>) -> bool {
+        parser.test(Delimited(Angles, CommaList(SpannedGlobalIdentifier)))
+    }
+
+    fn expect(&mut self, parser: &mut Parser<'parse>) -> Result<Self::Data, ErrorReported> {
+        parser.expect(Delimited(Angles, CommaList(SpannedGlobalIdentifier)))
+    }
+}
+
+/// Converts the generic parameters declared on `entity` (if any)
+/// into its `GenericDeclarations`, which extend those of
+/// `parent_item`.
+crate fn generic_declarations(
+    entity: Entity,
+    parent_item: Option<Entity>,
+    parameters: &[Spanned<GlobalIdentifier, FileName>],
+) -> WithError<Result<Arc<ty::GenericDeclarations>, ErrorReported>> {
+    let mut errors = vec![];
+
+    for (index, parameter) in parameters.iter().enumerate() {
+        if let Some(first) = parameters[..index].iter().find(|p| p.value == parameter.value) {
+            errors.push(
+                Diagnostic::new("duplicate generic parameter".into(), parameter.span)
+                    .with_code("duplicate-definition")
+                    .with_secondary_label("first declared here", first.span),
+            );
+        }
+    }
+
+    let declarations = parameters
+        .iter()
+        .map(|parameter| {
+            ty::GenericKind::Ty(ty::GenericTyDeclaration {
+                def_id: entity,
+                name: parameter.value,
+            })
+        })
+        .collect();
+
+    WithError {
+        value: Ok(Arc::new(ty::GenericDeclarations {
+            parent_item,
+            declarations,
+        })),
+        errors,
+    }
+}
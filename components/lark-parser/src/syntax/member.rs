@@ -26,8 +26,16 @@ use lark_span::Spanned;
 use lark_string::GlobalIdentifier;
 use lark_ty as ty;
 use lark_ty::declaration::Declaration;
+use lark_ty::declaration::DeclaredPermKind;
 use std::sync::Arc;
 
+/// ```ignore
+/// <id> `:` <ty>
+/// [ `def` [ `share` | `borrow` | `own` ] ] <id> <signature>
+/// ```
+///
+/// The permission given after `def` is the one with which the method
+/// accesses its (implicit) `self`; if omitted, it is `own`.
 #[derive(DebugWith)]
 pub struct Member;
 
@@ -39,7 +47,34 @@ impl Syntax<'parse> for Member {
     }
 
     fn expect(&mut self, parser: &mut Parser<'_>) -> Result<Self::Data, ErrorReported> {
-        let name = parser.expect(SpannedGlobalIdentifier)?;
+        let mut name = parser.expect(SpannedGlobalIdentifier)?;
+        let start = name.span;
+        let mut self_permission = DeclaredPermKind::Own;
+
+        // `def` (followed by an identifier) introduces a method, as
+        // opposed to a field or method that is named `def`
+        if &parser.input()[name.span] == "def" && parser.test(SpannedGlobalIdentifier) {
+            name = parser.expect(SpannedGlobalIdentifier)?;
+
+            if let Some(permission) = self_permission_kind(&parser.input()[name.span]) {
+                if parser.test(SpannedGlobalIdentifier) {
+                    self_permission = permission;
+                    name = parser.expect(SpannedGlobalIdentifier)?;
+                }
+            }
+
+            let signature = parser.expect(FunctionSignature)?;
+            let span = start.extended_until_end_of(parser.last_span());
+
+            return Ok(Spanned {
+                value: ParsedMember::ParsedMethod(ParsedMethod {
+                    name,
+                    self_permission,
+                    signature,
+                }),
+                span,
+            });
+        }
 
         if let Some(ty) =
             parser.parse_if_present(SkipNewline(Guard(Colon, SkipNewline(TypeReference))))
@@ -57,12 +92,25 @@ impl Syntax<'parse> for Member {
         let span = name.span.extended_until_end_of(parser.last_span());
 
         return Ok(Spanned {
-            value: ParsedMember::ParsedMethod(ParsedMethod { name, signature }),
+            value: ParsedMember::ParsedMethod(ParsedMethod {
+                name,
+                self_permission,
+                signature,
+            }),
             span,
         });
     }
 }
 
+fn self_permission_kind(text: &str) -> Option<DeclaredPermKind> {
+    match text {
+        "share" => Some(DeclaredPermKind::Share),
+        "borrow" => Some(DeclaredPermKind::Borrow),
+        "own" => Some(DeclaredPermKind::Own),
+        _ => None,
+    }
+}
+
 #[derive(DebugWith)]
 pub struct Field;
 
@@ -94,10 +142,11 @@ pub enum ParsedMember {
     ParsedField(ParsedField),
 }
 
-/// Represents a parse of something like `foo: Type`
+/// Represents a parse of something like `def share foo(x: Type) { .. }`
 #[derive(Clone, DebugWith)]
 pub struct ParsedMethod {
     pub name: Spanned<GlobalIdentifier, FileName>,
+    pub self_permission: DeclaredPermKind,
     pub signature: ParsedFunctionSignature,
 }
 
@@ -112,10 +161,12 @@ impl LazyParsedEntity for ParsedMethod {
 
     fn parse_generic_declarations(
         &self,
-        _entity: Entity,
-        _db: &dyn ParserDatabase,
+        entity: Entity,
+        db: &dyn ParserDatabase,
     ) -> WithError<Result<Arc<ty::GenericDeclarations>, ErrorReported>> {
-        WithError::ok(Ok(ty::GenericDeclarations::empty(None)))
+        // Members are in scope of the generics from their struct
+        let parent_entity = entity.untern(&db).parent();
+        WithError::ok(Ok(ty::GenericDeclarations::empty(parent_entity)))
     }
 
     fn parse_type(
@@ -127,13 +178,14 @@ impl LazyParsedEntity for ParsedMethod {
         // Rust.
         match db.generic_declarations(entity).into_value() {
             Ok(generic_declarations) => {
-                assert!(generic_declarations.is_empty());
+                let generics =
+                    crate::type_conversion::bound_var_generics(db, &generic_declarations);
                 let ty = crate::type_conversion::declaration_ty_named(
                     &db,
                     entity,
                     ty::declaration::DeclaredPermKind::Own,
                     ty::ReprKind::Direct,
-                    ty::Generics::empty(),
+                    generics,
                 );
                 WithError::ok(ty)
             }
@@ -148,7 +200,11 @@ impl LazyParsedEntity for ParsedMethod {
     ) -> WithError<Result<ty::Signature<Declaration>, ErrorReported>> {
         let parent_entity = entity.untern(&db).parent().unwrap();
         let parent_ty = db.ty(parent_entity).into_value();
-        self.signature.parse_signature(entity, db, Some(parent_ty))
+        let self_ty = ty::Ty {
+            perm: self.self_permission.intern(&db),
+            ..parent_ty
+        };
+        self.signature.parse_signature(entity, db, Some(self_ty))
     }
 
     fn parse_fn_body(&self, entity: Entity, db: &dyn ParserDatabase) -> WithError<hir::FnBody> {
@@ -160,10 +216,18 @@ impl LazyParsedEntity for ParsedMethod {
         self.signature
             .parse_fn_body(entity, db, Some(spanned_self_argument))
     }
+
+    fn parse_imported_module(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        InvalidParsedEntity.parse_imported_module(entity, db)
+    }
 }
 
 /// Represents a parse of something like `foo: Type`
-#[derive(Copy, Clone, DebugWith)]
+#[derive(Clone, DebugWith)]
 pub struct ParsedField {
     pub name: Spanned<GlobalIdentifier, FileName>,
     pub ty: ParsedTypeReference,
@@ -180,10 +244,12 @@ impl LazyParsedEntity for ParsedField {
 
     fn parse_generic_declarations(
         &self,
-        _entity: Entity,
-        _db: &dyn ParserDatabase,
+        entity: Entity,
+        db: &dyn ParserDatabase,
     ) -> WithError<Result<Arc<ty::GenericDeclarations>, ErrorReported>> {
-        WithError::ok(Ok(ty::GenericDeclarations::empty(None)))
+        // Members are in scope of the generics from their struct
+        let parent_entity = entity.untern(&db).parent();
+        WithError::ok(Ok(ty::GenericDeclarations::empty(parent_entity)))
     }
 
     fn parse_type(
@@ -205,4 +271,12 @@ impl LazyParsedEntity for ParsedField {
     fn parse_fn_body(&self, entity: Entity, db: &dyn ParserDatabase) -> WithError<hir::FnBody> {
         InvalidParsedEntity.parse_fn_body(entity, db)
     }
+
+    fn parse_imported_module(
+        &self,
+        entity: Entity,
+        db: &dyn ParserDatabase,
+    ) -> Result<Spanned<GlobalIdentifier, FileName>, ErrorReported> {
+        InvalidParsedEntity.parse_imported_module(entity, db)
+    }
 }
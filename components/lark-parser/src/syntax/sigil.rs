@@ -48,10 +48,13 @@ sigil_type! {
     pub struct CloseParenthesis = (LexToken::Sigil, ")");
     pub struct OpenSquare = (LexToken::Sigil, "[");
     pub struct CloseSquare = (LexToken::Sigil, "]");
+    pub struct OpenAngle = (LexToken::Sigil, "<");
+    pub struct CloseAngle = (LexToken::Sigil, ">");
     pub struct Colon = (LexToken::Sigil, ":");
     pub struct Semicolon = (LexToken::Sigil, ";");
     pub struct Comma = (LexToken::Sigil, ",");
     pub struct RightArrow = (LexToken::Sigil, "->");
+    pub struct FatArrow = (LexToken::Sigil, "=>");
     pub struct Dot = (LexToken::Sigil, ".");
     pub struct Let = (LexToken::Identifier, "let");
     pub struct ExclamationPoint = (LexToken::Sigil, "!");
@@ -93,3 +96,19 @@ impl Delimiter<'parse> for Parentheses {
         CloseParenthesis
     }
 }
+
+#[derive(DebugWith)]
+pub struct Angles;
+
+impl Delimiter<'parse> for Angles {
+    type Open = OpenAngle;
+    type Close = CloseAngle;
+
+    fn open_syntax(&self) -> Self::Open {
+        OpenAngle
+    }
+
+    fn close_syntax(&self) -> Self::Close {
+        CloseAngle
+    }
+}
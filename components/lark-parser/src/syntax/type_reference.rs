@@ -1,7 +1,11 @@
 use crate::parser::Parser;
+use crate::syntax::delimited::Delimited;
 use crate::syntax::identifier::SpannedGlobalIdentifier;
+use crate::syntax::list::CommaList;
+use crate::syntax::sigil::Angles;
 use crate::syntax::Syntax;
 use crate::ParserDatabase;
+use lark_collections::Seq;
 use lark_debug_derive::DebugWith;
 use lark_entity::Entity;
 use lark_error::{ErrorReported, ErrorSentinel, WithError};
@@ -27,14 +31,19 @@ impl Syntax<'parse> for TypeReference {
         parser: &mut Parser<'parse>,
     ) -> Result<ParsedTypeReference, ErrorReported> {
         let identifier = parser.expect(SpannedGlobalIdentifier)?;
+        let generics = match parser.parse_if_present(Delimited(Angles, CommaList(TypeReference))) {
+            Some(generics) => generics?,
+            None => Seq::default(),
+        };
         Ok(ParsedTypeReference::Named(NamedTypeReference {
             identifier,
+            generics,
         }))
     }
 }
 
 /// Parsed form of a type.
-#[derive(Copy, Clone, DebugWith)]
+#[derive(Clone, DebugWith)]
 pub enum ParsedTypeReference {
     Named(NamedTypeReference),
     Elided(Span<FileName>),
@@ -63,10 +72,11 @@ impl<Cx> ErrorSentinel<Cx> for ParsedTypeReference {
     }
 }
 
-/// Named type like `String` or (eventually) `Vec<u32>`
-#[derive(Copy, Clone, DebugWith)]
+/// Named type like `String` or `Vec<u32>`
+#[derive(Clone, DebugWith)]
 pub struct NamedTypeReference {
     pub identifier: Spanned<GlobalIdentifier, FileName>,
+    pub generics: Seq<ParsedTypeReference>,
 }
 
 impl NamedTypeReference {
@@ -75,8 +85,51 @@ impl NamedTypeReference {
         entity: Entity,
         db: &dyn ParserDatabase,
     ) -> WithError<ty::Ty<Declaration>> {
+        // A generic parameter like `T` shadows any item of the same name.
+        if let Some(bv) =
+            crate::type_conversion::resolve_generic_parameter(db, entity, self.identifier.value)
+        {
+            if !self.generics.is_empty() {
+                let msg = format!(
+                    "generic parameter `{}` does not take type arguments",
+                    self.identifier.untern(&db)
+                );
+                return WithError::report_error(&db, msg, self.identifier.span);
+            }
+
+            return WithError::ok(crate::type_conversion::bound_var_ty(&db, bv));
+        }
+
         match db.resolve_name(entity, self.identifier.value) {
-            Some(entity) => {
+            Some(named_entity) => {
+                let mut errors = vec![];
+
+                let expected_len = match db.generic_declarations(named_entity).into_value() {
+                    Ok(declarations) => {
+                        crate::type_conversion::generic_count(db, &declarations)
+                    }
+                    Err(err) => return WithError::error_sentinel(&db, err),
+                };
+                if expected_len != self.generics.len() {
+                    let msg = format!(
+                        "wrong number of type arguments: expected {}, found {}",
+                        expected_len,
+                        self.generics.len()
+                    );
+                    return WithError::report_error(&db, msg, self.identifier.span);
+                }
+
+                let generics = self
+                    .generics
+                    .iter()
+                    .map(|generic| {
+                        let ty = generic
+                            .parse_type(entity, db)
+                            .accumulate_errors_into(&mut errors);
+                        ty::GenericKind::Ty(ty)
+                    })
+                    .collect();
+
                 // FIXME(ndm) -- eventually, we will want some way to
                 // represent types with other permissions/reprs. We'll
                 // need fields on `NamedTypeReference`, and we'll need
@@ -85,12 +138,12 @@ impl NamedTypeReference {
                 // (the former might be more convenient).
                 let ty = crate::type_conversion::declaration_ty_named(
                     &db,
-                    entity,
+                    named_entity,
                     ty::declaration::DeclaredPermKind::Own,
                     ty::ReprKind::Direct,
-                    ty::Generics::empty(),
+                    generics,
                 );
-                WithError::ok(ty)
+                WithError { value: ty, errors }
             }
             None => {
                 let msg = format!("unknown type: `{}`", self.identifier.untern(&db));
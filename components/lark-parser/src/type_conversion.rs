@@ -3,6 +3,7 @@ use lark_debug_with::DebugWith;
 use lark_entity::{Entity, EntityData, LangItem};
 use lark_error::{ErrorReported, ErrorSentinel, WithError};
 use lark_intern::{Intern, Untern};
+use lark_string::GlobalIdentifier;
 use lark_ty as ty;
 use lark_ty::declaration::Declaration;
 use lark_ty::declaration::DeclarationTables;
@@ -69,12 +70,7 @@ crate fn ty(db: &impl ParserDatabase, entity: Entity) -> WithError<ty::Ty<Declar
         EntityData::LangItem(LangItem::Tuple(arity)) => {
             let generics: ty::Generics<Declaration> = (0..arity)
                 .map(|i| ty::BoundVar::new(i))
-                .map(|bv| ty::Ty {
-                    base: Declaration::intern_bound_var(db, bv),
-                    repr: ty::ReprKind::Direct,
-                    perm: Declaration::own_perm(db),
-                })
-                .map(|ty| ty::GenericKind::Ty(ty))
+                .map(|bv| ty::GenericKind::Ty(bound_var_ty(db, bv)))
                 .collect();
             WithError::ok(declaration_ty_named(
                 db,
@@ -132,6 +128,63 @@ crate fn unit_ty(db: &dyn ParserDatabase) -> ty::Ty<Declaration> {
     )
 }
 
+crate fn bound_var_ty(db: &dyn AsRef<DeclarationTables>, bv: ty::BoundVar) -> ty::Ty<Declaration> {
+    ty::Ty {
+        base: Declaration::intern_bound_var(db, bv),
+        repr: ty::ReprKind::Direct,
+        perm: Declaration::own_perm(db),
+    }
+}
+
+/// Counts the generic parameters in scope for an item with the given
+/// declarations, including those inherited from its parents.
+crate fn generic_count(db: &dyn ParserDatabase, declarations: &ty::GenericDeclarations) -> usize {
+    let parent_count = match declarations.parent_item {
+        Some(parent_item) => match db.generic_declarations(parent_item).into_value() {
+            Ok(parent_declarations) => generic_count(db, &parent_declarations),
+            Err(ErrorReported(_)) => 0,
+        },
+        None => 0,
+    };
+    parent_count + declarations.declarations.len()
+}
+
+/// The generics with which an item refers to itself from within its
+/// own declaration: each generic parameter in scope, as a bound
+/// variable. So for `struct Foo<T>`, the type of `Foo` is `Foo<T>`.
+crate fn bound_var_generics(
+    db: &dyn ParserDatabase,
+    declarations: &ty::GenericDeclarations,
+) -> ty::Generics<Declaration> {
+    (0..generic_count(db, declarations))
+        .map(|i| ty::GenericKind::Ty(bound_var_ty(&db, ty::BoundVar::new(i))))
+        .collect()
+}
+
+/// Searches the generic parameters in scope within `entity` for one
+/// named `name`, starting with the innermost declarations.
+crate fn resolve_generic_parameter(
+    db: &dyn ParserDatabase,
+    entity: Entity,
+    name: GlobalIdentifier,
+) -> Option<ty::BoundVar> {
+    let declarations = db.generic_declarations(entity).into_value().ok()?;
+    let parent_count = generic_count(db, &declarations) - declarations.declarations.len();
+
+    let own_index = declarations
+        .declarations
+        .iter()
+        .position(|ty::GenericKind::Ty(declaration)| declaration.name == name);
+
+    match own_index {
+        Some(index) => Some(ty::BoundVar::new(parent_count + index)),
+        None => {
+            let parent_item = declarations.parent_item?;
+            resolve_generic_parameter(db, parent_item, name)
+        }
+    }
+}
+
 crate fn declaration_ty_named(
     db: &dyn AsRef<DeclarationTables>,
     entity: Entity,
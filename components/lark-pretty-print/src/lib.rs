@@ -1,9 +1,10 @@
 use lark_entity::{Entity, EntityData, ItemKind, LangItem, MemberKind};
 use lark_intern::Untern;
 use lark_parser::ParserDatabase;
+use lark_string::GlobalIdentifier;
 use lark_ty::declaration::{Declaration, DeclaredPermKind};
 use lark_ty::full_inferred::{FullInferred, FullInferredTables};
-use lark_ty::{BaseData, BaseKind, BoundVarOr, PermKind, Ty, TypeFamily};
+use lark_ty::{BaseData, BaseKind, BoundVarOr, GenericKind, PermKind, Ty, TypeFamily};
 
 pub trait PrettyPrintDatabase: ParserDatabase + AsRef<FullInferredTables> {}
 
@@ -15,9 +16,7 @@ impl PrettyPrint for Ty<Declaration> {
     fn pretty_print(&self, db: &(impl PrettyPrintDatabase + ?Sized)) -> String {
         format!(
             "{}{}",
-            match self.perm.untern(&db) {
-                DeclaredPermKind::Own => "",
-            },
+            declared_perm_prefix(self.perm.untern(&db)),
             match self.base.untern(&db) {
                 BoundVarOr::BoundVar(var) => format!("{:?}", var),
                 BoundVarOr::Known(base_data) => base_data.pretty_print(db),
@@ -26,20 +25,87 @@ impl PrettyPrint for Ty<Declaration> {
     }
 }
 
+/// Pretty-prints a type from the signature (or other declaration) of
+/// `item`, giving its generic parameters by name.
+pub fn pretty_print_declared(
+    ty: &Ty<Declaration>,
+    item: Entity,
+    db: &(impl PrettyPrintDatabase + ?Sized),
+) -> String {
+    match ty.base.untern(&db) {
+        BoundVarOr::BoundVar(var) => match generic_names(db, item).get(var.as_usize()) {
+            Some(name) => format!(
+                "{}{}",
+                declared_perm_prefix(ty.perm.untern(&db)),
+                name.untern(&db)
+            ),
+            None => ty.pretty_print(db),
+        },
+        BoundVarOr::Known(_) => ty.pretty_print(db),
+    }
+}
+
+fn declared_perm_prefix(perm: DeclaredPermKind) -> &'static str {
+    match perm {
+        DeclaredPermKind::Own => "",
+        DeclaredPermKind::Share => "share ",
+        DeclaredPermKind::Borrow => "borrow ",
+    }
+}
+
+/// The names of the generic parameters in scope in `item`, starting
+/// with those of its parents, in the order of their bound variables.
+fn generic_names(db: &(impl PrettyPrintDatabase + ?Sized), item: Entity) -> Vec<GlobalIdentifier> {
+    match db.generic_declarations(item).into_value() {
+        Ok(declarations) => {
+            let mut names = match declarations.parent_item {
+                Some(parent_item) => generic_names(db, parent_item),
+                None => vec![],
+            };
+            names.extend(
+                declarations
+                    .declarations
+                    .iter()
+                    .map(|GenericKind::Ty(declaration)| declaration.name),
+            );
+            names
+        }
+        Err(_) => vec![],
+    }
+}
+
 impl PrettyPrint for Ty<FullInferred> {
     fn pretty_print(&self, db: &(impl PrettyPrintDatabase + ?Sized)) -> String {
         format!(
             "{}{}",
             match self.perm {
                 PermKind::Own => "",
-                PermKind::Share => "shared ",
-                PermKind::Borrow => "borrowed ",
+                PermKind::Share => "share ",
+                PermKind::Borrow => "borrow ",
             },
             self.base.untern(&db).pretty_print(db),
         )
     }
 }
 
+/// Pretty-prints an inferred type with its permission written out as
+/// in a declaration -- `own`, `share` or `borrow` -- even where it is
+/// `own`.
+pub fn pretty_print_with_perm(
+    ty: Ty<FullInferred>,
+    db: &(impl PrettyPrintDatabase + ?Sized),
+) -> String {
+    format!(
+        "{} {}",
+        match ty.perm {
+            PermKind::Own => "own",
+            PermKind::Share => "share",
+            PermKind::Borrow => "borrow",
+        },
+        ty.base.untern(&db).pretty_print(db),
+    )
+}
+
 impl<T: TypeFamily> PrettyPrint for BaseData<T> {
     fn pretty_print(&self, db: &(impl PrettyPrintDatabase + ?Sized)) -> String {
         self.kind.pretty_print(db)
@@ -117,6 +183,13 @@ impl PrettyPrint for Entity {
 
                 format!("{}{}", id.untern(&db).to_string(), output_sig)
             }
+            EntityData::ItemName {
+                kind: ItemKind::Import,
+                ..
+            } => match db.import_target(*self).into_value() {
+                Ok(target) => target.pretty_print(db),
+                Err(_) => "<error>".into(),
+            },
             x => format!("{:?}", x),
         }
     }
@@ -1,19 +1,29 @@
 use language_reporting as l_r;
-use lark_actor::{Actor, LspResponse, QueryRequest};
+use languageserver_types::{
+    code_action_kind, CodeAction, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity,
+    DocumentSymbol, Location, NumberOrString, ParameterInformation, ParameterLabel, Range,
+    SignatureHelp, SignatureInformation, SymbolInformation, TextEdit, WorkspaceEdit,
+};
+use lark_actor::{Actor, LspResponse, QueryRequest, RequestError, TaskId};
 use lark_entity::EntityTables;
+use lark_error::Severity;
 use lark_intern::{Intern, Untern};
 use lark_parser::{ParserDatabase, ParserDatabaseExt};
 use lark_pretty_print::PrettyPrintDatabase;
-use lark_span::{ByteIndex, FileName, Span};
+use lark_span::{ByteIndex, FileName, IntoFileName, OutOfBounds, PositionEncoding, Span};
 use lark_string::{GlobalIdentifier, GlobalIdentifierTables, Text};
 use salsa::{Database, ParallelDatabase, Snapshot};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use url::Url;
 
 pub mod ls_ops;
-use self::ls_ops::{Cancelled, LsDatabase};
+mod test;
+use self::ls_ops::{
+    CallSignature, Cancelled, LsDatabase, QuickFix, RangedDiagnostic, ReferencesError, Symbol,
+};
 
 #[salsa::database(lark_parser::ParserStorage, lark_type_check::TypeCheckStorage)]
 pub struct LarkDatabase {
@@ -134,7 +144,7 @@ impl l_r::ReportingFiles for &LarkDatabase {
     }
 
     fn byte_index(&self, file: Self::FileId, line: usize, column: usize) -> Option<usize> {
-        let b_i = ParserDatabase::byte_index(*self, file, line as u64, column as u64);
+        let b_i = ParserDatabase::byte_index(*self, file, line as u64, column as u64).ok()?;
         Some(b_i.to_usize())
     }
 
@@ -156,15 +166,31 @@ impl l_r::ReportingFiles for &LarkDatabase {
     }
 
     fn source(&self, span: Self::Span) -> Option<String> {
+        // The text after a label that runs onto later lines (like one
+        // for "the rest of the file") starts after the end of the
+        // label's first line, so there is none.
+        if span.start() > span.end() {
+            return Some(String::new());
+        }
+
         let file = span.file();
         Some(self.file_text(file)[span].to_string())
     }
 }
 
+/// How long to wait after a change before checking for errors.
+const ERROR_CHECK_DELAY: Duration = Duration::from_millis(200);
+
 pub struct QuerySystem {
     send_channel: Sender<LspResponse>,
     lark_db: LarkDatabase,
-    needs_error_check: bool,
+
+    /// When to next check for errors (if there have been mutations
+    /// since we last did).
+    error_check_due: Option<Instant>,
+
+    /// The version of each open file, as the IDE numbers them.
+    versions: HashMap<String, u64>,
 }
 
 impl QuerySystem {
@@ -172,7 +198,8 @@ impl QuerySystem {
         QuerySystem {
             send_channel,
             lark_db: LarkDatabase::default(),
-            needs_error_check: false,
+            error_check_due: None,
+            versions: HashMap::new(),
         }
     }
 }
@@ -192,8 +219,9 @@ impl Actor for QuerySystem {
                 }
             }
 
-            // After each mutation, we need to perform an error-check at some point.
-            self.needs_error_check = true;
+            // After each mutation, we need to perform an error-check at some point. We wait
+            // a little first, so that a burst of edits (as when typing) only gets checked once.
+            self.error_check_due = Some(Instant::now() + ERROR_CHECK_DELAY);
         }
 
         // OK, all mutations are processed. Now we can process the next non-mutation (if any).
@@ -201,21 +229,37 @@ impl Actor for QuerySystem {
             assert!(!message.is_mutation());
             self.process_message(message);
         }
+    }
 
-        // If there are no more pending messages, we can go ahead and
-        // start checking for errors.  Otherwise, return, and we'll be
-        // called again.
-        if messages.is_empty() && self.needs_error_check {
-            self.check_for_errors_and_report();
+    fn idle_timeout(&self) -> Option<Duration> {
+        self.error_check_due.map(|due| {
+            let now = Instant::now();
+            if due > now {
+                due - now
+            } else {
+                Duration::from_secs(0)
+            }
+        })
+    }
+
+    /// Once there have been no more messages for a while after a
+    /// mutation, we go ahead and start checking for errors.
+    fn idle(&mut self) {
+        match self.error_check_due {
+            Some(due) if due <= Instant::now() => self.check_for_errors_and_report(),
+            _ => {}
         }
     }
 }
 
 impl QuerySystem {
+    /// Publishes the diagnostics for every file -- including files
+    /// with none, so that the IDE clears any that it showed before.
     pub fn check_for_errors_and_report(&mut self) {
-        self.needs_error_check = false;
+        self.error_check_due = None;
         std::thread::spawn({
             let db = self.lark_db.snapshot();
+            let versions = self.versions.clone();
             let send_channel = self.send_channel.clone();
             move || {
                 match db.errors_for_project() {
@@ -224,11 +268,14 @@ impl QuerySystem {
                         for (key, value) in errors {
                             let send_channel = send_channel.clone();
                             let url = Url::parse(&key).unwrap();
-                            let ranges_with_default =
-                                value.iter().map(|x| (x.range, x.label.clone())).collect();
+                            let version = versions.get(&key).cloned();
+                            let diagnostics = value
+                                .iter()
+                                .map(|diagnostic| lsp_diagnostic(&db, diagnostic))
+                                .collect();
                             send(
                                 send_channel,
-                                LspResponse::Diagnostics(url, ranges_with_default),
+                                LspResponse::Diagnostics(url, version, diagnostics),
                             );
                         }
                     }
@@ -245,62 +292,60 @@ impl QuerySystem {
         log::info!("process_message(message={:#?})", message);
 
         match message {
-            QueryRequest::Initialize(task_id) => {
+            QueryRequest::Initialize(task_id, encoding) => {
+                self.lark_db.set_position_encoding(encoding);
+
                 let send_channel = self.send_channel.clone();
-                send(send_channel, LspResponse::Initialized(task_id));
+                send(send_channel, LspResponse::Initialized(task_id, encoding));
             }
 
-            QueryRequest::OpenFile(url, contents) => {
+            QueryRequest::OpenFile(url, version, contents) => {
                 let text = contents.intern(&self.lark_db).untern(&self.lark_db);
+                self.versions.insert(url.to_string(), version);
 
                 // Process sets on the same thread -- this not only gives them priority,
                 // it ensures an overall ordering to edits.
                 self.lark_db.add_file(url.as_str(), text);
             }
 
-            QueryRequest::EditFile(url, changes) => {
+            QueryRequest::EditFile(url, version, changes) => {
                 // Process sets on the same thread -- this not only gives them priority,
                 // it ensures an overall ordering to edits.
-                let path_id = self.lark_db.intern_string(url.as_str());
-                let file_name = FileName { id: path_id };
+                let file_name = url.as_str().into_file_name(&self.lark_db);
+                if !self.lark_db.file_names().contains(&file_name) {
+                    log::error!("edit to a file that is not open: {}", url);
+                    return;
+                }
 
                 let text = self.lark_db.file_text(file_name);
-                let mut current_contents = text.to_string();
-
-                for change in changes {
-                    let start_position = change.0.start;
-                    let start_offset = self.lark_db.byte_index(
-                        file_name,
-                        start_position.line,
-                        start_position.character,
-                    );
-
-                    let end_position = change.0.end;
-                    let end_offset = self.lark_db.byte_index(
-                        file_name,
-                        end_position.line,
-                        end_position.character,
-                    );
-
-                    unsafe {
-                        let vec = current_contents.as_mut_vec();
-                        vec.drain(start_offset.to_usize()..end_offset.to_usize());
+                let encoding = self.lark_db.position_encoding();
+                match apply_changes(&text, changes, encoding) {
+                    Ok(text) => {
+                        self.lark_db
+                            .query_mut(lark_parser::FileTextQuery)
+                            .set(file_name, Text::from(text));
+
+                        match version {
+                            Some(version) => self.versions.insert(url.to_string(), version),
+                            None => self.versions.remove(url.as_str()),
+                        };
+                    }
+                    Err(OutOfBounds) => {
+                        log::error!("edit outside of the text of {}; ignoring it", url);
                     }
-
-                    current_contents.insert_str(start_offset.to_usize(), &change.1);
                 }
-
-                let text = Text::from(current_contents);
-                self.lark_db
-                    .query_mut(lark_parser::FileTextQuery)
-                    .set(file_name, text);
+            }
+            QueryRequest::CloseFile(url) => {
+                // The file stays part of the project, so its errors are
+                // still reported, just without a version.
+                self.versions.remove(url.as_str());
             }
             QueryRequest::RenameAtPosition(task_id, url, position, new_name) => {
                 std::thread::spawn({
                     let db = self.lark_db.snapshot();
                     let send_channel = self.send_channel.clone();
                     move || {
-                        let _killme = KillTheProcess;
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
 
                         match db.rename_all_references_at_position(
                             url.as_str(),
@@ -314,9 +359,7 @@ impl QuerySystem {
                                     .collect();
                                 send(send_channel, LspResponse::WorkspaceEdits(task_id, result));
                             }
-                            _ => {
-                                send(send_channel, LspResponse::Nothing(task_id));
-                            }
+                            Err(error) => send_references_error(send_channel, task_id, error),
                         }
                     }
                 });
@@ -326,7 +369,7 @@ impl QuerySystem {
                     let db = self.lark_db.snapshot();
                     let send_channel = self.send_channel.clone();
                     move || {
-                        let _killme = KillTheProcess;
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
 
                         match db.find_all_references_at_position(url.as_str(), position) {
                             Ok(v) => {
@@ -336,9 +379,7 @@ impl QuerySystem {
                                     .collect();
                                 send(send_channel, LspResponse::Ranges(task_id, result));
                             }
-                            _ => {
-                                send(send_channel, LspResponse::Nothing(task_id));
-                            }
+                            Err(error) => send_references_error(send_channel, task_id, error),
                         }
                     }
                 });
@@ -348,7 +389,7 @@ impl QuerySystem {
                     let db = self.lark_db.snapshot();
                     let send_channel = self.send_channel.clone();
                     move || {
-                        let _killme = KillTheProcess;
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
 
                         match db.definition_range_at_position(url.as_str(), position, true) {
                             Ok(Some(v)) => {
@@ -364,12 +405,176 @@ impl QuerySystem {
                     }
                 });
             }
+            QueryRequest::CompletionAtPosition(task_id, url, position) => {
+                std::thread::spawn({
+                    let db = self.lark_db.snapshot();
+                    let send_channel = self.send_channel.clone();
+                    move || {
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
+
+                        match db.completions_at_position(url.as_str(), position) {
+                            Ok(completions) => {
+                                send(send_channel, LspResponse::Completions(task_id, completions));
+                            }
+                            Err(Cancelled) => {
+                                send(send_channel, LspResponse::Nothing(task_id));
+                            }
+                        }
+                    }
+                });
+            }
+            QueryRequest::SignatureHelpAtPosition(task_id, url, position) => {
+                std::thread::spawn({
+                    let db = self.lark_db.snapshot();
+                    let send_channel = self.send_channel.clone();
+                    move || {
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
+
+                        match db.signature_help_at_position(url.as_str(), position) {
+                            Ok(Some(call_signature)) => {
+                                let result = signature_help(call_signature);
+                                send(send_channel, LspResponse::SignatureHelp(task_id, result));
+                            }
+                            _ => {
+                                send(send_channel, LspResponse::Nothing(task_id));
+                            }
+                        }
+                    }
+                });
+            }
+            QueryRequest::SemanticTokens(task_id, url, previous_result_id) => {
+                std::thread::spawn({
+                    let db = self.lark_db.snapshot();
+                    let send_channel = self.send_channel.clone();
+                    move || {
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
+
+                        match db.semantic_tokens(url.as_str()) {
+                            Ok(tokens) => {
+                                send(
+                                    send_channel,
+                                    LspResponse::SemanticTokens(
+                                        task_id,
+                                        url,
+                                        previous_result_id,
+                                        tokens,
+                                    ),
+                                );
+                            }
+                            Err(Cancelled) => {
+                                send(send_channel, LspResponse::Nothing(task_id));
+                            }
+                        }
+                    }
+                });
+            }
+            QueryRequest::CodeActions(task_id, url, range) => {
+                std::thread::spawn({
+                    let db = self.lark_db.snapshot();
+                    let send_channel = self.send_channel.clone();
+                    move || {
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
+
+                        match db.quick_fixes_in_range(url.as_str(), range) {
+                            Ok(quick_fixes) => {
+                                let result = quick_fixes.into_iter().map(code_action).collect();
+                                send(send_channel, LspResponse::CodeActions(task_id, result));
+                            }
+                            Err(Cancelled) => {
+                                send(send_channel, LspResponse::Nothing(task_id));
+                            }
+                        }
+                    }
+                });
+            }
+            QueryRequest::Formatting(task_id, url, range) => {
+                std::thread::spawn({
+                    let db = self.lark_db.snapshot();
+                    let send_channel = self.send_channel.clone();
+                    move || {
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
+
+                        let edits = db.formatting_edits(url.as_str(), range);
+                        send(send_channel, LspResponse::TextEdits(task_id, edits));
+                    }
+                });
+            }
+            QueryRequest::DocumentSymbols(task_id, url) => {
+                std::thread::spawn({
+                    let db = self.lark_db.snapshot();
+                    let send_channel = self.send_channel.clone();
+                    move || {
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
+
+                        match db.document_symbols(url.as_str()) {
+                            Ok(symbols) => {
+                                let result = symbols.into_iter().map(document_symbol).collect();
+                                send(send_channel, LspResponse::DocumentSymbols(task_id, result));
+                            }
+                            Err(Cancelled) => {
+                                send(send_channel, LspResponse::Nothing(task_id));
+                            }
+                        }
+                    }
+                });
+            }
+            QueryRequest::InlayHints(task_id, url, range) => {
+                std::thread::spawn({
+                    let db = self.lark_db.snapshot();
+                    let send_channel = self.send_channel.clone();
+                    move || {
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
+
+                        match db.inlay_hints(url.as_str(), range) {
+                            Ok(hints) => {
+                                send(send_channel, LspResponse::InlayHints(task_id, hints));
+                            }
+                            Err(Cancelled) => {
+                                send(send_channel, LspResponse::Nothing(task_id));
+                            }
+                        }
+                    }
+                });
+            }
+            QueryRequest::WorkspaceSymbols(task_id, query) => {
+                std::thread::spawn({
+                    let db = self.lark_db.snapshot();
+                    let send_channel = self.send_channel.clone();
+                    move || {
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
+
+                        match db.workspace_symbols(&query) {
+                            Ok(symbols) => {
+                                let result = symbols
+                                    .into_iter()
+                                    .filter_map(|symbol| {
+                                        Some(SymbolInformation {
+                                            location: Location {
+                                                uri: Url::parse(&symbol.file).ok()?,
+                                                range: symbol.range,
+                                            },
+                                            name: symbol.name,
+                                            kind: symbol.kind,
+                                            deprecated: None,
+                                            container_name: symbol.container_name,
+                                        })
+                                    })
+                                    .collect();
+                                send(send_channel, LspResponse::WorkspaceSymbols(task_id, result));
+                            }
+                            Err(Cancelled) => {
+                                send(send_channel, LspResponse::Nothing(task_id));
+                            }
+                        }
+                    }
+                });
+            }
             QueryRequest::TypeAtPosition(task_id, url, position) => {
                 std::thread::spawn({
                     let db = self.lark_db.snapshot();
                     let send_channel = self.send_channel.clone();
                     move || {
-                        let _killme = KillTheProcess;
+                        let _guard = RespondOnPanic::new(task_id, &send_channel);
 
                         match db.hover_text_at_position(url.as_str(), position) {
                             Ok(Some(v)) => {
@@ -396,6 +601,36 @@ impl QuerySystem {
     }
 }
 
+/// A little struct which -- when dropped while panicking -- answers
+/// the task with an error, so that one failing query gives the IDE an
+/// error response instead of taking down the whole server.
+struct RespondOnPanic {
+    task_id: TaskId,
+    send_channel: Sender<LspResponse>,
+}
+
+impl RespondOnPanic {
+    fn new(task_id: TaskId, send_channel: &Sender<LspResponse>) -> Self {
+        RespondOnPanic {
+            task_id,
+            send_channel: send_channel.clone(),
+        }
+    }
+}
+
+impl Drop for RespondOnPanic {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            log::error!("panicked while handling task {}", self.task_id);
+            let message = String::from("internal error while handling the request");
+            send(
+                self.send_channel.clone(),
+                LspResponse::Error(self.task_id, RequestError::InternalError(message)),
+            );
+        }
+    }
+}
+
 fn send(channel: Sender<LspResponse>, message: LspResponse) {
     match channel.send(message) {
         Ok(..) => {}
@@ -405,9 +640,150 @@ fn send(channel: Sender<LspResponse>, message: LspResponse) {
     }
 }
 
+/// Answers a request for references (or a rename) that failed.
+fn send_references_error(channel: Sender<LspResponse>, task_id: TaskId, error: ReferencesError) {
+    match error {
+        ReferencesError::Cancelled => send(channel, LspResponse::Nothing(task_id)),
+        ReferencesError::UnknownField(name) => {
+            let message = format!("no field named `{}` to find references to", name);
+            send(
+                channel,
+                LspResponse::Error(task_id, RequestError::RequestFailed(message)),
+            );
+        }
+    }
+}
+
+/// Makes the LSP `changes` to `text` in order, with the columns of
+/// their ranges counted according to `encoding`. If any range is not
+/// within the text (as it is by then), none of the changes are made.
+fn apply_changes(
+    text: &str,
+    changes: Vec<(Option<Range>, String)>,
+    encoding: PositionEncoding,
+) -> Result<String, OutOfBounds> {
+    let mut text = text.to_string();
+    for (range, new_text) in changes {
+        match range {
+            Some(range) => {
+                let line_offsets = lark_span::line_offsets(&text);
+                let start = encoding.byte_index(&text, &line_offsets, range.start)?;
+                let end = encoding.byte_index(&text, &line_offsets, range.end)?;
+                if start > end {
+                    return Err(OutOfBounds);
+                }
+
+                text.replace_range(start.to_usize()..end.to_usize(), &new_text);
+            }
+            None => text = new_text,
+        }
+    }
+
+    Ok(text)
+}
+
+/// Converts `diagnostic` to the form that LSP uses, with its secondary
+/// labels as related information.
+fn lsp_diagnostic(db: &LarkDatabase, diagnostic: &RangedDiagnostic) -> Diagnostic {
+    let related_information: Vec<_> = diagnostic
+        .secondary_labels
+        .iter()
+        .map(|label| DiagnosticRelatedInformation {
+            location: Location {
+                uri: Url::parse(&label.span.file().id.untern(db)).unwrap(),
+                range: label.range,
+            },
+            message: label.label.clone(),
+        })
+        .collect();
+
+    Diagnostic {
+        range: diagnostic.range,
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::Error,
+            Severity::Warning => DiagnosticSeverity::Warning,
+            Severity::Note => DiagnosticSeverity::Information,
+        }),
+        code: diagnostic
+            .code
+            .map(|code| NumberOrString::String(code.to_string())),
+        source: Some("lark".to_string()),
+        message: diagnostic.label.clone(),
+        related_information: if related_information.is_empty() {
+            None
+        } else {
+            Some(related_information)
+        },
+    }
+}
+
+fn signature_help(call_signature: CallSignature) -> SignatureHelp {
+    let parameters = call_signature
+        .parameters
+        .into_iter()
+        .map(|parameter| ParameterInformation {
+            label: ParameterLabel::Simple(parameter),
+            documentation: None,
+        })
+        .collect();
+
+    SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label: call_signature.label,
+            documentation: None,
+            parameters: Some(parameters),
+        }],
+        active_signature: Some(0),
+        active_parameter: Some(call_signature.active_parameter as u64),
+    }
+}
+
+fn code_action(quick_fix: QuickFix) -> CodeAction {
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    for (file, range, new_text) in quick_fix.edits {
+        if let Ok(url) = Url::parse(&file) {
+            changes
+                .entry(url)
+                .or_insert_with(Vec::new)
+                .push(TextEdit { range, new_text });
+        }
+    }
+
+    CodeAction {
+        title: quick_fix.title,
+        kind: Some(code_action_kind::QUICKFIX.to_string()),
+        diagnostics: Some(vec![languageserver_types::Diagnostic::new_simple(
+            quick_fix.diagnostic_range,
+            quick_fix.diagnostic_label,
+        )]),
+        edit: Some(WorkspaceEdit::new(changes)),
+        command: None,
+    }
+}
+
+fn document_symbol(symbol: Symbol) -> DocumentSymbol {
+    DocumentSymbol {
+        name: symbol.name,
+        detail: Some(symbol.detail),
+        kind: symbol.kind,
+        deprecated: None,
+        range: symbol.range,
+        selection_range: symbol.selection_range,
+        children: if symbol.children.is_empty() {
+            None
+        } else {
+            Some(symbol.children.into_iter().map(document_symbol).collect())
+        },
+    }
+}
+
 /// A little struct which -- when dropped -- will abort the process if
-/// we have panicked. This is a temporary band-aid to have us die on
-/// panic and prevent tests from hanging.
+/// we have panicked. We use this on the actor's own thread, which
+/// cannot carry on once it has panicked part-way through a change.
+///
+/// This doesn't undo `RespondOnPanic`: the actor's thread only makes
+/// changes and spawns threads, and it is on those threads (which
+/// `KillTheProcess` never sees) that queries run and may panic.
 struct KillTheProcess;
 
 impl Drop for KillTheProcess {
@@ -4,32 +4,106 @@
 //! (e.g. `&uri`) that wouldn't be possible otherwise, which is
 //! convenient.
 
-use languageserver_types::{Position, Range};
-use lark_entity::{Entity, EntityData, ItemKind, MemberKind};
-use lark_error::Diagnostic;
+use languageserver_types::{Position, Range, SymbolKind};
+use lark_actor::{InlayHint, SemanticToken, SemanticTokenKind};
+use lark_entity::{Entity, EntityData, ItemKind, LangItem, MemberKind};
+use lark_error::{Diagnostic, Severity};
 use lark_intern::{Intern, Untern};
-use lark_parser::HoverTargetKind;
+use lark_parser::{HoverTargetKind, LexToken, ParserDatabaseExt};
 use lark_pretty_print::PrettyPrint;
 use lark_span::{ByteIndex, FileName, IntoFileName, Span};
+use lark_ty::{BaseKind, GenericKind};
 use std::collections::HashMap;
 
+/// A `Diagnostic`, along with the line/column range of its span.
 #[derive(Debug)]
 pub struct RangedDiagnostic {
     pub label: String,
     pub range: Range,
+    pub span: Span<FileName>,
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub secondary_labels: Vec<RangedLabel>,
 }
 
-impl RangedDiagnostic {
-    pub fn new(label: String, range: Range) -> RangedDiagnostic {
-        RangedDiagnostic { label, range }
-    }
+#[derive(Debug)]
+pub struct RangedLabel {
+    pub label: String,
+    pub range: Range,
+    pub span: Span<FileName>,
+}
+
+/// A struct, function or the like (or a member of one), as shown in
+/// outlines and found by symbol searches.
+#[derive(Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub detail: String,
+    pub kind: SymbolKind,
+    pub file: String,
+
+    /// The range of the whole definition.
+    pub range: Range,
+
+    /// The range of just the name.
+    pub selection_range: Range,
+
+    /// For a member, the name of the item it belongs to.
+    pub container_name: Option<String>,
+
+    /// The members (of a struct or enum) or fields (of a variant).
+    pub children: Vec<Symbol>,
+}
+
+/// The signature of the function or method being called at some
+/// position, as shown by signature help.
+#[derive(Debug)]
+pub struct CallSignature {
+    /// The whole signature, such as `add(x: uint, y: uint) -> uint`.
+    pub label: String,
+
+    /// The parameters (such as `x: uint`), which appear in `label`.
+    pub parameters: Vec<String>,
+
+    /// The index of the argument the position is in.
+    pub active_parameter: usize,
+}
+
+/// A fix suggested by an error, which the IDE can offer as a code
+/// action.
+#[derive(Debug)]
+pub struct QuickFix {
+    pub title: String,
+    pub diagnostic_label: String,
+    pub diagnostic_range: Range,
+
+    /// The edits that make up the fix: the file, the range to replace
+    /// and what to replace it with.
+    pub edits: Vec<(String, Range, String)>,
 }
 
 pub struct Cancelled;
 
 pub type Cancelable<T> = Result<T, Cancelled>;
 
-pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
+/// Why the references at a position could not be found.
+pub enum ReferencesError {
+    Cancelled,
+
+    /// The position is on a field access whose field the type checker
+    /// could not find (like `x.baz` where `x` has no field `baz`).
+    UnknownField(String),
+}
+
+impl From<Cancelled> for ReferencesError {
+    fn from(_: Cancelled) -> Self {
+        ReferencesError::Cancelled
+    }
+}
+
+pub trait LsDatabase:
+    lark_type_check::TypeCheckDatabase + ParserDatabaseExt + salsa::Database
+{
     fn check_for_cancellation(&self) -> Cancelable<()> {
         if self.salsa_runtime().is_current_revision_canceled() {
             Err(Cancelled)
@@ -43,34 +117,127 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
         let mut file_errors = HashMap::new();
 
         for &input_file in &*input_files {
-            self.check_for_cancellation()?;
+            let errors = self.errors_for_file(input_file)?;
 
-            // Check file for syntax errors
-            let mut errors = vec![];
-            let _ = self
-                .parsed_file(input_file)
-                .accumulate_errors_into(&mut errors);
+            let error_ranges = errors.iter().map(|x| self.ranged_diagnostic(x)).collect();
 
-            // Next, check entities in file for type-safety
-            let file_entity = EntityData::InputFile { file: input_file }.intern(self);
-            for &entity in self.descendant_entities(file_entity).iter() {
-                self.accumulate_errors_for_entity(entity, &mut errors)?;
-            }
+            file_errors.insert(input_file.id.untern(self).to_string(), error_ranges);
+        }
 
-            let error_ranges = errors
+        Ok(file_errors)
+    }
+
+    fn ranged_diagnostic(&self, diagnostic: &Diagnostic) -> RangedDiagnostic {
+        RangedDiagnostic {
+            label: diagnostic.label.clone(),
+            range: self.range(diagnostic.span),
+            span: diagnostic.span,
+            severity: diagnostic.severity,
+            code: diagnostic.code,
+            secondary_labels: diagnostic
+                .secondary_labels
                 .iter()
-                .map(|x| RangedDiagnostic::new(x.label.clone(), self.range(x.span)))
-                .collect();
+                .map(|l| RangedLabel {
+                    label: l.label.clone(),
+                    range: self.range(l.span),
+                    span: l.span,
+                })
+                .collect(),
+        }
+    }
 
-            file_errors.insert(input_file.id.untern(self).to_string(), error_ranges);
+    fn errors_for_file(&self, input_file: FileName) -> Cancelable<Vec<Diagnostic>> {
+        self.check_for_cancellation()?;
+
+        // Check file for syntax errors
+        let mut errors = vec![];
+        let _ = self
+            .parsed_file(input_file)
+            .accumulate_errors_into(&mut errors);
+        self.module_errors(input_file)
+            .accumulate_errors_into(&mut errors);
+
+        // Next, check entities in file for type-safety
+        let file_entity = EntityData::InputFile { file: input_file }.intern(self);
+        for &entity in self.descendant_entities(file_entity).iter() {
+            self.accumulate_errors_for_entity(entity, &mut errors)?;
         }
 
-        Ok(file_errors)
+        Ok(errors)
+    }
+
+    /// Returns the fixes suggested by the errors in a file whose spans
+    /// overlap the given range.
+    fn quick_fixes_in_range(&self, url: &str, range: Range) -> Cancelable<Vec<QuickFix>> {
+        let file = url.into_file_name(self);
+        if !self.file_names().contains(&file) {
+            return Ok(vec![]);
+        }
+
+        let (start, end) = match (
+            self.position_to_byte_index(url, range.start),
+            self.position_to_byte_index(url, range.end),
+        ) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Ok(vec![]),
+        };
+        let errors = self.errors_for_file(file)?;
+
+        Ok(errors
+            .iter()
+            .filter(|error| error.span.start() <= end && start <= error.span.end())
+            .flat_map(|error| {
+                error.suggestions.iter().map(move |suggestion| QuickFix {
+                    title: suggestion.label.clone(),
+                    diagnostic_label: error.label.clone(),
+                    diagnostic_range: self.range(error.span),
+                    edits: suggestion
+                        .edits
+                        .iter()
+                        .map(|edit| {
+                            (
+                                edit.span.file().id.untern(self).to_string(),
+                                self.range(edit.span),
+                                edit.replacement.clone(),
+                            )
+                        })
+                        .collect(),
+                })
+            })
+            .collect())
+    }
+
+    /// The edits that format a file (see `lark_fmt`), or just those
+    /// that touch `range` if one is given. A file with syntax errors is
+    /// left as it is. (The layout is always the same, whatever the IDE's
+    /// settings for tabs.)
+    fn formatting_edits(&self, url: &str, range: Option<Range>) -> Vec<(Range, String)> {
+        let file = url.into_file_name(self);
+        if !self.file_names().contains(&file) {
+            return vec![];
+        }
+
+        let mut edits = lark_fmt::format_edits(self, file).unwrap_or_default();
+        if let Some(range) = range {
+            let (start, end) = match (
+                self.position_to_byte_index(url, range.start),
+                self.position_to_byte_index(url, range.end),
+            ) {
+                (Some(start), Some(end)) => (start, end),
+                _ => return vec![],
+            };
+            edits.retain(|edit| edit.span.start() <= end && start <= edit.span.end());
+        }
+
+        edits
+            .into_iter()
+            .map(|edit| (self.range(edit.span), edit.replacement))
+            .collect()
     }
 
     fn range(&self, span: Span<FileName>) -> languageserver_types::Range {
-        let left = self.location(span.file(), span.start()).as_position();
-        let right = self.location(span.file(), span.end()).as_position();
+        let left = self.position(span.file(), span.start());
+        let right = self.position(span.file(), span.end());
         languageserver_types::Range::new(left, right)
     }
 
@@ -88,6 +255,10 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
             EntityData::ItemName {
                 kind: ItemKind::Struct,
                 ..
+            }
+            | EntityData::ItemName {
+                kind: ItemKind::Enum,
+                ..
             } => {
                 let _ = self
                     .generic_declarations(entity)
@@ -97,6 +268,10 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
             EntityData::MemberName {
                 kind: MemberKind::Field,
                 ..
+            }
+            | EntityData::MemberName {
+                kind: MemberKind::Variant,
+                ..
             } => {
                 let _ = self
                     .generic_declarations(entity)
@@ -127,6 +302,12 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
                 let _ = self.fn_body(entity).accumulate_errors_into(errors);
                 let _ = self.full_type_check(entity).accumulate_errors_into(errors);
             }
+            EntityData::ItemName {
+                kind: ItemKind::Import,
+                ..
+            } => {
+                let _ = self.import_target(entity).accumulate_errors_into(errors);
+            }
         }
 
         Ok(())
@@ -199,8 +380,10 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
                             lark_hir::PlaceData::Field {
                                 name: value_name, ..
                             } => {
-                                if possible_match_types.entities[&(*value_name).into()]
-                                    == field_entity
+                                // Fields that the type checker could not find
+                                // refer to nothing
+                                if possible_match_types.entities.get(&(*value_name).into())
+                                    == Some(&field_entity)
                                 {
                                     let span = fn_body.span(*value_name);
                                     let range = self.range(span);
@@ -238,7 +421,7 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
         url: &str,
         position: Position,
         new_name: &str,
-    ) -> Cancelable<Vec<(String, Range, String)>> {
+    ) -> Result<Vec<(String, Range, String)>, ReferencesError> {
         self.check_for_cancellation()?;
 
         let references = self.find_all_references_at_position(url, position)?;
@@ -253,13 +436,16 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
         &self,
         url: &str,
         position: Position,
-    ) -> Cancelable<Vec<(String, Range)>> {
+    ) -> Result<Vec<(String, Range)>, ReferencesError> {
         // First, let's add the definition site, as this is one of the references
         let definition_position = self.definition_range_at_position(url, position, true)?;
 
         // Then, we gather the uses
         let url_file_name = url.into_file_name(self);
-        let byte_index = self.position_to_byte_index(url, position);
+        let byte_index = match self.position_to_byte_index(url, position) {
+            Some(byte_index) => byte_index,
+            None => return Ok(vec![]),
+        };
         let targets = self.hover_targets(url_file_name, byte_index);
         self.check_for_cancellation()?;
 
@@ -271,13 +457,13 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
                     EntityData::MemberName {
                         kind: MemberKind::Field,
                         ..
-                    } => Some(self.find_all_references_to_field(hovered_entity)),
-                    _ => Some(self.find_all_references_to_definition(hovered_entity)),
+                    } => Some(Ok(self.find_all_references_to_field(hovered_entity))),
+                    _ => Some(Ok(self.find_all_references_to_definition(hovered_entity))),
                 },
                 HoverTargetKind::MetaIndex(entity, mi) => match mi {
                     lark_hir::MetaIndex::Variable(variable) => {
                         let fn_body = self.fn_body(entity).into_value();
-                        Some(self.find_all_references_to_variable(&fn_body, variable))
+                        Some(Ok(self.find_all_references_to_variable(&fn_body, variable)))
                     }
                     lark_hir::MetaIndex::Place(place_idx) => {
                         let fn_body = self.fn_body(entity).into_value();
@@ -285,16 +471,23 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
 
                         match p {
                             lark_hir::PlaceData::Entity(entity) => {
-                                Some(self.find_all_references_to_definition(entity))
+                                Some(Ok(self.find_all_references_to_definition(entity)))
                             }
                             lark_hir::PlaceData::Variable(variable) => {
-                                Some(self.find_all_references_to_variable(&fn_body, variable))
+                                Some(Ok(self.find_all_references_to_variable(&fn_body, variable)))
                             }
                             lark_hir::PlaceData::Field { name, .. } => {
                                 let source_types = &self.full_type_check(entity).into_value();
-                                let hovered_entity = source_types.entities[&name.into()];
 
-                                Some(self.find_all_references_to_field(hovered_entity))
+                                match source_types.entities.get(&name.into()) {
+                                    Some(&hovered_entity) => {
+                                        Some(Ok(self.find_all_references_to_field(hovered_entity)))
+                                    }
+                                    None => {
+                                        let text = fn_body.tables[name].text.untern(self);
+                                        Some(Err(ReferencesError::UnknownField(text.to_string())))
+                                    }
+                                }
                             }
                             _ => None,
                         }
@@ -304,21 +497,16 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
             })
             .next();
 
-        if results.is_some() {
-            let mut results = results.unwrap();
-
-            if definition_position.is_some() {
-                results.push(definition_position.unwrap());
-            }
+        let mut results = match results {
+            Some(results) => results?,
+            None => vec![],
+        };
 
-            Ok(results)
-        } else {
-            if definition_position.is_some() {
-                Ok(vec![definition_position.unwrap()])
-            } else {
-                Ok(vec![])
-            }
+        if let Some(definition_position) = definition_position {
+            results.push(definition_position);
         }
+
+        Ok(results)
     }
 
     fn get_entity_span_if_possible(
@@ -345,7 +533,10 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
         minimal_span: bool,
     ) -> Cancelable<Option<(String, Range)>> {
         let url_file_name = url.into_file_name(self);
-        let byte_index = self.position_to_byte_index(url, position);
+        let byte_index = match self.position_to_byte_index(url, position) {
+            Some(byte_index) => byte_index,
+            None => return Ok(None),
+        };
         let targets = self.hover_targets(url_file_name, byte_index);
         self.check_for_cancellation()?;
 
@@ -444,7 +635,10 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
     /// any).
     fn hover_text_at_position(&self, url: &str, position: Position) -> Cancelable<Option<String>> {
         let url_file_name = url.into_file_name(self);
-        let byte_index = self.position_to_byte_index(url, position);
+        let byte_index = match self.position_to_byte_index(url, position) {
+            Some(byte_index) => byte_index,
+            None => return Ok(None),
+        };
         let targets = self.hover_targets(url_file_name, byte_index);
         self.check_for_cancellation()?;
 
@@ -496,8 +690,825 @@ pub trait LsDatabase: lark_type_check::TypeCheckDatabase + salsa::Database {
             .next())
     }
 
-    fn position_to_byte_index(&self, url: &str, position: Position) -> ByteIndex {
-        let url_id = url.intern(self);
-        self.byte_index(FileName { id: url_id }, position.line, position.character)
+    /// Returns the completions (each a label and a detail, such as a
+    /// type or signature) to offer at a given position. After a `.`,
+    /// these are the members of the thing before it; otherwise, they
+    /// are the variables in scope plus the items of the file.
+    fn completions_at_position(
+        &self,
+        url: &str,
+        position: Position,
+    ) -> Cancelable<Vec<(String, String)>> {
+        let file = url.into_file_name(self);
+        let index = match self.position_to_byte_index(url, position) {
+            Some(byte_index) => byte_index.to_usize(),
+            None => return Ok(vec![]),
+        };
+        let text = self.file_text(file);
+        self.check_for_cancellation()?;
+
+        let before_cursor = match text.get(..index) {
+            Some(before_cursor) => before_cursor,
+            None => return Ok(vec![]),
+        };
+
+        // Skip back over whatever part of the name has been typed so far
+        let name_start = before_cursor.trim_end_matches(is_identifier_char).len();
+        let fn_entity = self.enclosing_fn_entity(file, index);
+
+        if before_cursor[..name_start].ends_with('.') {
+            return Ok(match fn_entity {
+                Some(fn_entity) => self.member_completions(fn_entity, name_start - 1),
+                None => vec![],
+            });
+        }
+
+        let mut completions = match fn_entity {
+            Some(fn_entity) => self.variable_completions(fn_entity, &text, index),
+            None => vec![],
+        };
+
+        let file_entity = EntityData::InputFile { file }.intern(self);
+        for &entity in self.child_entities(file_entity).iter() {
+            if let EntityData::ItemName { id, .. } = entity.untern(self) {
+                completions.push((id.untern(self).to_string(), self.entity_detail(entity)));
+            }
+        }
+
+        Ok(completions)
+    }
+
+    /// The innermost function or method whose body contains `index`.
+    fn enclosing_fn_entity(&self, file: FileName, index: usize) -> Option<Entity> {
+        let file_entity = EntityData::InputFile { file }.intern(self);
+        self.descendant_entities(file_entity)
+            .iter()
+            .cloned()
+            .filter(|entity| entity.untern(self).has_fn_body())
+            .map(|entity| (entity, self.entity_span(entity)))
+            .filter(|(_, span)| span.start().to_usize() <= index && index <= span.end().to_usize())
+            .min_by_key(|(_, span)| span.len())
+            .map(|(entity, _)| entity)
+    }
+
+    /// The members that can follow the `.` at `dot_index` in the body
+    /// of `fn_entity`: the fields and methods of a struct value, or
+    /// the variants of an enum.
+    fn member_completions(&self, fn_entity: Entity, dot_index: usize) -> Vec<(String, String)> {
+        let fn_body = self.fn_body(fn_entity).into_value();
+        let types = self.full_type_check(fn_entity).into_value();
+
+        // The owner is the outermost expression that ends at the dot
+        // (so `b` in `a + b.`, but `a.b` in `a.b.`).
+        let owner = fn_body
+            .tables
+            .spans
+            .iter()
+            .filter(|(index, span)| match index {
+                lark_hir::MetaIndex::Expression(_)
+                | lark_hir::MetaIndex::Place(_)
+                | lark_hir::MetaIndex::Variable(_) => span.end().to_usize() == dot_index,
+                _ => false,
+            })
+            .min_by_key(|(_, span)| span.start())
+            .map(|(&index, _)| index);
+
+        let (owner_entity, kinds): (_, &[MemberKind]) = match owner {
+            Some(lark_hir::MetaIndex::Place(place)) if self.is_enum_place(&fn_body, place) => {
+                match fn_body[place] {
+                    lark_hir::PlaceData::Entity(entity) => (entity, &[MemberKind::Variant]),
+                    _ => unreachable!(),
+                }
+            }
+            Some(owner) => match types.opt_ty(owner) {
+                Some(ty) => match ty.base.untern(self).kind {
+                    BaseKind::Named(entity) => match entity.untern(self) {
+                        EntityData::ItemName {
+                            kind: ItemKind::Struct,
+                            ..
+                        } => (entity, &[MemberKind::Field, MemberKind::Method]),
+                        _ => return vec![],
+                    },
+                    _ => return vec![],
+                },
+                None => return vec![],
+            },
+            None => return vec![],
+        };
+
+        match self.members(owner_entity) {
+            Ok(members) => members
+                .iter()
+                .filter(|member| kinds.contains(&member.kind))
+                .map(|member| {
+                    (
+                        member.name.untern(self).to_string(),
+                        self.entity_detail(member.entity),
+                    )
+                })
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    fn is_enum_place(&self, fn_body: &lark_hir::FnBody, place: lark_hir::Place) -> bool {
+        match fn_body[place] {
+            lark_hir::PlaceData::Entity(entity) => match entity.untern(self) {
+                EntityData::ItemName {
+                    kind: ItemKind::Enum,
+                    ..
+                } => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// The variables of `fn_entity` that are in scope at `index`, with
+    /// their types. Where one variable shadows another, only the inner
+    /// one is given.
+    fn variable_completions(
+        &self,
+        fn_entity: Entity,
+        text: &str,
+        index: usize,
+    ) -> Vec<(String, String)> {
+        let fn_body = self.fn_body(fn_entity).into_value();
+        let types = self.full_type_check(fn_entity).into_value();
+
+        let mut variables = vec![];
+        if let Ok(arguments) = &fn_body.arguments {
+            variables.extend(arguments.iter(&fn_body));
+        }
+
+        // A `let` is in scope from its end to the end of its block
+        for (expression, data) in fn_body.tables.expressions.iter_enumerated() {
+            if let lark_hir::ExpressionData::Let { variable, .. } = data {
+                let end = fn_body.span(expression).end().to_usize();
+                if end <= index && !leaves_block(&text[end..index]) {
+                    variables.push(*variable);
+                }
+            }
+        }
+
+        // The bindings of a match arm are in scope in its body
+        for arm in fn_body.tables.match_arms.iter() {
+            let span = fn_body.span(arm.body);
+            if span.start().to_usize() <= index && index <= span.end().to_usize() {
+                variables.extend(
+                    arm.bindings
+                        .iter_data(&fn_body)
+                        .map(|binding| binding.variable),
+                );
+            }
+        }
+
+        variables.sort_by_key(|&variable| fn_body.span(variable).start());
+
+        let mut completions: Vec<(String, String)> = vec![];
+        for variable in variables {
+            let name = fn_body[fn_body[variable].name]
+                .text
+                .untern(self)
+                .to_string();
+            let detail = match types.opt_ty(variable) {
+                Some(ty) => ty.pretty_print(self),
+                None => String::new(),
+            };
+            completions.retain(|(other_name, _)| *other_name != name);
+            completions.push((name, detail));
+        }
+
+        completions
+    }
+
+    /// What to show alongside `entity` in a list of completions or
+    /// symbols: its type or signature, or what kind of item it is.
+    fn entity_detail(&self, entity: Entity) -> String {
+        match entity.untern(self) {
+            EntityData::ItemName {
+                kind: ItemKind::Struct,
+                id,
+                ..
+            } => format!("struct {}", id.untern(self)),
+            EntityData::ItemName {
+                kind: ItemKind::Enum,
+                id,
+                ..
+            } => format!("enum {}", id.untern(self)),
+            EntityData::ItemName {
+                kind: ItemKind::Function,
+                id,
+                ..
+            }
+            | EntityData::MemberName {
+                kind: MemberKind::Method,
+                id,
+                ..
+            } => match self.signature(entity).into_value() {
+                Ok(_) => format!("def {}", entity.pretty_print(self)),
+                Err(_) => format!("def {}", id.untern(self)),
+            },
+            EntityData::MemberName {
+                kind: MemberKind::Field,
+                ..
+            } => self.ty(entity).into_value().pretty_print(self),
+            EntityData::MemberName {
+                kind: MemberKind::Variant,
+                base,
+                id,
+            } => match base.untern(self) {
+                EntityData::ItemName { id: enum_id, .. } => {
+                    format!("{}.{}", enum_id.untern(self), id.untern(self))
+                }
+                _ => id.untern(self).to_string(),
+            },
+            EntityData::ItemName {
+                kind: ItemKind::Import,
+                id,
+                ..
+            } => match self.import_target(entity).into_value() {
+                Ok(target) => self.entity_detail(target),
+                Err(_) => format!("use {}", id.untern(self)),
+            },
+            _ => entity.pretty_print(self),
+        }
+    }
+
+    /// Returns the signature of the innermost call whose arguments
+    /// contain a given position (if any).
+    fn signature_help_at_position(
+        &self,
+        url: &str,
+        position: Position,
+    ) -> Cancelable<Option<CallSignature>> {
+        let file = url.into_file_name(self);
+        let byte_index = match self.position_to_byte_index(url, position) {
+            Some(byte_index) => byte_index,
+            None => return Ok(None),
+        };
+        let index = byte_index.to_usize();
+        let text = self.file_text(file);
+        self.check_for_cancellation()?;
+
+        // `hover_targets` insists on finding something, so only ask it
+        // about positions inside of a function body
+        if self.enclosing_fn_entity(file, index).is_none() {
+            return Ok(None);
+        }
+        let targets = self.hover_targets(file, byte_index);
+
+        Ok(targets
+            .iter()
+            .rev()
+            .filter_map(|target| match target.kind {
+                HoverTargetKind::MetaIndex(entity, lark_hir::MetaIndex::Expression(expression)) => {
+                    Some((entity, expression))
+                }
+                _ => None,
+            })
+            .filter_map(|(fn_entity, expression)| {
+                let fn_body = self.fn_body(fn_entity).into_value();
+
+                // Find the callee, and where the arguments begin: just
+                // after the `(` that follows the function or method name
+                let (callee, name_end, is_method) = match fn_body[expression] {
+                    lark_hir::ExpressionData::Call { function, .. } => {
+                        let callee = match fn_body[function] {
+                            lark_hir::ExpressionData::Place { place } => match fn_body[place] {
+                                lark_hir::PlaceData::Entity(entity) => entity,
+                                _ => return None,
+                            },
+                            _ => return None,
+                        };
+                        (callee, fn_body.span(function).end().to_usize(), false)
+                    }
+                    lark_hir::ExpressionData::MethodCall { method, .. } => {
+                        let types = self.full_type_check(fn_entity).into_value();
+                        let callee = *types.entities.get(&method.into())?;
+                        (callee, fn_body.span(method).end().to_usize(), true)
+                    }
+                    _ => return None,
+                };
+
+                let after_name = text.get(name_end..index)?;
+                let paren = after_name.find('(')?;
+                if !after_name[..paren].trim().is_empty() {
+                    return None;
+                }
+                let arguments_text = &after_name[paren + 1..];
+
+                let mut call_signature = self.call_signature(callee, is_method)?;
+                call_signature.active_parameter = count_separators(arguments_text);
+                Some(call_signature)
+            })
+            .next())
+    }
+
+    /// The signature of `callee` (skipping `self`, for a method), with
+    /// the names of its parameters where they are known.
+    fn call_signature(&self, callee: Entity, is_method: bool) -> Option<CallSignature> {
+        let name = match callee.untern(self) {
+            EntityData::ItemName {
+                kind: ItemKind::Function,
+                id,
+                ..
+            }
+            | EntityData::MemberName {
+                kind: MemberKind::Method,
+                id,
+                ..
+            } => id.untern(self).to_string(),
+            _ => return None,
+        };
+
+        let signature = self.signature(callee).into_value().ok()?;
+        let skipped = if is_method { 1 } else { 0 };
+
+        let fn_body = self.fn_body(callee).into_value();
+        let names: Vec<String> = match &fn_body.arguments {
+            Ok(arguments) => arguments
+                .iter(&fn_body)
+                .map(|variable| {
+                    fn_body[fn_body[variable].name]
+                        .text
+                        .untern(self)
+                        .to_string()
+                })
+                .collect(),
+            Err(_) => vec![],
+        };
+
+        let parameters: Vec<String> = signature
+            .inputs
+            .iter()
+            .enumerate()
+            .skip(skipped)
+            .map(|(i, input)| {
+                let ty = lark_pretty_print::pretty_print_declared(input, callee, self);
+                match names.get(i) {
+                    Some(name) => format!("{}: {}", name, ty),
+                    None => ty,
+                }
+            })
+            .collect();
+
+        let output = lark_pretty_print::pretty_print_declared(&signature.output, callee, self);
+        Some(CallSignature {
+            label: format!("{}({}) -> {}", name, parameters.join(", "), output),
+            parameters,
+            active_parameter: 0,
+        })
+    }
+
+    /// The inferred types to show within `range` of a file: after the
+    /// variable of each `let`, and after each argument of each call
+    /// (but not the receiver of a method call).
+    fn inlay_hints(&self, url: &str, range: Range) -> Cancelable<Vec<InlayHint>> {
+        let file = url.into_file_name(self);
+        let file_entity = EntityData::InputFile { file }.intern(self);
+
+        let mut hints = vec![];
+        for &entity in self.descendant_entities(file_entity).iter() {
+            if !entity.untern(self).has_fn_body() {
+                continue;
+            }
+            self.check_for_cancellation()?;
+
+            let fn_body = self.fn_body(entity).into_value();
+            let types = self.full_type_check(entity).into_value();
+
+            let mut indices = vec![];
+            for data in fn_body.tables.expressions.iter() {
+                match data {
+                    lark_hir::ExpressionData::Let { variable, .. } => {
+                        indices.push(lark_hir::MetaIndex::Variable(*variable));
+                    }
+                    lark_hir::ExpressionData::Call { arguments, .. } => {
+                        let arguments = arguments.iter(&fn_body);
+                        indices.extend(arguments.map(lark_hir::MetaIndex::Expression));
+                    }
+                    lark_hir::ExpressionData::MethodCall { arguments, .. } => {
+                        let arguments = arguments.iter(&fn_body).skip(1);
+                        indices.extend(arguments.map(lark_hir::MetaIndex::Expression));
+                    }
+                    _ => {}
+                }
+            }
+
+            for index in indices {
+                let ty = match types.opt_ty(index) {
+                    Some(ty) => ty,
+                    None => continue,
+                };
+                if let BaseKind::Error = ty.base.untern(self).kind {
+                    continue;
+                }
+
+                let position = self.position(file, fn_body.span(index).end());
+                if range.start <= position && position <= range.end {
+                    hints.push(InlayHint {
+                        position,
+                        ty: lark_pretty_print::pretty_print_with_perm(ty, self),
+                    });
+                }
+            }
+        }
+
+        hints.sort_by_key(|hint| hint.position);
+        Ok(hints)
+    }
+
+    /// Classifies the comments, literals and names of a file for
+    /// semantic highlighting, in order. Names are classified by what
+    /// they resolve to.
+    fn semantic_tokens(&self, url: &str) -> Cancelable<Vec<SemanticToken>> {
+        let file = url.into_file_name(self);
+        let text = self.file_text(file);
+        let file_entity = EntityData::InputFile { file }.intern(self);
+        let entities = self.descendant_entities(file_entity);
+        let names = self.resolved_names(&entities);
+        self.check_for_cancellation()?;
+
+        let mut tokens = vec![];
+        for token in self.file_tokens(file).into_value().iter() {
+            let span = token.span;
+            let (kind, is_declaration) = match token.value {
+                LexToken::Comment => (SemanticTokenKind::Comment, false),
+                LexToken::String => (SemanticTokenKind::String, false),
+                LexToken::Integer => (SemanticTokenKind::Number, false),
+                LexToken::Identifier if KEYWORDS.contains(&&text[span]) => {
+                    (SemanticTokenKind::Keyword, false)
+                }
+                LexToken::Identifier => match names.get(&span) {
+                    Some(&name) => name,
+                    None => match self.resolve_unknown_name(&entities, span, &text[span]) {
+                        Some(kind) => (kind, false),
+                        None => continue,
+                    },
+                },
+                _ => continue,
+            };
+
+            // Tokens cannot span lines, so split up multi-line comments
+            // and strings
+            let mut start = span.start().to_usize();
+            for line in text[span].split('\n') {
+                let end = start + line.trim_end_matches('\r').len();
+                if end > start {
+                    tokens.push(SemanticToken {
+                        range: self.range(Span::new(file, start, end)),
+                        kind,
+                        is_declaration,
+                    });
+                }
+                start += line.len() + 1;
+            }
+        }
+
+        Ok(tokens)
+    }
+
+    /// The names found in the declarations and function bodies of
+    /// `entities`, by span, with what they resolved to (and whether
+    /// they are being declared there).
+    fn resolved_names(
+        &self,
+        entities: &[Entity],
+    ) -> HashMap<Span<FileName>, (SemanticTokenKind, bool)> {
+        let mut names = HashMap::new();
+
+        for &entity in entities {
+            if let Some(kind) = self.entity_token_kind(entity) {
+                let is_declaration = match entity.untern(self) {
+                    EntityData::ItemName {
+                        kind: ItemKind::Import,
+                        ..
+                    } => false,
+                    _ => true,
+                };
+                names.insert(
+                    self.characteristic_entity_span(entity),
+                    (kind, is_declaration),
+                );
+            }
+
+            if !entity.untern(self).has_fn_body() {
+                continue;
+            }
+
+            let fn_body = self.fn_body(entity).into_value();
+            let types = self.full_type_check(entity).into_value();
+            let arguments: Vec<_> = match &fn_body.arguments {
+                Ok(arguments) => arguments.iter(&fn_body).collect(),
+                Err(_) => vec![],
+            };
+            let variable_kind = |variable| {
+                if arguments.contains(&variable) {
+                    SemanticTokenKind::Parameter
+                } else {
+                    SemanticTokenKind::Variable
+                }
+            };
+
+            for (&index, &span) in fn_body.tables.spans.iter() {
+                let name = match index {
+                    lark_hir::MetaIndex::Variable(variable) => (variable_kind(variable), true),
+                    lark_hir::MetaIndex::Place(place) => match fn_body[place] {
+                        lark_hir::PlaceData::Variable(variable) => (variable_kind(variable), false),
+                        lark_hir::PlaceData::Entity(entity) => {
+                            match self.entity_token_kind(entity) {
+                                Some(kind) => (kind, false),
+                                None => continue,
+                            }
+                        }
+                        _ => continue,
+                    },
+                    lark_hir::MetaIndex::Identifier(identifier) => {
+                        match types.entities.get(&identifier.into()) {
+                            Some(&entity) => match self.entity_token_kind(entity) {
+                                Some(kind) => (kind, false),
+                                None => continue,
+                            },
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                names.entry(span).or_insert(name);
+            }
+        }
+
+        names
+    }
+
+    /// Classifies a name that is not part of any HIR (such as a type
+    /// in a signature) by looking it up in the innermost of `entities`
+    /// that contains it.
+    fn resolve_unknown_name(
+        &self,
+        entities: &[Entity],
+        span: Span<FileName>,
+        name: &str,
+    ) -> Option<SemanticTokenKind> {
+        let scope = entities
+            .iter()
+            .cloned()
+            .filter(|&entity| {
+                let entity_span = self.entity_span(entity);
+                entity_span.start() <= span.start() && span.end() <= entity_span.end()
+            })
+            .min_by_key(|&entity| self.entity_span(entity).len())?;
+
+        let name = name.intern(self);
+
+        // Generic parameters are in scope in their item and its members
+        let mut item = Some(scope);
+        while let Some(entity) = item {
+            match self.generic_declarations(entity).into_value() {
+                Ok(declarations) => {
+                    let is_generic = declarations
+                        .declarations
+                        .iter()
+                        .any(|GenericKind::Ty(declaration)| declaration.name == name);
+                    if is_generic {
+                        return Some(SemanticTokenKind::TypeParameter);
+                    }
+                    item = declarations.parent_item;
+                }
+                Err(_) => item = None,
+            }
+        }
+
+        self.entity_token_kind(self.resolve_name(scope, name)?)
+    }
+
+    fn entity_token_kind(&self, entity: Entity) -> Option<SemanticTokenKind> {
+        match entity.untern(self) {
+            EntityData::ItemName { kind, .. } => match kind {
+                ItemKind::Struct => Some(SemanticTokenKind::Struct),
+                ItemKind::Enum => Some(SemanticTokenKind::Enum),
+                ItemKind::Function => Some(SemanticTokenKind::Function),
+                ItemKind::Import => match self.import_target(entity).into_value() {
+                    Ok(target) => self.entity_token_kind(target),
+                    Err(_) => None,
+                },
+            },
+            EntityData::MemberName { kind, .. } => match kind {
+                MemberKind::Field => Some(SemanticTokenKind::Property),
+                MemberKind::Method => Some(SemanticTokenKind::Method),
+                MemberKind::Variant => Some(SemanticTokenKind::EnumMember),
+            },
+            EntityData::LangItem(lang_item) => match lang_item {
+                LangItem::Boolean | LangItem::Int | LangItem::Uint | LangItem::String => {
+                    Some(SemanticTokenKind::Type)
+                }
+                LangItem::True | LangItem::False => Some(SemanticTokenKind::Keyword),
+                LangItem::Debug => Some(SemanticTokenKind::Function),
+                LangItem::Tuple(_) => None,
+            },
+            EntityData::InputFile { .. } | EntityData::Error(_) => None,
+        }
+    }
+
+    /// The symbols defined in a file, with their members nested
+    /// inside of them.
+    fn document_symbols(&self, url: &str) -> Cancelable<Vec<Symbol>> {
+        let file = url.into_file_name(self);
+        if !self.file_names().contains(&file) {
+            return Ok(vec![]);
+        }
+
+        let entities = self.top_level_entities_in_file(file);
+        self.check_for_cancellation()?;
+
+        Ok(entities
+            .iter()
+            .filter_map(|&entity| self.symbol(entity, true))
+            .collect())
+    }
+
+    /// The symbols in any file whose names fuzzily match `query` (that
+    /// is, contain its characters in order, ignoring case), best
+    /// matches first.
+    fn workspace_symbols(&self, query: &str) -> Cancelable<Vec<Symbol>> {
+        let mut matches = vec![];
+
+        for &file in &*self.file_names() {
+            self.check_for_cancellation()?;
+
+            let file_entity = EntityData::InputFile { file }.intern(self);
+            for &entity in self.descendant_entities(file_entity).iter() {
+                if let Some(symbol) = self.symbol(entity, false) {
+                    if let Some(score) = fuzzy_match(query, &symbol.name) {
+                        matches.push((score, symbol));
+                    }
+                }
+            }
+        }
+
+        matches.sort_by(|(score1, symbol1), (score2, symbol2)| {
+            (score1, &symbol1.name).cmp(&(score2, &symbol2.name))
+        });
+        Ok(matches.into_iter().map(|(_, symbol)| symbol).collect())
+    }
+
+    /// The symbol for `entity`, if it has one (imports, for example,
+    /// do not). Its members are included if `with_children` is true.
+    fn symbol(&self, entity: Entity, with_children: bool) -> Option<Symbol> {
+        let (id, kind, container_name) = match entity.untern(self) {
+            EntityData::ItemName { kind, id, .. } => {
+                let kind = match kind {
+                    ItemKind::Struct => SymbolKind::Struct,
+                    ItemKind::Enum => SymbolKind::Enum,
+                    ItemKind::Function => SymbolKind::Function,
+                    ItemKind::Import => return None,
+                };
+                (id, kind, None)
+            }
+            EntityData::MemberName { kind, id, base } => {
+                let kind = match kind {
+                    MemberKind::Field => SymbolKind::Field,
+                    MemberKind::Method => SymbolKind::Method,
+                    MemberKind::Variant => SymbolKind::EnumMember,
+                };
+                let container_name = match base.untern(self) {
+                    EntityData::ItemName { id, .. } | EntityData::MemberName { id, .. } => {
+                        Some(id.untern(self).to_string())
+                    }
+                    _ => None,
+                };
+                (id, kind, container_name)
+            }
+            EntityData::Error(_) | EntityData::LangItem(_) | EntityData::InputFile { .. } => {
+                return None;
+            }
+        };
+
+        let children = match kind {
+            SymbolKind::Struct | SymbolKind::Enum | SymbolKind::EnumMember if with_children => self
+                .child_entities(entity)
+                .iter()
+                .filter_map(|&child| self.symbol(child, true))
+                .collect(),
+            _ => vec![],
+        };
+
+        let span = self.entity_span(entity);
+        Some(Symbol {
+            name: id.untern(self).to_string(),
+            detail: self.entity_detail(entity),
+            kind,
+            file: span.file().id.untern(self).to_string(),
+            range: self.range(span),
+            selection_range: self.range(self.characteristic_entity_span(entity)),
+            container_name,
+            children,
+        })
+    }
+
+    /// The byte index of `position` in the file `url`, if there is
+    /// such a file and `position` is within it.
+    fn position_to_byte_index(&self, url: &str, position: Position) -> Option<ByteIndex> {
+        let file = url.into_file_name(self);
+        if !self.file_names().contains(&file) {
+            return None;
+        }
+
+        self.byte_index(file, position.line, position.character)
+            .ok()
+    }
+}
+
+/// The names that the parser treats specially, wherever they appear.
+const KEYWORDS: &[&str] = &[
+    "def", "struct", "enum", "use", "let", "if", "else", "while", "match", "break", "continue",
+    "share", "borrow", "own",
+];
+
+/// If `name` contains the characters of `query` in order (ignoring
+/// case), returns how good a match it is: the number of characters of
+/// `name` that were skipped over, so that 0 is best.
+fn fuzzy_match(query: &str, name: &str) -> Option<usize> {
+    let mut name_chars = name.chars().flat_map(char::to_lowercase);
+    let mut skipped = 0;
+
+    for query_char in query.chars().flat_map(char::to_lowercase) {
+        loop {
+            match name_chars.next() {
+                Some(name_char) if name_char == query_char => break,
+                Some(_) => skipped += 1,
+                None => return None,
+            }
+        }
+    }
+
+    Some(skipped)
+}
+
+/// The number of `,` in `text` (the arguments of a call, up to some
+/// point) that separate arguments: that is, that are not inside of
+/// brackets, strings or comments.
+fn count_separators(text: &str) -> usize {
+    let mut depth = 0;
+    let mut separators = 0;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            ',' if depth == 0 => separators += 1,
+            '"' => rest = skip_string(rest),
+            '/' if rest.starts_with('/') || rest.starts_with('*') => rest = skip_comment(rest),
+            _ => {}
+        }
+    }
+    separators
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// True if `text` (some code that follows a point in a block) closes
+/// that block: that is, has more `}` than `{`, outside of strings and
+/// comments.
+fn leaves_block(text: &str) -> bool {
+    let mut depth = 0;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return true,
+            '}' => depth -= 1,
+            '"' => rest = skip_string(rest),
+            '/' if rest.starts_with('/') || rest.starts_with('*') => rest = skip_comment(rest),
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Given the text following the opening `"` of a string, returns the
+/// text after its closing `"`.
+fn skip_string(text: &str) -> &str {
+    let mut escaped = false;
+    let end = text.find(|c| {
+        let is_end = c == '"' && !escaped;
+        escaped = c == '\\' && !escaped;
+        is_end
+    });
+    end.map_or("", |end| &text[end + 1..])
+}
+
+/// Given the text following the `/` that starts a `//` or `/*`
+/// comment, returns the text after the comment.
+fn skip_comment(text: &str) -> &str {
+    if text.starts_with('/') {
+        text.find('\n').map_or("", |end| &text[end..])
+    } else {
+        text.find("*/").map_or("", |end| &text[end + 2..])
     }
 }
@@ -0,0 +1,33 @@
+#![cfg(test)]
+
+use crate::RespondOnPanic;
+use lark_actor::{LspResponse, RequestError};
+use std::sync::mpsc::channel;
+
+#[test]
+fn respond_on_panic() {
+    let (send_channel, receive_channel) = channel();
+
+    let thread = std::thread::spawn({
+        let send_channel = send_channel.clone();
+        move || {
+            let _guard = RespondOnPanic::new(22, &send_channel);
+            panic!("a query failed");
+        }
+    });
+    assert!(thread.join().is_err());
+
+    match receive_channel.try_recv() {
+        Ok(LspResponse::Error(22, RequestError::InternalError(_))) => {}
+        _ => panic!("expected an internal error for task 22"),
+    }
+}
+
+#[test]
+fn respond_on_success() {
+    let (send_channel, receive_channel) = channel();
+
+    // A guard that is dropped without a panic sends nothing
+    drop(RespondOnPanic::new(22, &send_channel));
+    assert!(receive_channel.try_recv().is_err());
+}
@@ -16,9 +16,3 @@ pub struct Location {
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, new)]
 pub struct OutOfBounds;
-
-impl Location {
-    pub fn as_position(&self) -> languageserver_types::Position {
-        languageserver_types::Position::new(self.line as u64, self.column as u64)
-    }
-}
@@ -0,0 +1,145 @@
+use crate::ByteIndex;
+use crate::OutOfBounds;
+use languageserver_types::Position;
+
+/// How the columns of the positions that we exchange with the IDE are
+/// counted. LSP counts UTF-16 code units, unless the IDE says at
+/// initialization that it can handle something else.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PositionEncoding {
+    /// Columns count bytes of UTF-8.
+    Utf8,
+
+    /// Columns count UTF-16 code units, so characters outside of the
+    /// basic multilingual plane count twice.
+    Utf16,
+}
+
+impl Default for PositionEncoding {
+    fn default() -> Self {
+        PositionEncoding::Utf16
+    }
+}
+
+impl PositionEncoding {
+    /// The name that LSP gives to this encoding.
+    pub fn name(self) -> &'static str {
+        match self {
+            PositionEncoding::Utf8 => "utf-8",
+            PositionEncoding::Utf16 => "utf-16",
+        }
+    }
+
+    /// The encoding that LSP calls `name`, if we support it.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "utf-8" => Some(PositionEncoding::Utf8),
+            "utf-16" => Some(PositionEncoding::Utf16),
+            _ => None,
+        }
+    }
+
+    /// The number of columns that `text` takes up.
+    pub fn columns(self, text: &str) -> usize {
+        match self {
+            PositionEncoding::Utf8 => text.len(),
+            PositionEncoding::Utf16 => text.chars().map(char::len_utf16).sum(),
+        }
+    }
+
+    /// The number of columns that the character `c` takes up.
+    fn char_columns(self, c: char) -> usize {
+        match self {
+            PositionEncoding::Utf8 => c.len_utf8(),
+            PositionEncoding::Utf16 => c.len_utf16(),
+        }
+    }
+
+    /// The position of `index` in `text`, given the `line_offsets` of
+    /// `text`.
+    pub fn position(self, text: &str, line_offsets: &[usize], index: ByteIndex) -> Position {
+        let index = index.to_usize();
+        let line = line_of(text, line_offsets, index);
+        let column = self.columns(&text[line_offsets[line]..index]);
+        Position::new(line as u64, column as u64)
+    }
+
+    /// The byte index of `position` in `text`, given the `line_offsets`
+    /// of `text`. As in LSP, a column past the end of its line means
+    /// the end of the line; but a line past the end of the text, or a
+    /// column in the middle of a character, is out of bounds.
+    pub fn byte_index(
+        self,
+        text: &str,
+        line_offsets: &[usize],
+        position: Position,
+    ) -> Result<ByteIndex, OutOfBounds> {
+        let line = position.line as usize;
+        let (start, end) = if line + 1 < line_offsets.len() {
+            let mut end = line_offsets[line + 1];
+            if text[..end].ends_with('\n') {
+                end -= 1;
+                if text[..end].ends_with('\r') {
+                    end -= 1;
+                }
+            }
+            (line_offsets[line], end)
+        } else if line + 1 == line_offsets.len() && ends_with_empty_line(text) {
+            (text.len(), text.len())
+        } else {
+            return Err(OutOfBounds);
+        };
+
+        let mut columns = 0;
+        for (offset, c) in text[start..end].char_indices() {
+            if columns == position.character {
+                return Ok(ByteIndex::from(start + offset));
+            } else if columns > position.character {
+                return Err(OutOfBounds);
+            }
+            columns += self.char_columns(c) as u64;
+        }
+
+        if columns > position.character {
+            return Err(OutOfBounds);
+        }
+        Ok(ByteIndex::from(end))
+    }
+}
+
+/// The index at which each line of `text` starts, followed by the
+/// length of `text`. So for the input "a\nb\r\nc" you would get
+/// `[0, 2, 5, 6]`.
+pub fn line_offsets(text: &str) -> Vec<usize> {
+    let mut accumulator = 0;
+    text.lines()
+        .map(|line_text| {
+            let line_start = accumulator;
+            accumulator += line_text.len();
+            if text[accumulator..].starts_with("\r\n") {
+                accumulator += 2;
+            } else if text[accumulator..].starts_with("\n") {
+                accumulator += 1;
+            }
+            line_start
+        })
+        .chain(std::iter::once(text.len()))
+        .collect()
+}
+
+/// The (0-based) line of `text` that the byte `index` is on, given the
+/// `line_offsets` of `text`.
+pub fn line_of(text: &str, line_offsets: &[usize], index: usize) -> usize {
+    match line_offsets.binary_search(&index) {
+        // The last offset is the end of the text, which only starts a
+        // line of its own if the text ends with a newline.
+        Ok(line) if line + 1 < line_offsets.len() || ends_with_empty_line(text) => line,
+        Ok(next_line) | Err(next_line) => next_line - 1,
+    }
+}
+
+/// Whether the last line of `text` is empty (which `str::lines` does
+/// not count as a line).
+fn ends_with_empty_line(text: &str) -> bool {
+    text.is_empty() || text.ends_with('\n')
+}
@@ -85,19 +85,34 @@ pub fn run_test_harness(
     let relative_test_path: &Path = relative_test_path.as_ref();
     let test_path: &Path = test_path.as_ref();
 
-    if is_dir {
-        // this is meant to be a manifest tes
-        unimplemented!("test directories");
-    }
-
     eprintln!("Test file: `{}`", test_path.display());
 
+    // A test directory is a multi-file project: its options come
+    // from the `main.lark` file within, and each of its files is
+    // added under the test name (e.g., `foo/main` and `foo/math`).
     let test_name = relative_test_path.with_extension("").display().to_string();
-
-    let file_contents = fs::read_to_string(&test_path)
-        .unwrap_or_else(|err| panic!("error reading `{}`: {}", test_path.display(), err));
-
-    let options = TestOptions::from_source_text(&test_path, &file_contents);
+    let (main_name, main_path, module_paths) = if is_dir {
+        let mut module_paths: Vec<PathBuf> = fs::read_dir(&test_path)
+            .unwrap_or_else(|err| panic!("error reading `{}`: {}", test_path.display(), err))
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map(|ext| ext == "lark").unwrap_or(false))
+            .filter(|path| path.file_stem().map(|stem| stem != "main").unwrap_or(false))
+            .collect();
+        module_paths.sort();
+
+        (
+            format!("{}/main", test_name),
+            test_path.join("main.lark"),
+            module_paths,
+        )
+    } else {
+        (test_name.clone(), test_path.to_owned(), vec![])
+    };
+
+    let file_contents = fs::read_to_string(&main_path)
+        .unwrap_or_else(|err| panic!("error reading `{}`: {}", main_path.display(), err));
+
+    let options = TestOptions::from_source_text(&main_path, &file_contents);
 
     eprintln!("Options: {:?}", options);
 
@@ -107,11 +122,22 @@ pub fn run_test_harness(
     }
 
     let mut db = LarkDatabase::default();
-    db.add_file(&test_name, &file_contents);
+    db.add_file(&main_name, &file_contents);
+
+    for module_path in &module_paths {
+        let module_name = format!(
+            "{}/{}",
+            test_name,
+            module_path.file_stem().unwrap().to_string_lossy()
+        );
+        let module_contents = fs::read_to_string(&module_path)
+            .unwrap_or_else(|err| panic!("error reading `{}`: {}", module_path.display(), err));
+        db.add_file(&module_name, &module_contents);
+    }
 
     TestContext {
         bless_mode,
-        test_name,
+        test_name: main_name,
         test_path,
         relative_test_path: &relative_test_path,
         db,
@@ -1,3 +1,4 @@
+use lark_cli::dump_hir::HirMode;
 use regex::Regex;
 use std::path::Path;
 
@@ -13,11 +14,42 @@ crate struct TestOptions {
     // Checked by code in `test::ls_test`.
     crate expected_hovers: Vec<ExpectedHover>,
 
+    // `//~ COMPLETION` annotations, with the character from the opening `/`.
+    // Checked by code in `test::ls_test`.
+    crate expected_completions: Vec<ExpectedCompletion>,
+
+    // `//~ SIGNATURE` annotations, with the character from the opening `/`.
+    // Checked by code in `test::ls_test`.
+    crate expected_signatures: Vec<ExpectedSignature>,
+
+    // `//~ TOKEN` annotations, with the character from the opening `/`.
+    // Checked by code in `test::ls_test`.
+    crate expected_tokens: Vec<ExpectedToken>,
+
+    // `//~ HINT` annotations, with the character from the opening `/`.
+    // Checked by code in `test::ls_test`.
+    crate expected_hints: Vec<ExpectedHint>,
+
+    // `//~ FIX` annotations, with the character from the opening `/`.
+    // Checked by code in `test::ls_test`.
+    crate expected_fixes: Vec<ExpectedFix>,
+
+    // `//~ SYMBOLS` annotations: together, each symbol of the file, like
+    // `struct Foo` or `field Foo.bar`. Checked by code in `test::ls_test`.
+    crate expected_symbols: Option<Vec<String>>,
+
+    // `//~ WORKSPACE_SYMBOLS` annotations. Checked by code in `test::ls_test`.
+    crate expected_workspace_symbols: Vec<ExpectedWorkspaceSymbols>,
+
     // Execution mode: do we run this code and -- if so -- how?
     //
     // Default: if there are errors, no. Otherwise, mode must be explicitly specified.
     crate execution_mode: Option<ExecutionMode>,
 
+    // `//~ runtime_error: message`: running the code stops with this error,
+    // whichever way we run it. Checked by code in `test::execution_test`.
+    crate runtime_error: Option<String>,
+
     // Do we dump out the HIR?
     crate hir_mode: Option<HirMode>,
 }
@@ -30,12 +62,6 @@ crate enum ExecutionMode {
     All,
 }
 
-#[derive(Copy, Clone, Debug)]
-crate enum HirMode {
-    Base,
-    Full,
-}
-
 #[derive(Clone, Debug)]
 crate struct ExpectedError {
     crate line_num: u64,
@@ -49,6 +75,60 @@ crate struct ExpectedHover {
     crate message: Regex,
 }
 
+#[derive(Clone, Debug)]
+crate struct ExpectedCompletion {
+    crate line_num: u64,
+    crate character_num: u64,
+
+    /// The labels of the completions, sorted.
+    crate labels: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+crate struct ExpectedSignature {
+    crate line_num: u64,
+    crate character_num: u64,
+
+    /// The label of the signature, with the active parameter in `[]`.
+    crate label: String,
+}
+
+#[derive(Clone, Debug)]
+crate struct ExpectedToken {
+    crate line_num: u64,
+    crate character_num: u64,
+
+    /// The kind of the semantic token, like `struct`, followed by
+    /// `declaration` if it is one.
+    crate description: String,
+}
+
+#[derive(Clone, Debug)]
+crate struct ExpectedHint {
+    crate line_num: u64,
+    crate character_num: u64,
+
+    /// The type shown by the inlay hint, like `own uint`.
+    crate ty: String,
+}
+
+#[derive(Clone, Debug)]
+crate struct ExpectedFix {
+    crate line_num: u64,
+    crate character_num: u64,
+
+    /// The title of one of the code actions offered there.
+    crate title: String,
+}
+
+#[derive(Clone, Debug)]
+crate struct ExpectedWorkspaceSymbols {
+    crate query: String,
+
+    /// The symbols found, best match first.
+    crate symbols: Vec<String>,
+}
+
 lazy_static::lazy_static! {
     static ref WITH_OPTION: Regex = Regex::new(r"^(\s*)//~ ([a-zA-Z_]+):(.*)").unwrap();
     static ref NO_OPTION: Regex = Regex::new(r"^(\s*)//~ ([a-zA-Z_]+)\s*$").unwrap();
@@ -121,6 +201,15 @@ impl TestOptions {
                 Ok(())
             }
 
+            "runtime_error" => {
+                if value.trim().is_empty() {
+                    Err("runtime_error requires the message of the error".to_string())
+                } else {
+                    self.runtime_error = Some(value.trim().to_string());
+                    Ok(())
+                }
+            }
+
             "hir" => {
                 self.hir_mode = Some(match value.trim() {
                     "base" => HirMode::Base,
@@ -147,6 +236,104 @@ impl TestOptions {
                 },
             },
 
+            // `//~ COMPLETION: a, b` expects completions `a` and `b` (in any
+            // order) at the same column as starting `/`
+            "COMPLETION" => match last_non_comment_line {
+                None => Err("cannot find line that completion applies to".to_string()),
+                Some(line_num) => {
+                    let mut labels = comma_separated(value);
+                    labels.sort();
+                    self.expected_completions.push(ExpectedCompletion {
+                        line_num,
+                        character_num: prefix.len() as u64,
+                        labels,
+                    });
+                    Ok(())
+                }
+            },
+
+            // `//~ SIGNATURE: add(x: uint, [y: uint]) -> uint` expects that
+            // signature, with `y` as the active parameter, at the same column
+            // as starting `/`
+            "SIGNATURE" => match last_non_comment_line {
+                None => Err("cannot find line that signature applies to".to_string()),
+                Some(line_num) => {
+                    self.expected_signatures.push(ExpectedSignature {
+                        line_num,
+                        character_num: prefix.len() as u64,
+                        label: value.trim().to_string(),
+                    });
+                    Ok(())
+                }
+            },
+
+            // `//~ TOKEN: property declaration` expects the semantic token at
+            // the same column as starting `/` to be the declaration of a
+            // property
+            "TOKEN" => match last_non_comment_line {
+                None => Err("cannot find line that token applies to".to_string()),
+                Some(line_num) => {
+                    self.expected_tokens.push(ExpectedToken {
+                        line_num,
+                        character_num: prefix.len() as u64,
+                        description: value.trim().to_string(),
+                    });
+                    Ok(())
+                }
+            },
+
+            // `//~ HINT: own uint` expects an inlay hint showing that type at
+            // the same column as starting `/`
+            "HINT" => match last_non_comment_line {
+                None => Err("cannot find line that hint applies to".to_string()),
+                Some(line_num) => {
+                    self.expected_hints.push(ExpectedHint {
+                        line_num,
+                        character_num: prefix.len() as u64,
+                        ty: value.trim().to_string(),
+                    });
+                    Ok(())
+                }
+            },
+
+            // `//~ FIX: Replace with `count`` expects a code action with that
+            // title at the same column as starting `/`
+            "FIX" => match last_non_comment_line {
+                None => Err("cannot find line that fix applies to".to_string()),
+                Some(line_num) => {
+                    self.expected_fixes.push(ExpectedFix {
+                        line_num,
+                        character_num: prefix.len() as u64,
+                        title: value.trim().to_string(),
+                    });
+                    Ok(())
+                }
+            },
+
+            // `//~ SYMBOLS: struct Foo, field Foo.bar` expects those symbols
+            // (in any order) in the outline of the file, along with those of
+            // any other `//~ SYMBOLS` annotations
+            "SYMBOLS" => {
+                self.expected_symbols
+                    .get_or_insert_with(Vec::new)
+                    .extend(comma_separated(value));
+                Ok(())
+            }
+
+            // `//~ WORKSPACE_SYMBOLS: ba => field Foo.bar, method Foo.baz`
+            // expects a search for `ba` to find those symbols, in that order
+            "WORKSPACE_SYMBOLS" => match value.find("=>") {
+                None => Err("expected `query => symbols`".to_string()),
+                Some(index) => {
+                    self.expected_workspace_symbols
+                        .push(ExpectedWorkspaceSymbols {
+                            query: value[..index].trim().to_string(),
+                            symbols: comma_separated(&value[index + 2..]),
+                        });
+                    Ok(())
+                }
+            },
+
             "ERROR" => match last_non_comment_line {
                 None => Err("cannot find line that error applies to".to_string()),
                 Some(line_num) => match Regex::new(value.trim()) {
@@ -163,3 +350,11 @@ impl TestOptions {
         }
     }
 }
+
+fn comma_separated(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
@@ -1,5 +1,6 @@
 use crate::harness::options::ExecutionMode;
 use crate::harness::options::TestOptions;
+use lark_build::CodegenType;
 use lark_query_system::ls_ops::Cancelled;
 use lark_query_system::ls_ops::LsDatabase;
 use lark_query_system::LarkDatabase;
@@ -51,14 +52,18 @@ impl TestContext<'_> {
             }
             Some(ExecutionMode::No) => {}
             Some(ExecutionMode::Build) => {
-                self.build_and_run_executable();
+                self.build_and_run_executable(CodegenType::Rust);
+                self.build_and_run_executable(CodegenType::C);
             }
             Some(ExecutionMode::Eval) => {
                 self.run_eval();
+                self.run_vm();
             }
             Some(ExecutionMode::All) => {
-                self.build_and_run_executable();
+                self.build_and_run_executable(CodegenType::Rust);
+                self.build_and_run_executable(CodegenType::C);
                 self.run_eval();
+                self.run_vm();
             }
         }
 
@@ -1,28 +1,69 @@
 use crate::harness::test::TestContext;
+use lark_build::CodegenType;
 use lark_cli::build::LarkDatabaseExt;
-use lark_query_system::ls_ops::Cancelled;
 use std::process::Command;
 
 impl TestContext<'_> {
-    crate fn build_and_run_executable(&self) {
-        let exe_path = self.executable_path();
+    crate fn build_and_run_executable(&self, codegen_type: CodegenType) {
+        let exe_path = self.executable_path(codegen_type);
         self.db
-            .build(exe_path.to_str().unwrap())
-            .unwrap_or_else(|Cancelled| panic!("cancelled"));
+            .build(exe_path.to_str().unwrap(), codegen_type)
+            .unwrap_or_else(|err| panic!("failed to build: {}", err));
 
         let cmd = Command::new(exe_path)
             .output()
             .expect("Failed to run compile test");
         let test_output = String::from_utf8(cmd.stdout).unwrap();
 
+        // Like `lark run`, the executable reports a runtime error (without
+        // saying where it happened) and exits with status 1
+        let stderr = String::from_utf8(cmd.stderr).unwrap();
+        match &self.options.runtime_error {
+            Some(message) => {
+                assert_eq!(stderr, format!("error: {}\n", message));
+                assert_eq!(cmd.status.code(), Some(1));
+            }
+            None => assert!(cmd.status.success(), "executable failed: {}", stderr),
+        }
+
         self.compare_reference_contents("output", test_output.as_bytes(), false);
     }
 
     crate fn run_eval(&self) {
         let mut handler = lark_eval::IOHandler::new(true);
-        lark_eval::eval(&self.db, &mut handler);
-        let lark_eval::IOHandler { redirect: output } = handler;
-        let output = output.unwrap();
+        let result = lark_eval::eval(&self.db, &mut handler);
+        self.check_runtime_error(result.err().map(|err| err.message));
+        let output = handler.redirect.unwrap();
         self.compare_reference_contents("output", output.as_bytes(), false);
     }
+
+    /// Runs the program on `lark_vm`, going through the bytecode's
+    /// binary format to check that it round-trips.
+    crate fn run_vm(&self) {
+        let program = lark_vm::compile(&self.db);
+
+        let mut bytes = vec![];
+        lark_vm::encode(&program, &mut bytes).unwrap();
+        let decoded = lark_vm::decode(&mut &bytes[..]).unwrap();
+        assert_eq!(program, decoded);
+
+        let mut output = vec![];
+        match lark_vm::run(&decoded, &mut output) {
+            Ok(()) => self.check_runtime_error(None),
+            Err(lark_vm::Error::Fault(fault)) => self.check_runtime_error(Some(fault.message)),
+            Err(err) => panic!("failed to run: {}", err),
+        }
+        self.compare_reference_contents("output", &output, false);
+    }
+
+    /// Checks that running the program stopped with the `runtime_error`
+    /// of the test, if it has one, and finished otherwise.
+    fn check_runtime_error(&self, actual: Option<String>) {
+        match (self.options.runtime_error.as_ref(), actual) {
+            (Some(expected), Some(actual)) => assert_eq!(*expected, actual),
+            (Some(expected), None) => panic!("expected runtime error `{}`", expected),
+            (None, Some(actual)) => panic!("runtime error: {}", actual),
+            (None, None) => {}
+        }
+    }
 }
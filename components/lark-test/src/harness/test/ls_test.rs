@@ -1,9 +1,13 @@
 use crate::harness::test::TestContext;
 use languageserver_types::{
-    ClientCapabilities, DidOpenTextDocumentParams, Hover, HoverContents, InitializeParams,
-    InitializeResult, MarkedString, Position, PublishDiagnosticsParams, TextDocumentIdentifier,
-    TextDocumentItem, TextDocumentPositionParams,
+    ClientCapabilities, CodeAction, CodeActionContext, CodeActionParams, CompletionList, CompletionParams, DidOpenTextDocumentParams,
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, Hover, HoverContents,
+    InitializeParams, InitializeResult, MarkedString, ParameterLabel, Position,
+    PublishDiagnosticsParams, Range, SignatureHelp, SymbolInformation, SymbolKind, TextDocumentIdentifier, TextDocumentItem,
+    TextDocumentPositionParams, WorkspaceSymbolParams,
 };
+use lark_language_server::inlay_hints::{InlayHint, InlayHintParams};
+use lark_language_server::semantic_tokens::{self, SemanticTokens, SemanticTokensParams};
 use lark_language_server::{JsonRPCNotification, JsonRPCResponse, LSPCommand};
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
@@ -12,7 +16,15 @@ use std::process::{Command, Stdio};
 
 impl TestContext<'_> {
     crate fn test_language_server(&self) -> Result<(), Box<std::error::Error>> {
-        if self.options.expected_hovers.is_empty() {
+        if self.options.expected_hovers.is_empty()
+            && self.options.expected_completions.is_empty()
+            && self.options.expected_signatures.is_empty()
+            && self.options.expected_tokens.is_empty()
+            && self.options.expected_hints.is_empty()
+            && self.options.expected_fixes.is_empty()
+            && self.options.expected_symbols.is_none()
+            && self.options.expected_workspace_symbols.is_empty()
+        {
             return Ok(());
         }
 
@@ -32,7 +44,10 @@ impl TestContext<'_> {
         let result = child_session.receive::<JsonRPCNotification<PublishDiagnosticsParams>>()?;
 
         assert_eq!(result.method, "textDocument/publishDiagnostics");
-        assert_eq!(result.params.diagnostics.len(), 0);
+        assert_eq!(
+            result.params.diagnostics.len(),
+            self.options.expected_errors.len()
+        );
 
         // Hover to get the type
         for hover in &self.options.expected_hovers {
@@ -58,10 +73,289 @@ impl TestContext<'_> {
             }
         }
 
+        for completion in &self.options.expected_completions {
+            child_session.send_completion(
+                901,
+                test_path,
+                completion.line_num,
+                completion.character_num,
+            )?;
+
+            let result = child_session.receive::<JsonRPCResponse<CompletionList>>()?;
+            assert_eq!(result.id, 901);
+
+            let mut labels: Vec<String> =
+                result.result.items.into_iter().map(|item| item.label).collect();
+            labels.sort();
+            if labels != completion.labels {
+                eprintln!(
+                    "{}:{}:{}: unexpected completions: `{}`",
+                    self.test_path.display(),
+                    completion.line_num + 1,
+                    completion.character_num + 1,
+                    labels.join(", "),
+                );
+
+                panic!("unexpected completions: {:?}", labels);
+            }
+        }
+
+        for signature in &self.options.expected_signatures {
+            child_session.send_signature_help(
+                904,
+                test_path,
+                signature.line_num,
+                signature.character_num,
+            )?;
+
+            let result = child_session.receive::<JsonRPCResponse<SignatureHelp>>()?;
+            assert_eq!(result.id, 904);
+
+            let label = signature_description(&result.result);
+            if label != signature.label {
+                eprintln!(
+                    "{}:{}:{}: unexpected signature: `{}`",
+                    self.test_path.display(),
+                    signature.line_num + 1,
+                    signature.character_num + 1,
+                    label,
+                );
+
+                panic!("unexpected signature: {}", label);
+            }
+        }
+
+        if !self.options.expected_tokens.is_empty() {
+            child_session.send_semantic_tokens(905, test_path)?;
+
+            let result = child_session.receive::<JsonRPCResponse<SemanticTokens>>()?;
+            assert_eq!(result.id, 905);
+
+            let tokens = decode_semantic_tokens(&result.result.data);
+            for expected in &self.options.expected_tokens {
+                let description = tokens
+                    .iter()
+                    .find(|(line, start, length, _)| {
+                        *line == expected.line_num
+                            && *start <= expected.character_num
+                            && expected.character_num < start + length
+                    })
+                    .map(|(_, _, _, description)| description.as_str())
+                    .unwrap_or("none");
+                if description != expected.description {
+                    eprintln!(
+                        "{}:{}:{}: unexpected token: `{}`",
+                        self.test_path.display(),
+                        expected.line_num + 1,
+                        expected.character_num + 1,
+                        description,
+                    );
+
+                    panic!("unexpected token: {}", description);
+                }
+            }
+        }
+
+        if !self.options.expected_hints.is_empty() {
+            child_session.send_inlay_hint(907, test_path)?;
+
+            let result = child_session.receive::<JsonRPCResponse<Vec<InlayHint>>>()?;
+            assert_eq!(result.id, 907);
+
+            for expected in &self.options.expected_hints {
+                let label = result
+                    .result
+                    .iter()
+                    .find(|hint| {
+                        hint.position
+                            == Position::new(expected.line_num, expected.character_num)
+                    })
+                    .map(|hint| hint.label.as_str())
+                    .unwrap_or("none");
+                if label != format!(": {}", expected.ty) {
+                    eprintln!(
+                        "{}:{}:{}: unexpected hint: `{}`",
+                        self.test_path.display(),
+                        expected.line_num + 1,
+                        expected.character_num + 1,
+                        label,
+                    );
+
+                    panic!("unexpected hint: {}", label);
+                }
+            }
+        }
+
+        for fix in &self.options.expected_fixes {
+            child_session.send_code_action(906, test_path, fix.line_num, fix.character_num)?;
+
+            let result = child_session.receive::<JsonRPCResponse<Vec<CodeAction>>>()?;
+            assert_eq!(result.id, 906);
+
+            let titles: Vec<&str> = result
+                .result
+                .iter()
+                .map(|action| action.title.as_str())
+                .collect();
+            if !titles.contains(&fix.title.as_str()) {
+                eprintln!(
+                    "{}:{}:{}: unexpected fixes: `{}`",
+                    self.test_path.display(),
+                    fix.line_num + 1,
+                    fix.character_num + 1,
+                    titles.join(", "),
+                );
+
+                panic!("unexpected fixes: {:?}", titles);
+            }
+        }
+
+        if let Some(expected_symbols) = &self.options.expected_symbols {
+            child_session.send_document_symbol(902, test_path)?;
+
+            let result = child_session.receive::<JsonRPCResponse<DocumentSymbolResponse>>()?;
+            assert_eq!(result.id, 902);
+
+            let mut symbols = vec![];
+            match result.result {
+                DocumentSymbolResponse::Nested(document_symbols) => {
+                    flatten_document_symbols(&document_symbols, None, &mut symbols);
+                }
+                x => panic!("Unexpected symbol response: {:?}", x),
+            }
+            symbols.sort();
+
+            let mut expected_symbols = expected_symbols.clone();
+            expected_symbols.sort();
+            if symbols != expected_symbols {
+                eprintln!(
+                    "{}: unexpected symbols: `{}`",
+                    self.test_path.display(),
+                    symbols.join(", "),
+                );
+
+                panic!("unexpected symbols: {:?}", symbols);
+            }
+        }
+
+        for expected in &self.options.expected_workspace_symbols {
+            child_session.send_workspace_symbol(903, &expected.query)?;
+
+            let result = child_session.receive::<JsonRPCResponse<Vec<SymbolInformation>>>()?;
+            assert_eq!(result.id, 903);
+
+            let symbols: Vec<String> = result
+                .result
+                .iter()
+                .map(|symbol| {
+                    symbol_description(symbol.kind, &symbol.name, &symbol.container_name)
+                })
+                .collect();
+            if symbols != expected.symbols {
+                eprintln!(
+                    "{}: unexpected symbols for `{}`: `{}`",
+                    self.test_path.display(),
+                    expected.query,
+                    symbols.join(", "),
+                );
+
+                panic!("unexpected symbols: {:?}", symbols);
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Describes `document_symbols` and their children, like `struct Foo`
+/// or `field Foo.bar`.
+fn flatten_document_symbols(
+    document_symbols: &[DocumentSymbol],
+    container_name: Option<String>,
+    symbols: &mut Vec<String>,
+) {
+    for symbol in document_symbols {
+        symbols.push(symbol_description(
+            symbol.kind,
+            &symbol.name,
+            &container_name,
+        ));
+
+        if let Some(children) = &symbol.children {
+            flatten_document_symbols(children, Some(symbol.name.clone()), symbols);
+        }
+    }
+}
+
+fn symbol_description(kind: SymbolKind, name: &str, container_name: &Option<String>) -> String {
+    let kind = match kind {
+        SymbolKind::Struct => "struct",
+        SymbolKind::Enum => "enum",
+        SymbolKind::Function => "function",
+        SymbolKind::Field => "field",
+        SymbolKind::Method => "method",
+        SymbolKind::EnumMember => "variant",
+        _ => "unknown",
+    };
+
+    match container_name {
+        Some(container_name) => format!("{} {}.{}", kind, container_name, name),
+        None => format!("{} {}", kind, name),
+    }
+}
+
+/// Decodes the `data` of semantic tokens into the line, start
+/// character, length and description (like `struct declaration`) of
+/// each token.
+fn decode_semantic_tokens(data: &[u64]) -> Vec<(u64, u64, u64, String)> {
+    let legend = semantic_tokens::options().legend;
+    let mut tokens = vec![];
+    let mut line = 0;
+    let mut start = 0;
+
+    for token in data.chunks(5) {
+        if token[0] != 0 {
+            start = 0;
+        }
+        line += token[0];
+        start += token[1];
+
+        let mut description = legend.token_types[token[3] as usize].clone();
+        for (index, modifier) in legend.token_modifiers.iter().enumerate() {
+            if token[4] & (1 << index) != 0 {
+                description.push(' ');
+                description.push_str(modifier);
+            }
+        }
+        tokens.push((line, start, token[2], description));
+    }
+
+    tokens
+}
+
+/// The label of the active signature, with its active parameter in `[]`.
+fn signature_description(signature_help: &SignatureHelp) -> String {
+    let signature = &signature_help.signatures[signature_help.active_signature.unwrap_or(0) as usize];
+    let mut label = signature.label.clone();
+
+    let active_parameter = signature_help
+        .active_parameter
+        .and_then(|index| signature.parameters.as_ref()?.get(index as usize));
+    if let Some(parameter) = active_parameter {
+        if let ParameterLabel::Simple(parameter) = &parameter.label {
+            // Look for the parameter after the name of the function
+            let start = label.find('(').unwrap_or(0);
+            if let Some(index) = label[start..].find(parameter.as_str()) {
+                let index = start + index;
+                label.insert(index + parameter.len(), ']');
+                label.insert(index, '[');
+            }
+        }
+    }
+
+    label
+}
+
 struct ChildSession {
     child: std::process::Child,
 }
@@ -143,18 +437,21 @@ impl ChildSession {
     fn send_init(&mut self, id: usize) -> Result<(), Box<std::error::Error>> {
         self.send(LSPCommand::initialize {
             id,
-            params: InitializeParams {
-                process_id: None,
-                root_path: None,
-                root_uri: None,
-                initialization_options: None,
-                capabilities: ClientCapabilities {
-                    experimental: None,
-                    text_document: None,
-                    workspace: None,
+            params: lark_language_server::InitializeParams {
+                standard: InitializeParams {
+                    process_id: None,
+                    root_path: None,
+                    root_uri: None,
+                    initialization_options: None,
+                    capabilities: ClientCapabilities {
+                        experimental: None,
+                        text_document: None,
+                        workspace: None,
+                    },
+                    trace: None,
+                    workspace_folders: None,
                 },
-                trace: None,
-                workspace_folders: None,
+                position_encodings: vec![],
             },
         })
     }
@@ -204,4 +501,161 @@ impl ChildSession {
             },
         })
     }
+
+    fn send_completion(
+        &mut self,
+        id: usize,
+        filepath: &str,
+        line: u64,
+        character: u64,
+    ) -> Result<(), Box<std::error::Error>> {
+        let path = std::path::Path::new(filepath).canonicalize()?;
+        self.send(LSPCommand::completion {
+            id,
+            params: CompletionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: url::Url::parse(&format!(
+                        "file:///{}",
+                        path.to_str().ok_or_else(|| {
+                            std::io::Error::new(std::io::ErrorKind::InvalidData, "Bad filepath")
+                        })?
+                    ))?,
+                },
+                position: Position { line, character },
+                context: None,
+            },
+        })
+    }
+
+    fn send_signature_help(
+        &mut self,
+        id: usize,
+        filepath: &str,
+        line: u64,
+        character: u64,
+    ) -> Result<(), Box<std::error::Error>> {
+        let path = std::path::Path::new(filepath).canonicalize()?;
+        self.send(LSPCommand::signatureHelp {
+            id,
+            params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: url::Url::parse(&format!(
+                        "file:///{}",
+                        path.to_str().ok_or_else(|| {
+                            std::io::Error::new(std::io::ErrorKind::InvalidData, "Bad filepath")
+                        })?
+                    ))?,
+                },
+                position: Position { line, character },
+            },
+        })
+    }
+
+    fn send_code_action(
+        &mut self,
+        id: usize,
+        filepath: &str,
+        line: u64,
+        character: u64,
+    ) -> Result<(), Box<std::error::Error>> {
+        let path = std::path::Path::new(filepath).canonicalize()?;
+        let position = Position { line, character };
+        self.send(LSPCommand::codeAction {
+            id,
+            params: CodeActionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: url::Url::parse(&format!(
+                        "file:///{}",
+                        path.to_str().ok_or_else(|| {
+                            std::io::Error::new(std::io::ErrorKind::InvalidData, "Bad filepath")
+                        })?
+                    ))?,
+                },
+                range: Range {
+                    start: position,
+                    end: position,
+                },
+                context: CodeActionContext {
+                    diagnostics: vec![],
+                    only: None,
+                },
+            },
+        })
+    }
+
+    fn send_semantic_tokens(
+        &mut self,
+        id: usize,
+        filepath: &str,
+    ) -> Result<(), Box<std::error::Error>> {
+        let path = std::path::Path::new(filepath).canonicalize()?;
+        self.send(LSPCommand::semanticTokensFull {
+            id,
+            params: SemanticTokensParams {
+                text_document: TextDocumentIdentifier {
+                    uri: url::Url::parse(&format!(
+                        "file:///{}",
+                        path.to_str().ok_or_else(|| {
+                            std::io::Error::new(std::io::ErrorKind::InvalidData, "Bad filepath")
+                        })?
+                    ))?,
+                },
+            },
+        })
+    }
+
+    fn send_inlay_hint(&mut self, id: usize, filepath: &str) -> Result<(), Box<std::error::Error>> {
+        let path = std::path::Path::new(filepath).canonicalize()?;
+        self.send(LSPCommand::inlayHint {
+            id,
+            params: InlayHintParams {
+                text_document: TextDocumentIdentifier {
+                    uri: url::Url::parse(&format!(
+                        "file:///{}",
+                        path.to_str().ok_or_else(|| {
+                            std::io::Error::new(std::io::ErrorKind::InvalidData, "Bad filepath")
+                        })?
+                    ))?,
+                },
+                range: Range {
+                    start: Position::new(0, 0),
+                    end: Position::new(u64::max_value(), 0),
+                },
+            },
+        })
+    }
+
+    fn send_document_symbol(
+        &mut self,
+        id: usize,
+        filepath: &str,
+    ) -> Result<(), Box<std::error::Error>> {
+        let path = std::path::Path::new(filepath).canonicalize()?;
+        self.send(LSPCommand::documentSymbol {
+            id,
+            params: DocumentSymbolParams {
+                text_document: TextDocumentIdentifier {
+                    uri: url::Url::parse(&format!(
+                        "file:///{}",
+                        path.to_str().ok_or_else(|| {
+                            std::io::Error::new(std::io::ErrorKind::InvalidData, "Bad filepath")
+                        })?
+                    ))?,
+                },
+            },
+        })
+    }
+
+    fn send_workspace_symbol(
+        &mut self,
+        id: usize,
+        query: &str,
+    ) -> Result<(), Box<std::error::Error>> {
+        self.send(LSPCommand::workspaceSymbol {
+            id,
+            params: WorkspaceSymbolParams {
+                query: query.to_string(),
+            },
+        })
+    }
 }
@@ -1,4 +1,5 @@
 use crate::harness::test::TestContext;
+use lark_build::CodegenType;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
@@ -12,12 +13,16 @@ impl TestContext<'_> {
         self.test_path.with_extension(extension)
     }
 
-    crate fn executable_path(&self) -> PathBuf {
-        if cfg!(windows) {
-            self.output_path("exe")
-        } else {
-            self.output_path("")
-        }
+    /// The path of the executable built with the given backend; each
+    /// backend gets its own, so that they can't clobber each other.
+    crate fn executable_path(&self, codegen_type: CodegenType) -> PathBuf {
+        let extension = match (codegen_type, cfg!(windows)) {
+            (CodegenType::Rust, false) => "",
+            (CodegenType::Rust, true) => "exe",
+            (CodegenType::C, false) => "c-out",
+            (CodegenType::C, true) => "c.exe",
+        };
+        self.output_path(extension)
     }
 
     /// Generates a path for an output file with the given extension.
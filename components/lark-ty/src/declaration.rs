@@ -75,10 +75,13 @@ where
     }
 }
 
-/// For now, we only support `own T` in declarations.
+/// Types in declarations are `own T`, except for the `self` of a
+/// method, which may also be declared `share` or `borrow`.
 #[derive(Copy, Clone, Debug, DebugWith, PartialEq, Eq, Hash)]
 pub enum DeclaredPermKind {
     Own,
+    Share,
+    Borrow,
 }
 
 lark_intern::intern_tables! {
@@ -49,10 +49,13 @@ crate fn base_type_check(
     for _ in unresolved_variables {
         // FIXME: Decent diagnostics for unresolved inference
         // variables.
-        errors.push(Diagnostic::new(
-            "Unresolved variable".into(),
-            fn_body.span(fn_body.root_expression),
-        ));
+        errors.push(
+            Diagnostic::new(
+                "Unresolved variable".into(),
+                fn_body.span(fn_body.root_expression),
+            )
+            .with_code("type-error"),
+        );
     }
 
     WithError {
@@ -59,6 +59,12 @@ crate struct AnalysisIr {
     /// in an uninitialized state.
     crate local_path: Vec<Path>,
 
+    /// Paths whose value is copied, rather than moved, even when
+    /// accessed with an owned permission: globals (which can never
+    /// become uninitialized) and slots holding a scalar value such as
+    /// a `bool` or `uint`.
+    crate copy_path: Vec<Path>,
+
     crate imprecise_path: Vec<Path>,
 
     /// An "access" of the given path with the given permission takes place
@@ -97,12 +103,13 @@ crate struct AnalysisResults {
 
 impl AnalysisIr {
     crate fn new(
+        db: &impl TypeCheckDatabase,
         fn_body: &hir::FnBody,
         results: &TypeCheckResults<FullInference>,
         constraints: &FxIndexSet<ConstraintAt>,
         unify: &mut UnificationTable<FullInferenceTables, hir::MetaIndex>,
     ) -> AnalysisIr {
-        builder::AnalysisBuilder::analyze(fn_body, results, constraints, unify)
+        builder::AnalysisBuilder::analyze(db, fn_body, results, constraints, unify)
     }
 
     fn dump(&self, cx: &DumpCx<'_, impl TypeCheckDatabase>) {
@@ -113,6 +120,7 @@ impl AnalysisIr {
         cx.dump_facts("cfg_edge", self.cfg_edge.iter()).unwrap();
         cx.dump_facts("owner_path", self.owner_path.iter()).unwrap();
         cx.dump_facts("local_path", self.local_path.iter()).unwrap();
+        cx.dump_facts("copy_path", self.copy_path.iter()).unwrap();
         cx.dump_facts("imprecise_path", self.imprecise_path.iter())
             .unwrap();
         cx.dump_facts("access", self.access.iter()).unwrap();
@@ -152,7 +160,10 @@ impl AnalysisIr {
                 l => panic!("move of imprecise path at `{:?}`", l),
             };
 
-            errors.push(Diagnostic::new(format!("move of imprecise path"), span));
+            errors.push(
+                Diagnostic::new(format!("move of imprecise path"), span)
+                    .with_code("imprecise-move"),
+            );
         }
 
         for &(_path, node) in initialization.error_access_to_uninitialized_path.iter() {
@@ -162,10 +173,10 @@ impl AnalysisIr {
                 l => panic!("move of imprecise path at `{:?}`", l),
             };
 
-            errors.push(Diagnostic::new(
-                format!("access to uninitialized path"),
-                span,
-            ));
+            errors.push(
+                Diagnostic::new(format!("access to uninitialized path"), span)
+                    .with_code("uninitialized-access"),
+            );
         }
 
         AnalysisResults { perm_kinds, errors }
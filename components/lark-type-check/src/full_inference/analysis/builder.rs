@@ -9,10 +9,13 @@ use crate::full_inference::FullInferenceTables;
 use crate::full_inference::Perm;
 use crate::results::TypeCheckResults;
 use crate::HirLocation;
+use crate::TypeCheckDatabase;
 use lark_collections::map::Entry;
 use lark_collections::{FxIndexMap, FxIndexSet, IndexVec, U32Index};
 use lark_debug_with::DebugWith;
+use lark_entity::{Entity, EntityData, LangItem};
 use lark_hir as hir;
+use lark_intern::Intern;
 use lark_ty as ty;
 use lark_unify::UnificationTable;
 use std::hash::Hash;
@@ -24,10 +27,20 @@ crate struct AnalysisBuilder<'me> {
     results: &'me TypeCheckResults<FullInference>,
     unify: &'me mut UnificationTable<FullInferenceTables, hir::MetaIndex>,
     reverse_path_datas: FxIndexMap<PathData, ()>,
+
+    /// The types (`bool`, `uint`, ...) whose values are copied rather
+    /// than moved.
+    scalar_entities: FxIndexSet<Entity>,
+
+    /// For each loop we are currently building, the node that begins
+    /// an iteration (the target of `continue`) and the node that
+    /// follows the loop (the target of `break`).
+    loop_stack: Vec<(Node, Node)>,
 }
 
 impl AnalysisBuilder<'_> {
     crate fn analyze(
+        db: &impl TypeCheckDatabase,
         fn_body: &hir::FnBody,
         results: &TypeCheckResults<FullInference>,
         constraints: &FxIndexSet<ConstraintAt>,
@@ -40,6 +53,11 @@ impl AnalysisBuilder<'_> {
             constraints,
             unify,
             reverse_path_datas: Default::default(),
+            scalar_entities: [LangItem::Boolean, LangItem::Uint, LangItem::Int]
+                .iter()
+                .map(|&lang_item| EntityData::LangItem(lang_item).intern(db))
+                .collect(),
+            loop_stack: vec![],
         };
 
         let start_node = builder.push_node(HirLocation::Start);
@@ -201,10 +219,14 @@ impl AnalysisBuilder<'_> {
                     self.analysis.local_path.push(path);
                 }
 
-                PathData::Entity(_) | PathData::Field { .. } | PathData::Index { .. } => {
-                    // These paths are either initialized from a base
-                    // path, or do not need to be initialized (e.g., a
-                    // global).
+                PathData::Entity(_) => {
+                    // Globals do not need to be initialized, and
+                    // accessing them never moves out of them.
+                    self.analysis.copy_path.push(path);
+                }
+
+                PathData::Field { .. } | PathData::Index { .. } => {
+                    // These paths are initialized from a base path.
                 }
             }
 
@@ -235,6 +257,18 @@ impl AnalysisBuilder<'_> {
         }
     }
 
+    /// True if values of type `ty` are copied, rather than moved,
+    /// when accessed.
+    fn copied_on_access(&mut self, ty: ty::Ty<FullInference>) -> bool {
+        match self.unify.shallow_resolve_data(ty.base) {
+            Ok(ty::BaseData {
+                kind: ty::BaseKind::Named(entity),
+                ..
+            }) => self.scalar_entities.contains(&entity),
+            _ => false,
+        }
+    }
+
     /// Indicates that the result of `expression` is used at `node` --
     /// this will add `used` facts for all the permission variables in
     /// the type of `expression`.
@@ -318,6 +352,10 @@ impl BuildCfgNode for hir::Expression {
                 let path = builder.path(*place);
                 builder.access(perm, path, self_node);
 
+                if builder.copied_on_access(builder.results.ty(self)) {
+                    builder.analysis.copy_path.push(path);
+                }
+
                 self_node
             }
 
@@ -381,10 +419,88 @@ impl BuildCfgNode for hir::Expression {
                 join_node
             }
 
-            hir::ExpressionData::Binary { left, right, .. } => {
+            hir::ExpressionData::Match { value, arms } => {
+                let value_node = builder.build_node(start_node, value);
+
+                // We say that a `match` "executes" when the value is
+                // tested, which is also when the arms' bindings are
+                // assigned:
+                let self_node = builder.push_node_edge(value_node, self.into());
+                builder.use_result_of(self_node, *value);
+                for arm in arms.iter(builder.fn_body) {
+                    for binding in builder.fn_body[arm].bindings.iter(builder.fn_body) {
+                        let variable = builder.fn_body[binding].variable;
+                        let variable_path = builder.variable_path(variable);
+                        builder.generate_assignment_facts(variable_path, self_node);
+                    }
+                }
+
+                // Then exactly one of the arms comes afterwards, and
+                // the control-flows rejoin:
+                let join_node = builder.push_node(HirLocation::AfterExpression(self));
+                for arm in arms.iter(builder.fn_body) {
+                    let arm_node = builder.build_node(self_node, builder.fn_body[arm].body);
+                    builder.push_edge(arm_node, join_node);
+                }
+
+                join_node
+            }
+
+            hir::ExpressionData::While { condition, body } => {
+                // Each iteration begins by re-evaluating the condition:
+                let head_node = builder.push_node_edge(start_node, HirLocation::LoopHead(self));
+                let condition_node = builder.build_node(head_node, condition);
+
+                // As with `if`, the loop "executes" when the condition is tested:
+                let self_node = builder.push_node_edge(condition_node, self.into());
+                builder.use_result_of(self_node, *condition);
+
+                // If the test fails, we exit the loop:
+                let exit_node =
+                    builder.push_node_edge(self_node, HirLocation::AfterExpression(self));
+
+                // Otherwise, we execute the body and loop back around:
+                builder.loop_stack.push((head_node, exit_node));
+                let body_node = builder.build_node(self_node, body);
+                builder.loop_stack.pop();
+                builder.push_edge(body_node, head_node);
+
+                exit_node
+            }
+
+            hir::ExpressionData::Break {} | hir::ExpressionData::Continue {} => {
+                let self_node = builder.push_node_edge(start_node, self.into());
+
+                let &(head_node, exit_node) = builder
+                    .loop_stack
+                    .last()
+                    .expect("`break` or `continue` outside of a loop");
+                let target_node = match builder.fn_body[self] {
+                    hir::ExpressionData::Break {} => exit_node,
+                    _ => head_node,
+                };
+                builder.push_edge(self_node, target_node);
+
+                // Nothing after a `break` or `continue` is reachable
+                // from it, so whatever comes next starts from a fresh
+                // node with no incoming edges:
+                builder.push_node(HirLocation::AfterExpression(self))
+            }
+
+            hir::ExpressionData::Binary {
+                operator,
+                left,
+                right,
+            } => {
                 let left_node = builder.build_node(start_node, left);
                 let right_node = builder.build_node(left_node, right);
                 let self_node = builder.push_node_edge(right_node, self.into());
+
+                // `&&` and `||` may skip the right-hand side entirely:
+                if let hir::BinaryOperator::And | hir::BinaryOperator::Or = operator {
+                    builder.push_edge(left_node, self_node);
+                }
+
                 builder.use_result_of(self_node, *left);
                 builder.use_result_of(self_node, *right);
                 self_node
@@ -194,6 +194,14 @@ impl Initialization {
         // .input owned
         let owned = &kind_inference.owned;
 
+        // .decl copy_path(Path:path)
+        // .input copy_path
+        let copy_path: Relation<(Path, ())> = analysis_ir
+            .copy_path
+            .iter()
+            .map(|&path| (path, ()))
+            .collect();
+
         // .decl moved(Path:Path, Node:node)
         //
         // Indicates that the path `Path` is **moved** at the given node.
@@ -201,8 +209,13 @@ impl Initialization {
         // moved(Path, Node) :-
         //   access(Perm, Path, Node),
         //   owned(Perm),
-        let moved =
-            Relation::from_join(&access_by_perm, owned, |&_, &(path, node), &_| (path, node));
+        //   !copy_path(Path).
+        let moved = Relation::from_iter(
+            Relation::from_join(&access_by_perm, owned, |&_, &(path, node), &_| (path, node))
+                .iter()
+                .filter(|&&(path, _)| copy_path.binary_search(&(path, ())).is_err())
+                .cloned(),
+        );
         cx.dump_facts("moved", moved.iter()).unwrap();
 
         // .decl cfg_edge(Node1:node, Node2:node)
@@ -40,6 +40,7 @@ crate fn full_type_check(
     type_checker.check_fn_body();
 
     let analysis = AnalysisIr::new(
+        db,
         &fn_body,
         &type_checker.storage.results,
         &type_checker.storage.constraints,
@@ -67,10 +68,13 @@ crate fn full_type_check(
     for _ in unresolved_variables {
         // FIXME: Decent diagnostics for unresolved inference
         // variables.
-        errors.push(Diagnostic::new(
-            "Unresolved variable".into(),
-            fn_body.span(fn_body.root_expression),
-        ));
+        errors.push(
+            Diagnostic::new(
+                "Unresolved variable".into(),
+                fn_body.span(fn_body.root_expression),
+            )
+            .with_code("type-error"),
+        );
     }
 
     WithError {
@@ -244,6 +244,8 @@ impl SubstitutionDelegate<FullInference> for TypeChecker<'me, FullInference, Ful
     fn map_perm(&mut self, perm: declaration::Perm) -> Perm {
         match perm.untern(self) {
             DeclaredPermKind::Own => PermData::Known(PermKind::Own).intern(self),
+            DeclaredPermKind::Share => PermData::Known(PermKind::Share).intern(self),
+            DeclaredPermKind::Borrow => PermData::Known(PermKind::Borrow).intern(self),
         }
     }
 
@@ -259,6 +261,13 @@ impl SubstitutionDelegate<FullInference> for TypeChecker<'me, FullInference, Ful
                 // the result is just `U`.
                 ty
             }
+
+            DeclaredPermKind::Share | DeclaredPermKind::Borrow => {
+                // Otherwise, the result is `U` accessed with the
+                // declared permission.
+                let perm = self.map_perm(perm);
+                Ty { perm, ..ty }
+            }
         }
     }
 }
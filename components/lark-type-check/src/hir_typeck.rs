@@ -9,13 +9,16 @@ use lark_debug_with::DebugWith;
 use lark_entity::{Entity, EntityData, ItemKind, LangItem, MemberKind};
 use lark_error::ErrorReported;
 use lark_error::ErrorSentinel;
+use lark_error::{Diagnostic, SuggestedEdit};
 use lark_hir as hir;
 use lark_intern::Untern;
 use lark_pretty_print::PrettyPrint;
+use lark_span::{FileName, Span};
 use lark_ty::declaration::Declaration;
+use lark_ty::Generics;
 use lark_ty::Signature;
 use lark_ty::Ty;
-use lark_ty::{BaseData, BaseKind};
+use lark_ty::{BaseData, BaseKind, BoundVarOr, GenericKind};
 use lark_unify::InferVar;
 use lark_unify::Inferable;
 
@@ -145,12 +148,16 @@ where
                 function,
                 arguments,
             } => {
-                let function_ty = self.check_expression(Mode::Synthesize, function);
+                // Calling a function does not depend on the permissions
+                // with which we access it, so resolve the call using its
+                // full type (whose generics are those we must infer).
+                let function_ty = self.compute_expression_ty(Mode::Synthesize, function);
+                self.record_max_expression_ty(function, function_ty);
                 self.compute_fn_call_ty(expression, function_ty, arguments)
             }
 
             hir::ExpressionData::Aggregate { entity, fields } => {
-                self.check_aggregate(expression, entity, fields)
+                self.check_aggregate(mode, expression, entity, fields)
             }
 
             hir::ExpressionData::Sequence { first, second } => {
@@ -178,9 +185,37 @@ where
                 ty
             }
 
+            hir::ExpressionData::While { condition, body } => {
+                self.check_expression(CheckType(self.boolean_type(), expression.into()), condition);
+                self.check_expression(CheckType(self.unit_type(), expression.into()), body);
+                self.unit_type()
+            }
+
+            hir::ExpressionData::Match { value, arms } => {
+                self.check_match(mode, expression, value, arms)
+            }
+
+            hir::ExpressionData::Break {} | hir::ExpressionData::Continue {} => self.unit_type(),
+
             hir::ExpressionData::Literal { data } => match data.kind {
                 hir::LiteralKind::String => self.string_type(),
-                hir::LiteralKind::UnsignedInteger => self.uint_type(),
+                hir::LiteralKind::SignedInteger => {
+                    self.check_integer_literal(expression, data, LangItem::Int)
+                }
+                hir::LiteralKind::UnsignedInteger => {
+                    // An integer literal like `22` is an `int` if that is
+                    // what its context expects, and a `uint` otherwise.
+                    let expects_int = match mode {
+                        CheckType(expected_ty, _) => self.known_to_be_int(expected_ty),
+                        Synthesize => false,
+                    };
+                    let lang_item = if expects_int {
+                        LangItem::Int
+                    } else {
+                        LangItem::Uint
+                    };
+                    self.check_integer_literal(expression, data, lang_item)
+                }
             },
 
             hir::ExpressionData::Unit {} => self.unit_type(),
@@ -462,8 +497,49 @@ where
         self.error_type()
     }
 
+    /// An edit that adds `member` after the `fields` of the aggregate
+    /// expression at `span` (like `Foo(a: 1)`), with a default value
+    /// for its type if it has one, and otherwise a variable with the
+    /// same name. Also returns the name of the member.
+    fn missing_member_edit(
+        &self,
+        span: Span<FileName>,
+        fields: hir::List<hir::IdentifiedExpression>,
+        member: Entity,
+    ) -> Option<(String, SuggestedEdit)> {
+        let name = match member.untern(self) {
+            EntityData::MemberName { id, .. } => id.untern(&self.db).to_string(),
+            _ => return None,
+        };
+
+        let value = match self.db.ty(member).into_value().base.untern(&self.db) {
+            BoundVarOr::Known(BaseData {
+                kind: BaseKind::Named(entity),
+                ..
+            }) => match entity.untern(self) {
+                EntityData::LangItem(LangItem::Boolean) => "false".to_string(),
+                EntityData::LangItem(LangItem::Int) | EntityData::LangItem(LangItem::Uint) => {
+                    "0".to_string()
+                }
+                EntityData::LangItem(LangItem::String) => "\"\"".to_string(),
+                _ => name.clone(),
+            },
+            _ => name.clone(),
+        };
+
+        // Only a variant can have no fields, and then it has no
+        // parentheses either (like `Shape.Empty`)
+        let (end, text) = match fields.iter(&self.hir).last() {
+            Some(field) => (self.hir.span(field).end(), format!(", {}: {}", name, value)),
+            None => (span.end(), format!("({}: {})", name, value)),
+        };
+        let edit = SuggestedEdit::new(Span::new(span.file(), end, end), text);
+        Some((name, edit))
+    }
+
     fn check_aggregate(
         &mut self,
+        mode: Mode<F>,
         expression: hir::Expression,
         entity: Entity,
         fields: hir::List<hir::IdentifiedExpression>,
@@ -474,6 +550,10 @@ where
             EntityData::ItemName {
                 kind: ItemKind::Struct,
                 ..
+            }
+            | EntityData::MemberName {
+                kind: MemberKind::Variant,
+                ..
             } => {
                 // see code below
             }
@@ -513,7 +593,7 @@ where
                 .iter()
                 .filter(|f| match f.kind {
                     MemberKind::Field => true,
-                    MemberKind::Method => false,
+                    MemberKind::Method | MemberKind::Variant => false,
                 })
                 .map(|m| m.entity)
                 .collect(),
@@ -548,20 +628,232 @@ where
         }
 
         // If we are missing any members, that's an error.
-        for _missing_member in missing_members {
-            self.record_error("missing member", expression);
+        for missing_member in missing_members {
+            let span = self.hir.span(expression);
+            let mut diagnostic =
+                Diagnostic::new("missing member".to_string(), span).with_code("missing-member");
+            if let Some((name, edit)) = self.missing_member_edit(span, fields, missing_member) {
+                diagnostic = diagnostic
+                    .with_suggestion(format!("Add missing member `{}`", name), vec![edit]);
+            }
+            self.errors.push(diagnostic);
 
             // Propagate this error to the generics, since they may be
             // underconstrained as a result.
             self.propagate_error(expression, &generics);
         }
 
+        // A variant need not mention all of the generics of its enum
+        // (e.g., `Maybe.None`), in which case only the type that is
+        // expected can determine them.
+        if let (EntityData::MemberName { base, .. }, CheckType(expected_ty, location)) =
+            (entity.untern(self), mode)
+        {
+            if let Ok(BaseData {
+                kind: BaseKind::Named(expected_entity),
+                generics: expected_generics,
+            }) = self.unify.shallow_resolve_data(expected_ty.base)
+            {
+                if expected_entity == base {
+                    for (generic, expected_generic) in generics.iter().zip(&expected_generics) {
+                        let (GenericKind::Ty(ty), GenericKind::Ty(expected_ty)) =
+                            (generic, expected_generic);
+                        self.equate(expression, location, ty, expected_ty);
+                    }
+                }
+            }
+        }
+
         // The final type is the type of the entity with the given
         // generics substituted.
         let entity_ty = self.db.ty(entity).into_value();
         self.substitute(expression, &generics, entity_ty)
     }
 
+    fn check_match(
+        &mut self,
+        mode: Mode<F>,
+        expression: hir::Expression,
+        value: hir::Expression,
+        arms: hir::List<hir::MatchArm>,
+    ) -> Ty<F> {
+        let hir = &self.hir.clone();
+        let value_ty = self.check_expression(Synthesize, value);
+
+        // The enum being matched is the one whose variants the arms
+        // name. (If there are none, the arms are all `_`, and the value
+        // may be of any type.)
+        let enum_entity = arms
+            .iter_data(hir)
+            .filter_map(|arm| arm.variant)
+            .filter_map(|variant| match variant.untern(self) {
+                EntityData::MemberName {
+                    base,
+                    kind: MemberKind::Variant,
+                    ..
+                } => Some(base),
+                _ => None,
+            })
+            .next();
+
+        let generics = match enum_entity {
+            Some(enum_entity) => {
+                let generics = self.record_entity_and_get_generics(expression, enum_entity);
+                let enum_decl_ty = self.db.ty(enum_entity).into_value();
+                let enum_ty = self.substitute(expression, &generics, enum_decl_ty);
+
+                // The value may be accessed with any permissions; it is
+                // only its base type that must be the enum.
+                let expected_ty = Ty {
+                    base: enum_ty.base,
+                    ..value_ty
+                };
+                self.equate(expression, value, value_ty, expected_ty);
+
+                Some(generics)
+            }
+
+            None => None,
+        };
+
+        // Each arm must name a variant of the enum (that no previous
+        // arm has covered), and binds variables to the fields of that
+        // variant.
+        let mut covered_variants: FxIndexSet<Entity> = FxIndexSet::default();
+        let mut has_wildcard = false;
+        let mut has_error = false;
+        for arm in arms.iter(hir) {
+            let arm_data = self.hir[arm];
+
+            if has_wildcard {
+                self.record_error("unreachable match arm", arm);
+            }
+
+            match arm_data.variant {
+                None => has_wildcard = true,
+
+                Some(variant) => {
+                    let variant_data = variant.untern(self);
+                    let is_variant_of_enum = match variant_data {
+                        EntityData::MemberName { base, .. } => Some(base) == enum_entity,
+                        _ => false,
+                    };
+
+                    if let EntityData::Error(_) = variant_data {
+                        has_error = true;
+                    } else if !is_variant_of_enum {
+                        self.record_error("variant of a different enum", arm);
+                        has_error = true;
+                    } else if !covered_variants.insert(variant) && !has_wildcard {
+                        self.record_error("unreachable match arm", arm);
+                    }
+
+                    for binding in arm_data.bindings.iter(hir) {
+                        let binding_data = self.hir[binding];
+                        let field_ty = match (&generics, is_variant_of_enum) {
+                            (Some(generics), true) => self.check_match_binding(
+                                expression,
+                                value_ty,
+                                variant,
+                                generics,
+                                binding_data,
+                            ),
+                            _ => self.error_type(),
+                        };
+                        self.record_variable_ty(binding_data.variable, field_ty);
+                    }
+                }
+            }
+        }
+
+        // Unless there is a `_` arm, every variant must be covered.
+        if let Some(enum_entity) = enum_entity {
+            if !has_wildcard && !has_error {
+                self.check_match_exhaustive(expression, enum_entity, &covered_variants);
+            }
+        }
+
+        let ty = self.type_or_infer_variable(mode);
+        for arm_data in arms.iter_data(hir) {
+            self.check_expression(
+                CheckType(ty, HirLocation::AfterExpression(expression)),
+                arm_data.body,
+            );
+        }
+
+        ty
+    }
+
+    /// Helper for `check_match`: computes the type of the variable
+    /// bound to a field of `variant`, which is accessed with the
+    /// permissions of the value being matched.
+    fn check_match_binding(
+        &mut self,
+        expression: hir::Expression,
+        value_ty: Ty<F>,
+        variant: Entity,
+        generics: &Generics<F>,
+        binding_data: hir::IdentifiedVariableData,
+    ) -> Ty<F> {
+        let field_name = self.hir[binding_data.identifier].text;
+        match self.db.member_entity(variant, MemberKind::Field, field_name) {
+            Some(field_entity) => {
+                self.record_entity(binding_data.identifier, field_entity);
+
+                let field_decl_ty = self.db.ty(field_entity).into_value();
+                let field_ty = self.substitute(expression, generics, field_decl_ty);
+                self.apply_owner_perm(expression, expression, value_ty.perm, field_ty)
+            }
+
+            None => {
+                self.record_error("unknown field", binding_data.identifier);
+                self.error_type()
+            }
+        }
+    }
+
+    /// Helper for `check_match`: reports an error if some variant of
+    /// `enum_entity` is not among `covered_variants`.
+    fn check_match_exhaustive(
+        &mut self,
+        expression: hir::Expression,
+        enum_entity: Entity,
+        covered_variants: &FxIndexSet<Entity>,
+    ) {
+        let members = match self.db.members(enum_entity) {
+            Ok(members) => members,
+            Err(ErrorReported(_)) => return,
+        };
+
+        let enum_name = match enum_entity.untern(self) {
+            EntityData::ItemName { id, .. } => id,
+            _ => return,
+        };
+
+        let missing_variants: Vec<String> = members
+            .iter()
+            .filter(|member| member.kind == MemberKind::Variant)
+            .filter(|member| !covered_variants.contains(&member.entity))
+            .map(|member| {
+                format!(
+                    "`{}.{}`",
+                    enum_name.untern(&self.db),
+                    member.name.untern(&self.db),
+                )
+            })
+            .collect();
+
+        if !missing_variants.is_empty() {
+            self.record_error(
+                format!(
+                    "match is not exhaustive: missing {}",
+                    missing_variants.join(", ")
+                ),
+                expression,
+            );
+        }
+    }
+
     fn check_binary(
         &mut self,
         expression: hir::Expression,
@@ -569,12 +861,19 @@ where
         left: hir::Expression,
         right: hir::Expression,
     ) -> Ty<F> {
+        // The logical operators simply require booleans on both sides.
+        if let hir::BinaryOperator::And | hir::BinaryOperator::Or = operator {
+            let boolean_type = self.boolean_type();
+            self.check_expression(CheckType(boolean_type, expression.into()), left);
+            self.check_expression(CheckType(boolean_type, expression.into()), right);
+            return boolean_type;
+        }
+
         // For (most) binary operators, we need to know the type of
         // left + right before we can say anything about the result
         // type. So use `with_base_data` to get a callback once that is
         // known.
-        let left_ty = self.check_expression(Synthesize, left);
-        let right_ty = self.check_expression(Synthesize, right);
+        let (left_ty, right_ty) = self.check_binary_operands(left, right);
         let result_ty = self.with_base_data(
             expression,
             expression,
@@ -597,8 +896,15 @@ where
         );
 
         match operator {
-            hir::BinaryOperator::Equals | hir::BinaryOperator::NotEquals => {
-                // For the `==` and `!=` operators, we know the result
+            hir::BinaryOperator::Equals
+            | hir::BinaryOperator::NotEquals
+            | hir::BinaryOperator::LessThan
+            | hir::BinaryOperator::LessThanOrEqual
+            | hir::BinaryOperator::GreaterThan
+            | hir::BinaryOperator::GreaterThanOrEqual
+            | hir::BinaryOperator::And
+            | hir::BinaryOperator::Or => {
+                // For the comparison operators, we know the result
                 // will be boolean, so even if `result_ty` is an
                 // inference variable, we can unify it *now* rather
                 // than wait until the input types are known.
@@ -610,10 +916,93 @@ where
             hir::BinaryOperator::Add
             | hir::BinaryOperator::Subtract
             | hir::BinaryOperator::Multiply
-            | hir::BinaryOperator::Divide => result_ty,
+            | hir::BinaryOperator::Divide
+            | hir::BinaryOperator::Modulo => result_ty,
+        }
+    }
+
+    /// Synthesizes the types of the operands of a binary operator. An
+    /// integer literal on one side takes its type from the other side,
+    /// so that (e.g.) `x + 1` works when `x` is an `int`.
+    fn check_binary_operands(
+        &mut self,
+        left: hir::Expression,
+        right: hir::Expression,
+    ) -> (Ty<F>, Ty<F>) {
+        if self.is_unsigned_integer_literal(left) && !self.is_unsigned_integer_literal(right) {
+            let right_ty = self.check_expression(Synthesize, right);
+            let left_ty = self.check_literal_operand(right_ty, left);
+            (left_ty, right_ty)
+        } else {
+            let left_ty = self.check_expression(Synthesize, left);
+            let right_ty = if self.is_unsigned_integer_literal(right) {
+                self.check_literal_operand(left_ty, right)
+            } else {
+                self.check_expression(Synthesize, right)
+            };
+            (left_ty, right_ty)
+        }
+    }
+
+    fn is_unsigned_integer_literal(&self, expression: hir::Expression) -> bool {
+        match self.hir[expression] {
+            hir::ExpressionData::Literal {
+                data:
+                    hir::LiteralData {
+                        kind: hir::LiteralKind::UnsignedInteger,
+                        ..
+                    },
+            } => true,
+            _ => false,
+        }
+    }
+
+    /// Type-check the literal `literal`, using `hint` only to decide
+    /// what kind of integer it is. Any mismatch is reported by the
+    /// operator itself.
+    fn check_literal_operand(&mut self, hint: Ty<F>, literal: hir::Expression) -> Ty<F> {
+        let max_ty = self.compute_expression_ty(CheckType(hint, literal.into()), literal);
+        self.record_max_expression_ty(literal, max_ty)
+    }
+
+    /// True if `ty` is already known to be `int`.
+    fn known_to_be_int(&mut self, ty: Ty<F>) -> bool {
+        match self.unify.shallow_resolve_data(ty.base) {
+            Ok(BaseData {
+                kind: BaseKind::Named(entity),
+                ..
+            }) => match entity.untern(self) {
+                EntityData::LangItem(LangItem::Int) => true,
+                _ => false,
+            },
+            _ => false,
         }
     }
 
+    /// Computes the type (`int` or `uint`, as given by `lang_item`)
+    /// of an integer literal, reporting an error if its value does
+    /// not fit.
+    fn check_integer_literal(
+        &mut self,
+        expression: hir::Expression,
+        data: hir::LiteralData,
+        lang_item: LangItem,
+    ) -> Ty<F> {
+        let text = data.value.untern(&self.db).replace('_', "");
+        let (fits, type_name) = match lang_item {
+            LangItem::Int => (text.parse::<i32>().is_ok(), "int"),
+            _ => (text.parse::<u32>().is_ok(), "uint"),
+        };
+        if !fits {
+            self.record_error(
+                format!("integer literal out of range for `{}`", type_name),
+                expression,
+            );
+        }
+
+        self.primitive_type(lang_item)
+    }
+
     /// Invoked to check a binary operator once the base-data for the
     /// left and right types are known.
     fn check_binary_with_both_inputs_known(
@@ -626,16 +1015,36 @@ where
         let int_type = self.int_type();
         let uint_type = self.uint_type();
         let boolean_type = self.boolean_type();
+        let is_comparison = match operator {
+            hir::BinaryOperator::LessThan
+            | hir::BinaryOperator::LessThanOrEqual
+            | hir::BinaryOperator::GreaterThan
+            | hir::BinaryOperator::GreaterThanOrEqual => true,
+            _ => false,
+        };
 
         match operator {
             hir::BinaryOperator::Add
             | hir::BinaryOperator::Subtract
             | hir::BinaryOperator::Multiply
-            | hir::BinaryOperator::Divide => match (&left_base_data.kind, &right_base_data.kind) {
+            | hir::BinaryOperator::Divide
+            | hir::BinaryOperator::Modulo
+            | hir::BinaryOperator::LessThan
+            | hir::BinaryOperator::LessThanOrEqual
+            | hir::BinaryOperator::GreaterThan
+            | hir::BinaryOperator::GreaterThanOrEqual => match (
+                &left_base_data.kind,
+                &right_base_data.kind,
+            ) {
                 (BaseKind::Named(entity), BaseKind::Named(right_entity))
                     if entity == right_entity =>
                 {
                     match entity.untern(self) {
+                        EntityData::LangItem(LangItem::Int) | EntityData::LangItem(LangItem::Uint)
+                            if is_comparison =>
+                        {
+                            boolean_type
+                        }
                         EntityData::LangItem(LangItem::Int) => int_type,
                         EntityData::LangItem(LangItem::Uint) => uint_type,
                         EntityData::Error(_) => self.error_type(),
@@ -684,6 +1093,11 @@ where
                 // Either way, yields a boolean
                 boolean_type
             }
+
+            hir::BinaryOperator::And | hir::BinaryOperator::Or => {
+                // Checked eagerly by `check_binary`
+                unreachable!()
+            }
         }
     }
 
@@ -733,6 +1147,26 @@ where
                     self.error_type()
                 }
             },
+
+            hir::UnaryOperator::Negate => match &value_base_data.kind {
+                BaseKind::Named(entity) => match entity.untern(self) {
+                    EntityData::LangItem(LangItem::Int) => self.int_type(),
+
+                    EntityData::Error(_) => self.error_type(),
+
+                    _ => {
+                        self.record_error("incompatible type for '-' operator", expression);
+                        self.error_type()
+                    }
+                },
+
+                BaseKind::Error => self.error_type(),
+
+                BaseKind::Placeholder(_) => {
+                    self.record_error("unknown expression for operator", expression);
+                    self.error_type()
+                }
+            },
         }
     }
 }
@@ -234,6 +234,7 @@ enum HirLocation {
     Return,
     Expression(hir::Expression),
     AfterExpression(hir::Expression),
+    LoopHead(hir::Expression),
     Place(hir::Place),
     Error,
 }
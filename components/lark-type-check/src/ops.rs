@@ -74,7 +74,7 @@ where
         F::error_type(self)
     }
 
-    fn primitive_type(&self, item: LangItem) -> Ty<F> {
+    crate fn primitive_type(&self, item: LangItem) -> Ty<F> {
         let entity = EntityData::LangItem(item).intern(self);
         Ty {
             repr: F::direct_repr(self),
@@ -96,7 +96,8 @@ where
         location: impl Into<hir::MetaIndex>,
     ) {
         let span = self.hir.span(location.into());
-        self.errors.push(Diagnostic::new(label.into(), span));
+        self.errors
+            .push(Diagnostic::new(label.into(), span).with_code("type-error"));
     }
 
     crate fn own_perm(&mut self) -> F::Perm {
@@ -221,17 +221,32 @@ impl<Interners, Cause> UnificationTable<Interners, Cause> {
             }
 
             (Err(var1), Ok(_)) => {
-                self.bind_unbound_var_to_value(cause, var1, Value::cast_from(key2));
+                let value2 = self.known_value(key2);
+                self.bind_unbound_var_to_value(cause, var1, value2);
                 Ok(())
             }
 
             (Ok(_), Err(var2)) => {
-                self.bind_unbound_var_to_value(cause, var2, Value::cast_from(key1));
+                let value1 = self.known_value(key1);
+                self.bind_unbound_var_to_value(cause, var2, value1);
                 Ok(())
             }
         }
     }
 
+    /// Given a `key` that is known to have a value, returns that value. If
+    /// `key` is an inference variable, this is the value it was bound to
+    /// (so that we never bind a variable to another variable).
+    fn known_value<K>(&mut self, key: K) -> Value
+    where
+        K: Inferable<Interners>,
+    {
+        match key.as_infer_var(&self.interners) {
+            Some(var) => self.probe(var).unwrap(),
+            None => Value::cast_from(key),
+        }
+    }
+
     /// Creates a new inference variable.
     fn new_infer_var(&mut self) -> InferVar {
         self.trace.push(None);
@@ -312,7 +327,7 @@ impl<Interners, Cause> UnificationTable<Interners, Cause> {
         root_to: InferVar,
         rank_to: Rank,
     ) {
-        assert!(self.trace[index_from].is_none());
+        assert!(self.trace[root_from].is_none());
 
         self.infers[root_from] = InferData::Redirect(root_to);
         self.trace[root_from] = Some(UnificationTrace {
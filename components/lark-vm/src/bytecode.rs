@@ -0,0 +1,163 @@
+//! The bytecode executed by the VM. A `Program` is self-contained:
+//! it refers to functions, types and constants by their index within
+//! the program (rather than by entity), so that it can be saved and
+//! loaded again by a later run.
+
+/// The index of a function in `Program::functions`.
+pub type FunctionIndex = u32;
+
+/// The index of a type (a struct or enum variant) in `Program::types`.
+pub type TypeIndex = u32;
+
+/// The index of a string in `Program::strings`.
+pub type StringIndex = u32;
+
+/// The index of a local variable slot within the current frame. The
+/// first slots of a frame hold the function's parameters.
+pub type Slot = u32;
+
+/// The index of an instruction within the current function.
+pub type Label = u32;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Program {
+    /// Every function that the program may call.
+    pub functions: Vec<Function>,
+
+    /// The structs and enum variants that the program constructs.
+    pub types: Vec<TypeInfo>,
+
+    /// String literals, the messages for `Instruction::Panic` and the
+    /// names of the files in `SourceSpan`s.
+    pub strings: Vec<String>,
+
+    /// The `main` function of each file, in the order that they run.
+    pub entry_points: Vec<FunctionIndex>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    /// The name of the function, for use in diagnostics.
+    pub name: String,
+
+    /// The number of parameters, which occupy the first slots.
+    pub parameters: u32,
+
+    /// The total number of slots (including the parameters).
+    pub slots: u32,
+
+    pub code: Vec<Instruction>,
+
+    /// Where in the source each instruction that can fault (or that
+    /// calls a function) came from, by the label of the instruction
+    /// and in order of label. This lets a fault say where it happened
+    /// and which calls led to it.
+    pub spans: Vec<(Label, SourceSpan)>,
+}
+
+impl Function {
+    /// The source of the instruction at `label`, if it was recorded.
+    pub fn span(&self, label: Label) -> Option<SourceSpan> {
+        let index = self
+            .spans
+            .binary_search_by_key(&label, |&(label, _)| label)
+            .ok()?;
+        Some(self.spans[index].1)
+    }
+}
+
+/// A range of bytes in a source file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SourceSpan {
+    /// The name of the file, as an index into `Program::strings`.
+    pub file: StringIndex,
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeInfo {
+    /// The name of the struct (`Point`) or variant (`Shape.Circle`).
+    pub name: String,
+
+    /// The names of the fields, in declaration order.
+    pub fields: Vec<String>,
+}
+
+/// Each instruction pops its operands from the operand stack and
+/// pushes its result (if any).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// Pushes `()`.
+    Unit,
+    Bool(bool),
+    U32(u32),
+    I32(i32),
+    Str(StringIndex),
+
+    /// Pushes the value of a slot.
+    Load(Slot),
+
+    /// Pops a value into a slot.
+    Store(Slot),
+
+    Pop,
+
+    /// Swaps the two values on top of the stack.
+    Swap,
+
+    /// Pops the values of `fields` fields (pushed in declaration
+    /// order) and pushes a new struct of the given type.
+    MakeStruct {
+        ty: TypeIndex,
+        fields: u32,
+    },
+
+    /// Pops a struct and pushes the value of its field.
+    GetField(u32),
+
+    /// Pops a value and then a struct; pushes a copy of the struct
+    /// with the field replaced by the value.
+    SetField(u32),
+
+    /// Pops a struct and pushes whether it has the given type (used
+    /// to test the variant of an enum).
+    IsType(TypeIndex),
+
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Equals,
+    NotEquals,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    Not,
+    Negate,
+
+    Jump(Label),
+
+    /// Pops a boolean, jumping if it is false.
+    JumpIfFalse(Label),
+
+    /// Pops the arguments and calls the function, pushing its result.
+    /// If `write_back` is set, the final value of the first parameter
+    /// is pushed afterwards (this is how a `borrow self` method hands
+    /// back its modified `self`).
+    Call {
+        function: FunctionIndex,
+        write_back: bool,
+    },
+
+    /// Pops the result of the function and returns it.
+    Return,
+
+    /// Pops a value and prints it, as `debug` does.
+    Print,
+
+    /// Aborts execution with the given message.
+    Panic(StringIndex),
+}
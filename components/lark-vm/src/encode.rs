@@ -0,0 +1,316 @@
+//! The binary format for a `Program`: a header (`MAGIC` followed by
+//! `VERSION`), and then the program itself, with all integers in
+//! little-endian order and each sequence preceded by its length.
+
+use crate::bytecode::{Function, Instruction, Program, SourceSpan, TypeInfo};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+mod test;
+
+const MAGIC: &[u8; 4] = b"LKBC";
+
+/// The version of the format. Bump this whenever the format (or the
+/// meaning of an instruction) changes, so that stale cached programs
+/// are rejected.
+pub const VERSION: u32 = 2;
+
+pub fn encode(program: &Program, writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_u32::<LittleEndian>(VERSION)?;
+
+    write_seq(writer, &program.functions, write_function)?;
+    write_seq(writer, &program.types, write_type)?;
+    write_seq(writer, &program.strings, |writer, string| {
+        write_string(writer, string)
+    })?;
+    write_seq(writer, &program.entry_points, |writer, &function| {
+        writer.write_u32::<LittleEndian>(function)
+    })?;
+
+    Ok(())
+}
+
+pub fn decode(reader: &mut impl Read) -> io::Result<Program> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a Lark bytecode file"));
+    }
+
+    if reader.read_u32::<LittleEndian>()? != VERSION {
+        return Err(invalid_data("unsupported bytecode version"));
+    }
+
+    let program = Program {
+        functions: read_seq(reader, read_function)?,
+        types: read_seq(reader, read_type)?,
+        strings: read_seq(reader, read_string)?,
+        entry_points: read_seq(reader, |reader| reader.read_u32::<LittleEndian>())?,
+    };
+
+    validate(&program)?;
+
+    Ok(program)
+}
+
+/// Checks that every index in `program` is in bounds and that no
+/// function can run off its end, so that a corrupt file is rejected up
+/// front rather than when it runs. (What depends on the values at run
+/// time -- the types of operands, the depth of the stack and the
+/// fields of a struct -- the `Vm` checks as it goes.)
+fn validate(program: &Program) -> io::Result<()> {
+    let function_count = program.functions.len() as u32;
+    let type_count = program.types.len() as u32;
+    let string_count = program.strings.len() as u32;
+
+    if program.entry_points.iter().any(|&f| f >= function_count) {
+        return Err(invalid_data("entry point out of range"));
+    }
+
+    for function in &program.functions {
+        let code_len = function.code.len() as u32;
+        if function.parameters > function.slots {
+            return Err(invalid_data("more parameters than slots"));
+        }
+
+        for instruction in &function.code {
+            let in_bounds = match *instruction {
+                Instruction::Str(string) | Instruction::Panic(string) => string < string_count,
+                Instruction::Load(slot) | Instruction::Store(slot) => slot < function.slots,
+                Instruction::MakeStruct { ty, fields } => {
+                    ty < type_count && fields as usize == program.types[ty as usize].fields.len()
+                }
+                Instruction::IsType(ty) => ty < type_count,
+                Instruction::Jump(label) | Instruction::JumpIfFalse(label) => label < code_len,
+                Instruction::Call {
+                    function,
+                    write_back,
+                } => {
+                    // Writing back needs a `self` parameter to write back
+                    function < function_count
+                        && (!write_back || program.functions[function as usize].parameters > 0)
+                }
+                _ => true,
+            };
+
+            if !in_bounds {
+                return Err(invalid_data("index out of range"));
+            }
+        }
+
+        match function.code.last() {
+            Some(Instruction::Return)
+            | Some(Instruction::Jump(_))
+            | Some(Instruction::Panic(_)) => {}
+            _ => return Err(invalid_data("function does not end in a return")),
+        }
+
+        let spans_in_bounds = function
+            .spans
+            .iter()
+            .all(|&(label, span)| label < code_len && span.file < string_count);
+        let spans_in_order = function.spans.windows(2).all(|w| w[0].0 < w[1].0);
+        if !spans_in_bounds || !spans_in_order {
+            return Err(invalid_data("bad span table"));
+        }
+    }
+
+    Ok(())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_seq<W: Write, T>(
+    writer: &mut W,
+    items: &[T],
+    mut write_item: impl FnMut(&mut W, &T) -> io::Result<()>,
+) -> io::Result<()> {
+    writer.write_u32::<LittleEndian>(items.len() as u32)?;
+    for item in items {
+        write_item(writer, item)?;
+    }
+    Ok(())
+}
+
+fn read_seq<R: Read, T>(
+    reader: &mut R,
+    mut read_item: impl FnMut(&mut R) -> io::Result<T>,
+) -> io::Result<Vec<T>> {
+    let len = reader.read_u32::<LittleEndian>()?;
+
+    // Don't trust `len` for the initial allocation: a corrupt file
+    // would otherwise make us allocate huge amounts of memory
+    let mut items = Vec::with_capacity(len.min(1024) as usize);
+    for _ in 0..len {
+        items.push(read_item(reader)?);
+    }
+    Ok(items)
+}
+
+fn write_string(writer: &mut impl Write, string: &str) -> io::Result<()> {
+    writer.write_u32::<LittleEndian>(string.len() as u32)?;
+    writer.write_all(string.as_bytes())
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    let bytes = read_seq(reader, |reader| reader.read_u8())?;
+    String::from_utf8(bytes).map_err(|_| invalid_data("string is not valid UTF-8"))
+}
+
+fn write_function(writer: &mut impl Write, function: &Function) -> io::Result<()> {
+    write_string(writer, &function.name)?;
+    writer.write_u32::<LittleEndian>(function.parameters)?;
+    writer.write_u32::<LittleEndian>(function.slots)?;
+    write_seq(writer, &function.code, |writer, &instruction| {
+        write_instruction(writer, instruction)
+    })?;
+    write_seq(writer, &function.spans, |writer, &(label, span)| {
+        writer.write_u32::<LittleEndian>(label)?;
+        writer.write_u32::<LittleEndian>(span.file)?;
+        writer.write_u32::<LittleEndian>(span.start)?;
+        writer.write_u32::<LittleEndian>(span.end)
+    })
+}
+
+fn read_function(reader: &mut impl Read) -> io::Result<Function> {
+    Ok(Function {
+        name: read_string(reader)?,
+        parameters: reader.read_u32::<LittleEndian>()?,
+        slots: reader.read_u32::<LittleEndian>()?,
+        code: read_seq(reader, read_instruction)?,
+        spans: read_seq(reader, |reader| {
+            let label = reader.read_u32::<LittleEndian>()?;
+            let span = SourceSpan {
+                file: reader.read_u32::<LittleEndian>()?,
+                start: reader.read_u32::<LittleEndian>()?,
+                end: reader.read_u32::<LittleEndian>()?,
+            };
+            Ok((label, span))
+        })?,
+    })
+}
+
+fn write_type(writer: &mut impl Write, ty: &TypeInfo) -> io::Result<()> {
+    write_string(writer, &ty.name)?;
+    write_seq(writer, &ty.fields, |writer, field| {
+        write_string(writer, field)
+    })
+}
+
+fn read_type(reader: &mut impl Read) -> io::Result<TypeInfo> {
+    Ok(TypeInfo {
+        name: read_string(reader)?,
+        fields: read_seq(reader, read_string)?,
+    })
+}
+
+fn write_instruction(writer: &mut impl Write, instruction: Instruction) -> io::Result<()> {
+    // Each instruction is its opcode followed by its operands (if any)
+    let (opcode, operand) = match instruction {
+        Instruction::Unit => (0, None),
+        Instruction::Bool(false) => (1, None),
+        Instruction::Bool(true) => (2, None),
+        Instruction::U32(value) => (3, Some(value)),
+        Instruction::I32(value) => (4, Some(value as u32)),
+        Instruction::Str(string) => (5, Some(string)),
+        Instruction::Load(slot) => (6, Some(slot)),
+        Instruction::Store(slot) => (7, Some(slot)),
+        Instruction::Pop => (8, None),
+        Instruction::Swap => (9, None),
+        Instruction::MakeStruct { ty, fields } => {
+            writer.write_u8(10)?;
+            writer.write_u32::<LittleEndian>(ty)?;
+            return writer.write_u32::<LittleEndian>(fields);
+        }
+        Instruction::GetField(field) => (11, Some(field)),
+        Instruction::SetField(field) => (12, Some(field)),
+        Instruction::IsType(ty) => (13, Some(ty)),
+        Instruction::Add => (14, None),
+        Instruction::Subtract => (15, None),
+        Instruction::Multiply => (16, None),
+        Instruction::Divide => (17, None),
+        Instruction::Modulo => (18, None),
+        Instruction::Equals => (19, None),
+        Instruction::NotEquals => (20, None),
+        Instruction::LessThan => (21, None),
+        Instruction::LessThanOrEqual => (22, None),
+        Instruction::GreaterThan => (23, None),
+        Instruction::GreaterThanOrEqual => (24, None),
+        Instruction::Not => (25, None),
+        Instruction::Negate => (26, None),
+        Instruction::Jump(label) => (27, Some(label)),
+        Instruction::JumpIfFalse(label) => (28, Some(label)),
+        Instruction::Call {
+            function,
+            write_back: false,
+        } => (29, Some(function)),
+        Instruction::Call {
+            function,
+            write_back: true,
+        } => (30, Some(function)),
+        Instruction::Return => (31, None),
+        Instruction::Print => (32, None),
+        Instruction::Panic(message) => (33, Some(message)),
+    };
+
+    writer.write_u8(opcode)?;
+    if let Some(operand) = operand {
+        writer.write_u32::<LittleEndian>(operand)?;
+    }
+    Ok(())
+}
+
+fn read_instruction(reader: &mut impl Read) -> io::Result<Instruction> {
+    let opcode = reader.read_u8()?;
+    let mut operand = || reader.read_u32::<LittleEndian>();
+
+    Ok(match opcode {
+        0 => Instruction::Unit,
+        1 => Instruction::Bool(false),
+        2 => Instruction::Bool(true),
+        3 => Instruction::U32(operand()?),
+        4 => Instruction::I32(operand()? as i32),
+        5 => Instruction::Str(operand()?),
+        6 => Instruction::Load(operand()?),
+        7 => Instruction::Store(operand()?),
+        8 => Instruction::Pop,
+        9 => Instruction::Swap,
+        10 => Instruction::MakeStruct {
+            ty: operand()?,
+            fields: operand()?,
+        },
+        11 => Instruction::GetField(operand()?),
+        12 => Instruction::SetField(operand()?),
+        13 => Instruction::IsType(operand()?),
+        14 => Instruction::Add,
+        15 => Instruction::Subtract,
+        16 => Instruction::Multiply,
+        17 => Instruction::Divide,
+        18 => Instruction::Modulo,
+        19 => Instruction::Equals,
+        20 => Instruction::NotEquals,
+        21 => Instruction::LessThan,
+        22 => Instruction::LessThanOrEqual,
+        23 => Instruction::GreaterThan,
+        24 => Instruction::GreaterThanOrEqual,
+        25 => Instruction::Not,
+        26 => Instruction::Negate,
+        27 => Instruction::Jump(operand()?),
+        28 => Instruction::JumpIfFalse(operand()?),
+        29 => Instruction::Call {
+            function: operand()?,
+            write_back: false,
+        },
+        30 => Instruction::Call {
+            function: operand()?,
+            write_back: true,
+        },
+        31 => Instruction::Return,
+        32 => Instruction::Print,
+        33 => Instruction::Panic(operand()?),
+        _ => return Err(invalid_data("unknown opcode")),
+    })
+}
@@ -0,0 +1,252 @@
+#![cfg(test)]
+
+use crate::bytecode::{Function, Instruction, Program, SourceSpan, TypeInfo};
+use crate::encode::{decode, encode, VERSION};
+use std::io;
+
+/// A program that uses every kind of instruction.
+fn sample_program() -> Program {
+    let span = SourceSpan {
+        file: 0,
+        start: 3,
+        end: 7,
+    };
+
+    let main = Function {
+        name: "main".to_string(),
+        parameters: 0,
+        slots: 1,
+        code: vec![
+            Instruction::Unit,
+            Instruction::Bool(false),
+            Instruction::Bool(true),
+            Instruction::U32(u32::max_value()),
+            Instruction::I32(-22),
+            Instruction::Str(1),
+            Instruction::Load(0),
+            Instruction::Store(0),
+            Instruction::Pop,
+            Instruction::Swap,
+            Instruction::MakeStruct { ty: 0, fields: 2 },
+            Instruction::GetField(1),
+            Instruction::SetField(1),
+            Instruction::IsType(0),
+            Instruction::Add,
+            Instruction::Subtract,
+            Instruction::Multiply,
+            Instruction::Divide,
+            Instruction::Modulo,
+            Instruction::Equals,
+            Instruction::NotEquals,
+            Instruction::LessThan,
+            Instruction::LessThanOrEqual,
+            Instruction::GreaterThan,
+            Instruction::GreaterThanOrEqual,
+            Instruction::Not,
+            Instruction::Negate,
+            Instruction::Jump(0),
+            Instruction::JumpIfFalse(29),
+            Instruction::Call {
+                function: 1,
+                write_back: false,
+            },
+            Instruction::Call {
+                function: 1,
+                write_back: true,
+            },
+            Instruction::Print,
+            Instruction::Panic(2),
+            Instruction::Return,
+        ],
+        spans: vec![(14, span), (29, span)],
+    };
+
+    let method = Function {
+        name: "Point.x".to_string(),
+        parameters: 1,
+        slots: 1,
+        code: vec![Instruction::Load(0), Instruction::Return],
+        spans: vec![],
+    };
+
+    Program {
+        functions: vec![main, method],
+        types: vec![TypeInfo {
+            name: "Point".to_string(),
+            fields: vec!["x".to_string(), "y".to_string()],
+        }],
+        strings: vec![
+            "main.lark".to_string(),
+            "🦀".to_string(),
+            "oh no".to_string(),
+        ],
+        entry_points: vec![0],
+    }
+}
+
+fn encoded(program: &Program) -> Vec<u8> {
+    let mut bytes = vec![];
+    encode(program, &mut bytes).unwrap();
+    bytes
+}
+
+/// Asserts that `program` is encoded just fine, but rejected when
+/// decoded.
+fn assert_rejected(program: Program) {
+    let error = decode(&mut &encoded(&program)[..]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", error);
+}
+
+#[test]
+fn round_trip() {
+    let program = sample_program();
+    assert_eq!(decode(&mut &encoded(&program)[..]).unwrap(), program);
+}
+
+#[test]
+fn empty_program() {
+    let program = Program::default();
+    assert_eq!(decode(&mut &encoded(&program)[..]).unwrap(), program);
+}
+
+#[test]
+fn truncated() {
+    let bytes = encoded(&sample_program());
+    for len in 0..bytes.len() {
+        assert!(decode(&mut &bytes[..len]).is_err(), "decoded {} bytes", len);
+    }
+}
+
+#[test]
+fn bad_header() {
+    let mut bytes = encoded(&sample_program());
+    bytes[0] = b'X';
+    assert!(decode(&mut &bytes[..]).is_err());
+
+    let mut bytes = encoded(&sample_program());
+    bytes[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    assert!(decode(&mut &bytes[..]).is_err());
+}
+
+#[test]
+fn unknown_opcode() {
+    let program = Program {
+        functions: vec![Function {
+            name: "f".to_string(),
+            parameters: 0,
+            slots: 0,
+            code: vec![Instruction::Return],
+            spans: vec![],
+        }],
+        ..Program::default()
+    };
+    let mut bytes = encoded(&program);
+
+    // The header, the number of functions, the name, the parameters,
+    // the slots and the length of the code come first
+    let opcode = 8 + 4 + (4 + 1) + 4 + 4 + 4;
+    assert_eq!(bytes[opcode], 31);
+    bytes[opcode] = 200;
+    assert!(decode(&mut &bytes[..]).is_err());
+}
+
+#[test]
+fn huge_length() {
+    // A corrupt length must not make us allocate (or read) forever
+    let mut bytes = encoded(&Program::default());
+    bytes[8..12].copy_from_slice(&u32::max_value().to_le_bytes());
+    assert!(decode(&mut &bytes[..]).is_err());
+}
+
+#[test]
+fn invalid_utf8() {
+    let mut program = sample_program();
+    program.strings[2] = "\u{7f}".to_string();
+    let mut bytes = encoded(&program);
+    let index = bytes.iter().rposition(|&byte| byte == 0x7f).unwrap();
+    bytes[index] = 0xff;
+    assert!(decode(&mut &bytes[..]).is_err());
+}
+
+#[test]
+fn index_out_of_range() {
+    let with_instruction = |instruction| {
+        let mut program = sample_program();
+        program.functions[0].code.insert(0, instruction);
+        program.functions[0].spans.clear();
+        program
+    };
+
+    assert_rejected(with_instruction(Instruction::Str(3)));
+    assert_rejected(with_instruction(Instruction::Panic(3)));
+    assert_rejected(with_instruction(Instruction::Load(1)));
+    assert_rejected(with_instruction(Instruction::Store(1)));
+    assert_rejected(with_instruction(Instruction::IsType(1)));
+    assert_rejected(with_instruction(Instruction::MakeStruct {
+        ty: 1,
+        fields: 2,
+    }));
+    assert_rejected(with_instruction(Instruction::Jump(100)));
+    assert_rejected(with_instruction(Instruction::JumpIfFalse(100)));
+    assert_rejected(with_instruction(Instruction::Call {
+        function: 2,
+        write_back: false,
+    }));
+
+    let mut program = sample_program();
+    program.entry_points.push(2);
+    assert_rejected(program);
+}
+
+#[test]
+fn wrong_number_of_fields() {
+    let mut program = sample_program();
+    program.functions[0].code[10] = Instruction::MakeStruct { ty: 0, fields: 3 };
+    assert_rejected(program);
+}
+
+#[test]
+fn more_parameters_than_slots() {
+    let mut program = sample_program();
+    program.functions[1].parameters = 2;
+    assert_rejected(program);
+}
+
+#[test]
+fn write_back_without_self() {
+    let mut program = sample_program();
+    program.functions[1].parameters = 0;
+    assert_rejected(program);
+}
+
+#[test]
+fn no_return() {
+    let mut program = sample_program();
+    program.functions[1].code.pop();
+    assert_rejected(program);
+
+    let mut program = sample_program();
+    program.functions[1].code.clear();
+    assert_rejected(program);
+}
+
+#[test]
+fn bad_span_table() {
+    let span = SourceSpan {
+        file: 0,
+        start: 0,
+        end: 1,
+    };
+
+    let mut program = sample_program();
+    program.functions[1].spans = vec![(2, span)];
+    assert_rejected(program);
+
+    let mut program = sample_program();
+    program.functions[1].spans = vec![(0, SourceSpan { file: 3, ..span })];
+    assert_rejected(program);
+
+    let mut program = sample_program();
+    program.functions[1].spans = vec![(1, span), (0, span)];
+    assert_rejected(program);
+}
@@ -0,0 +1,26 @@
+//! A bytecode compiler and virtual machine for Lark. Rather than
+//! walking the HIR as `lark_eval` does, we lower each function into a
+//! compact stack bytecode (see `bytecode`) once, which the `Vm` then
+//! executes. A compiled `Program` doesn't refer to the database, so
+//! it can be saved (see `encode`) and run again later without
+//! recompiling.
+
+#![feature(crate_visibility_modifier)]
+#![feature(in_band_lifetimes)]
+
+pub mod bytecode;
+mod encode;
+mod lower;
+mod vm;
+
+pub use crate::bytecode::Program;
+pub use crate::encode::{decode, encode, VERSION};
+pub use crate::lower::compile;
+pub use crate::vm::{Error, Fault, FaultCall, StructValue, Value, Vm};
+
+use std::io::Write;
+
+/// Runs `program`, sending its `debug` output to `output`.
+pub fn run(program: &Program, output: impl Write) -> Result<(), Error> {
+    Vm::new(program, output).run()
+}
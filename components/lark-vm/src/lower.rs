@@ -0,0 +1,810 @@
+//! Lowers the type-checked HIR of each function into bytecode.
+
+use crate::bytecode::{
+    Function, FunctionIndex, Instruction, Label, Program, Slot, SourceSpan, StringIndex, TypeIndex,
+    TypeInfo,
+};
+use lark_collections::{FxIndexMap, FxIndexSet};
+use lark_entity::{Entity, EntityData, ItemKind, LangItem, MemberKind};
+use lark_hir as hir;
+use lark_intern::{Intern, Untern};
+use lark_parser::{ParserDatabase, ParserDatabaseExt};
+use lark_query_system::LarkDatabase;
+use lark_span::{FileName, Span};
+use lark_ty::base_inferred::BaseInferred;
+use lark_ty::declaration::DeclaredPermKind;
+use lark_ty::{BaseData, BaseKind};
+use lark_type_check::{TypeCheckDatabase, TypeCheckResults};
+use std::sync::Arc;
+
+/// Compiles the `main` function of each file (in file order, as
+/// `lark_eval` runs them), along with everything that they call.
+pub fn compile(db: &LarkDatabase) -> Program {
+    let mut builder = ProgramBuilder {
+        db,
+        functions: FxIndexSet::default(),
+        types: FxIndexSet::default(),
+        strings: FxIndexSet::default(),
+        program: Program::default(),
+    };
+
+    let main_name = "main".intern(db);
+    for &input_file in &*db.file_names() {
+        for &entity in &*db.top_level_entities_in_file(input_file) {
+            match entity.untern(db) {
+                EntityData::ItemName {
+                    kind: ItemKind::Function,
+                    id,
+                    ..
+                } if id == main_name => {
+                    let function = builder.function_index(entity);
+                    builder.program.entry_points.push(function);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Lowering a function may discover new functions to lower
+    while builder.program.functions.len() < builder.functions.len() {
+        let entity = *builder
+            .functions
+            .get_index(builder.program.functions.len())
+            .unwrap();
+        let function = builder.lower_function(entity);
+        builder.program.functions.push(function);
+    }
+
+    builder.program.types = builder
+        .types
+        .iter()
+        .map(|&entity| TypeInfo {
+            name: entity_name(db, entity),
+            fields: field_entities(db, entity)
+                .iter()
+                .map(|&field| match field.untern(db) {
+                    EntityData::MemberName { id, .. } => id.untern(db).to_string(),
+                    _ => unreachable!(),
+                })
+                .collect(),
+        })
+        .collect();
+    builder.program.strings = builder.strings.into_iter().collect();
+
+    builder.program
+}
+
+/// The name of a function or type, for use at runtime: `foo`, or
+/// `Foo.bar` for a member.
+fn entity_name(db: &LarkDatabase, entity: Entity) -> String {
+    match entity.untern(db) {
+        EntityData::ItemName { id, .. } => id.untern(db).to_string(),
+        EntityData::MemberName { base, id, .. } => {
+            format!("{}.{}", entity_name(db, base), id.untern(db))
+        }
+        _ => String::from("<error>"),
+    }
+}
+
+/// The fields of a struct or variant, in declaration order.
+fn field_entities(db: &LarkDatabase, entity: Entity) -> Vec<Entity> {
+    match db.members(entity) {
+        Ok(members) => members
+            .iter()
+            .map(|member| member.entity)
+            .filter(|member| match member.untern(db) {
+                EntityData::MemberName {
+                    kind: MemberKind::Field,
+                    ..
+                } => true,
+                _ => false,
+            })
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+struct ProgramBuilder<'db> {
+    db: &'db LarkDatabase,
+
+    /// Every function that we have referenced, in the order of their
+    /// `FunctionIndex`; those not yet in `program.functions` have yet
+    /// to be lowered.
+    functions: FxIndexSet<Entity>,
+    types: FxIndexSet<Entity>,
+    strings: FxIndexSet<String>,
+    program: Program,
+}
+
+impl ProgramBuilder<'db> {
+    fn function_index(&mut self, entity: Entity) -> FunctionIndex {
+        self.functions.insert_full(entity).0 as FunctionIndex
+    }
+
+    fn type_index(&mut self, entity: Entity) -> TypeIndex {
+        self.types.insert_full(entity).0 as TypeIndex
+    }
+
+    fn string_index(&mut self, string: &str) -> StringIndex {
+        match self.strings.get_full(string) {
+            Some((index, _)) => index as StringIndex,
+            None => self.strings.insert_full(string.to_string()).0 as StringIndex,
+        }
+    }
+
+    fn lower_function(&mut self, entity: Entity) -> Function {
+        let db = self.db;
+        let fn_body = db.fn_body(entity).into_value();
+        let results = db.base_type_check(entity).into_value();
+
+        let mut builder = FunctionBuilder {
+            program: self,
+            fn_body: &fn_body,
+            results,
+            slots: FxIndexMap::default(),
+            slot_count: 0,
+            code: vec![],
+            spans: vec![],
+            span: fn_body.span(fn_body.root_expression),
+            depth: 0,
+            loops: vec![],
+        };
+
+        let parameters = match &fn_body.arguments {
+            Ok(arguments) => {
+                for argument in arguments.iter(&fn_body) {
+                    builder.slot(argument);
+                }
+                arguments.len() as u32
+            }
+            Err(_) => 0,
+        };
+
+        builder.lower_expression(fn_body.root_expression);
+        builder.emit(Instruction::Return);
+
+        Function {
+            name: entity_name(db, entity),
+            parameters,
+            slots: builder.slot_count,
+            code: builder.code,
+            spans: builder.spans,
+        }
+    }
+}
+
+struct FunctionBuilder<'me, 'db> {
+    program: &'me mut ProgramBuilder<'db>,
+    fn_body: &'me hir::FnBody,
+    results: Arc<TypeCheckResults<BaseInferred>>,
+
+    /// The slot assigned to each variable.
+    slots: FxIndexMap<hir::Variable, Slot>,
+
+    /// The number of slots, including temporaries (which aren't in
+    /// `slots`).
+    slot_count: u32,
+
+    code: Vec<Instruction>,
+
+    /// See `Function::spans`.
+    spans: Vec<(Label, SourceSpan)>,
+
+    /// The expression whose own code is being emitted.
+    span: Span<FileName>,
+
+    /// How many values are on the operand stack at this point in the
+    /// code; `break` and `continue` use this to clean up the values
+    /// pushed since the start of the loop.
+    depth: u32,
+
+    /// The loops that enclose the code being lowered, innermost last.
+    loops: Vec<Loop>,
+}
+
+struct Loop {
+    /// Where `continue` jumps to.
+    start: Label,
+
+    /// The `Jump` instructions for each `break`, which are patched to
+    /// jump to the end of the loop once it is known.
+    breaks: Vec<usize>,
+
+    /// The stack depth at the start of each iteration.
+    depth: u32,
+}
+
+impl FunctionBuilder<'_, 'db> {
+    fn db(&self) -> &'db LarkDatabase {
+        self.program.db
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        let (pops, pushes) = match instruction {
+            Instruction::Unit
+            | Instruction::Bool(_)
+            | Instruction::U32(_)
+            | Instruction::I32(_)
+            | Instruction::Str(_)
+            | Instruction::Load(_) => (0, 1),
+            Instruction::Store(_)
+            | Instruction::Pop
+            | Instruction::Print
+            | Instruction::JumpIfFalse(_)
+            | Instruction::Return => (1, 0),
+            Instruction::Swap
+            | Instruction::Jump(_)
+            | Instruction::Panic(_)
+            | Instruction::GetField(_)
+            | Instruction::IsType(_)
+            | Instruction::Not
+            | Instruction::Negate => (0, 0),
+            Instruction::SetField(_)
+            | Instruction::Add
+            | Instruction::Subtract
+            | Instruction::Multiply
+            | Instruction::Divide
+            | Instruction::Modulo
+            | Instruction::Equals
+            | Instruction::NotEquals
+            | Instruction::LessThan
+            | Instruction::LessThanOrEqual
+            | Instruction::GreaterThan
+            | Instruction::GreaterThanOrEqual => (2, 1),
+            Instruction::MakeStruct { fields, .. } => (fields, 1),
+            Instruction::Call {
+                function,
+                write_back,
+            } => {
+                let entity = *self.program.functions.get_index(function as usize).unwrap();
+                let parameters = match &self.db().fn_body(entity).value.arguments {
+                    Ok(arguments) => arguments.len() as u32,
+                    Err(_) => 0,
+                };
+                (parameters, if write_back { 2 } else { 1 })
+            }
+        };
+
+        let records_span = match instruction {
+            Instruction::Add
+            | Instruction::Subtract
+            | Instruction::Multiply
+            | Instruction::Divide
+            | Instruction::Modulo
+            | Instruction::Negate
+            | Instruction::Panic(_)
+            | Instruction::Call { .. } => true,
+            _ => false,
+        };
+        if records_span {
+            let span = self.source_span();
+            self.spans.push((self.here(), span));
+        }
+
+        self.depth = self.depth - pops + pushes;
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    /// `self.span`, in a form that doesn't refer to the database.
+    fn source_span(&mut self) -> SourceSpan {
+        let file = self.span.file().id.untern(self.db()).to_string();
+        SourceSpan {
+            file: self.program.string_index(&file),
+            start: self.span.start().to_usize() as u32,
+            end: self.span.end().to_usize() as u32,
+        }
+    }
+
+    /// The label of the next instruction to be emitted.
+    fn here(&self) -> Label {
+        self.code.len() as Label
+    }
+
+    /// Makes the jump at `jump` go to the next instruction.
+    fn patch(&mut self, jump: usize) {
+        let here = self.here();
+        match &mut self.code[jump] {
+            Instruction::Jump(label) | Instruction::JumpIfFalse(label) => *label = here,
+            instruction => panic!("cannot patch {:?}", instruction),
+        }
+    }
+
+    fn slot(&mut self, variable: hir::Variable) -> Slot {
+        match self.slots.get(&variable) {
+            Some(&slot) => slot,
+            None => {
+                let slot = self.temporary();
+                self.slots.insert(variable, slot);
+                slot
+            }
+        }
+    }
+
+    fn temporary(&mut self) -> Slot {
+        self.slot_count += 1;
+        self.slot_count - 1
+    }
+
+    /// Emits a `Panic` that stands in for an expression we can't
+    /// lower (because it contains an error, say); like any expression,
+    /// it is treated as leaving a value on the stack.
+    fn emit_panic(&mut self, message: &str) {
+        let message = self.program.string_index(message);
+        self.emit(Instruction::Panic(message));
+        self.depth += 1;
+    }
+
+    /// The position of the field that `identifier` (as in `foo.bar`
+    /// or `Foo(bar: ..)`) was resolved to, if any.
+    fn field_index(&self, identifier: hir::Identifier) -> Option<u32> {
+        let db = self.db();
+        let field = *self.results.entities.get(&identifier.into())?;
+        let owner = field.untern(db).parent()?;
+        let index = field_entities(db, owner).iter().position(|&f| f == field)?;
+        Some(index as u32)
+    }
+
+    /// True if the type checker decided that the integer literal
+    /// `expression` is an `int` (rather than a `uint`).
+    fn is_int_literal(&self, expression: hir::Expression) -> bool {
+        let db = self.db();
+        match self.results.ty(expression).base.untern(db) {
+            BaseData {
+                kind: BaseKind::Named(entity),
+                ..
+            } => entity.untern(db) == EntityData::LangItem(LangItem::Int),
+            _ => false,
+        }
+    }
+
+    /// Emits code that leaves the value of `expression` on the stack.
+    fn lower_expression(&mut self, expression: hir::Expression) {
+        // A fault in the code of `expression` itself (rather than of
+        // one of its subexpressions) is reported at `expression`
+        let outer_span = std::mem::replace(&mut self.span, self.fn_body.span(expression));
+        self.lower_expression_data(expression);
+        self.span = outer_span;
+    }
+
+    fn lower_expression_data(&mut self, expression: hir::Expression) {
+        let fn_body = self.fn_body;
+
+        match fn_body[expression] {
+            hir::ExpressionData::Let {
+                variable,
+                initializer,
+                body,
+            } => {
+                match initializer {
+                    Some(initializer) => self.lower_expression(initializer),
+                    None => {
+                        self.emit(Instruction::Unit);
+                    }
+                }
+                let slot = self.slot(variable);
+                self.emit(Instruction::Store(slot));
+                self.lower_expression(body);
+            }
+
+            hir::ExpressionData::Place { place } => self.lower_place(place),
+
+            hir::ExpressionData::Assignment { place, value } => {
+                self.lower_expression(value);
+                self.lower_store(place);
+                self.emit(Instruction::Unit);
+            }
+
+            hir::ExpressionData::MethodCall { method, arguments } => {
+                let db = self.db();
+                let method = match self.results.entities.get(&method.into()) {
+                    Some(&method) => method,
+                    None => return self.emit_panic("call to an unknown method"),
+                };
+
+                for argument in arguments.iter(fn_body) {
+                    self.lower_expression(argument);
+                }
+
+                // A `borrow` method may modify its `self`, so copy the
+                // result back into the place that we invoked it on.
+                let owner = arguments
+                    .first(fn_body)
+                    .and_then(|owner| match fn_body[owner] {
+                        hir::ExpressionData::Place { place } => match fn_body[place] {
+                            hir::PlaceData::Variable(_) | hir::PlaceData::Field { .. } => {
+                                Some(place)
+                            }
+                            hir::PlaceData::Entity(_) | hir::PlaceData::Temporary(_) => None,
+                        },
+                        _ => None,
+                    });
+                let borrows_self = match db.signature(method).into_value() {
+                    Ok(signature) => signature
+                        .inputs
+                        .first()
+                        .map(|input| input.perm.untern(db) == DeclaredPermKind::Borrow)
+                        .unwrap_or(false),
+                    Err(_) => false,
+                };
+                let write_back = owner.filter(|_| borrows_self);
+
+                let function = self.program.function_index(method);
+                self.emit(Instruction::Call {
+                    function,
+                    write_back: write_back.is_some(),
+                });
+                if let Some(owner) = write_back {
+                    self.lower_store(owner);
+                }
+            }
+
+            hir::ExpressionData::Call {
+                function,
+                arguments,
+            } => {
+                let entity = match fn_body[function] {
+                    hir::ExpressionData::Place { place } => match fn_body[place] {
+                        hir::PlaceData::Entity(entity) => Some(entity),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+
+                match entity.map(|entity| (entity, entity.untern(self.db()))) {
+                    Some((_, EntityData::LangItem(LangItem::Debug))) => {
+                        for argument in arguments.iter(fn_body) {
+                            self.lower_expression(argument);
+                            self.emit(Instruction::Print);
+                        }
+                        self.emit(Instruction::Unit);
+                    }
+                    Some((
+                        entity,
+                        EntityData::ItemName {
+                            kind: ItemKind::Function,
+                            ..
+                        },
+                    )) => {
+                        for argument in arguments.iter(fn_body) {
+                            self.lower_expression(argument);
+                        }
+                        let function = self.program.function_index(entity);
+                        self.emit(Instruction::Call {
+                            function,
+                            write_back: false,
+                        });
+                    }
+                    _ => self.emit_panic("call of a value that is not a function"),
+                }
+            }
+
+            hir::ExpressionData::Sequence { first, second } => {
+                self.lower_expression(first);
+                self.emit(Instruction::Pop);
+                self.lower_expression(second);
+            }
+
+            hir::ExpressionData::If {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                self.lower_expression(condition);
+                let to_false = self.emit(Instruction::JumpIfFalse(0));
+                self.lower_expression(if_true);
+                let to_end = self.emit(Instruction::Jump(0));
+                self.depth -= 1;
+                self.patch(to_false);
+                self.lower_expression(if_false);
+                self.patch(to_end);
+            }
+
+            hir::ExpressionData::While { condition, body } => {
+                let start = self.here();
+                self.lower_expression(condition);
+                let to_end = self.emit(Instruction::JumpIfFalse(0));
+
+                self.loops.push(Loop {
+                    start,
+                    breaks: vec![],
+                    depth: self.depth,
+                });
+                self.lower_expression(body);
+                self.emit(Instruction::Pop);
+                self.emit(Instruction::Jump(start));
+                let the_loop = self.loops.pop().unwrap();
+
+                self.patch(to_end);
+                for jump in the_loop.breaks {
+                    self.patch(jump);
+                }
+                self.emit(Instruction::Unit);
+            }
+
+            hir::ExpressionData::Match { value, arms } => {
+                self.lower_expression(value);
+                let matched = self.temporary();
+                self.emit(Instruction::Store(matched));
+
+                let depth = self.depth;
+                let mut to_end = vec![];
+                for arm in arms.iter(fn_body) {
+                    let hir::MatchArmData {
+                        variant,
+                        bindings,
+                        body,
+                    } = fn_body[arm];
+
+                    let to_next_arm = variant.map(|variant| {
+                        let ty = self.program.type_index(variant);
+                        self.emit(Instruction::Load(matched));
+                        self.emit(Instruction::IsType(ty));
+                        self.emit(Instruction::JumpIfFalse(0))
+                    });
+
+                    for binding in bindings.iter(fn_body) {
+                        let hir::IdentifiedVariableData {
+                            identifier,
+                            variable,
+                        } = fn_body[binding];
+                        match self.field_index(identifier) {
+                            Some(field) => {
+                                self.emit(Instruction::Load(matched));
+                                self.emit(Instruction::GetField(field));
+                            }
+                            None => self.emit_panic("binding of an unknown field"),
+                        }
+                        let slot = self.slot(variable);
+                        self.emit(Instruction::Store(slot));
+                    }
+
+                    self.lower_expression(body);
+                    to_end.push(self.emit(Instruction::Jump(0)));
+                    self.depth = depth;
+
+                    if let Some(to_next_arm) = to_next_arm {
+                        self.patch(to_next_arm);
+                    }
+                }
+
+                self.emit_panic("no arm of `match` matches the value");
+                for jump in to_end {
+                    self.patch(jump);
+                }
+            }
+
+            hir::ExpressionData::Break {} | hir::ExpressionData::Continue {} => {
+                let depth = self.depth;
+                let (loop_depth, start) = match self.loops.last() {
+                    Some(the_loop) => (the_loop.depth, the_loop.start),
+                    None => return self.emit_panic("`break` or `continue` outside of a loop"),
+                };
+
+                // Discard whatever was pushed since the loop began
+                for _ in loop_depth..depth {
+                    self.emit(Instruction::Pop);
+                }
+
+                match fn_body[expression] {
+                    hir::ExpressionData::Break {} => {
+                        let jump = self.emit(Instruction::Jump(0));
+                        self.loops.last_mut().unwrap().breaks.push(jump);
+                    }
+                    _ => {
+                        self.emit(Instruction::Jump(start));
+                    }
+                }
+
+                // Control never gets past the jump, but as far as the
+                // surrounding code is concerned we produced a value.
+                self.depth = depth + 1;
+            }
+
+            hir::ExpressionData::Binary {
+                operator,
+                left,
+                right,
+            } => self.lower_binary(operator, left, right),
+
+            hir::ExpressionData::Unary { operator, value } => {
+                self.lower_expression(value);
+                self.emit(match operator {
+                    hir::UnaryOperator::Not => Instruction::Not,
+                    hir::UnaryOperator::Negate => Instruction::Negate,
+                });
+            }
+
+            hir::ExpressionData::Literal { data } => {
+                let text = data.value.untern(self.db());
+                match data.kind {
+                    hir::LiteralKind::UnsignedInteger | hir::LiteralKind::SignedInteger => {
+                        let digits = text.replace('_', "");
+                        let instruction = if self.is_int_literal(expression) {
+                            digits.parse().ok().map(Instruction::I32)
+                        } else {
+                            digits.parse().ok().map(Instruction::U32)
+                        };
+                        match instruction {
+                            Some(instruction) => {
+                                self.emit(instruction);
+                            }
+                            None => self.emit_panic("integer literal out of range"),
+                        }
+                    }
+                    hir::LiteralKind::String => {
+                        let text = text.to_string();
+                        let string = self.program.string_index(&text[1..text.len() - 1]);
+                        self.emit(Instruction::Str(string));
+                    }
+                }
+            }
+
+            hir::ExpressionData::Aggregate { entity, fields } => {
+                self.lower_aggregate(entity, fields)
+            }
+
+            hir::ExpressionData::Unit {} => {
+                self.emit(Instruction::Unit);
+            }
+
+            hir::ExpressionData::Error { .. } => self.emit_panic("encountered a compilation error"),
+        }
+    }
+
+    fn lower_binary(
+        &mut self,
+        operator: hir::BinaryOperator,
+        left: hir::Expression,
+        right: hir::Expression,
+    ) {
+        self.lower_expression(left);
+
+        // `&&` and `||` only evaluate their right-hand side if needed
+        match operator {
+            hir::BinaryOperator::And => {
+                let to_false = self.emit(Instruction::JumpIfFalse(0));
+                self.lower_expression(right);
+                let to_end = self.emit(Instruction::Jump(0));
+                self.depth -= 1;
+                self.patch(to_false);
+                self.emit(Instruction::Bool(false));
+                self.patch(to_end);
+                return;
+            }
+            hir::BinaryOperator::Or => {
+                let to_right = self.emit(Instruction::JumpIfFalse(0));
+                self.emit(Instruction::Bool(true));
+                let to_end = self.emit(Instruction::Jump(0));
+                self.depth -= 1;
+                self.patch(to_right);
+                self.lower_expression(right);
+                self.patch(to_end);
+                return;
+            }
+            _ => {}
+        }
+
+        self.lower_expression(right);
+        self.emit(match operator {
+            hir::BinaryOperator::Add => Instruction::Add,
+            hir::BinaryOperator::Subtract => Instruction::Subtract,
+            hir::BinaryOperator::Multiply => Instruction::Multiply,
+            hir::BinaryOperator::Divide => Instruction::Divide,
+            hir::BinaryOperator::Modulo => Instruction::Modulo,
+            hir::BinaryOperator::Equals => Instruction::Equals,
+            hir::BinaryOperator::NotEquals => Instruction::NotEquals,
+            hir::BinaryOperator::LessThan => Instruction::LessThan,
+            hir::BinaryOperator::LessThanOrEqual => Instruction::LessThanOrEqual,
+            hir::BinaryOperator::GreaterThan => Instruction::GreaterThan,
+            hir::BinaryOperator::GreaterThanOrEqual => Instruction::GreaterThanOrEqual,
+            hir::BinaryOperator::And | hir::BinaryOperator::Or => unreachable!(),
+        });
+    }
+
+    fn lower_aggregate(&mut self, entity: Entity, fields: hir::List<hir::IdentifiedExpression>) {
+        let fn_body = self.fn_body;
+
+        let mut field_indices = vec![];
+        for field in fields.iter(fn_body) {
+            match self.field_index(fn_body[field].identifier) {
+                Some(index) => field_indices.push(index),
+                None => return self.emit_panic("aggregate with an unknown field"),
+            }
+        }
+
+        let field_count = field_entities(self.db(), entity).len();
+        let mut sorted_indices = field_indices.clone();
+        sorted_indices.sort();
+        sorted_indices.dedup();
+        if sorted_indices.len() != field_count || field_indices.len() != field_count {
+            return self.emit_panic("aggregate with missing fields");
+        }
+
+        // The fields are evaluated in the order they are written, but
+        // `MakeStruct` wants them in declaration order; if the two
+        // differ, we go via temporaries.
+        let in_order = field_indices
+            .iter()
+            .enumerate()
+            .all(|(i, &index)| i as u32 == index);
+        if in_order {
+            for field in fields.iter(fn_body) {
+                self.lower_expression(fn_body[field].expression);
+            }
+        } else {
+            let mut temporaries = vec![0; field_count];
+            for (field, &index) in fields.iter(fn_body).zip(&field_indices) {
+                self.lower_expression(fn_body[field].expression);
+                let temporary = self.temporary();
+                self.emit(Instruction::Store(temporary));
+                temporaries[index as usize] = temporary;
+            }
+            for temporary in temporaries {
+                self.emit(Instruction::Load(temporary));
+            }
+        }
+
+        let ty = self.program.type_index(entity);
+        self.emit(Instruction::MakeStruct {
+            ty,
+            fields: field_count as u32,
+        });
+    }
+
+    /// Emits code that pushes the value in `place`.
+    fn lower_place(&mut self, place: hir::Place) {
+        match self.fn_body[place] {
+            hir::PlaceData::Variable(variable) => {
+                let slot = self.slot(variable);
+                self.emit(Instruction::Load(slot));
+            }
+            hir::PlaceData::Entity(entity) => match entity.untern(self.db()) {
+                EntityData::LangItem(LangItem::True) => {
+                    self.emit(Instruction::Bool(true));
+                }
+                EntityData::LangItem(LangItem::False) => {
+                    self.emit(Instruction::Bool(false));
+                }
+                _ => self.emit_panic("use of an item as a value"),
+            },
+            hir::PlaceData::Field { owner, name } => match self.field_index(name) {
+                Some(field) => {
+                    self.lower_place(owner);
+                    self.emit(Instruction::GetField(field));
+                }
+                None => self.emit_panic("access to an unknown field"),
+            },
+            hir::PlaceData::Temporary(expression) => self.lower_expression(expression),
+        }
+    }
+
+    /// Emits code that pops a value into `place`; for a field, this
+    /// updates the struct that the field belongs to.
+    fn lower_store(&mut self, place: hir::Place) {
+        match self.fn_body[place] {
+            hir::PlaceData::Variable(variable) => {
+                let slot = self.slot(variable);
+                self.emit(Instruction::Store(slot));
+            }
+            hir::PlaceData::Field { owner, name } => match self.field_index(name) {
+                Some(field) => {
+                    self.lower_place(owner);
+                    self.emit(Instruction::Swap);
+                    self.emit(Instruction::SetField(field));
+                    self.lower_store(owner);
+                }
+                None => {
+                    self.emit(Instruction::Pop);
+                    self.emit_panic("assignment to an unknown field");
+                    self.emit(Instruction::Pop);
+                }
+            },
+            hir::PlaceData::Entity(_) | hir::PlaceData::Temporary(_) => {
+                self.emit(Instruction::Pop);
+                self.emit_panic("assignment to a value that is not a place");
+                self.emit(Instruction::Pop);
+            }
+        }
+    }
+}
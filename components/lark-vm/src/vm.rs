@@ -0,0 +1,482 @@
+//! Executes a `Program`. All frames share one stack: the slots of a
+//! frame come first (starting with its parameters), followed by its
+//! operands.
+
+use crate::bytecode::{FunctionIndex, Instruction, Label, Program, SourceSpan, TypeIndex};
+use std::cmp::Ordering;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Why a program stopped before it finished.
+#[derive(Debug)]
+pub enum Error {
+    /// Writing the program's output failed.
+    Io(io::Error),
+
+    /// The program did something that has no result, like dividing by
+    /// zero.
+    Fault(Fault),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub message: String,
+
+    /// The part of the program that failed (if the bytecode says).
+    pub span: Option<SourceSpan>,
+
+    /// The calls that were in progress when the fault occurred,
+    /// innermost first.
+    pub call_stack: Vec<FaultCall>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FaultCall {
+    /// The function that was called.
+    pub function: FunctionIndex,
+
+    /// The call (if the bytecode says where it is).
+    pub span: Option<SourceSpan>,
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Fault(fault) => write!(f, "{}", fault.message),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Void,
+    Bool(bool),
+    U32(u32),
+    I32(i32),
+    Str(Rc<str>),
+
+    /// A struct or enum variant. Structs are shared until they are
+    /// modified, at which point they are copied (if need be).
+    Struct(Rc<StructValue>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructValue {
+    pub ty: TypeIndex,
+    pub fields: Vec<Value>,
+}
+
+impl Value {
+    /// Displays this value as `debug` prints it.
+    pub fn display(&'me self, program: &'me Program) -> impl fmt::Display + 'me {
+        DisplayValue {
+            value: self,
+            program,
+        }
+    }
+}
+
+struct DisplayValue<'me> {
+    value: &'me Value,
+    program: &'me Program,
+}
+
+impl fmt::Display for DisplayValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Value::Void => write!(f, "<void>"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::U32(u) => write!(f, "{}", u),
+            Value::I32(i) => write!(f, "{}", i),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Struct(s) => {
+                // Written the way the value would be constructed:
+                // `Point(x: 1, y: 2)`
+                let ty = &self.program.types[s.ty as usize];
+                write!(f, "{}", ty.name)?;
+                if !s.fields.is_empty() {
+                    write!(f, "(")?;
+                    for (i, (name, value)) in ty.fields.iter().zip(&s.fields).enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{}: {}", name, value.display(self.program))?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+struct Frame {
+    function: FunctionIndex,
+
+    /// The next instruction to execute.
+    pc: usize,
+
+    /// Where the frame's slots start on the stack.
+    base: usize,
+
+    /// See `Instruction::Call`.
+    write_back: bool,
+}
+
+pub struct Vm<'me, W: Write> {
+    program: &'me Program,
+    output: W,
+
+    /// `program.strings`, ready to be pushed without copying.
+    strings: Vec<Rc<str>>,
+
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+}
+
+impl<W: Write> Vm<'me, W> {
+    /// Creates a VM whose `debug` output goes to `output`.
+    pub fn new(program: &'me Program, output: W) -> Self {
+        Vm {
+            program,
+            output,
+            strings: program
+                .strings
+                .iter()
+                .map(|s| Rc::from(s.as_str()))
+                .collect(),
+            stack: vec![],
+            frames: vec![],
+        }
+    }
+
+    pub fn into_output(self) -> W {
+        self.output
+    }
+
+    /// Runs each of the program's entry points in turn.
+    pub fn run(&mut self) -> Result<(), Error> {
+        for &entry_point in &self.program.entry_points {
+            self.call(entry_point, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Calls `function` on the `argument_count` values on top of the
+    /// stack, returning its result.
+    pub fn call(&mut self, function: FunctionIndex, argument_count: usize) -> Result<Value, Error> {
+        let outer_frames = self.frames.len();
+        self.push_frame(function, argument_count, false)?;
+
+        while self.frames.len() > outer_frames {
+            self.step()?;
+        }
+
+        self.pop()
+    }
+
+    fn push_frame(
+        &mut self,
+        function: FunctionIndex,
+        argument_count: usize,
+        write_back: bool,
+    ) -> Result<(), Error> {
+        if argument_count > self.operand_count() {
+            return Err(self.fault("operand stack underflow"));
+        }
+
+        let base = self.stack.len() - argument_count;
+        let slots = self.program.functions[function as usize].slots as usize;
+        self.stack.resize(base + slots, Value::Void);
+        self.frames.push(Frame {
+            function,
+            pc: 0,
+            base,
+            write_back,
+        });
+        Ok(())
+    }
+
+    /// The number of values on the stack above the slots of the
+    /// current frame. (Bytecode that pops more than this is malformed,
+    /// and faults rather than reading the slots of a frame.)
+    fn operand_count(&self) -> usize {
+        let floor = match self.frames.last() {
+            Some(frame) => {
+                frame.base + self.program.functions[frame.function as usize].slots as usize
+            }
+            None => 0,
+        };
+        self.stack.len().saturating_sub(floor)
+    }
+
+    fn pop(&mut self) -> Result<Value, Error> {
+        if self.operand_count() == 0 {
+            return Err(self.fault("operand stack underflow"));
+        }
+        Ok(self.stack.pop().unwrap())
+    }
+
+    /// Pops the top `count` values, in the order they were pushed.
+    fn pop_many(&mut self, count: usize) -> Result<Vec<Value>, Error> {
+        if count > self.operand_count() {
+            return Err(self.fault("operand stack underflow"));
+        }
+        Ok(self.stack.split_off(self.stack.len() - count))
+    }
+
+    fn pop_bool(&mut self) -> Result<bool, Error> {
+        match self.pop()? {
+            Value::Bool(b) => Ok(b),
+            _ => Err(self.fault("expected a boolean value")),
+        }
+    }
+
+    fn pop_struct(&mut self) -> Result<Rc<StructValue>, Error> {
+        match self.pop()? {
+            Value::Struct(s) => Ok(s),
+            _ => Err(self.fault("member access (.) into value that is not a struct")),
+        }
+    }
+
+    /// Executes a single instruction.
+    fn step(&mut self) -> Result<(), Error> {
+        let program = self.program;
+        let frame = self.frames.last_mut().unwrap();
+        let function = &program.functions[frame.function as usize];
+        let instruction = match function.code.get(frame.pc) {
+            Some(&instruction) => instruction,
+            None => return Err(self.fault("function ended without returning")),
+        };
+        let base = frame.base;
+        frame.pc += 1;
+
+        match instruction {
+            Instruction::Unit => self.stack.push(Value::Void),
+            Instruction::Bool(b) => self.stack.push(Value::Bool(b)),
+            Instruction::U32(u) => self.stack.push(Value::U32(u)),
+            Instruction::I32(i) => self.stack.push(Value::I32(i)),
+            Instruction::Str(string) => {
+                let string = self.strings[string as usize].clone();
+                self.stack.push(Value::Str(string));
+            }
+
+            Instruction::Load(slot) => {
+                let value = self.stack[base + slot as usize].clone();
+                self.stack.push(value);
+            }
+            Instruction::Store(slot) => {
+                let value = self.pop()?;
+                self.stack[base + slot as usize] = value;
+            }
+            Instruction::Pop => {
+                self.pop()?;
+            }
+            Instruction::Swap => {
+                let mut values = self.pop_many(2)?;
+                values.swap(0, 1);
+                self.stack.extend(values);
+            }
+
+            Instruction::MakeStruct { ty, fields } => {
+                let fields = self.pop_many(fields as usize)?;
+                self.stack
+                    .push(Value::Struct(Rc::new(StructValue { ty, fields })));
+            }
+            Instruction::GetField(field) => {
+                let s = self.pop_struct()?;
+                match s.fields.get(field as usize) {
+                    Some(value) => self.stack.push(value.clone()),
+                    None => return Err(self.fault("field index out of range")),
+                }
+            }
+            Instruction::SetField(field) => {
+                let value = self.pop()?;
+                let mut s = self.pop_struct()?;
+                match Rc::make_mut(&mut s).fields.get_mut(field as usize) {
+                    Some(slot) => *slot = value,
+                    None => return Err(self.fault("field index out of range")),
+                }
+                self.stack.push(Value::Struct(s));
+            }
+            Instruction::IsType(ty) => {
+                let is_type = self.pop_struct()?.ty == ty;
+                self.stack.push(Value::Bool(is_type));
+            }
+
+            Instruction::Add
+            | Instruction::Subtract
+            | Instruction::Multiply
+            | Instruction::Divide
+            | Instruction::Modulo => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                let result = arithmetic(instruction, lhs, rhs).map_err(|m| self.fault(m))?;
+                self.stack.push(result);
+            }
+            Instruction::LessThan
+            | Instruction::LessThanOrEqual
+            | Instruction::GreaterThan
+            | Instruction::GreaterThanOrEqual => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                let result = comparison(instruction, lhs, rhs).map_err(|m| self.fault(m))?;
+                self.stack.push(result);
+            }
+            Instruction::Equals | Instruction::NotEquals => {
+                let rhs = self.pop()?;
+                let lhs = self.pop()?;
+                let equal = values_equal(lhs, rhs).map_err(|m| self.fault(m))?;
+                self.stack
+                    .push(Value::Bool(equal == (instruction == Instruction::Equals)));
+            }
+            Instruction::Not => {
+                let b = self.pop_bool()?;
+                self.stack.push(Value::Bool(!b));
+            }
+            Instruction::Negate => match self.pop()? {
+                Value::I32(i) => match i.checked_neg() {
+                    Some(negated) => self.stack.push(Value::I32(negated)),
+                    None => return Err(self.fault("arithmetic overflow")),
+                },
+                _ => return Err(self.fault("unary operator applied to unsupported value")),
+            },
+
+            Instruction::Jump(label) => self.frames.last_mut().unwrap().pc = label as usize,
+            Instruction::JumpIfFalse(label) => {
+                if !self.pop_bool()? {
+                    self.frames.last_mut().unwrap().pc = label as usize;
+                }
+            }
+
+            Instruction::Call {
+                function,
+                write_back,
+            } => {
+                let parameters = program.functions[function as usize].parameters as usize;
+                self.push_frame(function, parameters, write_back)?;
+            }
+            Instruction::Return => {
+                let result = self.pop()?;
+                let frame = self.frames.pop().unwrap();
+                let self_value = if frame.write_back {
+                    Some(std::mem::replace(&mut self.stack[frame.base], Value::Void))
+                } else {
+                    None
+                };
+                self.stack.truncate(frame.base);
+                self.stack.push(result);
+                self.stack.extend(self_value);
+            }
+
+            Instruction::Print => {
+                let value = self.pop()?;
+                writeln!(self.output, "{}", value.display(program))?;
+            }
+            Instruction::Panic(message) => {
+                return Err(self.fault(program.strings[message as usize].as_str()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A fault in the instruction that was just executed.
+    fn fault(&self, message: impl Into<String>) -> Error {
+        // The `pc` of each frame is just past the instruction that it
+        // is executing: the one that faulted, or a call (except when a
+        // function runs off its end before executing anything)
+        let span = |frame: &Frame| {
+            let function = &self.program.functions[frame.function as usize];
+            (frame.pc as Label)
+                .checked_sub(1)
+                .and_then(|label| function.span(label))
+        };
+
+        let call_stack = self
+            .frames
+            .windows(2)
+            .rev()
+            .map(|frames| FaultCall {
+                function: frames[1].function,
+                span: span(&frames[0]),
+            })
+            .collect();
+
+        Error::Fault(Fault {
+            message: message.into(),
+            span: self.frames.last().and_then(span),
+            call_stack,
+        })
+    }
+}
+
+fn values_equal(lhs: Value, rhs: Value) -> Result<bool, String> {
+    match (lhs, rhs) {
+        (Value::U32(l), Value::U32(r)) => Ok(l == r),
+        (Value::I32(l), Value::I32(r)) => Ok(l == r),
+        (Value::Bool(l), Value::Bool(r)) => Ok(l == r),
+        (Value::Str(l), Value::Str(r)) => Ok(l == r),
+        _ => Err("comparison of unsupported values".to_string()),
+    }
+}
+
+/// Applies an arithmetic operator, failing on overflow and division
+/// by zero just as `lark_eval` does.
+fn arithmetic(instruction: Instruction, lhs: Value, rhs: Value) -> Result<Value, String> {
+    let divides = match instruction {
+        Instruction::Divide | Instruction::Modulo => true,
+        _ => false,
+    };
+
+    let result = match (lhs, rhs) {
+        (Value::U32(_), Value::U32(0)) | (Value::I32(_), Value::I32(0)) if divides => {
+            return Err("division by zero".to_string());
+        }
+        (Value::U32(l), Value::U32(r)) => match instruction {
+            Instruction::Add => l.checked_add(r),
+            Instruction::Subtract => l.checked_sub(r),
+            Instruction::Multiply => l.checked_mul(r),
+            Instruction::Divide => l.checked_div(r),
+            Instruction::Modulo => l.checked_rem(r),
+            _ => unreachable!(),
+        }
+        .map(Value::U32),
+        (Value::I32(l), Value::I32(r)) => match instruction {
+            Instruction::Add => l.checked_add(r),
+            Instruction::Subtract => l.checked_sub(r),
+            Instruction::Multiply => l.checked_mul(r),
+            Instruction::Divide => l.checked_div(r),
+            Instruction::Modulo => l.checked_rem(r),
+            _ => unreachable!(),
+        }
+        .map(Value::I32),
+        _ => return Err("arithmetic on non-numeric values".to_string()),
+    };
+
+    result.ok_or_else(|| "arithmetic overflow".to_string())
+}
+
+fn comparison(instruction: Instruction, lhs: Value, rhs: Value) -> Result<Value, String> {
+    let ordering = match (lhs, rhs) {
+        (Value::U32(l), Value::U32(r)) => l.cmp(&r),
+        (Value::I32(l), Value::I32(r)) => l.cmp(&r),
+        _ => return Err("comparison of non-numeric values".to_string()),
+    };
+
+    Ok(Value::Bool(match instruction {
+        Instruction::LessThan => ordering == Ordering::Less,
+        Instruction::LessThanOrEqual => ordering != Ordering::Greater,
+        Instruction::GreaterThan => ordering == Ordering::Greater,
+        Instruction::GreaterThanOrEqual => ordering != Ordering::Less,
+        _ => unreachable!(),
+    }))
+}
@@ -0,0 +1,361 @@
+#[cfg(test)]
+mod tests {
+    use lark_vm::bytecode::Instruction;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    /// Runs `lark` with the given arguments, returning its exit status
+    /// and what it wrote to stdout and stderr.
+    fn lark_all_output(args: &[&str]) -> (i32, String, String) {
+        let output = Command::new("cargo")
+            .arg("run")
+            .arg("--quiet")
+            .arg("--")
+            .arg("--log-dir")
+            .arg(std::env::temp_dir().join("lark_test_logs"))
+            .args(args)
+            .output()
+            .expect("Failed to run lark");
+
+        let status = output.status.code().expect("lark was killed by a signal");
+        (
+            status,
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    }
+
+    /// Runs `lark` with the given arguments, returning its exit status
+    /// and what it wrote to stdout.
+    fn lark_output(args: &[&str]) -> (i32, String) {
+        let (status, stdout, _) = lark_all_output(args);
+        (status, stdout)
+    }
+
+    /// Runs `lark` with the given arguments and `input` on stdin,
+    /// returning its exit status and what it wrote to stdout.
+    fn lark_with_input(args: &[&str], input: &str) -> (i32, String) {
+        let mut child = Command::new("cargo")
+            .arg("run")
+            .arg("--quiet")
+            .arg("--")
+            .arg("--log-dir")
+            .arg(std::env::temp_dir().join("lark_test_logs"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to run lark");
+
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(input.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+
+        let status = output.status.code().expect("lark was killed by a signal");
+        (status, String::from_utf8(output.stdout).unwrap())
+    }
+
+    fn lark(args: &[&str]) -> i32 {
+        lark_output(args).0
+    }
+
+    #[test]
+    fn check_success() {
+        assert_eq!(lark(&["check", "tests/test_files/add.lark"]), 0);
+    }
+
+    #[test]
+    fn check_errors() {
+        let path = "tests/test_files/error_type_mismatch.lark";
+        assert_eq!(lark(&["check", "--color", "never", path]), 1);
+    }
+
+    #[test]
+    fn check_duplicate_module() {
+        // `a/util.lark` and `b/util.lark` would both be the module `util`
+        let project =
+            std::env::temp_dir().join(format!("lark_cli_duplicate_module_{}", std::process::id()));
+        for directory in &["a", "b"] {
+            let directory = project.join(directory);
+            std::fs::create_dir_all(&directory).unwrap();
+            std::fs::write(directory.join("util.lark"), "def helper() {\n}\n").unwrap();
+        }
+
+        let (status, _, stderr) =
+            lark_all_output(&["check", "--color", "never", project.to_str().unwrap()]);
+        std::fs::remove_dir_all(&project).unwrap();
+        assert_eq!(status, 1);
+
+        let lines: Vec<&str> = stderr.lines().collect();
+        assert_eq!(lines[0], "error: duplicate module `util`", "{}", stderr);
+        assert!(lines[1].ends_with("b/util.lark:1:0"), "{}", stderr);
+        assert!(stderr.contains("also the module of `"), "{}", stderr);
+    }
+
+    #[test]
+    fn check_errors_json() {
+        let path = "tests/test_files/error_type_mismatch.lark";
+        let (status, stdout) = lark_output(&["check", "--message-format=json", path]);
+        assert_eq!(status, 1);
+
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines.len(), 1);
+
+        let diagnostic: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(diagnostic["file"], path);
+        assert_eq!(diagnostic["severity"], "error");
+        assert_eq!(diagnostic["message"], "mismatched types (uint vs bool)");
+        assert_eq!(diagnostic["range"]["start"]["line"], 2);
+        assert_eq!(diagnostic["range"]["start"]["column"], 3);
+        assert_eq!(diagnostic["span"]["end"], 39);
+    }
+
+    #[test]
+    fn check_errors_json_non_ascii() {
+        let path = "tests/test_files/error_type_mismatch_non_ascii.lark";
+        let (status, stdout) = lark_output(&["check", "--message-format=json", path]);
+        assert_eq!(status, 1);
+
+        // Columns count characters, so the `🦀` (four bytes, and two
+        // UTF-16 code units) before the error counts once
+        let diagnostic: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+        assert_eq!(diagnostic["range"]["start"]["line"], 2);
+        assert_eq!(diagnostic["range"]["start"]["column"], 14);
+        assert_eq!(diagnostic["range"]["end"]["column"], 15);
+        assert_eq!(diagnostic["span"]["start"], 52);
+
+        // ...just as in the human-readable output (whose columns start
+        // from 0)
+        let (_, _, stderr) = lark_all_output(&["check", "--color", "never", path]);
+        assert!(stderr.contains(&format!("- {}:2:13", path)));
+    }
+
+    #[test]
+    fn run_runtime_error() {
+        let path = "tests/test_files/runtime_error_divide_by_zero.lark";
+        let (status, stdout, stderr) = lark_all_output(&["run", "--color", "never", path]);
+        assert_eq!(status, 1);
+        assert_eq!(stdout, "5\n");
+
+        let lines: Vec<&str> = stderr.lines().collect();
+        assert_eq!(lines[0], "error: division by zero");
+        assert_eq!(lines[1], format!("- {}:4:4", path));
+        assert!(lines.contains(&format!("- {}:9:10", path).as_str()));
+        assert!(stderr.contains("in this call to `average`"));
+    }
+
+    #[test]
+    fn run_overflow() {
+        // The same error and exit status as the built executables, see
+        // the `runtime_error` comment of the test file
+        let path = "tests/test_files/runtime_error_overflow_uint.lark";
+        let (status, stdout, stderr) = lark_all_output(&["run", "--color", "never", path]);
+        assert_eq!(status, 1);
+        assert_eq!(stdout, "4294967295\n");
+
+        let lines: Vec<&str> = stderr.lines().collect();
+        assert_eq!(lines[0], "error: arithmetic overflow");
+        assert_eq!(lines[1], format!("- {}:7:10", path));
+    }
+
+    #[test]
+    fn build_c_debug_aggregates() {
+        let path = "tests/test_files/debug_aggregates.lark";
+        let executable =
+            std::env::temp_dir().join(format!("lark_cli_debug_aggregates_{}", std::process::id()));
+        let executable = executable.to_str().unwrap();
+        let (status, _, stderr) =
+            lark_all_output(&["build", "--backend", "c", path, "-o", executable]);
+        assert_eq!(status, 0, "{}", stderr);
+
+        // The C backend shows structs and enums just as `lark run` does
+        let output = Command::new(executable).output().unwrap();
+        let expected = "\
+Point(x: 1, y: -2)
+Wrapper(value: Point(x: 3, y: 4))
+Wrapper(value: text)
+Shape.Circle(radius: 5)
+Shape.Empty
+";
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+        assert_eq!(lark_output(&["run", path]), (0, expected.to_string()));
+
+        std::fs::remove_file(executable).unwrap();
+    }
+
+    #[test]
+    fn build_c_unsupported() {
+        let path = "tests/test_files/function_value.lark";
+        let executable =
+            std::env::temp_dir().join(format!("lark_cli_function_value_{}", std::process::id()));
+        let args = [
+            "build",
+            "--color",
+            "never",
+            "--backend",
+            "c",
+            path,
+            "-o",
+            executable.to_str().unwrap(),
+        ];
+        let (status, _, stderr) = lark_all_output(&args);
+        assert_eq!(status, 1);
+        assert!(stderr.contains("using a function as a value is not supported by the C backend"));
+        assert!(stderr.contains(&format!("- {}:8:12", path)));
+        assert!(!executable.exists());
+    }
+
+    #[test]
+    fn run_with_corrupt_cache() {
+        // Each run gets its own cache directory
+        let temp_dir = std::env::temp_dir().join(format!("lark_cli_cache_{}", std::process::id()));
+        let run = || {
+            Command::new("cargo")
+                .arg("run")
+                .arg("--quiet")
+                .arg("--")
+                .arg("--log-dir")
+                .arg(std::env::temp_dir().join("lark_test_logs"))
+                .arg("run")
+                .arg("tests/test_files/call.lark")
+                .env("XDG_CACHE_HOME", &temp_dir)
+                .output()
+                .expect("Failed to run lark")
+        };
+
+        let output = run();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"false\n");
+
+        // Only the user can plant programs in the cache
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(temp_dir.join("lark")).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o700);
+        }
+
+        // A cached program that can't be decoded is just compiled again
+        for entry in std::fs::read_dir(temp_dir.join("lark")).unwrap() {
+            std::fs::write(entry.unwrap().path(), b"LKBC not really bytecode").unwrap();
+        }
+        let output = run();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"false\n");
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn run_malformed_bytecode() {
+        let path = "tests/test_files/call.lark";
+        let temp_dir =
+            std::env::temp_dir().join(format!("lark_cli_malformed_{}", std::process::id()));
+        let run = || {
+            Command::new("cargo")
+                .arg("run")
+                .arg("--quiet")
+                .arg("--")
+                .arg("--log-dir")
+                .arg(std::env::temp_dir().join("lark_test_logs"))
+                .args(&["run", "--color", "never", path])
+                .env("XDG_CACHE_HOME", &temp_dir)
+                .output()
+                .expect("Failed to run lark")
+        };
+
+        assert!(run().status.success());
+
+        // Replace the call in `main` with a member access into the
+        // `bool` argument, which the type checker would never allow
+        for entry in std::fs::read_dir(temp_dir.join("lark")).unwrap() {
+            let cache_path = entry.unwrap().path();
+            let bytes = std::fs::read(&cache_path).unwrap();
+            let mut program = lark_vm::decode(&mut &bytes[..]).unwrap();
+            let main = program.entry_points[0] as usize;
+            for instruction in &mut program.functions[main].code {
+                if let Instruction::Call { .. } = instruction {
+                    *instruction = Instruction::GetField(0);
+                }
+            }
+
+            let mut bytes = vec![];
+            lark_vm::encode(&program, &mut bytes).unwrap();
+            std::fs::write(&cache_path, bytes).unwrap();
+        }
+
+        // ...which the VM reports, where the call was, rather than
+        // panicking
+        let output = run();
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        let lines: Vec<&str> = stderr.lines().collect();
+        assert_eq!(
+            lines[0],
+            "error: member access (.) into value that is not a struct"
+        );
+        assert_eq!(lines[1], format!("- {}:8:4", path));
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn debug() {
+        let path = "tests/test_files/call.lark";
+        let (status, stdout) = lark_with_input(&["debug", path], "b 4\nc\nbt\np x\nc\n");
+        assert_eq!(status, 0);
+        assert_eq!(
+            stdout,
+            "\
+Lark debugger (`help` lists the commands)
+main at tests/test_files/call.lark:8
+   8 |     print_bool(false)
+(debug) Breakpoint at tests/test_files/call.lark:4
+(debug) print_bool at tests/test_files/call.lark:4
+   4 |     debug(x)
+(debug) #0 print_bool at tests/test_files/call.lark:4
+#1 main at tests/test_files/call.lark:8
+(debug) false
+(debug) false
+The program finished
+"
+        );
+    }
+
+    #[test]
+    fn fmt() {
+        // Each run gets its own file, so that concurrent runs don't race
+        let path = std::env::temp_dir().join(format!("lark_cli_fmt_{}.lark", std::process::id()));
+        std::fs::write(&path, "def main(){debug(1)}").unwrap();
+        let path = path.to_str().unwrap();
+
+        let (status, stdout) = lark_output(&["fmt", "--check", path]);
+        assert_eq!(status, 1);
+        assert_eq!(stdout.trim(), path);
+
+        assert_eq!(lark(&["fmt", path]), 0);
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "def main() { debug(1) }\n"
+        );
+        assert_eq!(lark(&["fmt", "--check", path]), 0);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert_eq!(lark(&[]), 2);
+        assert_eq!(lark(&["check"]), 2);
+        assert_eq!(lark(&["frobnicate", "tests/test_files/add.lark"]), 2);
+    }
+
+    #[test]
+    fn missing_file() {
+        assert_eq!(lark(&["check", "tests/test_files/no_such_file.lark"]), 3);
+    }
+}
@@ -0,0 +1,246 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::process::{Child, ChildStdout, Command, Stdio};
+
+    struct ChildSession {
+        child: Child,
+        reader: BufReader<ChildStdout>,
+        seq: u64,
+    }
+
+    impl Drop for ChildSession {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+        }
+    }
+
+    impl ChildSession {
+        fn spawn() -> ChildSession {
+            let mut child = Command::new("cargo")
+                .arg("run")
+                .arg("--quiet")
+                .arg("--")
+                .arg("--log-dir")
+                .arg(std::env::temp_dir().join("lark_test_logs"))
+                .arg("debug-adapter")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .expect("Failed to spawn child process");
+
+            let reader = BufReader::new(child.stdout.take().unwrap());
+            ChildSession {
+                child,
+                reader,
+                seq: 0,
+            }
+        }
+
+        /// Sends the request `command` to the debug adapter.
+        fn send(&mut self, command: &str, arguments: Value) {
+            self.seq += 1;
+            let message = json!({
+                "seq": self.seq,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            })
+            .to_string();
+
+            let stdin = self.child.stdin.as_mut().unwrap();
+            write!(
+                stdin,
+                "Content-Length: {}\r\n\r\n{}",
+                message.len(),
+                message
+            )
+            .unwrap();
+            stdin.flush().unwrap();
+        }
+
+        /// Receives the next message from the debug adapter.
+        fn receive(&mut self) -> Value {
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                self.reader.read_line(&mut line).unwrap();
+                let line = line.trim();
+                if line.is_empty() {
+                    break;
+                }
+                content_length = line["Content-Length:".len()..].trim().parse().unwrap();
+            }
+
+            let mut buffer = vec![0u8; content_length];
+            self.reader.read_exact(&mut buffer).unwrap();
+            serde_json::from_slice(&buffer).unwrap()
+        }
+
+        /// Receives the response to the last request, checking that it
+        /// succeeded, and returns its body.
+        fn receive_response(&mut self) -> Value {
+            let response = self.receive();
+            assert_eq!(response["type"], "response");
+            assert_eq!(response["request_seq"], self.seq);
+            assert_eq!(response["success"], true, "{}", response);
+            response["body"].clone()
+        }
+
+        /// Receives the event `event`, returning its body.
+        fn receive_event(&mut self, event: &str) -> Value {
+            let message = self.receive();
+            assert_eq!(message["type"], "event");
+            assert_eq!(message["event"], event, "{}", message);
+            message["body"].clone()
+        }
+
+        /// Sends `disconnect`, returning the exit status of the adapter.
+        fn disconnect(&mut self) -> i32 {
+            self.send("disconnect", json!({}));
+            self.receive_response();
+            let status = self.child.wait().unwrap();
+            status.code().expect("the adapter was killed by a signal")
+        }
+
+        fn initialize(&mut self) {
+            self.send(
+                "initialize",
+                json!({ "adapterID": "lark", "linesStartAt1": true, "columnsStartAt1": true }),
+            );
+            let capabilities = self.receive_response();
+            assert_eq!(capabilities["supportsConfigurationDoneRequest"], true);
+            self.receive_event("initialized");
+        }
+    }
+
+    #[test]
+    fn breakpoint() {
+        let path = "tests/test_files/call.lark";
+        let mut session = ChildSession::spawn();
+        session.initialize();
+
+        session.send("launch", json!({ "program": path }));
+        session.receive_response();
+
+        session.send(
+            "setBreakpoints",
+            json!({ "source": { "path": path }, "breakpoints": [{ "line": 4 }] }),
+        );
+        let body = session.receive_response();
+        assert_eq!(
+            body["breakpoints"],
+            json!([{ "verified": true, "line": 4 }])
+        );
+
+        session.send("configurationDone", json!({}));
+        session.receive_response();
+
+        let stopped = session.receive_event("stopped");
+        assert_eq!(stopped["reason"], "breakpoint");
+
+        session.send("stackTrace", json!({ "threadId": 1 }));
+        let body = session.receive_response();
+        let frames: Vec<_> = body["stackFrames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|frame| {
+                (
+                    frame["name"].as_str().unwrap().to_string(),
+                    frame["line"].as_u64().unwrap(),
+                    frame["source"]["path"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            frames,
+            vec![
+                ("print_bool".to_string(), 4, path.to_string()),
+                ("main".to_string(), 8, path.to_string()),
+            ]
+        );
+
+        session.send("scopes", json!({ "frameId": 0 }));
+        let body = session.receive_response();
+        let reference = body["scopes"][0]["variablesReference"].clone();
+
+        session.send("variables", json!({ "variablesReference": reference }));
+        let body = session.receive_response();
+        assert_eq!(
+            body["variables"],
+            json!([{ "name": "x", "value": "false", "variablesReference": 0 }])
+        );
+
+        session.send("continue", json!({ "threadId": 1 }));
+        session.receive_response();
+
+        let output = session.receive_event("output");
+        assert_eq!(output["output"], "false\n");
+        assert_eq!(session.receive_event("exited")["exitCode"], 0);
+        session.receive_event("terminated");
+
+        assert_eq!(session.disconnect(), 0);
+    }
+
+    #[test]
+    fn step() {
+        let path = "tests/test_files/call.lark";
+        let mut session = ChildSession::spawn();
+        session.initialize();
+
+        session.send("launch", json!({ "program": path, "stopOnEntry": true }));
+        session.receive_response();
+        session.send("configurationDone", json!({}));
+        session.receive_response();
+
+        let mut stops = vec![];
+        loop {
+            let message = session.receive();
+            match message["event"].as_str() {
+                Some("stopped") => {}
+                Some("output") => continue,
+                Some("exited") => break,
+                _ => panic!("unexpected message: {}", message),
+            }
+
+            session.send("stackTrace", json!({ "threadId": 1 }));
+            let frame = session.receive_response()["stackFrames"][0].clone();
+            stops.push((
+                message["body"]["reason"].as_str().unwrap().to_string(),
+                frame["line"].as_u64().unwrap(),
+            ));
+
+            session.send("stepIn", json!({ "threadId": 1 }));
+            session.receive_response();
+        }
+
+        assert_eq!(
+            stops,
+            vec![("entry".to_string(), 8), ("step".to_string(), 4)]
+        );
+
+        session.receive_event("terminated");
+        assert_eq!(session.disconnect(), 0);
+    }
+
+    #[test]
+    fn launch_errors() {
+        let mut session = ChildSession::spawn();
+        session.initialize();
+
+        session.send(
+            "launch",
+            json!({ "program": "tests/test_files/error_type_mismatch.lark" }),
+        );
+        let response = session.receive();
+        assert_eq!(response["success"], false);
+        assert!(response["message"].as_str().unwrap().contains("error"));
+
+        session.send("stackTrace", json!({ "threadId": 1 }));
+        assert_eq!(session.receive()["success"], false);
+
+        assert_eq!(session.disconnect(), 0);
+    }
+}
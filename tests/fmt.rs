@@ -0,0 +1,279 @@
+use lark_fmt::{format_edits, format_file};
+use lark_query_system::ls_ops::{Cancelled, LsDatabase};
+use lark_test::*;
+use std::fs;
+use unindent::unindent;
+
+/// Formats `text`, panicking if it has syntax errors.
+fn format(text: &str) -> String {
+    let file_name = "input.lark";
+    let db = db_with_test(file_name, text);
+    match format_file(&db, file_name.into_file_name(&db)) {
+        Ok(formatted) => formatted,
+        Err(errors) => panic!("unexpected syntax errors: {:?}", errors),
+    }
+}
+
+/// Checks that `input` formats as `expected`, and that `expected` is
+/// already formatted.
+fn assert_formats(input: &str, expected: &str) {
+    let expected = unindent(expected);
+    assert_eq!(format(&unindent(input)), expected);
+    assert_eq!(format(&expected), expected);
+}
+
+#[test]
+fn spacing() {
+    assert_formats(
+        "def add(x:uint,y:uint)->uint{x+y}",
+        "
+        def add(x: uint, y: uint) -> uint { x + y }
+        ",
+    );
+}
+
+#[test]
+fn operators() {
+    assert_formats(
+        "
+        def main() {
+          let a = - 7
+          if !(a<b)&&a>=b { debug(a- -1) }
+        }
+        ",
+        "
+        def main() {
+            let a = -7
+            if !(a < b) && a >= b { debug(a - -1) }
+        }
+        ",
+    );
+}
+
+#[test]
+fn generics() {
+    assert_formats(
+        "def swap<A,B>(pair:Pair<A,B>)->Pair<B,A>{flip(pair.second,pair.first)}",
+        "
+        def swap<A, B>(pair: Pair<A, B>) -> Pair<B, A> { flip(pair.second, pair.first) }
+        ",
+    );
+}
+
+#[test]
+fn indentation_and_blank_lines() {
+    assert_formats(
+        "
+
+
+        use math.square
+        use shapes.Point
+        def main() {
+        debug(1)
+
+
+
+                debug(2)
+
+        }
+        // Does nothing
+        def nothing() {
+        }
+        ",
+        "
+        use math.square
+        use shapes.Point
+
+        def main() {
+            debug(1)
+
+            debug(2)
+        }
+
+        // Does nothing
+        def nothing() {
+        }
+        ",
+    );
+}
+
+#[test]
+fn lists() {
+    assert_formats(
+        "
+        struct Point {
+          x: uint
+          y: uint,
+        }
+
+        def add3(x: uint,
+          y: uint, z: uint,) -> uint {
+          add(x, add(y, z,),)
+        }
+        ",
+        "
+        struct Point {
+            x: uint,
+            y: uint,
+        }
+
+        def add3(
+            x: uint,
+            y: uint,
+            z: uint,
+        ) -> uint {
+            add(x, add(y, z))
+        }
+        ",
+    );
+}
+
+#[test]
+fn aggregate_fields() {
+    let expected = "
+        struct Point { x: uint, y: uint }
+
+        def main() {
+            let point = Point(
+                x: 1,
+                y: 2,
+            )
+        }
+        ";
+    assert_formats(
+        "
+        struct Point { x: uint, y: uint }
+
+        def main() {
+          let point = Point(x: 1,
+            y: 2)
+        }
+        ",
+        expected,
+    );
+
+    // The fields can start on the line after the `(`
+    assert!(error_labels(&unindent(expected)).is_empty());
+}
+
+#[test]
+fn match_arms() {
+    assert_formats(
+        "
+        def area(shape: Shape) -> uint {
+          match shape {
+            Shape.Square(side) => side * side
+          }
+        }
+        ",
+        "
+        def area(shape: Shape) -> uint {
+            match shape {
+                Shape.Square(side) => side * side,
+            }
+        }
+        ",
+    );
+}
+
+#[test]
+fn comments() {
+    assert_formats(
+        "
+        def main() {
+                // Say hi
+          debug(1)   // one
+        }
+        ",
+        "
+        def main() {
+            // Say hi
+            debug(1) // one
+        }
+        ",
+    );
+}
+
+#[test]
+fn edits_are_small() {
+    let file_name = "input.lark";
+    let db = db_with_test(file_name, "def main() {\n  debug(1)\n}\n");
+    let edits = format_edits(&db, file_name.into_file_name(&db)).unwrap();
+
+    assert_eq!(edits.len(), 1);
+    assert_eq!(edits[0].span.start().to_usize(), 12);
+    assert_eq!(edits[0].span.end().to_usize(), 15);
+    assert_eq!(edits[0].replacement, "\n    ");
+}
+
+#[test]
+fn syntax_errors() {
+    let file_name = "input.lark";
+    let db = db_with_test(file_name, "struct {");
+    assert!(format_edits(&db, file_name.into_file_name(&db)).is_err());
+}
+
+/// The labels of the errors in `text`, when it is the only file.
+fn error_labels(text: &str) -> Vec<String> {
+    let file_name = "input.lark";
+    let db = db_with_test(file_name, text);
+    let mut labels: Vec<String> = db
+        .errors_for_file(file_name.into_file_name(&db))
+        .unwrap_or_else(|Cancelled| panic!("cancelled?"))
+        .into_iter()
+        .map(|error| error.label)
+        .collect();
+    labels.sort();
+    labels
+}
+
+#[test]
+fn test_files() {
+    // A test that is a directory has a file for each module
+    let mut paths = vec![];
+    for test_path in search_files("tests/test_files") {
+        if test_path.is_dir {
+            for entry in fs::read_dir(&test_path.test_path).unwrap() {
+                paths.push(entry.unwrap().path());
+            }
+        } else {
+            paths.push(test_path.test_path);
+        }
+    }
+    paths.retain(|path| {
+        path.extension()
+            .map_or(false, |extension| extension == "lark")
+    });
+
+    let mut formatted_files = 0;
+    for path in &paths {
+        let text = fs::read_to_string(path).unwrap();
+        let file_name = "input.lark";
+        let db = db_with_test(file_name, &text);
+
+        // Some tests are about syntax errors, which we don't format
+        let formatted = match format_file(&db, file_name.into_file_name(&db)) {
+            Ok(formatted) => formatted,
+            Err(_) => continue,
+        };
+        formatted_files += 1;
+
+        assert_eq!(
+            format(&formatted),
+            formatted,
+            "formatting `{}` twice changes it",
+            path.display()
+        );
+
+        // Function bodies are only parsed as they are needed, so check
+        // them too (along with everything else)
+        assert_eq!(
+            error_labels(&formatted),
+            error_labels(&text),
+            "formatting `{}` changes its errors",
+            path.display()
+        );
+    }
+
+    assert!(formatted_files > paths.len() / 2);
+}
@@ -1,12 +1,24 @@
 #[cfg(test)]
 mod tests {
     use languageserver_types::{
-        ClientCapabilities, DidOpenTextDocumentParams, InitializeParams, InitializeResult,
-        PublishDiagnosticsParams, TextDocumentItem,
+        CancelParams, ClientCapabilities, CodeAction, CodeActionContext, CodeActionParams,
+        DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+        DocumentFormattingParams, DocumentRangeFormattingParams, FormattingOptions, Hover,
+        HoverContents, InitializeParams, InitializeResult, MarkedString, NumberOrString, Position,
+        PublishDiagnosticsParams, Range, TextDocumentContentChangeEvent, TextDocumentIdentifier,
+        TextDocumentItem, TextDocumentPositionParams, TextEdit, VersionedTextDocumentIdentifier,
+    };
+    use lark_language_server::inlay_hints::{InlayHint, InlayHintParams};
+    use lark_language_server::semantic_tokens::{
+        SemanticTokens, SemanticTokensDeltaParams, SemanticTokensDeltaResult, SemanticTokensParams,
+    };
+    use lark_language_server::{
+        JsonRPCErrorResponse, JsonRPCNotification, JsonRPCResponse, LSPCommand, INVALID_REQUEST,
+        REQUEST_FAILED,
     };
-    use lark_language_server::{JsonRPCNotification, JsonRPCResponse, LSPCommand};
     use serde::{Deserialize, Serialize};
-    use std::io::{Read, Write};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
     use std::panic;
     use std::process::{Command, Stdio};
 
@@ -20,11 +32,58 @@ mod tests {
         }
     }
 
+    /// Sends `msg` to the server at the other end of `writer`.
+    fn write_message<T: Serialize>(
+        writer: &mut impl Write,
+        msg: T,
+    ) -> Result<(), Box<std::error::Error>> {
+        let msg_raw = serde_json::to_string(&msg)?;
+
+        writer.write_all(format!("Content-Length: {}\r\n\r\n", msg_raw.len()).as_bytes())?;
+        writer.write_all(msg_raw.as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Receives the next message from the server at the other end of
+    /// `reader`.
+    fn read_message<T: for<'de> Deserialize<'de>>(
+        reader: &mut impl Read,
+    ) -> Result<T, Box<std::error::Error>> {
+        let mut buffer = [0; 16];
+        reader.read_exact(&mut buffer[..])?;
+
+        let mut digits = String::new();
+        let mut digit = [0; 1];
+        loop {
+            reader.read_exact(&mut digit[..])?;
+            let char_digit = digit[0] as char;
+
+            if char_digit.is_digit(10) {
+                digits.push(char_digit);
+            } else {
+                let mut whitespace = [0; 3];
+                reader.read_exact(&mut whitespace[..])?;
+                break;
+            }
+        }
+        let num_bytes: usize = digits.trim().parse()?;
+        let mut buffer = vec![0u8; num_bytes];
+        let _ = reader.read_exact(&mut buffer);
+
+        let buffer_string = String::from_utf8(buffer)?;
+
+        let response: T = serde_json::from_str(&buffer_string)?;
+        Ok(response)
+    }
+
     impl ChildSession {
         fn spawn() -> ChildSession {
             let child = Command::new("cargo")
                 .arg("run")
                 .arg("--")
+                .arg("--log-dir")
+                .arg(std::env::temp_dir().join("lark_test_logs"))
                 .arg("ide")
                 .stdin(Stdio::piped())
                 .stdout(Stdio::piped())
@@ -40,17 +99,7 @@ mod tests {
                 "can connect to child stdin",
             ))?;
 
-            let msg_raw = serde_json::to_string(&msg)?;
-
-            child_stdin
-                .write_all(format!("Content-Length: {}\r\n\r\n", msg_raw.len()).as_bytes())
-                .expect("Failed to write to stdin");
-            child_stdin
-                .write_all(msg_raw.as_bytes())
-                .expect("Failed to write to stdin");
-            //let _ = io::stdout().flush();
-
-            Ok(())
+            write_message(child_stdin, msg)
         }
 
         fn receive<T: for<'de> Deserialize<'de>>(&mut self) -> Result<T, Box<std::error::Error>> {
@@ -59,48 +108,66 @@ mod tests {
                 "can connect to child stdout",
             ))?;
 
-            let mut buffer = [0; 16];
-            child_stdout.read(&mut buffer[..])?;
+            read_message(child_stdout)
+        }
 
-            let mut digits = String::new();
-            let mut digit = [0; 1];
+        /// Receives the response to a request, skipping over any
+        /// notifications (such as diagnostics) that come first.
+        fn receive_response<T: for<'de> Deserialize<'de>>(
+            &mut self,
+        ) -> Result<JsonRPCResponse<T>, Box<std::error::Error>> {
             loop {
-                child_stdout.read(&mut digit[..])?;
-                let char_digit = digit[0] as char;
-
-                if char_digit.is_digit(10) {
-                    digits.push(char_digit);
-                } else {
-                    let mut whitespace = [0; 3];
-                    child_stdout.read(&mut whitespace[..])?;
-                    break;
+                let message = self.receive::<serde_json::Value>()?;
+                if message.get("method").is_none() {
+                    return Ok(serde_json::from_value(message)?);
                 }
             }
-            let num_bytes: usize = digits.trim().parse()?;
-            let mut buffer = vec![0u8; num_bytes];
-            let _ = child_stdout.read_exact(&mut buffer);
+        }
 
-            let buffer_string = String::from_utf8(buffer)?;
+        /// Receives the error response to a request, skipping over any
+        /// notifications that come first.
+        fn receive_error(&mut self) -> Result<JsonRPCErrorResponse, Box<std::error::Error>> {
+            loop {
+                let message = self.receive::<serde_json::Value>()?;
+                if message.get("method").is_none() {
+                    return Ok(serde_json::from_value(message)?);
+                }
+            }
+        }
 
-            let response: T = serde_json::from_str(&buffer_string)?;
-            Ok(response)
+        /// Sends `exit`, returning the exit status of the server.
+        fn exit(&mut self) -> Result<i32, Box<std::error::Error>> {
+            self.send(LSPCommand::exit)?;
+            let status = self.child.wait()?;
+            Ok(status.code().expect("the server was killed by a signal"))
         }
 
         fn send_init(&mut self, id: usize) -> Result<(), Box<std::error::Error>> {
+            self.send_init_with_encodings(id, vec![])
+        }
+
+        fn send_init_with_encodings(
+            &mut self,
+            id: usize,
+            position_encodings: Vec<String>,
+        ) -> Result<(), Box<std::error::Error>> {
             self.send(LSPCommand::initialize {
                 id,
-                params: InitializeParams {
-                    process_id: None,
-                    root_path: None,
-                    root_uri: None,
-                    initialization_options: None,
-                    capabilities: ClientCapabilities {
-                        experimental: None,
-                        text_document: None,
-                        workspace: None,
+                params: lark_language_server::InitializeParams {
+                    standard: InitializeParams {
+                        process_id: None,
+                        root_path: None,
+                        root_uri: None,
+                        initialization_options: None,
+                        capabilities: ClientCapabilities {
+                            experimental: None,
+                            text_document: None,
+                            workspace: None,
+                        },
+                        trace: None,
+                        workspace_folders: None,
                     },
-                    trace: None,
-                    workspace_folders: None,
+                    position_encodings,
                 },
             })
         }
@@ -127,6 +194,108 @@ mod tests {
         }
     }
 
+    impl ChildSession {
+        /// Sends the changes to the text of `filepath` (none of which
+        /// have a `range_length`).
+        fn send_changes(
+            &mut self,
+            filepath: &str,
+            version: u64,
+            changes: Vec<(Option<Range>, &str)>,
+        ) -> Result<(), Box<std::error::Error>> {
+            self.send(LSPCommand::didChange {
+                params: DidChangeTextDocumentParams {
+                    text_document: VersionedTextDocumentIdentifier {
+                        uri: file_url(filepath)?,
+                        version: Some(version),
+                    },
+                    content_changes: changes
+                        .into_iter()
+                        .map(|(range, text)| TextDocumentContentChangeEvent {
+                            range,
+                            range_length: None,
+                            text: text.to_string(),
+                        })
+                        .collect(),
+                },
+            })
+        }
+
+        /// The parameters of the next `textDocument/publishDiagnostics`
+        /// notification, skipping over any other messages.
+        fn receive_diagnostics(&mut self) -> Result<serde_json::Value, Box<std::error::Error>> {
+            loop {
+                let mut message = self.receive::<serde_json::Value>()?;
+                if message["method"] == "textDocument/publishDiagnostics" {
+                    return Ok(message["params"].take());
+                }
+            }
+        }
+
+        /// The hover text at `position` in `filepath`.
+        fn hover(
+            &mut self,
+            id: usize,
+            filepath: &str,
+            position: Position,
+        ) -> Result<String, Box<std::error::Error>> {
+            self.send(LSPCommand::hover {
+                id,
+                params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: file_url(filepath)?,
+                    },
+                    position,
+                },
+            })?;
+
+            let result = self.receive_response::<Hover>()?;
+            assert_eq!(result.id, id);
+            match result.result.contents {
+                HoverContents::Scalar(MarkedString::String(text)) => Ok(text),
+                contents => panic!("unexpected hover contents: {:?}", contents),
+            }
+        }
+
+        /// The edits of the code action called `title` at `position` in
+        /// `filepath`.
+        fn fix_edits(
+            &mut self,
+            id: usize,
+            filepath: &str,
+            position: Position,
+            title: &str,
+        ) -> Result<Vec<TextEdit>, Box<std::error::Error>> {
+            self.send(LSPCommand::codeAction {
+                id,
+                params: CodeActionParams {
+                    text_document: TextDocumentIdentifier {
+                        uri: file_url(filepath)?,
+                    },
+                    range: Range::new(position, position),
+                    context: CodeActionContext {
+                        diagnostics: vec![],
+                        only: None,
+                    },
+                },
+            })?;
+
+            let result = self.receive_response::<Vec<CodeAction>>()?;
+            assert_eq!(result.id, id);
+            let action = result
+                .result
+                .into_iter()
+                .find(|action| action.title == title)
+                .unwrap_or_else(|| panic!("no fix called {:?}", title));
+            Ok(action
+                .edit
+                .into_iter()
+                .flat_map(|edit| edit.changes.into_iter().flat_map(|changes| changes))
+                .flat_map(|(_, edits)| edits)
+                .collect())
+        }
+    }
+
     #[test]
     fn find_expected_error_message() -> Result<(), Box<std::error::Error>> {
         let mut child_session = ChildSession::spawn();
@@ -149,4 +318,629 @@ mod tests {
 
         Ok(())
     }
+
+    fn file_url(filepath: &str) -> Result<url::Url, Box<std::error::Error>> {
+        let path = std::path::Path::new(filepath).canonicalize()?;
+        Ok(url::Url::parse(&format!(
+            "file:///{}",
+            path.to_str().ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Bad filepath")
+            })?
+        ))?)
+    }
+
+    #[test]
+    fn semantic_tokens_legend() -> Result<(), Box<std::error::Error>> {
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<serde_json::Value>()?;
+        assert_eq!(result.id, 100);
+
+        // The shape the VS Code client (vscode-languageclient 7) looks for
+        // before it asks for any tokens
+        let provider = &result.result["capabilities"]["semanticTokensProvider"];
+        let token_types = provider["legend"]["tokenTypes"].as_array().unwrap();
+        assert!(token_types.contains(&"function".into()));
+        assert!(token_types.contains(&"variable".into()));
+        assert_eq!(
+            provider["legend"]["tokenModifiers"],
+            serde_json::json!(["declaration"])
+        );
+        assert_eq!(provider["full"]["delta"], true);
+
+        Ok(())
+    }
+
+    #[test]
+    fn semantic_tokens_delta_matches_full() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/semantic_tokens.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<InitializeResult>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+
+        child_session.send(LSPCommand::semanticTokensFull {
+            id: 101,
+            params: SemanticTokensParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+            },
+        })?;
+        let before = child_session.receive_response::<SemanticTokens>()?;
+        assert_eq!(before.id, 101);
+
+        // Add a `let` to the start of `main`
+        let position = Position::new(19, 4);
+        child_session.send(LSPCommand::didChange {
+            params: DidChangeTextDocumentParams {
+                text_document: VersionedTextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                    version: Some(2),
+                },
+                content_changes: vec![TextDocumentContentChangeEvent {
+                    range: Some(Range::new(position, position)),
+                    range_length: None,
+                    text: "let unused = 22\n    ".to_string(),
+                }],
+            },
+        })?;
+
+        child_session.send(LSPCommand::semanticTokensFullDelta {
+            id: 102,
+            params: SemanticTokensDeltaParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+                previous_result_id: before.result.result_id.unwrap(),
+            },
+        })?;
+        let delta = child_session.receive_response::<SemanticTokensDeltaResult>()?;
+        assert_eq!(delta.id, 102);
+
+        let mut data = before.result.data;
+        match delta.result {
+            SemanticTokensDeltaResult::Delta(delta) => {
+                assert!(!delta.edits.is_empty());
+                for edit in delta.edits.iter().rev() {
+                    let start = edit.start as usize;
+                    let end = start + edit.delete_count as usize;
+                    data.splice(start..end, edit.data.iter().cloned());
+                }
+            }
+            SemanticTokensDeltaResult::Full(_) => panic!("expected a delta"),
+        }
+
+        child_session.send(LSPCommand::semanticTokensFull {
+            id: 103,
+            params: SemanticTokensParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+            },
+        })?;
+        let after = child_session.receive_response::<SemanticTokens>()?;
+        assert_eq!(after.id, 103);
+        assert_eq!(data, after.result.data);
+
+        Ok(())
+    }
+
+    fn formatting_options() -> FormattingOptions {
+        FormattingOptions {
+            tab_size: 4,
+            insert_spaces: true,
+            properties: Default::default(),
+        }
+    }
+
+    #[test]
+    fn formatting() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/call.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<InitializeResult>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+
+        // The whole file: the trailing comma goes, and a final newline
+        // is added
+        child_session.send(LSPCommand::formatting {
+            id: 101,
+            params: DocumentFormattingParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+                options: formatting_options(),
+            },
+        })?;
+        let result = child_session.receive_response::<Vec<TextEdit>>()?;
+        assert_eq!(result.id, 101);
+        assert_eq!(
+            result.result,
+            vec![
+                TextEdit::new(
+                    Range::new(Position::new(2, 22), Position::new(2, 23)),
+                    String::new(),
+                ),
+                TextEdit::new(
+                    Range::new(Position::new(8, 1), Position::new(8, 1)),
+                    "\n".to_string(),
+                ),
+            ]
+        );
+
+        // Just the signature of `print_bool`
+        child_session.send(LSPCommand::rangeFormatting {
+            id: 102,
+            params: DocumentRangeFormattingParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+                range: Range::new(Position::new(2, 0), Position::new(2, 25)),
+                options: formatting_options(),
+            },
+        })?;
+        let result = child_session.receive_response::<Vec<TextEdit>>()?;
+        assert_eq!(result.id, 102);
+        assert_eq!(
+            result.result,
+            vec![TextEdit::new(
+                Range::new(Position::new(2, 22), Position::new(2, 23)),
+                String::new(),
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn utf16_edits() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/non_ascii.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<serde_json::Value>()?;
+        assert_eq!(result.id, 100);
+        assert_eq!(result.result["capabilities"]["positionEncoding"], "utf-16");
+
+        child_session.send_open(filepath)?;
+
+        // `ä` comes after `"🦀🦀"`, which is six UTF-16 code units
+        let line = "    let b = both(\"🦀🦀\", ä)";
+        assert_eq!(
+            std::fs::read_to_string(filepath)?.lines().nth(17),
+            Some(line)
+        );
+        assert_eq!(
+            child_session.hover(101, filepath, Position::new(17, 25))?,
+            "uint"
+        );
+
+        // Changes outside of the text, or inside a character, are ignored
+        let past_the_end = Range::new(Position::new(40, 0), Position::new(40, 1));
+        child_session.send_changes(filepath, 2, vec![(Some(past_the_end), "x")])?;
+        let inside_crab = Range::new(Position::new(17, 19), Position::new(17, 19));
+        child_session.send_changes(filepath, 3, vec![(Some(inside_crab), "x")])?;
+        assert_eq!(
+            child_session.hover(102, filepath, Position::new(17, 25))?,
+            "uint"
+        );
+
+        // Replace `"🦀🦀"` with `"ö"`, which moves `ä` back three
+        let string = Range::new(Position::new(17, 17), Position::new(17, 23));
+        child_session.send_changes(filepath, 4, vec![(Some(string), "\"ö\"")])?;
+        assert_eq!(
+            child_session.hover(103, filepath, Position::new(17, 22))?,
+            "uint"
+        );
+
+        // A change without a range replaces the whole text
+        let text = "def main() {\n    let s = \"🦀\"\n    debug(s)\n}\n";
+        child_session.send_changes(filepath, 5, vec![(None, text)])?;
+        assert_eq!(
+            child_session.hover(104, filepath, Position::new(2, 10))?,
+            "String"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn utf8_positions() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/non_ascii.lark";
+        let mut child_session = ChildSession::spawn();
+
+        let encodings = vec!["utf-16".to_string(), "utf-8".to_string()];
+        child_session.send_init_with_encodings(100, encodings)?;
+        let result = child_session.receive_response::<serde_json::Value>()?;
+        assert_eq!(result.id, 100);
+        assert_eq!(result.result["capabilities"]["positionEncoding"], "utf-8");
+
+        child_session.send_open(filepath)?;
+
+        // The argument `ö` in `let ä = größe(ö)`, and `ä` in
+        // `let b = both("🦀🦀", ä)`, counting bytes
+        assert_eq!(
+            child_session.hover(101, filepath, Position::new(15, 21))?,
+            "uint"
+        );
+        assert_eq!(
+            child_session.hover(102, filepath, Position::new(17, 29))?,
+            "uint"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_are_versioned_and_cleared() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/error_type_mismatch.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<InitializeResult>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+        let params = child_session.receive_diagnostics()?;
+        assert_eq!(params["uri"], file_url(filepath)?.as_str());
+        assert_eq!(params["version"], 1);
+        let diagnostics = params["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["code"], "type-error");
+        assert_eq!(diagnostics[0]["source"], "lark");
+
+        // Fixing the error publishes an empty set, which clears it
+        let fixed = "def foo(x: bool, y: uint) -> bool {\n  x\n}\n";
+        child_session.send_changes(filepath, 2, vec![(None, fixed)])?;
+        let params = child_session.receive_diagnostics()?;
+        assert_eq!(params["version"], 2);
+        assert_eq!(params["diagnostics"], serde_json::json!([]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_after_rapid_edits() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/error_type_mismatch.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<InitializeResult>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+        child_session.receive_diagnostics()?;
+
+        // Typing `def f<T, T>() {}` a bit at a time only gets checked
+        // once we stop
+        for (version, text) in vec![
+            (2, "def f<T"),
+            (3, "def f<T, T>"),
+            (4, "def f<T, T>() {}\n"),
+        ] {
+            child_session.send_changes(filepath, version, vec![(None, text)])?;
+        }
+        let params = child_session.receive_diagnostics()?;
+        assert_eq!(params["version"], 4);
+        let diagnostics = params["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["code"], "duplicate-definition");
+        assert_eq!(diagnostics[0]["range"]["start"]["character"], 9);
+
+        let related = diagnostics[0]["relatedInformation"].as_array().unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0]["message"], "first declared here");
+        assert_eq!(related[0]["location"]["uri"], file_url(filepath)?.as_str());
+        assert_eq!(related[0]["location"]["range"]["start"]["character"], 6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_version_forgotten_on_close() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/error_type_mismatch.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<InitializeResult>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+        let params = child_session.receive_diagnostics()?;
+        assert_eq!(params["version"], 1);
+
+        // The file is still checked once it is closed, but the IDE no
+        // longer tracks its version
+        child_session.send(LSPCommand::didClose {
+            params: DidCloseTextDocumentParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+            },
+        })?;
+        let params = child_session.receive_diagnostics()?;
+        assert_eq!(params["uri"], file_url(filepath)?.as_str());
+        assert_eq!(params["version"], serde_json::Value::Null);
+        assert_eq!(params["diagnostics"].as_array().unwrap().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn inlay_hints_in_range() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/inlay_hints.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<serde_json::Value>()?;
+        assert_eq!(result.id, 100);
+        assert_eq!(result.result["capabilities"]["inlayHintProvider"], true);
+
+        child_session.send_open(filepath)?;
+
+        // Just the body of `main`
+        child_session.send(LSPCommand::inlayHint {
+            id: 101,
+            params: InlayHintParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+                range: Range::new(Position::new(32, 0), Position::new(36, 0)),
+            },
+        })?;
+        let result = child_session.receive_response::<Vec<InlayHint>>()?;
+        assert_eq!(result.id, 101);
+
+        let hints: Vec<(Position, &str)> = result
+            .result
+            .iter()
+            .map(|hint| (hint.position, hint.label.as_str()))
+            .collect();
+        assert_eq!(
+            hints,
+            vec![
+                (Position::new(32, 12), ": own Pair"),
+                (Position::new(34, 20), ": own Pair"),
+                (Position::new(34, 21), ": own uint"),
+            ]
+        );
+        assert!(result.result.iter().all(|hint| hint.kind == 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_function_fix() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/create_function_fix.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<serde_json::Value>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+
+        // The parameters come from the parsed arguments, whatever the
+        // quotes, commas and parentheses in their text and comments
+        let edits = child_session.fix_edits(
+            101,
+            filepath,
+            Position::new(2, 2),
+            "Create function `total`",
+        )?;
+        let parameters = "arg0: String, arg1: String, count: uint, arg3: int, arg4: bool";
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].new_text,
+            format!("\n\ndef total({}) {{\n}}", parameters)
+        );
+
+        // Repeated names are numbered
+        let edits = child_session.fix_edits(
+            102,
+            filepath,
+            Position::new(29, 2),
+            "Create function `twice`",
+        )?;
+        let parameters = "x: uint, x1: uint, b: uint, b1: uint, x2: uint";
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].new_text,
+            format!("\n\ndef twice({}) {{\n}}", parameters)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_member_fix() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/missing_member_fix.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<serde_json::Value>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+
+        // Each member goes right after the last field, whatever the
+        // parentheses, commas and comments around it: the fix for the
+        // aggregate on `line` (starting at `start`) inserts `text` at
+        // `end`
+        let cases = vec![
+            (16, 14, "y", 26, ", y: 0"),
+            (20, 16, "label", 38, ", label: \"\""),
+            (23, 15, "side", 27, "(side: 0)"),
+        ];
+        for (id, (line, start, name, end, text)) in (101..).zip(cases) {
+            let title = format!("Add missing member `{}`", name);
+            let position = Position::new(line, start);
+            let edits = child_session.fix_edits(id, filepath, position, &title)?;
+            let at = Position::new(line, end);
+            assert_eq!(edits, vec![TextEdit::new(Range::new(at, at), text.into())]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn shutdown_then_exit() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/inlay_hints.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<InitializeResult>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+
+        child_session.send(LSPCommand::shutdown { id: 101 })?;
+        let result = child_session.receive_response::<()>()?;
+        assert_eq!(result.id, 101);
+
+        // Requests after `shutdown` are errors
+        child_session.send(LSPCommand::hover {
+            id: 102,
+            params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+                position: Position::new(17, 8),
+            },
+        })?;
+        let result = child_session.receive_error()?;
+        assert_eq!(result.id, 102);
+        assert_eq!(result.error.code, INVALID_REQUEST);
+
+        assert_eq!(child_session.exit()?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn exit_without_shutdown() -> Result<(), Box<std::error::Error>> {
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<InitializeResult>()?;
+        assert_eq!(result.id, 100);
+
+        assert_eq!(child_session.exit()?, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn failing_requests() -> Result<(), Box<std::error::Error>> {
+        let filepath = "tests/test_files/inlay_hints.lark";
+        let mut child_session = ChildSession::spawn();
+
+        child_session.send_init(100)?;
+        let result = child_session.receive_response::<InitializeResult>()?;
+        assert_eq!(result.id, 100);
+
+        child_session.send_open(filepath)?;
+
+        // Finding the references to a field that doesn't exist fails
+        let text = "struct Foo {\n    bar: bool\n}\n\ndef foo(x: Foo) -> bool {\n    x.baz\n}\n";
+        child_session.send_changes(filepath, 2, vec![(None, text)])?;
+        child_session.send(LSPCommand::references {
+            id: 101,
+            params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: file_url(filepath)?,
+                },
+                position: Position::new(5, 7),
+            },
+        })?;
+        let result = child_session.receive_error()?;
+        assert_eq!(result.id, 101);
+        assert_eq!(result.error.code, REQUEST_FAILED);
+
+        // Cancelling a request that we don't know about (which a
+        // string ID can't be) is ignored
+        child_session.send(LSPCommand::cancelRequest {
+            params: CancelParams {
+                id: NumberOrString::String("not-a-request".to_string()),
+            },
+        })?;
+
+        // The server carries on after both
+        assert_eq!(
+            child_session.hover(102, filepath, Position::new(5, 4))?,
+            "Foo"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn listen_over_tcp() -> Result<(), Box<std::error::Error>> {
+        let mut child = Command::new("cargo")
+            .arg("run")
+            .arg("--")
+            .arg("--log-dir")
+            .arg(std::env::temp_dir().join("lark_test_logs"))
+            .arg("ide")
+            .arg("--listen")
+            .arg("127.0.0.1:0")
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // The server says which port it got once it is listening
+        let mut stderr = BufReader::new(child.stderr.take().unwrap());
+        let address = loop {
+            let mut line = String::new();
+            if stderr.read_line(&mut line)? == 0 {
+                panic!("the server stopped without listening");
+            }
+            if line.starts_with("Listening on ") {
+                break line["Listening on ".len()..].trim().to_string();
+            }
+        };
+
+        let mut stream = TcpStream::connect(&address)?;
+        write_message(
+            &mut stream,
+            LSPCommand::initialize {
+                id: 100,
+                params: lark_language_server::InitializeParams {
+                    standard: InitializeParams {
+                        process_id: None,
+                        root_path: None,
+                        root_uri: None,
+                        initialization_options: None,
+                        capabilities: ClientCapabilities {
+                            experimental: None,
+                            text_document: None,
+                            workspace: None,
+                        },
+                        trace: None,
+                        workspace_folders: None,
+                    },
+                    position_encodings: vec![],
+                },
+            },
+        )?;
+        let result: JsonRPCResponse<InitializeResult> = read_message(&mut stream)?;
+        assert_eq!(result.id, 100);
+
+        write_message(&mut stream, LSPCommand::shutdown { id: 101 })?;
+        let result: JsonRPCResponse<()> = read_message(&mut stream)?;
+        assert_eq!(result.id, 101);
+
+        write_message(&mut stream, LSPCommand::exit)?;
+        assert_eq!(child.wait()?.code(), Some(0));
+
+        Ok(())
+    }
 }
@@ -42,3 +42,64 @@ fn location() {
         &loc_4,
     );
 }
+
+#[test]
+fn position_encodings() {
+    use languageserver_types::Position;
+    use lark_span::{OutOfBounds, PositionEncoding};
+
+    let file_name = "foo.lark";
+    // `ö` is bytes 4..6, `🦀` is bytes 10..14, and `x` is byte 16
+    let mut db = db_with_test(file_name, "let ö = \"🦀\"\nx");
+    let file_name = file_name.into_file_name(&db);
+
+    // UTF-16 is the default, in which `🦀` is two code units
+    assert_eq!(
+        db.position(file_name, ByteIndex::from(14)),
+        Position::new(0, 11)
+    );
+    assert_eq!(
+        db.position(file_name, ByteIndex::from(16)),
+        Position::new(1, 0)
+    );
+    assert_eq!(
+        db.position(file_name, ByteIndex::from(17)),
+        Position::new(1, 1)
+    );
+    assert_eq!(db.byte_index(file_name, 0, 11), Ok(ByteIndex::from(14)));
+    assert_eq!(db.byte_index(file_name, 1, 1), Ok(ByteIndex::from(17)));
+
+    // In the middle of `🦀`, or past the last line
+    assert_eq!(db.byte_index(file_name, 0, 10), Err(OutOfBounds));
+    assert_eq!(db.byte_index(file_name, 2, 0), Err(OutOfBounds));
+
+    // Past the end of a line means the end of that line
+    assert_eq!(db.byte_index(file_name, 0, 100), Ok(ByteIndex::from(15)));
+
+    db.set_position_encoding(PositionEncoding::Utf8);
+    assert_eq!(
+        db.position(file_name, ByteIndex::from(14)),
+        Position::new(0, 14)
+    );
+    assert_eq!(db.byte_index(file_name, 0, 6), Ok(ByteIndex::from(6)));
+    assert_eq!(db.byte_index(file_name, 0, 5), Err(OutOfBounds));
+}
+
+#[test]
+fn position_at_end_of_file() {
+    use languageserver_types::Position;
+
+    // The end of a file is only on a line of its own after a newline
+    for (text, position) in &[("ab", Position::new(0, 2)), ("ab\n", Position::new(1, 0))] {
+        let file_name = "foo.lark";
+        let db = db_with_test(file_name, text);
+        let file_name = file_name.into_file_name(&db);
+        let end = ByteIndex::from(text.len());
+
+        assert_eq!(db.position(file_name, end), *position);
+        assert_eq!(
+            db.byte_index(file_name, position.line, position.character),
+            Ok(end)
+        );
+    }
+}
@@ -19,6 +19,8 @@ mod tests {
             let child = Command::new("cargo")
                 .arg("run")
                 .arg("--")
+                .arg("--log-dir")
+                .arg(std::env::temp_dir().join("lark_test_logs"))
                 .arg("repl")
                 .stdin(Stdio::piped())
                 .stdout(Stdio::piped())
@@ -82,4 +84,163 @@ mod tests {
 
         assert_eq!(result, " true\n>");
     }
+
+    #[test]
+    fn repl_test_runtime_error() {
+        let mut child_session = ChildSession::spawn();
+
+        let _ = child_session.receive();
+
+        child_session.send("let x = 1\n").unwrap();
+        let _result = child_session.receive().unwrap();
+
+        // The error is reported on stderr, and the REPL carries on
+        child_session.send("debug(x / 0)\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " >");
+
+        child_session.send("debug(x + 1)\n").unwrap();
+        let result = child_session.receive().unwrap();
+
+        assert_eq!(result, " 2\n>");
+    }
+
+    #[test]
+    fn repl_test_definitions() {
+        let mut child_session = ChildSession::spawn();
+
+        let _ = child_session.receive();
+
+        // Inputs with open braces continue onto the next line
+        child_session
+            .send("struct Point {\n  x: uint,\n  y: uint,\n}\n")
+            .unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " ... ... ... >");
+
+        child_session
+            .send("def sum(p: Point) -> uint {\n  p.x + p.y\n}\n")
+            .unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session.send("let p = Point(x: 3, y: 4)\n").unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session.send("sum(p)\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " 7\n>");
+
+        // Redefining a function replaces it, and keeps the variables
+        child_session
+            .send("def sum(p: Point) -> uint { p.x * p.y }\n")
+            .unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session.send("sum(p)\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " 12\n>");
+    }
+
+    #[test]
+    fn repl_test_no_reevaluation() {
+        let mut child_session = ChildSession::spawn();
+
+        let _ = child_session.receive();
+
+        child_session.send("let x = 1\n").unwrap();
+        let _result = child_session.receive().unwrap();
+
+        // Earlier inputs are not run again, so this prints only once
+        child_session.send("debug(x)\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " 1\n>");
+
+        child_session.send("x = x + 1\n").unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session.send("x\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " 2\n>");
+    }
+
+    #[test]
+    fn repl_test_commands() {
+        let mut child_session = ChildSession::spawn();
+
+        let _ = child_session.receive();
+
+        child_session.send("let x = 1\n").unwrap();
+        let _result = child_session.receive().unwrap();
+
+        // `:type` checks an expression without running it
+        child_session.send(":type x > 0\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " bool\n>");
+
+        child_session
+            .send(":load tests/test_files/call.lark\n")
+            .unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session.send(":type print_bool(true)\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " void\n>");
+
+        child_session.send("print_bool(true)\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " true\n>");
+
+        // `:reset` forgets the definitions and the variables
+        child_session.send(":reset\n").unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session.send(":v\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " >");
+
+        child_session.send(":p\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " \n>");
+    }
+
+    #[test]
+    fn repl_test_generic_and_enum_bindings() {
+        let mut child_session = ChildSession::spawn();
+
+        let _ = child_session.receive();
+
+        child_session
+            .send("struct Wrapper<T> {\n  value: T\n}\n")
+            .unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session
+            .send("enum Maybe {\n  Some(value: uint),\n  None\n}\n")
+            .unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " ... ... ... >");
+
+        // Later inputs take the bindings as parameters, declared with
+        // their full types (`Wrapper<uint>`, not just `Wrapper`)
+        child_session.send("let w = Wrapper(value: 1)\n").unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session.send("w.value = w.value + 1\n").unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session.send("w.value\n").unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " 2\n>");
+
+        child_session
+            .send("let m = Maybe.Some(value: 3)\n")
+            .unwrap();
+        let _result = child_session.receive().unwrap();
+
+        child_session
+            .send("match m { Maybe.Some(value) => value, Maybe.None => 0 }\n")
+            .unwrap();
+        let result = child_session.receive().unwrap();
+        assert_eq!(result, " 3\n>");
+    }
 }